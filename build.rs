@@ -0,0 +1,13 @@
+// with `LongPathsEnabled` set in the registry, Windows only lifts `MAX_PATH` for programs whose
+// manifest declares them long-path aware; the GNU linker has no option to embed one
+fn main() {
+    println!("cargo:rerun-if-changed=dtmgr.exe.manifest");
+    let target_os = std::env::var("CARGO_CFG_TARGET_OS").unwrap_or_default();
+    let target_env = std::env::var("CARGO_CFG_TARGET_ENV").unwrap_or_default();
+    if target_os == "windows" && target_env == "msvc" {
+        let dir = std::env::var("CARGO_MANIFEST_DIR").expect("cargo sets CARGO_MANIFEST_DIR");
+        let manifest = std::path::Path::new(&dir).join("dtmgr.exe.manifest");
+        println!("cargo:rustc-link-arg-bins=/MANIFEST:EMBED");
+        println!("cargo:rustc-link-arg-bins=/MANIFESTINPUT:{}", manifest.display());
+    }
+}
@@ -0,0 +1,278 @@
+use std::collections::BTreeMap as Map;
+use std::collections::BTreeSet as Set;
+use std::io::{Read, Write};
+use std::path::{Component, Path, PathBuf};
+use flate2::Compression;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use serde::{Deserialize, Serialize};
+use sha3::{Digest, Sha3_256};
+use tracing::{debug, warn};
+use crate::{install_log, DtMgrError};
+
+const ARCHIVE_INFO_NAME: &str = "dtmgr-archive.json";
+
+/// Stored with an archived environment, to put it in place in another project
+#[derive(Debug, Deserialize, Serialize)]
+struct ArchiveInfo {
+    /// The project the environment was built for, which its generated files name
+    project: PathBuf,
+    /// The sha3-256 of every file, by its path in the archive, when links were followed
+    #[serde(default, skip_serializing_if = "Map::is_empty")]
+    files: Map<String, String>,
+}
+
+// every path below `dir`, parents before their contents, with `follow_links` descending into
+// linked directories as well
+fn tree_entries(root: &Path, dir: &Path, follow_links: bool, result: &mut Vec<PathBuf>) -> Result<(), DtMgrError> {
+    let entries = std::fs::read_dir(dir)
+        .map_err(|e| DtMgrError::ReadFile { path: dir.to_owned(), source: e })?;
+    let mut paths: Vec<PathBuf> = entries.filter_map(|e| e.ok()).map(|e| e.path()).collect();
+    paths.sort();
+
+    for path in paths {
+        let relative = path.strip_prefix(root)
+            .expect("read_dir returns paths inside the directory")
+            .to_owned();
+        // the logs belong to the machine that built the environment
+        if relative == Path::new(install_log::LOGS_DIR_NAME) {
+            continue;
+        }
+        let metadata = if follow_links { path.metadata() } else { path.symlink_metadata() };
+        let is_dir = metadata.is_ok_and(|m| m.is_dir());
+        result.push(relative);
+        if is_dir {
+            tree_entries(root, &path, follow_links, result)?;
+        }
+    }
+    Ok(())
+}
+
+// tar paths always use `/`, whatever the platform
+fn archive_path(relative: &Path) -> String {
+    let parts: Vec<_> = relative.components()
+        .filter_map(|c| match c {
+            Component::Normal(part) => Some(part.to_string_lossy()),
+            _ => None,
+        })
+        .collect();
+    parts.join("/")
+}
+
+/// Hashes what passes through it on the way into the archive
+struct HashingReader<R> {
+    inner: R,
+    hasher: Sha3_256,
+}
+
+impl<R: Read> Read for HashingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.hasher.update(&buf[..read]);
+        Ok(read)
+    }
+}
+
+fn file_hash(path: &Path) -> std::io::Result<String> {
+    let mut hasher = Sha3_256::new();
+    std::fs::File::open(path)
+        .and_then(|mut file| std::io::copy(&mut file, &mut hasher))?;
+    let hash: [u8; 32] = hasher.finalize().into();
+    Ok(hex::encode(hash))
+}
+
+/// Writes `dot_dir` to `writer` as a gzipped tarball.
+///
+/// Links stay links unless `follow_links` is set, in which case the files they lead to are
+/// stored and checksummed instead, so the archive works without the TeX Live they came from.
+/// A file links lead to with the same content as one stored before, which packages often ship,
+/// is stored as a hard link to it.
+pub fn pack<W: Write>(project_dir: &Path, dot_dir: &Path, follow_links: bool, writer: W) -> Result<W, DtMgrError> {
+    let create = |e| DtMgrError::CreateArchive { dir: dot_dir.to_owned(), source: e };
+
+    let mut entries = Vec::new();
+    tree_entries(dot_dir, dot_dir, follow_links, &mut entries)?;
+
+    let mut builder = tar::Builder::new(GzEncoder::new(writer, Compression::default()));
+    builder.follow_symlinks(follow_links);
+
+    let mut files = Map::new();
+    // only files as large as one stored before are read twice, to hash them before storing them
+    let mut sizes = Set::new();
+    let mut stored = Map::new();
+    let mut deduplicated = 0;
+    let dot_dir_target = dot_dir.canonicalize().map_err(create)?;
+    let mut dangling = 0;
+    for relative in entries.iter() {
+        let path = dot_dir.join(relative);
+        let name = archive_path(relative);
+        let metadata = match follow_links {
+            true => match path.metadata() {
+                Ok(metadata) => metadata,
+                // e.g. documentation TeX Live was told not to install
+                Err(_) => {
+                    debug!("leaving out {}, which links to a missing file", path.display());
+                    dangling += 1;
+                    continue;
+                }
+            },
+            false => path.symlink_metadata().map_err(create)?,
+        };
+        if !follow_links || !metadata.is_file() {
+            builder.append_path_with_name(&path, &name).map_err(create)?;
+            continue;
+        }
+
+        // the environment's own files are left alone, as installs rewrite some of them in place
+        let linked = path.canonicalize().is_ok_and(|target| !target.starts_with(&dot_dir_target));
+        let known_hash = match linked && sizes.contains(&metadata.len()) {
+            true => Some(file_hash(&path).map_err(create)?),
+            false => None,
+        };
+        if let Some((hash, first)) = known_hash.as_ref().and_then(|hash| Some((hash, stored.get(hash)?))) {
+            let mut header = tar::Header::new_gnu();
+            header.set_metadata(&metadata);
+            header.set_entry_type(tar::EntryType::Link);
+            header.set_size(0);
+            builder.append_link(&mut header, &name, first).map_err(create)?;
+            files.insert(name, hash.clone());
+            deduplicated += 1;
+            continue;
+        }
+
+        let file = std::fs::File::open(&path).map_err(create)?;
+        let mut reader = HashingReader { inner: file, hasher: Sha3_256::new() };
+        let mut header = tar::Header::new_gnu();
+        header.set_metadata(&metadata);
+        builder.append_data(&mut header, &name, &mut reader).map_err(create)?;
+        let hash: [u8; 32] = reader.hasher.finalize().into();
+        let hash = hex::encode(hash);
+        if linked {
+            sizes.insert(metadata.len());
+            stored.insert(hash.clone(), name.clone());
+        }
+        files.insert(name, hash);
+    }
+
+    if dangling > 0 {
+        warn!("left out {} links to missing files", dangling);
+    }
+    if deduplicated > 0 {
+        debug!("stored {} files as links to others with the same content", deduplicated);
+    }
+
+    // last, as the checksums are only known now
+    let info = serde_json::to_vec(&ArchiveInfo { project: project_dir.to_owned(), files })
+        .map_err(|e| DtMgrError::JsonSerialize { source: e })?;
+    let mut header = tar::Header::new_gnu();
+    header.set_size(info.len() as u64);
+    header.set_mode(0o644);
+    header.set_cksum();
+    builder.append_data(&mut header, ARCHIVE_INFO_NAME, info.as_slice()).map_err(create)?;
+
+    builder.into_inner()
+        .and_then(|encoder| encoder.finish())
+        .map_err(create)
+}
+
+// the files an archive with checksums holds, as they came out of it
+fn verify_checksums(dot_dir: &Path, files: &Map<String, String>) -> Result<(), DtMgrError> {
+    for (name, expected) in files.iter() {
+        let path = dot_dir.join(name);
+        let hash = file_hash(&path)
+            .map_err(|e| DtMgrError::ReadFile { path: path.clone(), source: e })?;
+        if hash != *expected {
+            return Err(DtMgrError::ArchiveChecksum { file: path });
+        }
+    }
+    Ok(())
+}
+
+/// Extracts an archive [`pack`] made into the empty `dot_dir`, checking the files against their
+/// checksums and pointing what named the project it was built for at `project_dir` instead
+pub fn unpack<R: Read>(reader: R, project_dir: &Path, dot_dir: &Path) -> Result<(), DtMgrError> {
+    let unpack = |e| DtMgrError::UnpackArchive { dir: dot_dir.to_owned(), source: e };
+
+    let mut archive = tar::Archive::new(GzDecoder::new(reader));
+    archive.set_preserve_permissions(true);
+    let mut info = None;
+    let mut unpacked_files = Vec::new();
+    for entry in archive.entries().map_err(unpack)? {
+        let mut entry = entry.map_err(unpack)?;
+        // as `tar -C dir .` would write it, too
+        let is_info = {
+            let path = entry.path().map_err(unpack)?;
+            path.strip_prefix(".").unwrap_or(&path) == Path::new(ARCHIVE_INFO_NAME)
+        };
+        if is_info {
+            let mut content = Vec::new();
+            entry.read_to_end(&mut content).map_err(unpack)?;
+            info = Some(serde_json::from_slice::<ArchiveInfo>(&content)
+                .map_err(|e| DtMgrError::JsonParse { source: e })?);
+            continue;
+        }
+        // files stored once for several names come back as hard links
+        let entry_type = entry.header().entry_type();
+        if entry_type.is_file() || entry_type.is_hard_link() {
+            unpacked_files.push(archive_path(&entry.path().map_err(unpack)?));
+        }
+        // refuses paths that would leave `dot_dir`
+        entry.unpack_in(dot_dir).map_err(unpack)?;
+    }
+
+    let Some(info) = info else {
+        return Err(unpack(std::io::Error::other(format!("the archive has no {}", ARCHIVE_INFO_NAME))));
+    };
+    if !info.files.is_empty() {
+        // a file without a checksum was slipped in
+        if let Some(unknown) = unpacked_files.iter().find(|name| !info.files.contains_key(*name)) {
+            return Err(DtMgrError::ArchiveChecksum { file: dot_dir.join(unknown) });
+        }
+        verify_checksums(dot_dir, &info.files)?;
+    }
+    if info.project != project_dir {
+        debug!("moving the environment from {} to {}", info.project.display(), project_dir.display());
+        relocate(dot_dir, &info.project, project_dir)?;
+    }
+    Ok(())
+}
+
+// the generated files and links that name the project, as `install` writes them
+fn relocate(dot_dir: &Path, from: &Path, to: &Path) -> Result<(), DtMgrError> {
+    let (Some(from_text), Some(to_text)) = (from.to_str(), to.to_str()) else {
+        return Err(DtMgrError::NotUnicode { what: String::from("the project's path") });
+    };
+
+    let mut entries = Vec::new();
+    tree_entries(dot_dir, dot_dir, false, &mut entries)?;
+    for relative in entries.iter() {
+        let path = dot_dir.join(relative);
+        let Ok(metadata) = path.symlink_metadata() else {
+            continue;
+        };
+
+        if metadata.is_symlink() {
+            let target = std::fs::read_link(&path)
+                .map_err(|e| DtMgrError::ReadFile { path: path.clone(), source: e })?;
+            if let Ok(inside) = target.strip_prefix(from) {
+                std::fs::remove_file(&path)
+                    .map_err(|e| DtMgrError::WriteFile { file: path.clone(), source: e })?;
+                crate::link::create_link(crate::link::LinkStrategy::Symlink, &to.join(inside), &path)
+                    .map_err(|e| DtMgrError::CreateSymlink { src: to.join(inside), dst: path.clone(), source: e })?;
+            }
+            continue;
+        }
+
+        // texmf.cnf, the launchers in bin/ and the shims in bin/shims/
+        let generated = relative == Path::new("texmf.cnf")
+            || (relative.parent() == Some(Path::new("bin")) && metadata.is_file())
+            || relative.parent() == Some(&Path::new("bin").join("shims"));
+        if generated {
+            let content = std::fs::read_to_string(&path)
+                .map_err(|e| DtMgrError::ReadFile { path: path.clone(), source: e })?;
+            std::fs::write(&path, content.replace(from_text, to_text))
+                .map_err(|e| DtMgrError::WriteFile { file: path.clone(), source: e })?;
+        }
+    }
+    Ok(())
+}
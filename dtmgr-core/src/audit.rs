@@ -0,0 +1,141 @@
+use serde::Serialize;
+use tracing::warn;
+use crate::graph::{self, Anomaly, DependencyGraph};
+use crate::output::{self, OutputFormat};
+use crate::{network, DtMgrConfig, DtMgrError, TlPObjInfo};
+
+// the catalogue topic of packages CTAN keeps only for old documents
+const OBSOLETE_TOPIC: &str = "obsolete";
+
+// packages TeX Live renamed, split up or folded into others, and those the catalogue only
+// marks obsolete that have a well-known successor
+const SUCCESSORS: [(&str, &[&str]); 12] = [
+    ("pdftex-def", &["graphics-def"]),
+    ("xetex-def", &["graphics-def"]),
+    ("luatex-def", &["graphics-def"]),
+    ("dvipdfm", &["dvipdfmx"]),
+    ("ms", &["everysel", "everyshi", "prelim2e", "ragged2e"]),
+    ("ifxetex", &["iftex"]),
+    ("ifluatex", &["iftex"]),
+    ("subfigure", &["subcaption"]),
+    ("a4wide", &["geometry"]),
+    ("glossary", &["glossaries"]),
+    ("here", &["float"]),
+    ("floatflt", &["wrapfig"]),
+];
+
+/// What is wrong with a package of the resolved set
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum Problem {
+    /// The catalogue marks it obsolete, or it has a successor, but the repository still has it
+    Obsolete,
+    /// The repository no longer has it under this name, but has its successors
+    Renamed,
+    /// Neither TeX Live nor the repository has it
+    Missing,
+}
+
+/// A package `dtmgr audit` reports
+#[derive(Debug, Serialize)]
+pub struct Finding {
+    pub package: String,
+    pub problem: Problem,
+    /// Packages to depend on instead
+    pub replacements: Vec<String>,
+    /// The chain of dependencies from a listed package to this one, empty if it is listed itself
+    pub required_by: Vec<String>,
+}
+
+fn successors(name: &str) -> Vec<String> {
+    SUCCESSORS.iter()
+        .find(|(old, _)| *old == name)
+        .map(|(_, new)| new.iter().map(|n| n.to_string()).collect())
+        .unwrap_or_default()
+}
+
+fn is_obsolete(pkg: &TlPObjInfo) -> bool {
+    pkg.cataloguedata.iter().chain(pkg.rcataloguedata.iter())
+        .filter_map(|catalogue| catalogue.topics.as_deref())
+        .any(|topics| topics.split_whitespace().any(|topic| topic == OBSOLETE_TOPIC))
+}
+
+fn absent(package: String, required_by: Vec<String>) -> Finding {
+    let replacements = successors(&package);
+    let problem = if replacements.is_empty() { Problem::Missing } else { Problem::Renamed };
+    Finding { package, problem, replacements, required_by }
+}
+
+/// Packages of `graph` that are obsolete, and those neither TeX Live nor the repository has or
+/// that only the installed TeX Live still has, with what to depend on instead where it is known.
+///
+/// Offline, the repository cannot be asked, so packages installed but no longer in it go
+/// unnoticed.
+pub fn audit(graph: &DependencyGraph) -> Result<Vec<Finding>, DtMgrError> {
+    let repository = if network::offline() {
+        warn!("offline, so packages the repository no longer has are not looked for");
+        None
+    } else {
+        Some(crate::remote_tlpdb()?)
+    };
+    let required_by = |name: &str| -> Vec<String> {
+        let mut chain: Vec<String> = graph.path_to(name).unwrap_or_default().into_iter().map(String::from).collect();
+        chain.pop();
+        chain
+    };
+
+    let mut findings = Vec::new();
+    for node in graph.iter() {
+        let name = node.name();
+        if repository.as_ref().is_some_and(|repository| !repository.contains_key(name)) {
+            findings.push(absent(name.to_owned(), required_by(name)));
+            continue;
+        }
+        let replacements = successors(name);
+        let obsolete = graph.packages().get(name).is_some_and(is_obsolete);
+        if obsolete || !replacements.is_empty() {
+            findings.push(Finding { package: name.to_owned(), problem: Problem::Obsolete, replacements, required_by: required_by(name) });
+        }
+    }
+    for anomaly in graph.anomalies() {
+        if let Anomaly::Unavailable { chain } = anomaly {
+            let (package, dependents) = chain.split_last().expect("an unavailable package is in its chain");
+            findings.push(absent(package.clone(), dependents.to_vec()));
+        }
+    }
+    findings.sort_by(|a, b| a.package.cmp(&b.package));
+    Ok(findings)
+}
+
+fn print_findings(findings: &[Finding]) {
+    if findings.is_empty() {
+        println!("no obsolete, renamed or missing packages");
+    }
+    for finding in findings {
+        let problem = match finding.problem {
+            Problem::Obsolete => "is obsolete",
+            Problem::Renamed => "was renamed or split up",
+            Problem::Missing => "is not in the repository",
+        };
+        let mut line = format!("{} {}", finding.package, problem);
+        if !finding.required_by.is_empty() {
+            line += &format!(", required by {}", finding.required_by.join(" -> "));
+        }
+        if !finding.replacements.is_empty() {
+            line += &format!("; use {} instead", finding.replacements.join(", "));
+        }
+        println!("{}", line);
+    }
+}
+
+/// Resolves `config` on `platform` and reports what [`audit`] finds, returning whether it found
+/// nothing
+pub fn run_audit(config: &DtMgrConfig, platform: &str, format: OutputFormat) -> Result<bool, DtMgrError> {
+    let graph = graph::resolve(config, platform, &graph::TlmgrBackend)?;
+    let findings = audit(&graph)?;
+    match format {
+        OutputFormat::Human => print_findings(&findings),
+        OutputFormat::Json => output::print_json(&findings)?,
+    }
+    Ok(findings.is_empty())
+}
@@ -0,0 +1,41 @@
+use std::collections::BTreeMap as Map;
+use crate::TlPObjInfo;
+
+/// `major.minor` of a catalogue version like `3.20` or `2.19a`
+fn major_minor(version: &str) -> Option<(u32, u32)> {
+    let (major, rest) = version.trim().trim_start_matches('v').split_once('.')?;
+    let minor: String = rest.chars().take_while(char::is_ascii_digit).collect();
+    Some((major.parse().ok()?, minor.parse().ok()?))
+}
+
+/// The biber release that reads what biblatex `major.minor` writes, from biber's documentation.
+/// Since biblatex 3.7 their minor versions go together.
+fn biber_for(biblatex: (u32, u32)) -> Option<(u32, u32)> {
+    match biblatex {
+        (3, minor) if minor >= 7 => Some((2, minor)),
+        (3, 5 | 6) => Some((2, 6)),
+        (3, minor @ 0..=4) => Some((2, minor + 1)),
+        (2, 9) => Some((2, 0)),
+        _ => None,
+    }
+}
+
+/// If the environment has both biblatex and biber but of releases that do not work together,
+/// what is wrong and how to fix it. biber refuses biblatex's control file then, with an error
+/// that does not say which of the two is behind.
+pub fn compatibility_problem(dep_tree: &Map<String, TlPObjInfo>) -> Option<String> {
+    let biblatex_version = dep_tree.get("biblatex")?.catalogue_version()?;
+    let biber_version = dep_tree.get("biber")?.catalogue_version()?;
+    let needed = biber_for(major_minor(biblatex_version)?)?;
+    let biber = major_minor(biber_version)?;
+    if biber == needed {
+        return None;
+    }
+
+    let behind = if biber < needed { "biber" } else { "biblatex" };
+    Some(format!(
+        "biblatex {} needs biber {}.{}, but the environment has biber {}; update {} with \
+        `tlmgr update {}`, or every project's TeX Live packages with `dtmgr upgrade-texlive`",
+        biblatex_version, needed.0, needed.1, biber_version, behind, behind,
+    ))
+}
@@ -0,0 +1,153 @@
+use std::collections::BTreeMap as Map;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use crate::{DtMgrError, KPSE_SEPARATOR};
+
+const DEFAULT_MAIN: &str = "main.tex";
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, JsonSchema, Hash, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Engine {
+    #[default]
+    Pdflatex,
+    Xelatex,
+    Lualatex,
+    Latex,
+}
+
+impl Engine {
+    /// latexmk's `$pdf_mode` for this engine
+    pub fn pdf_mode(self) -> u8 {
+        match self {
+            Engine::Pdflatex => 1,
+            Engine::Xelatex => 5,
+            Engine::Lualatex => 4,
+            Engine::Latex => 0,
+        }
+    }
+
+    fn latexmk_flag(self) -> &'static str {
+        match self {
+            Engine::Pdflatex => "-pdf",
+            Engine::Xelatex => "-xelatex",
+            Engine::Lualatex => "-lualatex",
+            Engine::Latex => "-dvi",
+        }
+    }
+}
+
+/// How `dtmgr build` runs latexmk.
+///
+/// This is both the `[build]` section of `dtmgr.toml` and each entry of
+/// `[documents]`, where any setting given overrides the one from `[build]`.
+#[derive(Clone, Debug, Default, Deserialize, JsonSchema, Hash)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct BuildConfig {
+    main: Option<PathBuf>,
+    engine: Option<Engine>,
+    output_dir: Option<PathBuf>,
+    latexmk_options: Option<Vec<String>>,
+}
+
+impl BuildConfig {
+    pub fn main(&self) -> PathBuf {
+        self.main.clone().unwrap_or_else(|| PathBuf::from(DEFAULT_MAIN))
+    }
+
+    pub fn engine(&self) -> Engine {
+        self.engine.unwrap_or_default()
+    }
+
+    pub fn output_dir(&self) -> Option<&PathBuf> {
+        self.output_dir.as_ref()
+    }
+
+    /// Points TeX and the bibliography/index tools run by `cmd` at the output directory.
+    ///
+    /// TEXMFOUTPUT lets TeX, bibtex and makeindex write there even if the sources
+    /// are read-only, and the `*INPUTS` additions let bibtex, which runs from the
+    /// output directory, still find the project's `.bib` and `.bst` files.
+    pub fn apply_output_environment(&self, cmd: &mut Command, project_dir: &Path) -> Result<(), DtMgrError> {
+        let Some(output_dir) = &self.output_dir else {
+            return Ok(());
+        };
+
+        let output_dir = project_dir.join(output_dir);
+        std::fs::create_dir_all(&output_dir)
+            .map_err(|e| DtMgrError::CreateDirectory { dir: output_dir.clone(), source: e })?;
+        cmd.env("TEXMFOUTPUT", &output_dir);
+
+        // a trailing separator keeps kpathsea's default path after ours
+        for var in ["BIBINPUTS", "BSTINPUTS"] {
+            let mut value = OsString::from(project_dir);
+            value.push(KPSE_SEPARATOR.to_string());
+            cmd.env(var, value);
+        }
+        Ok(())
+    }
+
+    /// `self` with every setting `document` gives taking precedence
+    fn merged_with(&self, document: &BuildConfig) -> BuildConfig {
+        BuildConfig {
+            main: document.main.clone().or_else(|| self.main.clone()),
+            engine: document.engine.or(self.engine),
+            output_dir: document.output_dir.clone().or_else(|| self.output_dir.clone()),
+            latexmk_options: document.latexmk_options.clone().or_else(|| self.latexmk_options.clone()),
+        }
+    }
+
+    /// latexmk's options, without the program or the file to build
+    pub fn latexmk_arguments(&self, extra_args: &[String]) -> Vec<String> {
+        let mut arguments = vec![String::from(self.engine().latexmk_flag())];
+        if let Some(output_dir) = &self.output_dir {
+            arguments.push(format!("-outdir={}", output_dir.display()));
+        }
+        arguments.extend(self.latexmk_options.iter().flatten().cloned());
+        arguments.extend(extra_args.iter().cloned());
+        arguments
+    }
+
+    /// `latexmk` and its arguments, relative to the project directory
+    pub fn latexmk_command(&self, extra_args: &[String]) -> Vec<String> {
+        let mut command = vec![String::from("latexmk")];
+        command.extend(self.latexmk_arguments(extra_args));
+        command.push(self.main().display().to_string());
+        command
+    }
+}
+
+/// A document to build; unnamed when the project only has a `[build]` section
+pub struct BuildTarget {
+    pub name: Option<String>,
+    pub config: BuildConfig,
+}
+
+/// The documents `dtmgr build [target] [--all]` refers to
+pub fn select_targets(
+    build: &BuildConfig,
+    documents: &Map<String, BuildConfig>,
+    target: Option<&str>,
+    all: bool,
+) -> Result<Vec<BuildTarget>, DtMgrError> {
+    let named = |name: &String, document: &BuildConfig| BuildTarget {
+        name: Some(name.clone()),
+        config: build.merged_with(document),
+    };
+
+    match target {
+        Some(target) => documents.get_key_value(target)
+            .map(|(name, document)| vec![named(name, document)])
+            .ok_or_else(|| DtMgrError::UnknownDocument {
+                name: target.to_owned(),
+                available: documents.keys().cloned().collect::<Vec<_>>().join(", "),
+            }),
+        None if documents.is_empty() => Ok(vec![BuildTarget { name: None, config: build.clone() }]),
+        None if all || documents.len() == 1 => Ok(documents.iter().map(|(name, document)| named(name, document)).collect()),
+        None => Err(DtMgrError::AmbiguousDocument {
+            available: documents.keys().cloned().collect::<Vec<_>>().join(", "),
+        }),
+    }
+}
@@ -0,0 +1,213 @@
+use std::collections::BTreeMap as Map;
+use std::collections::BTreeSet as Set;
+use std::collections::btree_map::Entry;
+use std::io::{Seek, Write};
+use std::path::{Path, PathBuf};
+use clap::ValueEnum;
+use flate2::Compression;
+use flate2::write::GzEncoder;
+use sha2::{Digest, Sha256};
+use tracing::debug;
+use crate::{DtMgrConfig, DtMgrError};
+
+// what Tectonic reads a bundle's digest from in a zip bundle
+const DIGEST_FILE_NAME: &str = "SHA256SUM";
+const TTB_SIGNATURE: &[u8; 14] = b"tectonicbundle";
+const TTB_VERSION: u32 = 1;
+// signature, version, index offset and lengths, digest, then reserved bytes
+const TTB_HEADER_LEN: usize = 70;
+// the single search path of a TTB bundle; `//` searches every directory below
+const TTB_ROOT: &str = "/dtmgr";
+// directories of a TEXMF tree no TeX run reads
+const SKIPPED_DIRS: [&str; 3] = ["doc", "source", "scripts"];
+// formats are built for TeX Live's engines, which Tectonic does not share
+const SKIPPED_EXTENSIONS: [&str; 2] = ["fmt", "log"];
+
+#[derive(Clone, Copy, Debug, Default, ValueEnum)]
+pub enum BundleFormat {
+    /// Tectonic's indexed bundle format, read by `tectonic -b bundle.ttb`
+    #[default]
+    Ttb,
+    /// The older zip bundle, every file at the top level next to a `SHA256SUM`
+    Zip,
+}
+
+impl BundleFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            BundleFormat::Ttb => "ttb",
+            BundleFormat::Zip => "zip",
+        }
+    }
+}
+
+/// The TEXMF trees of the environment, in the order kpathsea searches them
+fn trees(config: &DtMgrConfig, dtmgr_directory: &Path, dot_dir: &Path) -> Result<Vec<PathBuf>, DtMgrError> {
+    let mut trees = Vec::new();
+    if let Some(tree) = &config.texmf {
+        trees.push(crate::project_tree(dtmgr_directory, tree)?);
+    }
+    trees.extend(["texmf-config", "texmf-var", "texmf-dist"].map(|tree| dot_dir.join(tree)));
+    for tree in config.extra_trees.iter() {
+        trees.push(crate::project_tree(dtmgr_directory, tree)?);
+    }
+    Ok(trees)
+}
+
+/// The files of the packages only `dev-dependencies` bring in, by their path in `texmf-dist`,
+/// which a bundle for building the documents leaves out
+pub fn dev_only_files(config: &mut DtMgrConfig, platform: &str) -> Result<Set<String>, DtMgrError> {
+    if config.dev_dependencies.is_empty() {
+        return Ok(Set::new());
+    }
+    let with_dev = crate::build_dependency_tree(config, platform)?;
+    let dev_dependencies = std::mem::take(&mut config.dev_dependencies);
+    let without_dev = crate::build_dependency_tree(config, platform);
+    config.dev_dependencies = dev_dependencies;
+    let without_dev = without_dev?;
+    Ok(with_dev.values()
+        .filter(|pkg| !without_dev.contains_key(&pkg.name))
+        .flat_map(|pkg| pkg.runfiles.iter().flatten())
+        .filter_map(|file| file.strip_prefix("texmf-dist/"))
+        .map(str::to_owned)
+        .collect())
+}
+
+fn collect(dir: &Path, relative: &str, skipped: &Set<String>, files: &mut Map<String, (String, PathBuf)>, shadowed: &mut usize) -> Result<(), DtMgrError> {
+    let mut entries: Vec<PathBuf> = std::fs::read_dir(dir)
+        .map_err(|e| DtMgrError::ReadFile { path: dir.to_owned(), source: e })?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .collect();
+    entries.sort();
+
+    for path in entries {
+        let name = path.file_name().unwrap_or_default().to_string_lossy().into_owned();
+        if name.starts_with('.') || name == "ls-R" {
+            continue;
+        }
+        let inner = format!("{}/{}", relative, name);
+        // follows links, which is how the environment has most of its files
+        if path.is_dir() {
+            if !(relative.is_empty() && SKIPPED_DIRS.contains(&name.as_str())) {
+                collect(&path, &inner, skipped, files, shadowed)?;
+            }
+        } else if skipped.contains(inner.trim_start_matches('/')) {
+            continue;
+        } else if path.is_file() && !path.extension().is_some_and(|e| SKIPPED_EXTENSIONS.contains(&e.to_string_lossy().as_ref())) {
+            // Tectonic finds files by name alone, so the first tree to have one wins
+            match files.entry(name) {
+                Entry::Occupied(_) => *shadowed += 1,
+                Entry::Vacant(entry) => {
+                    entry.insert((inner, path));
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Every file a TeX run in the environment could read, by name, with its path inside its tree
+/// and where it is, but for the `skipped` files of `texmf-dist`
+fn files(config: &DtMgrConfig, dtmgr_directory: &Path, dot_dir: &Path, skipped: &Set<String>) -> Result<Map<String, (String, PathBuf)>, DtMgrError> {
+    let mut files = Map::new();
+    let mut shadowed = 0;
+    let texmf_dist = dot_dir.join("texmf-dist");
+    for tree in trees(config, dtmgr_directory, dot_dir)?.iter().filter(|tree| tree.is_dir()) {
+        let skipped = if *tree == texmf_dist { skipped } else { &Set::new() };
+        collect(tree, "", skipped, &mut files, &mut shadowed)?;
+    }
+    if shadowed > 0 {
+        debug!("left out {} files named like one in an earlier tree", shadowed);
+    }
+    Ok(files)
+}
+
+fn read(path: &Path) -> Result<Vec<u8>, DtMgrError> {
+    std::fs::read(path).map_err(|e| DtMgrError::ReadFile { path: path.to_owned(), source: e })
+}
+
+fn gzip(data: &[u8]) -> std::io::Result<Vec<u8>> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(data)?;
+    encoder.finish()
+}
+
+/// The hash Tectonic caches a bundle under, over the names and hashes of all its files
+fn digest(hashes: &Map<&str, String>) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    for (name, hash) in hashes.iter() {
+        hasher.update(format!("{}  {}\n", hash, name));
+    }
+    hasher.finalize().into()
+}
+
+fn write_zip<W: Write + Seek>(files: &Map<String, (String, PathBuf)>, writer: W, output: &Path) -> Result<(), DtMgrError> {
+    let write = |e: std::io::Error| DtMgrError::WriteFile { file: output.to_owned(), source: e };
+    let mut zip = zip::ZipWriter::new(writer);
+    let options = zip::write::SimpleFileOptions::default();
+    let mut hashes = Map::new();
+    for (name, (_, path)) in files.iter() {
+        let data = read(path)?;
+        hashes.insert(name.as_str(), hex::encode(Sha256::digest(&data)));
+        zip.start_file(name.as_str(), options).map_err(|e| write(e.into()))?;
+        zip.write_all(&data).map_err(write)?;
+    }
+    zip.start_file(DIGEST_FILE_NAME, options).map_err(|e| write(e.into()))?;
+    zip.write_all(hex::encode(digest(&hashes)).as_bytes()).map_err(write)?;
+    zip.finish().map_err(|e| write(e.into()))?;
+    Ok(())
+}
+
+fn write_ttb<W: Write + Seek>(files: &Map<String, (String, PathBuf)>, mut writer: W, output: &Path) -> Result<(), DtMgrError> {
+    let write = |e: std::io::Error| DtMgrError::WriteFile { file: output.to_owned(), source: e };
+    // written again at the end, once the index and digest are known
+    writer.write_all(&[0; TTB_HEADER_LEN]).map_err(write)?;
+
+    let mut offset = TTB_HEADER_LEN as u64;
+    let mut hashes = Map::new();
+    let mut file_list = String::new();
+    for (name, (inner, path)) in files.iter() {
+        let data = read(path)?;
+        let hash = hex::encode(Sha256::digest(&data));
+        let compressed = gzip(&data).map_err(write)?;
+        writer.write_all(&compressed).map_err(write)?;
+        file_list.push_str(&format!("{} {} {} {} {}{}\n", offset, compressed.len(), data.len(), hash, TTB_ROOT, inner));
+        offset += compressed.len() as u64;
+        hashes.insert(name.as_str(), hash);
+    }
+
+    let index = format!("[DEFAULTSEARCH]\nMAIN\n[SEARCH:MAIN]\n{}//\n[FILELIST]\n{}", TTB_ROOT, file_list);
+    let compressed_index = gzip(index.as_bytes()).map_err(write)?;
+    writer.write_all(&compressed_index).map_err(write)?;
+
+    let mut header = Vec::with_capacity(TTB_HEADER_LEN);
+    header.extend_from_slice(TTB_SIGNATURE);
+    header.extend_from_slice(&TTB_VERSION.to_le_bytes());
+    header.extend_from_slice(&offset.to_le_bytes());
+    header.extend_from_slice(&(compressed_index.len() as u32).to_le_bytes());
+    header.extend_from_slice(&(index.len() as u32).to_le_bytes());
+    header.extend_from_slice(&digest(&hashes));
+    header.resize(TTB_HEADER_LEN, 0);
+    writer.rewind()
+        .and_then(|()| writer.write_all(&header))
+        .and_then(|()| writer.flush())
+        .map_err(write)
+}
+
+/// Writes the files of the environment in `dot_dir` as a Tectonic bundle to `output`, returning
+/// how many it holds.
+///
+/// Only what a TeX run reads goes in: documentation, sources, scripts, `ls-R` and TeX Live's
+/// formats stay out, and of files with the same name the one kpathsea would find first. The
+/// `skipped` files of `texmf-dist` stay out as well, see [`dev_only_files`].
+pub fn write(config: &DtMgrConfig, dtmgr_directory: &Path, dot_dir: &Path, skipped: &Set<String>, format: BundleFormat, output: &Path) -> Result<usize, DtMgrError> {
+    let files = files(config, dtmgr_directory, dot_dir, skipped)?;
+    let file = std::fs::File::create(output)
+        .map_err(|e| DtMgrError::WriteFile { file: output.to_owned(), source: e })?;
+    let writer = std::io::BufWriter::new(file);
+    match format {
+        BundleFormat::Ttb => write_ttb(&files, writer, output)?,
+        BundleFormat::Zip => write_zip(&files, writer, output)?,
+    }
+    Ok(files.len())
+}
@@ -0,0 +1,163 @@
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use clap::Subcommand;
+use serde::Serialize;
+use sha3::{Digest, Sha3_256};
+use crate::output::{self, OutputFormat};
+use crate::size::format_size;
+use crate::{home, shared_var, DtMgrError};
+
+// touched whenever an install uses the entry, as directories' own times only say when they were made
+pub(crate) const LAST_USED_NAME: &str = ".dtmgr-last-used";
+
+// where each kind of entry lives, below the cache directory
+const AREAS: [&str; 4] = ["git/db", "git/checkouts", "ctan", shared_var::AREA];
+
+#[derive(Subcommand)]
+pub enum CacheCommand {
+    /// Show where the cache and dtmgr's other global directories are, and how much space the
+    /// cache takes
+    Info {
+        #[arg(long, value_enum, default_value_t)]
+        format: OutputFormat,
+    },
+    /// Delete the whole cache
+    Clean,
+    /// Delete what no install has used for a while
+    Prune {
+        /// e.g. `30days` or `12h`
+        #[arg(long, value_name = "DURATION", value_parser = humantime::parse_duration)]
+        older_than: Duration,
+    },
+}
+
+/// A directory name for `url` that stays readable but cannot collide with another URL's
+pub fn url_key(name: &str, url: &str) -> String {
+    let hash: [u8; 32] = Sha3_256::digest(url.as_bytes()).into();
+    format!("{}-{}", name, &hex::encode(hash)[..16])
+}
+
+/// Records that an install used the cache entry `dir`, so `dtmgr cache prune` keeps it
+pub fn mark_used(dir: &Path) {
+    // at worst the entry is pruned and downloaded again
+    let _ = std::fs::write(dir.join(LAST_USED_NAME), b"");
+}
+
+// the bytes below `path`, without following links into TeX Live or elsewhere
+fn disk_size(path: &Path) -> u64 {
+    let Ok(metadata) = path.symlink_metadata() else {
+        return 0;
+    };
+    if !metadata.is_dir() {
+        return metadata.len();
+    }
+    std::fs::read_dir(path).into_iter()
+        .flatten()
+        .filter_map(|e| e.ok())
+        .map(|e| disk_size(&e.path()))
+        .sum()
+}
+
+struct Entry {
+    area: &'static str,
+    path: PathBuf,
+    last_used: SystemTime,
+}
+
+fn entries(cache: &Path) -> Vec<Entry> {
+    let mut result = Vec::new();
+    for area in AREAS {
+        let dir = cache.join(area);
+        for entry in std::fs::read_dir(&dir).into_iter().flatten().filter_map(|e| e.ok()) {
+            let path = entry.path();
+            let last_used = path.join(LAST_USED_NAME).metadata()
+                .or_else(|_| path.metadata())
+                .and_then(|m| m.modified())
+                .unwrap_or(SystemTime::UNIX_EPOCH);
+            result.push(Entry { area, path, last_used });
+        }
+    }
+    result
+}
+
+#[derive(Debug, Serialize)]
+struct AreaInfo {
+    area: &'static str,
+    entries: usize,
+    size: u64,
+}
+
+#[derive(Debug, Serialize)]
+struct CacheInfo {
+    path: PathBuf,
+    areas: Vec<AreaInfo>,
+    size: u64,
+    data: Option<PathBuf>,
+    config: Option<PathBuf>,
+}
+
+fn remove_entry(path: &Path) -> Result<(), DtMgrError> {
+    let removed = if path.symlink_metadata().is_ok_and(|m| m.is_dir()) {
+        std::fs::remove_dir_all(path)
+    } else {
+        std::fs::remove_file(path)
+    };
+    removed.map_err(|e| DtMgrError::RemoveDirectory { dir: path.to_owned(), source: e })
+}
+
+pub fn run_cache_command(command: CacheCommand) -> Result<(), DtMgrError> {
+    let cache = home::cache_dir()?;
+    match command {
+        CacheCommand::Info { format } => {
+            let entries = entries(&cache);
+            let areas: Vec<AreaInfo> = AREAS.iter()
+                .map(|area| {
+                    let in_area: Vec<&Entry> = entries.iter().filter(|e| e.area == *area).collect();
+                    AreaInfo { area, entries: in_area.len(), size: in_area.iter().map(|e| disk_size(&e.path)).sum() }
+                })
+                .collect();
+            let info = CacheInfo {
+                size: disk_size(&cache),
+                path: cache,
+                areas,
+                data: home::data_dir().ok(),
+                config: home::config_dir(),
+            };
+            match format {
+                OutputFormat::Human => {
+                    println!("cache: {}", info.path.display());
+                    for area in info.areas.iter() {
+                        println!("  {}: {} entries, {}", area.area, area.entries, format_size(area.size));
+                    }
+                    println!("  total: {}", format_size(info.size));
+                    for (name, dir) in [("data", &info.data), ("config", &info.config)] {
+                        match dir {
+                            Some(dir) => println!("{}: {}", name, dir.display()),
+                            None => println!("{}: none on this system", name),
+                        }
+                    }
+                }
+                OutputFormat::Json => output::print_json(&info)?,
+            }
+        }
+        CacheCommand::Clean => {
+            let size = disk_size(&cache);
+            if cache.exists() {
+                remove_entry(&cache)?;
+            }
+            println!("removed {} ({})", cache.display(), format_size(size));
+        }
+        CacheCommand::Prune { older_than } => {
+            let cutoff = SystemTime::now().checked_sub(older_than).unwrap_or(SystemTime::UNIX_EPOCH);
+            let mut removed = 0;
+            let mut freed = 0;
+            for entry in entries(&cache).into_iter().filter(|e| e.last_used < cutoff) {
+                freed += disk_size(&entry.path);
+                remove_entry(&entry.path)?;
+                removed += 1;
+            }
+            println!("removed {} entries ({})", removed, format_size(freed));
+        }
+    }
+    Ok(())
+}
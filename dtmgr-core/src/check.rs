@@ -0,0 +1,125 @@
+use std::collections::BTreeMap as Map;
+use serde::Serialize;
+use tracing::warn;
+use crate::config_span::{self, ConfigSpan};
+use crate::graph::{self, DependencyGraph};
+use crate::{biber, licenses, tlpdb, DtMgrConfig, DtMgrError};
+
+// the share of the environment above which a single collection is worth a warning
+const COLLECTION_SHARE: f64 = 0.5;
+// how close an installed package's name has to be to an unknown one to be suggested
+const SIMILARITY_THRESHOLD: f64 = 0.85;
+
+/// The packages installed in TeX Live, which unknown names are compared with
+fn installed_names() -> Vec<String> {
+    let Ok(root) = crate::get_texlive_root() else {
+        return Vec::new();
+    };
+    std::fs::read_to_string(tlpdb::local_tlpdb(root))
+        .map(|content| tlpdb::revisions(&content).keys().map(|name| name.to_string()).collect())
+        .unwrap_or_default()
+}
+
+/// Something about `dtmgr.toml` that keeps it from being installed
+#[derive(Debug, Serialize)]
+pub struct ConfigProblem {
+    pub message: String,
+    /// The part of `dtmgr.toml` the problem is with, if it is one place
+    pub span: Option<ConfigSpan>,
+}
+
+impl From<String> for ConfigProblem {
+    fn from(message: String) -> Self {
+        ConfigProblem { message, span: None }
+    }
+}
+
+fn unknown_dependency(name: &str, declared_in: Option<&String>, installed: &[String]) -> ConfigProblem {
+    let mut similar: Vec<(f64, &str)> = installed.iter()
+        .map(|candidate| (strsim::jaro_winkler(name, candidate), candidate.as_str()))
+        .filter(|(similarity, _)| *similarity >= SIMILARITY_THRESHOLD)
+        .collect();
+    similar.sort_by(|a, b| b.0.total_cmp(&a.0));
+    let mut message = format!("TeX Live has no package `{}`", name);
+    // a package only a source declares has no place in `dtmgr.toml` to point at
+    if let Some(location) = declared_in {
+        message += &format!(", declared in {}", location);
+    }
+    if let Some((_, candidate)) = similar.first() {
+        message += &format!(" (did you mean `{}`?)", candidate);
+    }
+    ConfigProblem { message, span: config_span::dependency(name) }
+}
+
+/// Packages that would end up in the environment twice under different names: `[sources]`
+/// entries taking the same package, or one downloading a package that also comes from TeX Live
+fn duplicates(config: &DtMgrConfig) -> Vec<String> {
+    let mut problems = Vec::new();
+
+    let mut by_origin: Map<String, Vec<&str>> = Map::new();
+    for (name, source) in config.sources.iter() {
+        by_origin.entry(source.origin()).or_default().push(name);
+    }
+    for (origin, names) in by_origin.iter().filter(|(_, names)| names.len() > 1) {
+        problems.push(format!("[sources] {} all take {}", names.join(", "), origin));
+    }
+
+    for (name, source) in config.sources.iter() {
+        if let Some(package) = source.ctan_package()
+            && package != name
+            && config.dependencies.contains(package)
+            && !config.sources.contains_key(package) {
+            problems.push(format!("`{}` comes from TeX Live, and [sources] `{}` downloads it from CTAN as well", package, name));
+        }
+    }
+
+    for package in config.dependencies.intersection(&config.dev_dependencies) {
+        problems.push(format!("`{}` is listed in both `dependencies` and `dev-dependencies`", package));
+    }
+    problems
+}
+
+/// Warns if one collection brings in most of the environment, which is seldom meant
+fn warn_about_large_collection(config: &DtMgrConfig, graph: &DependencyGraph) {
+    let largest = graph.iter()
+        .filter(|node| graph::is_collection(node.name()))
+        .map(|node| (graph.closure([node.name()]).len(), node.name()))
+        .max();
+    if let Some((size, name)) = largest
+        && size as f64 > graph.len() as f64 * COLLECTION_SHARE {
+        let hint = if config.collections.is_full() {
+            "; `collections = \"shallow\"` leaves out the collections it depends on"
+        } else {
+            ""
+        };
+        warn!("`{}` brings in {} of the environment's {} packages{}", name, size, graph.len(), hint);
+    }
+}
+
+/// Checks, without changing anything, that installing `config` on `platform` can work: every
+/// dependency exists, no package is listed twice, the license policy allows every package and
+/// biblatex and biber work together. Every problem found is reported at once.
+pub fn check(config: &DtMgrConfig, platform: &str) -> Result<DependencyGraph, DtMgrError> {
+    let mut problems: Vec<ConfigProblem> = duplicates(config).into_iter().map(ConfigProblem::from).collect();
+
+    let graph = graph::resolve(config, platform, &graph::TlmgrBackend)?;
+    let unknown: Vec<&String> = config.dependencies.iter().chain(config.dev_dependencies.iter())
+        .filter(|dep| !config.sources.contains_key(*dep) && !graph.contains(dep))
+        .collect();
+    if !unknown.is_empty() {
+        let installed = installed_names();
+        problems.extend(unknown.into_iter().map(|name| unknown_dependency(name, config.declared_in.get(name), &installed)));
+    }
+
+    if let Err(e) = licenses::check_license_policy(&config.licenses, graph.packages()) {
+        problems.push(ConfigProblem::from(e.to_string()));
+    }
+    problems.extend(biber::compatibility_problem(graph.packages()).map(ConfigProblem::from));
+    warn_about_large_collection(config, &graph);
+
+    if problems.is_empty() {
+        Ok(graph)
+    } else {
+        Err(DtMgrError::ConfigProblems { problems })
+    }
+}
@@ -0,0 +1,68 @@
+use std::io::IsTerminal;
+use std::sync::atomic::{AtomicBool, Ordering};
+use anstyle::{AnsiColor, Style};
+use clap::ValueEnum;
+
+#[derive(Clone, Copy, Debug, Default, ValueEnum)]
+pub enum ColorChoice {
+    /// Color when writing to a terminal and `NO_COLOR` is not set
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+static STDOUT: AtomicBool = AtomicBool::new(false);
+static STDERR: AtomicBool = AtomicBool::new(false);
+
+pub fn init(choice: ColorChoice) {
+    // https://no-color.org: any non-empty value disables color
+    let no_color = std::env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty());
+    let (stdout, stderr) = match choice {
+        ColorChoice::Always => (true, true),
+        ColorChoice::Never => (false, false),
+        ColorChoice::Auto if no_color => (false, false),
+        ColorChoice::Auto => (std::io::stdout().is_terminal(), std::io::stderr().is_terminal()),
+    };
+    STDOUT.store(stdout, Ordering::Relaxed);
+    STDERR.store(stderr, Ordering::Relaxed);
+}
+
+pub fn stderr_enabled() -> bool {
+    STDERR.load(Ordering::Relaxed)
+}
+
+/// Which stream the painted text is printed to, as each may or may not be a terminal
+#[derive(Clone, Copy)]
+pub enum Stream {
+    Stdout,
+    Stderr,
+}
+
+fn paint(style: Style, text: &str, stream: Stream) -> String {
+    let enabled = match stream {
+        Stream::Stdout => STDOUT.load(Ordering::Relaxed),
+        Stream::Stderr => stderr_enabled(),
+    };
+    if enabled {
+        format!("{style}{text}{style:#}")
+    } else {
+        String::from(text)
+    }
+}
+
+pub fn good(text: &str, stream: Stream) -> String {
+    paint(Style::new().fg_color(Some(AnsiColor::Green.into())), text, stream)
+}
+
+pub fn warning(text: &str, stream: Stream) -> String {
+    paint(Style::new().fg_color(Some(AnsiColor::Yellow.into())), text, stream)
+}
+
+pub fn error(text: &str, stream: Stream) -> String {
+    paint(Style::new().fg_color(Some(AnsiColor::Red.into())).bold(), text, stream)
+}
+
+pub fn emphasis(text: &str, stream: Stream) -> String {
+    paint(Style::new().bold(), text, stream)
+}
@@ -0,0 +1,69 @@
+use crate::DtMgrError;
+
+/// Splits a command written in a config file into program and arguments.
+///
+/// Follows POSIX shell word splitting for quotes and backslashes, but expands
+/// nothing, since the command is never handed to a shell.
+pub fn split_command_line(line: &str) -> Result<Vec<String>, DtMgrError> {
+    let mut words = Vec::new();
+    let mut current = String::new();
+    let mut in_word = false;
+    let mut chars = line.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\'' => {
+                in_word = true;
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some(c) => current.push(c),
+                        None => return Err(DtMgrError::UnterminatedQuote { command: line.to_owned() }),
+                    }
+                }
+            }
+            '"' => {
+                in_word = true;
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some(c @ ('"' | '\\')) => current.push(c),
+                            Some(c) => {
+                                current.push('\\');
+                                current.push(c);
+                            }
+                            None => return Err(DtMgrError::UnterminatedQuote { command: line.to_owned() }),
+                        },
+                        Some(c) => current.push(c),
+                        None => return Err(DtMgrError::UnterminatedQuote { command: line.to_owned() }),
+                    }
+                }
+            }
+            '\\' => {
+                in_word = true;
+                if let Some(c) = chars.next() {
+                    current.push(c);
+                }
+            }
+            c if c.is_whitespace() => {
+                if in_word {
+                    words.push(std::mem::take(&mut current));
+                    in_word = false;
+                }
+            }
+            c => {
+                in_word = true;
+                current.push(c);
+            }
+        }
+    }
+    if in_word {
+        words.push(current);
+    }
+
+    if words.is_empty() {
+        return Err(DtMgrError::EmptyCommand);
+    }
+    Ok(words)
+}
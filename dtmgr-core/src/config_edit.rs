@@ -0,0 +1,115 @@
+use std::collections::BTreeMap as Map;
+use std::path::Path;
+use toml_edit::{Array, DocumentMut, Item, Value};
+use crate::DtMgrError;
+
+fn read_document(path: &Path) -> Result<DocumentMut, DtMgrError> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| DtMgrError::ReadFile { path: path.to_owned(), source: e })?;
+    content.parse::<DocumentMut>()
+        .map_err(|e| DtMgrError::EditConfig { source: e })
+}
+
+fn write_document(path: &Path, document: &DocumentMut) -> Result<(), DtMgrError> {
+    std::fs::write(path, document.to_string())
+        .map_err(|e| DtMgrError::WriteFile { file: path.to_owned(), source: e })
+}
+
+fn dependencies_array(document: &mut DocumentMut) -> Result<&mut Array, DtMgrError> {
+    let item = document.entry("dependencies")
+        .or_insert_with(|| Item::Value(Value::Array(Array::new())));
+    item.as_array_mut().ok_or(DtMgrError::DependenciesNotArray)
+}
+
+/// The `[aliases]` of the document, so that a package listed under a familiar name is found by
+/// its TeX Live name and the other way around
+fn aliases(document: &DocumentMut) -> Map<String, String> {
+    document.get("aliases")
+        .and_then(Item::as_table_like)
+        .map(|table| table.iter()
+            .filter_map(|(name, target)| Some((name.to_owned(), target.as_str()?.to_owned())))
+            .collect())
+        .unwrap_or_default()
+}
+
+/// The package an entry of `dependencies` names, itself or as the `name` of a table with options
+pub(crate) fn listed_name(value: &Value) -> Option<&str> {
+    value.as_str().or_else(|| value.as_inline_table()?.get("name")?.as_str())
+}
+
+fn same_package<'a>(aliases: &'a Map<String, String>, a: &'a str, b: &'a str) -> bool {
+    crate::resolve_alias(aliases, a) == crate::resolve_alias(aliases, b)
+}
+
+/// Appends `packages` to the `dependencies` of the config at `path`, keeping its formatting.
+///
+/// Returns the packages that were not already listed, under their own or an aliased name.
+pub fn add_dependencies<S: AsRef<str>>(path: impl AsRef<Path>, packages: &[S]) -> Result<Vec<String>, DtMgrError> {
+    let mut document = read_document(path.as_ref())?;
+    let aliases = aliases(&document);
+    let dependencies = dependencies_array(&mut document)?;
+
+    let mut added = Vec::new();
+    for package in packages.iter().map(|p| p.as_ref()) {
+        if dependencies.iter().any(|v| listed_name(v).is_some_and(|listed| same_package(&aliases, listed, package))) {
+            continue;
+        }
+
+        // keep one package per line if that is how the array is laid out
+        let multiline = dependencies.iter().last()
+            .and_then(|last| last.decor().prefix())
+            .and_then(|prefix| prefix.as_str())
+            .is_some_and(|prefix| prefix.contains('\n'));
+        if multiline {
+            let mut value = Value::from(package);
+            *value.decor_mut() = dependencies.iter().last().expect("checked above").decor().clone();
+            dependencies.push_formatted(value);
+        } else {
+            dependencies.push(package);
+        }
+        added.push(package.to_owned());
+    }
+
+    if !added.is_empty() {
+        write_document(path.as_ref(), &document)?;
+    }
+    Ok(added)
+}
+
+/// Removes `packages` from the `dependencies` of the config at `path`, keeping its formatting.
+/// A package listed under an alias is removed as well.
+///
+/// Returns the packages that were actually listed.
+pub fn remove_dependencies<S: AsRef<str>>(path: impl AsRef<Path>, packages: &[S]) -> Result<Vec<String>, DtMgrError> {
+    let mut document = read_document(path.as_ref())?;
+    let aliases = aliases(&document);
+    let dependencies = dependencies_array(&mut document)?;
+
+    let mut removed = Vec::new();
+    for package in packages.iter().map(|p| p.as_ref()) {
+        let position = dependencies.iter().position(|v| listed_name(v).is_some_and(|listed| same_package(&aliases, listed, package)));
+        if let Some(idx) = position {
+            dependencies.remove(idx);
+            removed.push(package.to_owned());
+        }
+    }
+
+    if !removed.is_empty() {
+        write_document(path.as_ref(), &document)?;
+    }
+    Ok(removed)
+}
+
+/// Writes a fresh config listing `packages`, one per line
+pub fn write_new_config<S: AsRef<str>>(path: impl AsRef<Path>, packages: &[S]) -> Result<(), DtMgrError> {
+    let mut content = String::from("dependencies = [\n");
+    for package in packages.iter() {
+        content.push_str("    ");
+        content.push_str(&Value::from(package.as_ref()).to_string());
+        content.push_str(",\n");
+    }
+    content.push_str("]\n");
+
+    std::fs::write(path.as_ref(), content)
+        .map_err(|e| DtMgrError::WriteFile { file: path.as_ref().to_owned(), source: e })
+}
@@ -0,0 +1,84 @@
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use serde::Serialize;
+use crate::color::{self, Stream};
+
+// the `dtmgr.toml` `parse_config` read last, so problems found after parsing can point into it
+static CONFIG_SOURCE: Mutex<Option<(PathBuf, String)>> = Mutex::new(None);
+
+/// A place in a configuration file, with the line it is on so that errors can show it
+#[derive(Clone, Debug, Serialize)]
+pub struct ConfigSpan {
+    pub file: PathBuf,
+    pub line: usize,
+    pub column: usize,
+    #[serde(skip)]
+    text: String,
+    /// How many characters of `text` from `column` on the span covers
+    #[serde(skip)]
+    width: usize,
+}
+
+impl ConfigSpan {
+    /// Where the bytes `span` of `content`, as read from `file`, are
+    pub fn new(file: &Path, content: &str, span: Range<usize>) -> ConfigSpan {
+        let start = span.start.min(content.len());
+        let before = &content[..start];
+        let line_start = before.rfind('\n').map_or(0, |idx| idx + 1);
+        let line_end = content[start..].find('\n').map_or(content.len(), |idx| start + idx);
+        let end = span.end.clamp(start, line_end);
+        ConfigSpan {
+            file: file.to_owned(),
+            line: before.matches('\n').count() + 1,
+            column: content[line_start..start].chars().count() + 1,
+            text: content[line_start..line_end].trim_end_matches('\r').to_owned(),
+            width: content[start..end].chars().count().max(1),
+        }
+    }
+
+    /// The line with the span marked and `label` next to it, the way rustc shows code
+    pub fn render(&self, label: &str, stream: Stream) -> String {
+        let number = self.line.to_string();
+        let gutter = " ".repeat(number.len());
+        let bar = color::emphasis("|", stream);
+        // tabs stay tabs, so the marks line up with the line above however wide they are shown
+        let indent: String = self.text.chars()
+            .take(self.column - 1)
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect();
+        let marks = color::error(&"^".repeat(self.width), stream);
+        format!(
+            "{gutter}{} {}:{}:{}\n{gutter} {bar}\n{} {bar} {}\n{gutter} {bar} {indent}{marks} {}",
+            color::emphasis("-->", stream), self.file.display(), self.line, self.column,
+            color::emphasis(&number, stream), self.text,
+            label,
+        )
+    }
+}
+
+/// Keeps the content of the `dtmgr.toml` at `file` for [`dependency`]
+pub(crate) fn remember(file: &Path, content: &str) {
+    if let Ok(mut source) = CONFIG_SOURCE.lock() {
+        *source = Some((file.to_owned(), content.to_owned()));
+    }
+}
+
+/// Where the last `dtmgr.toml` read lists the dependency `name`, under that name or an alias of
+/// it, in `dependencies` or `dev-dependencies`
+pub fn dependency(name: &str) -> Option<ConfigSpan> {
+    let source = CONFIG_SOURCE.lock().ok()?;
+    let (file, content) = source.as_ref()?;
+    let document = toml_edit::Document::parse(content.as_str()).ok()?;
+    let aliases = document.get("aliases").and_then(|aliases| aliases.as_table_like());
+    let listed = ["dependencies", "dev-dependencies"].into_iter()
+        .filter_map(|key| document.get(key)?.as_array())
+        .flat_map(|array| array.iter());
+    let value = listed.into_iter().find(|value| crate::config_edit::listed_name(value).is_some_and(|listed| {
+        listed == name || aliases
+            .and_then(|aliases| aliases.get(listed))
+            .and_then(|alias| alias.as_str())
+            .is_some_and(|alias| alias == name)
+    }))?;
+    Some(ConfigSpan::new(file, content, value.span()?))
+}
@@ -0,0 +1,18 @@
+use std::collections::BTreeMap as Map;
+use crate::TlPObjInfo;
+
+/// The TeX Live package with ConTeXt and its `mtxrun` and `context` scripts
+const CONTEXT_PACKAGE: &str = "context";
+
+/// What tlmgr runs after installing ConTeXt, and `dtmgr install` in an environment with it.
+///
+/// ConTeXt finds files through its own database in `TEXMFCACHE` rather than `ls-R`, and its
+/// formats are not in `fmtutil.cnf`, so neither the `ls-R` databases nor `fmtutil-sys` prepare it.
+pub const STEPS: [(&str, &[&str]); 2] = [
+    ("generating the ConTeXt file database", &["mtxrun", "--generate"]),
+    ("building the ConTeXt format", &["context", "--make"]),
+];
+
+pub fn in_closure(dep_tree: &Map<String, TlPObjInfo>) -> bool {
+    dep_tree.contains_key(CONTEXT_PACKAGE)
+}
@@ -0,0 +1,153 @@
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, PoisonError};
+use serde::Deserialize;
+use sha3::{Digest, Sha3_256};
+use tracing::{debug, info, warn};
+use crate::lockfile::{LockedArchive, Lockfile};
+use crate::{cache, home, http, network, DtMgrError};
+
+const CTAN_API: &str = "https://ctan.org/json/2.0/pkg";
+const CTAN_MIRROR: &str = "https://mirrors.ctan.org";
+
+// sources are fetched in parallel, each recording its download in the same lockfile
+static LOCKFILE: Mutex<()> = Mutex::new(());
+
+// https://ctan.org/help/json/2.0/pkg, only the fields dtmgr reads
+#[derive(Debug, Deserialize)]
+struct CtanPackage {
+    version: Option<CtanVersion>,
+    ctan: Option<CtanLocation>,
+    /// Of the package's TDS archive, below `/install`, if it has one
+    install: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CtanVersion {
+    number: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CtanLocation {
+    path: String,
+}
+
+/// The version CTAN has of `package` and where its archive is on a mirror, preferring the TDS
+/// archive over the package's directory
+fn current_archive(package: &str) -> Result<(String, String), DtMgrError> {
+    let url = format!("{}/{}", CTAN_API, package);
+    let body = network::retry(|| http::get(&url))?;
+    let info: CtanPackage = serde_json::from_slice(&body)
+        .map_err(|e| DtMgrError::JsonParse { source: e })?;
+
+    let version = info.version.and_then(|v| v.number).unwrap_or_default();
+    let path = match (info.install, info.ctan) {
+        (Some(install), _) => format!("/install{}", install),
+        (None, Some(location)) => format!("{}.zip", location.path.trim_end_matches('/')),
+        (None, None) => return Err(DtMgrError::NotOnCtan { package: package.to_owned() }),
+    };
+    Ok((version, path))
+}
+
+/// Downloads the archive at `path` from the mirror `dtmgr mirror select` picked and
+/// `mirrors.ctan.org`, or from the `[network]` mirrors in turn if those keep failing
+fn download(path: &str) -> Result<Vec<u8>, DtMgrError> {
+    let mirrors: Vec<String> = network::mirror().into_iter()
+        .chain(std::iter::once(CTAN_MIRROR.to_owned()))
+        .chain(network::ctan_mirrors())
+        .collect();
+    for (i, mirror) in mirrors.iter().enumerate() {
+        let url = format!("{}{}", mirror, path);
+        match network::retry(|| http::get(&url)) {
+            Err(e) if i + 1 < mirrors.len() && network::is_transient(&e) => {
+                warn!("{}, trying {}", e, mirrors[i + 1]);
+            }
+            result => return result,
+        }
+    }
+    unreachable!("there is always at least one mirror")
+}
+
+fn extract(archive: &[u8], url: &str, dir: &Path) -> Result<(), DtMgrError> {
+    // extracted next to the final directory, which only appears once it is complete
+    let mut partial = dir.as_os_str().to_owned();
+    partial.push(".partial");
+    let partial = PathBuf::from(partial);
+    let _ = std::fs::remove_dir_all(&partial);
+
+    let extracted = zip::ZipArchive::new(Cursor::new(archive))
+        .and_then(|mut zip| zip.extract(&partial));
+    if let Err(e) = extracted {
+        let _ = std::fs::remove_dir_all(&partial);
+        return Err(DtMgrError::Extract { url: url.to_owned(), source: e });
+    }
+    std::fs::rename(&partial, dir)
+        .map_err(|e| DtMgrError::MoveDirectory { from: partial, to: dir.to_owned(), source: e })
+}
+
+/// The package's files as extracted: a TDS archive is a tree by itself, a package's directory
+/// archive holds the directory
+fn package_dir(extracted: &Path, package: &str) -> PathBuf {
+    let inner = extracted.join(package);
+    if extracted.join("tex").is_dir() || !inner.is_dir() {
+        extracted.to_owned()
+    } else {
+        inner
+    }
+}
+
+/// Downloads `package` from CTAN into the cache, unless the version the lockfile records for
+/// `name` is there already, and returns where it was extracted.
+///
+/// The first download of a version is recorded in the lockfile; later ones have to match it.
+pub fn fetch(name: &str, package: &str, version: Option<&str>, dtmgr_directory: &Path) -> Result<PathBuf, DtMgrError> {
+    let ctan_dir = home::cache_dir()?.join("ctan");
+    let lockfile = Lockfile::read(dtmgr_directory)?;
+    let extracted_dir = |locked: &LockedArchive| ctan_dir.join(format!("{}-{}-{}", package, locked.version, &locked.sha3_256[..12]));
+
+    let locked = lockfile.ctan.get(name)
+        .filter(|locked| version.is_none_or(|v| v == locked.version));
+    if let Some(locked) = locked {
+        let dir = extracted_dir(locked);
+        if dir.is_dir() {
+            debug!("using {} {} from {}", package, locked.version, dir.display());
+            cache::mark_used(&dir);
+            return Ok(package_dir(&dir, package));
+        }
+    }
+
+    if network::offline() {
+        return Err(DtMgrError::Offline { needed: format!("`{}` from CTAN", package) });
+    }
+    let (current, path) = current_archive(package)?;
+    if let Some(version) = version
+        && version != current {
+        // CTAN only serves the current version of a package
+        return Err(DtMgrError::CtanVersionMismatch { package: package.to_owned(), wanted: version.to_owned(), available: current });
+    }
+
+    info!("downloading {} {} from CTAN", package, current);
+    let archive = download(&path)?;
+    let hash: [u8; 32] = Sha3_256::digest(&archive).into();
+    // recorded as on the redirector, whichever mirror it came from
+    let url = format!("{}{}", CTAN_MIRROR, path);
+    let downloaded = LockedArchive { version: current, url, sha3_256: hex::encode(hash) };
+    if let Some(locked) = locked
+        && locked.sha3_256 != downloaded.sha3_256 {
+        return Err(DtMgrError::ChecksumMismatch { url: downloaded.url, locked: locked.sha3_256.clone(), downloaded: downloaded.sha3_256 });
+    }
+
+    let dir = extracted_dir(&downloaded);
+    if !dir.is_dir() {
+        extract(&archive, &downloaded.url, &dir)?;
+    }
+    if locked.is_none() {
+        let _guard = LOCKFILE.lock().unwrap_or_else(PoisonError::into_inner);
+        // read again, as another source may have been recorded since
+        let mut lockfile = Lockfile::read(dtmgr_directory)?;
+        lockfile.ctan.insert(name.to_owned(), downloaded);
+        lockfile.write(dtmgr_directory)?;
+    }
+    cache::mark_used(&dir);
+    Ok(package_dir(&dir, package))
+}
@@ -0,0 +1,30 @@
+use std::path::Path;
+use crate::DtMgrError;
+
+/// Package names listed in an l3build/TeX Live `DEPENDS.txt`.
+///
+/// Each line is either a bare package name or `hard <name>`/`soft <name>`;
+/// `package <name>` lines only start a new section and are skipped.
+pub fn parse_depends_txt(content: &str) -> Vec<String> {
+    let mut result = Vec::new();
+    for line in content.lines() {
+        let line = line.split(['#', '%']).next().unwrap_or_default();
+        let mut words = line.split_whitespace();
+        let name = match words.next() {
+            Some("hard") | Some("soft") => words.next(),
+            Some("package") => None,
+            other => other,
+        };
+        if let Some(name) = name
+            && !result.iter().any(|n| n == name) {
+            result.push(name.to_owned());
+        }
+    }
+    result
+}
+
+pub fn read_depends_txt(path: impl AsRef<Path>) -> Result<Vec<String>, DtMgrError> {
+    let content = std::fs::read_to_string(path.as_ref())
+        .map_err(|e| DtMgrError::ReadFile { path: path.as_ref().to_owned(), source: e })?;
+    Ok(parse_depends_txt(&content))
+}
@@ -0,0 +1,23 @@
+use std::path::Path;
+use crate::TlPObjInfo;
+
+/// The package's PDF manuals, as linked into `dot_dir`
+pub fn print_pdf_docfiles(pkg: &TlPObjInfo, dot_dir: &Path) {
+    let pdfs: Vec<_> = pkg.docfiles.iter().flatten()
+        .filter(|doc| doc.file.ends_with(".pdf"))
+        .collect();
+    if pdfs.is_empty() {
+        println!("`{}` ships no PDF documentation", pkg.name);
+        return;
+    }
+
+    for doc in pdfs {
+        let mut line = dot_dir.join(&doc.file).display().to_string();
+        // e.g. "Package documentation" and "de" for a German manual
+        let notes: Vec<&str> = [&doc.detail, &doc.lang].into_iter().flatten().map(String::as_str).collect();
+        if !notes.is_empty() {
+            line.push_str(&format!(" ({})", notes.join(", ")));
+        }
+        println!("{}", line);
+    }
+}
@@ -0,0 +1,103 @@
+use std::path::{Path, PathBuf};
+use clap::ValueEnum;
+use serde_json::{json, Map, Value};
+use crate::build::BuildConfig;
+use crate::DtMgrError;
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum Editor {
+    Vscode,
+}
+
+// what editors pass to latexmk on top of the project's own options
+const EDITOR_LATEXMK_OPTIONS: [&str; 3] = ["-synctex=1", "-interaction=nonstopmode", "-file-line-error"];
+
+/// Where the extension's settings point
+pub struct EditorEnvironment<'a> {
+    /// This executable, which builds through `dtmgr run` so the editor needs no environment of its own
+    pub dtmgr: PathBuf,
+    /// `bin/<platform>` inside `.dtmgr`
+    pub bin_dir: PathBuf,
+    pub build: &'a BuildConfig,
+}
+
+impl EditorEnvironment<'_> {
+    fn tool(&self, name: &str) -> String {
+        self.bin_dir.join(String::from(name) + std::env::consts::EXE_SUFFIX).display().to_string()
+    }
+
+    fn build_args(&self, file_placeholder: &str) -> Vec<String> {
+        let extra: Vec<String> = EDITOR_LATEXMK_OPTIONS.iter().map(|o| String::from(*o)).collect();
+        let mut args = vec![String::from("run"), String::from("latexmk")];
+        args.extend(self.build.latexmk_arguments(&extra));
+        args.push(String::from(file_placeholder));
+        args
+    }
+
+    fn output_dir(&self) -> Option<String> {
+        self.build.output_dir().map(|d| d.display().to_string())
+    }
+
+    fn latex_workshop_settings(&self) -> Vec<(&'static str, Value)> {
+        let mut settings = vec![
+            ("latex-workshop.latex.tools", json!([{
+                "name": "dtmgr",
+                "command": self.dtmgr.display().to_string(),
+                "args": self.build_args("%DOC%"),
+            }])),
+            ("latex-workshop.latex.recipes", json!([{ "name": "dtmgr", "tools": ["dtmgr"] }])),
+            // package and citation completion
+            ("latex-workshop.kpsewhich.path", json!(self.tool("kpsewhich"))),
+            ("latex-workshop.synctex.path", json!(self.tool("synctex"))),
+        ];
+        if let Some(output_dir) = self.output_dir() {
+            settings.push(("latex-workshop.latex.outDir", json!(format!("%DIR%/{}", output_dir))));
+        }
+        settings
+    }
+
+    fn texlab_settings(&self) -> Vec<(&'static str, Value)> {
+        let mut settings = vec![
+            ("texlab.build.executable", json!(self.dtmgr.display().to_string())),
+            ("texlab.build.args", json!(self.build_args("%f"))),
+        ];
+        if let Some(output_dir) = self.output_dir() {
+            settings.push(("texlab.build.auxDirectory", json!(output_dir)));
+            settings.push(("texlab.build.pdfDirectory", json!(output_dir)));
+        }
+        settings
+    }
+}
+
+/// Adds the settings to `.vscode/settings.json`, keeping whatever else it configures
+pub fn write_vscode_settings(project_dir: &Path, environment: &EditorEnvironment, texlab: bool) -> Result<PathBuf, DtMgrError> {
+    let vscode_dir = project_dir.join(".vscode");
+    std::fs::create_dir_all(&vscode_dir)
+        .map_err(|e| DtMgrError::CreateDirectory { dir: vscode_dir.clone(), source: e })?;
+
+    let settings_file = vscode_dir.join("settings.json");
+    let mut settings: Map<String, Value> = if settings_file.is_file() {
+        let content = std::fs::read(&settings_file)
+            .map_err(|e| DtMgrError::ReadFile { path: settings_file.clone(), source: e })?;
+        serde_json::from_slice(&content)
+            .map_err(|e| DtMgrError::JsonParse { source: e })?
+    } else {
+        Map::new()
+    };
+
+    let new_settings = if texlab {
+        environment.texlab_settings()
+    } else {
+        environment.latex_workshop_settings()
+    };
+    for (key, value) in new_settings {
+        settings.insert(String::from(key), value);
+    }
+
+    let mut content = serde_json::to_string_pretty(&settings)
+        .map_err(|e| DtMgrError::JsonSerialize { source: e })?;
+    content.push('\n');
+    std::fs::write(&settings_file, content)
+        .map_err(|e| DtMgrError::WriteFile { file: settings_file.clone(), source: e })?;
+    Ok(settings_file)
+}
@@ -0,0 +1,66 @@
+use std::ffi::OsString;
+use clap::ValueEnum;
+use crate::DtMgrError;
+
+#[derive(Clone, Copy, Debug, Default, ValueEnum)]
+pub enum EnvFormat {
+    /// `export NAME='value'` lines for POSIX shells
+    #[default]
+    Shell,
+    /// A JSON object of names to values
+    Json,
+    /// `export NAME := value` lines to include in a Makefile
+    Make,
+    /// Lines to append to `$GITHUB_ENV` in a GitHub Actions step
+    Github,
+}
+
+pub fn shell_quote(value: &str) -> String {
+    String::from("'") + &value.replace('\'', "'\\''") + "'"
+}
+
+// a delimiter that cannot end the value early
+fn heredoc_delimiter(value: &str) -> String {
+    let mut delimiter = String::from("DTMGR_EOF");
+    while value.contains(&delimiter) {
+        delimiter.push('_');
+    }
+    delimiter
+}
+
+/// The environment's variables in `format`, one assignment per line
+pub fn render_env(format: EnvFormat, variables: &[(&str, OsString)]) -> Result<String, DtMgrError> {
+    // none of the formats can carry arbitrary bytes
+    let variables = variables.iter()
+        .map(|(name, value)| match value.to_str() {
+            Some(value) => Ok((*name, String::from(value))),
+            None => Err(DtMgrError::NotUnicode { what: format!("the value of {}", name) }),
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    if let EnvFormat::Json = format {
+        let object: serde_json::Map<String, serde_json::Value> = variables.iter()
+            .map(|(name, value)| (String::from(*name), serde_json::Value::from(value.as_str())))
+            .collect();
+        let mut result = serde_json::to_string_pretty(&object)
+            .map_err(|e| DtMgrError::JsonSerialize { source: e })?;
+        result.push('\n');
+        return Ok(result);
+    }
+
+    let mut result = String::new();
+    for (name, value) in variables.iter() {
+        let line = match format {
+            EnvFormat::Shell => format!("export {}={}\n", name, shell_quote(value)),
+            EnvFormat::Make => format!("export {} := {}\n", name, value.replace('$', "$$")),
+            EnvFormat::Github if value.contains('\n') => {
+                let delimiter = heredoc_delimiter(value);
+                format!("{}<<{}\n{}\n{}\n", name, delimiter, value, delimiter)
+            }
+            EnvFormat::Github => format!("{}={}\n", name, value),
+            EnvFormat::Json => unreachable!("handled above"),
+        };
+        result.push_str(&line);
+    }
+    Ok(result)
+}
@@ -0,0 +1,195 @@
+use std::error::Error;
+use std::path::Path;
+use clap::ValueEnum;
+use serde::Serialize;
+use crate::color::{self, Stream};
+use crate::config_span::ConfigSpan;
+use crate::DtMgrError;
+
+#[derive(Clone, Copy, Debug, Default, ValueEnum)]
+pub enum ErrorFormat {
+    #[default]
+    Human,
+    /// A JSON object with the error's code, message, sources, paths and locations in `dtmgr.toml`
+    Json,
+}
+
+impl DtMgrError {
+    /// An identifier for the kind of error that stays the same across releases,
+    /// unlike the message
+    pub fn code(&self) -> &'static str {
+        match self {
+            DtMgrError::ParseConfig { .. } => "parse-config",
+            DtMgrError::UnknownConfigKey { .. } => "unknown-config-key",
+            DtMgrError::ReadFile { .. } => "read-file",
+            DtMgrError::HashConfig { .. } => "hash-config",
+            DtMgrError::CommandExecution { .. } => "command-execution",
+            DtMgrError::CommandStatus { .. } => "command-status",
+            DtMgrError::JsonParse { .. } => "json-parse",
+            DtMgrError::JsonSerialize { .. } => "json-serialize",
+            DtMgrError::CurrentDirectory { .. } => "current-directory",
+            DtMgrError::FindConfig { .. } => "find-config",
+            DtMgrError::CreateDirectory { .. } => "create-directory",
+            DtMgrError::WriteFile { .. } => "write-file",
+            DtMgrError::CreateSymlink { .. } => "create-symlink",
+            DtMgrError::LicensePolicy { .. } => "license-policy",
+            DtMgrError::EditConfig { .. } => "edit-config",
+            DtMgrError::DependenciesNotArray => "dependencies-not-array",
+            DtMgrError::NoProvider { .. } => "no-provider",
+            DtMgrError::AmbiguousProvider { .. } => "ambiguous-provider",
+            DtMgrError::MissingRecorderFile { .. } => "missing-recorder-file",
+            DtMgrError::ConfigExists { .. } => "file-exists",
+            DtMgrError::UnknownDocument { .. } => "unknown-document",
+            DtMgrError::AmbiguousDocument { .. } => "ambiguous-document",
+            DtMgrError::UnknownScript { .. } => "unknown-script",
+            DtMgrError::UnknownTemplate { .. } => "unknown-template",
+            DtMgrError::DirectoryNotEmpty { .. } => "directory-not-empty",
+            DtMgrError::UnknownPackage { .. } => "unknown-package",
+            DtMgrError::NotInEnvironment { .. } => "not-in-environment",
+            DtMgrError::ToolNotProvided { .. } => "tool-not-provided",
+            DtMgrError::CurrentExecutable { .. } => "current-executable",
+            DtMgrError::UnterminatedQuote { .. } => "unterminated-quote",
+            DtMgrError::EmptyCommand => "empty-command",
+            DtMgrError::Watch { .. } => "watch",
+            DtMgrError::NotUnicode { .. } => "not-unicode",
+            DtMgrError::JoinPaths { .. } => "join-paths",
+            DtMgrError::PathContainsSeparator { .. } => "path-contains-separator",
+            DtMgrError::MoveDirectory { .. } => "move-directory",
+            DtMgrError::InvalidSource { .. } => "invalid-source",
+            DtMgrError::NoCacheDirectory => "no-cache-directory",
+            DtMgrError::NoDataDirectory => "no-data-directory",
+            DtMgrError::NoConfigDirectory => "no-config-directory",
+            DtMgrError::NoMirrorReachable { .. } => "no-mirror-reachable",
+            DtMgrError::ConfigProblems { .. } => "config-problems",
+            DtMgrError::Terminal { .. } => "terminal",
+            DtMgrError::RevisionNotFound { .. } => "revision-not-found",
+            DtMgrError::UnknownSourceLayout { .. } => "unknown-source-layout",
+            DtMgrError::Download { .. } => "download",
+            DtMgrError::Extract { .. } => "extract",
+            DtMgrError::NotOnCtan { .. } => "not-on-ctan",
+            DtMgrError::CtanVersionMismatch { .. } => "ctan-version-mismatch",
+            DtMgrError::ChecksumMismatch { .. } => "checksum-mismatch",
+            DtMgrError::ParseLockfile { .. } => "parse-lockfile",
+            DtMgrError::SerializeLockfile { .. } => "serialize-lockfile",
+            DtMgrError::InvalidOverride { .. } => "invalid-override",
+            DtMgrError::ParsePatch { .. } => "parse-patch",
+            DtMgrError::ApplyPatch { .. } => "apply-patch",
+            DtMgrError::InvalidGlob { .. } => "invalid-glob",
+            DtMgrError::Upload { .. } => "upload",
+            DtMgrError::CreateArchive { .. } => "create-archive",
+            DtMgrError::UnpackArchive { .. } => "unpack-archive",
+            DtMgrError::ArchiveChecksum { .. } => "archive-checksum",
+            DtMgrError::ArchivePlatform { .. } => "archive-platform",
+            DtMgrError::NoWorkspaceMembers => "no-workspace-members",
+            DtMgrError::UnknownMember { .. } => "unknown-member",
+            DtMgrError::CommandTimeout { .. } => "command-timeout",
+            DtMgrError::InvalidProxy => "invalid-proxy",
+            DtMgrError::Offline { .. } => "offline",
+            DtMgrError::InvalidReleaseVersion { .. } => "invalid-release-version",
+            DtMgrError::MissingReleaseAsset { .. } => "missing-release-asset",
+            DtMgrError::ReleaseChecksum { .. } => "release-checksum",
+            DtMgrError::ReplaceExecutable { .. } => "replace-executable",
+            DtMgrError::FontDirectoryNotFound { .. } => "font-directory-not-found",
+            DtMgrError::WorkingDirectoryNotFound { .. } => "working-directory-not-found",
+            DtMgrError::CannotRepair { .. } => "cannot-repair",
+            DtMgrError::TexliveRootNotFound { .. } => "texlive-root-not-found",
+            DtMgrError::TexliveRootAndToolchain => "texlive-root-and-toolchain",
+            DtMgrError::InvalidToolchain { .. } => "invalid-toolchain",
+            DtMgrError::UnknownToolchain { .. } => "unknown-toolchain",
+            DtMgrError::ToolchainNotInstalled { .. } => "toolchain-not-installed",
+            DtMgrError::TreeNotFound { .. } => "tree-not-found",
+            DtMgrError::InvalidKpathseaVariable { .. } => "invalid-kpathsea-variable",
+            DtMgrError::RemoveDirectory { .. } => "remove-directory",
+        }
+    }
+
+    /// The files and directories the error is about
+    pub fn paths(&self) -> Vec<&Path> {
+        match self {
+            DtMgrError::ReadFile { path, .. } => vec![path],
+            DtMgrError::FindConfig { cwd } => vec![cwd],
+            DtMgrError::CreateDirectory { dir, .. } => vec![dir],
+            DtMgrError::WriteFile { file, .. } => vec![file],
+            DtMgrError::CreateSymlink { src, dst, .. } => vec![src, dst],
+            DtMgrError::MissingRecorderFile { dir } => vec![dir],
+            DtMgrError::ConfigExists { path } => vec![path],
+            DtMgrError::DirectoryNotEmpty { dir } => vec![dir],
+            DtMgrError::PathContainsSeparator { path, .. } => vec![path],
+            DtMgrError::MoveDirectory { from, to, .. } => vec![from, to],
+            DtMgrError::UnknownSourceLayout { dir } => vec![dir],
+            DtMgrError::ParsePatch { patch, .. } => vec![patch],
+            DtMgrError::ApplyPatch { patch, .. } => vec![patch],
+            DtMgrError::CreateArchive { dir, .. } => vec![dir],
+            DtMgrError::UnpackArchive { dir, .. } => vec![dir],
+            DtMgrError::ArchiveChecksum { file } => vec![file],
+            DtMgrError::ReplaceExecutable { exe, .. } => vec![exe],
+            DtMgrError::FontDirectoryNotFound { dir } => vec![dir],
+            DtMgrError::WorkingDirectoryNotFound { dir } => vec![dir],
+            DtMgrError::TexliveRootNotFound { dir } => vec![dir],
+            DtMgrError::TreeNotFound { dir } => vec![dir],
+            DtMgrError::RemoveDirectory { dir, .. } => vec![dir],
+            _ => Vec::new(),
+        }
+    }
+
+    /// The places in `dtmgr.toml` the error is about, each with what is wrong there
+    pub fn locations(&self) -> Vec<(&ConfigSpan, String)> {
+        match self {
+            DtMgrError::ParseConfig { source, span: Some(span) } => vec![(span, source.message().to_owned())],
+            DtMgrError::UnknownConfigKey { span, .. } => vec![(span, String::from("unknown key"))],
+            DtMgrError::ConfigProblems { problems } => problems.iter()
+                .filter_map(|problem| problem.span.as_ref().map(|span| (span, problem.message.clone())))
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct ErrorReport<'a> {
+    code: &'static str,
+    message: String,
+    /// Messages of the underlying errors, outermost first
+    sources: Vec<String>,
+    paths: Vec<&'a Path>,
+    locations: Vec<Location<'a>>,
+}
+
+#[derive(Serialize)]
+struct Location<'a> {
+    #[serde(flatten)]
+    span: &'a ConfigSpan,
+    message: String,
+}
+
+pub fn report_error(err: &DtMgrError, format: ErrorFormat) {
+    match format {
+        ErrorFormat::Human => {
+            let mut message = err.to_string();
+            if let DtMgrError::CommandStatus { command, .. } = err {
+                message = message.replacen(command.as_str(), &color::emphasis(command, Stream::Stderr), 1);
+            }
+            eprintln!("{} {}", color::error("error:", Stream::Stderr), message);
+            for (span, label) in err.locations() {
+                eprintln!("{}", span.render(&label, Stream::Stderr));
+            }
+        }
+        ErrorFormat::Json => {
+            let mut sources = Vec::new();
+            let mut source = err.source();
+            while let Some(cause) = source {
+                sources.push(cause.to_string());
+                source = cause.source();
+            }
+
+            let report = ErrorReport {
+                code: err.code(), message: err.to_string(), sources, paths: err.paths(),
+                locations: err.locations().into_iter().map(|(span, message)| Location { span, message }).collect(),
+            };
+            match serde_json::to_string(&report) {
+                Ok(json) => eprintln!("{}", json),
+                Err(_) => eprintln!("{}", err),
+            }
+        }
+    }
+}
@@ -0,0 +1,53 @@
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use serde::Serialize;
+
+type Listener = Arc<dyn Fn(&Event) + Send + Sync>;
+
+static LISTENER: Mutex<Option<Listener>> = Mutex::new(None);
+
+/// Something [`crate::install`] is doing, for frontends that show progress their own way rather
+/// than read dtmgr's output
+#[derive(Clone, Debug, Serialize)]
+#[serde(tag = "event", rename_all = "kebab-case")]
+pub enum Event {
+    /// [`crate::install`] started; it may find the environment up to date and finish right away
+    InstallStarted { project: PathBuf },
+    ResolutionStarted,
+    Resolved { packages: usize },
+    /// The `number`th of `total` packages was linked into the environment
+    PackageLinked { package: String, number: usize, total: usize },
+    /// The `number`th of `total` steps that finish the environment started, e.g. building formats
+    StepStarted { description: String, number: u64, total: u64 },
+    /// A tool the install ran exited, with its exit code unless it was ended by a signal
+    CommandFinished { command: String, seconds: f64, status: Option<i32> },
+    /// A warning dtmgr logged; these only arrive with [`crate::report::Warnings`] among the
+    /// layers of the `tracing` subscriber
+    Warning { message: String },
+    InstallFinished { succeeded: bool },
+}
+
+/// Calls `listener` with every [`Event`] from now on, replacing any earlier listener.
+///
+/// It is called on whichever thread the event happens on, so it should hand the event on, e.g.
+/// to a channel, rather than call into dtmgr.
+pub fn set_listener(listener: impl Fn(&Event) + Send + Sync + 'static) {
+    if let Ok(mut current) = LISTENER.lock() {
+        *current = Some(Arc::new(listener));
+    }
+}
+
+pub fn clear_listener() {
+    if let Ok(mut current) = LISTENER.lock() {
+        *current = None;
+    }
+}
+
+pub(crate) fn emit(event: Event) {
+    // called without the lock held, so that a listener logging a warning, which comes back
+    // here through `report::Warnings`, does not wait on itself
+    let listener = LISTENER.lock().ok().and_then(|listener| listener.clone());
+    if let Some(listener) = listener {
+        listener(&event);
+    }
+}
@@ -0,0 +1,56 @@
+use std::collections::BTreeMap as Map;
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use crate::{DtMgrConfig, DtMgrError};
+
+fn glob_set(patterns: &[String]) -> Result<GlobSet, DtMgrError> {
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns.iter() {
+        let glob = Glob::new(pattern)
+            .map_err(|e| DtMgrError::InvalidGlob { pattern: pattern.clone(), source: e })?;
+        builder.add(glob);
+    }
+    builder.build()
+        .map_err(|e| DtMgrError::InvalidGlob { pattern: patterns.join(", "), source: e })
+}
+
+/// The files `exclude`, `[package-exclude]` and the options of dependencies keep out of the
+/// environment
+pub struct Exclusions {
+    all: GlobSet,
+    packages: Map<String, GlobSet>,
+    /// The `docs` option of the packages that have one
+    docs: Map<String, bool>,
+}
+
+impl Exclusions {
+    pub fn new(config: &DtMgrConfig) -> Result<Exclusions, DtMgrError> {
+        let mut patterns = config.package_exclude.clone();
+        for (name, options) in config.package_options.iter() {
+            patterns.entry(name.clone()).or_default().extend(options.exclude.iter().cloned());
+        }
+        let packages = patterns.iter()
+            .map(|(name, patterns)| Ok((name.clone(), glob_set(patterns)?)))
+            .collect::<Result<_, DtMgrError>>()?;
+        let docs = config.package_options.iter()
+            .filter_map(|(name, options)| Some((name.clone(), options.docs?)))
+            .collect();
+        Ok(Exclusions { all: glob_set(&config.exclude)?, packages, docs })
+    }
+
+    /// Whether `package`'s `file`, a path in TeX Live, is left out.
+    ///
+    /// Patterns may leave out the leading `texmf-dist/`, so `source/**` skips every source tree.
+    pub fn excludes(&self, package: &str, file: &str) -> bool {
+        let in_tree = file.strip_prefix("texmf-dist/").unwrap_or(file);
+        // where TeX Live keeps every package's documentation
+        let is_doc = in_tree.starts_with("doc/");
+        let all = match (is_doc, self.docs.get(package)) {
+            (true, Some(false)) => return true,
+            (true, Some(true)) => None,
+            _ => Some(&self.all),
+        };
+        all.into_iter()
+            .chain(self.packages.get(package))
+            .any(|set| set.is_match(file) || set.is_match(in_tree))
+    }
+}
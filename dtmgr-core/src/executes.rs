@@ -0,0 +1,275 @@
+use std::collections::BTreeMap as Map;
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+use tracing::{debug, warn};
+use crate::{command_line, DtMgrError, TlPObjInfo};
+
+const FMTUTIL_CNF: &str = "texmf-dist/web2c/fmtutil.cnf";
+const UPDMAP_CFG: &str = "texmf-dist/web2c/updmap.cfg";
+
+/// Files TeX Live generates from `execute` directives rather than shipping, so they are written
+/// for the environment instead of linked
+pub const GENERATED_RUNFILES: [&str; 2] = [FMTUTIL_CNF, UPDMAP_CFG];
+
+// where tlmgr writes the hyphenation databases, inside TEXMFSYSVAR
+const LANGUAGE_DAT: &str = "texmf-var/tex/generic/config/language.dat";
+const LANGUAGE_DEF: &str = "texmf-var/tex/generic/config/language.def";
+const LANGUAGE_DAT_LUA: &str = "texmf-var/tex/generic/config/language.dat.lua";
+
+/// The generated files `fmtutil-sys` builds the formats from
+pub const FORMAT_FILES: [&str; 4] = [FMTUTIL_CNF, LANGUAGE_DAT, LANGUAGE_DEF, LANGUAGE_DAT_LUA];
+/// The generated file `updmap-sys` merges the font maps from
+pub const MAP_FILES: [&str; 1] = [UPDMAP_CFG];
+
+// the headers tlmgr starts each generated file with, shipped by kpathsea, texlive-scripts and hyphen-base
+const FMTUTIL_HEADER: &str = "texmf-dist/web2c/fmtutil-hdr.cnf";
+const UPDMAP_HEADER: &str = "texmf-dist/web2c/updmap-hdr.cfg";
+const LANGUAGE_DAT_HEADER: &str = "texmf-dist/tex/generic/config/language.us";
+const LANGUAGE_DEF_HEADER: &str = "texmf-dist/tex/generic/config/language.us.def";
+const LANGUAGE_LUA_HEADER: &str = "texmf-dist/tex/generic/config/language.us.lua";
+
+/// An `AddFormat` directive, one line of `fmtutil.cnf`
+struct Format {
+    name: String,
+    engine: String,
+    patterns: String,
+    options: String,
+    disabled: bool,
+}
+
+/// An `AddHyphen` directive, one language in the hyphenation databases
+struct Hyphen {
+    name: String,
+    synonyms: Vec<String>,
+    lefthyphenmin: String,
+    righthyphenmin: String,
+    file: String,
+    file_patterns: Option<String>,
+    file_exceptions: Option<String>,
+    luaspecial: Option<String>,
+    databases: Vec<String>,
+}
+
+impl Hyphen {
+    fn in_database(&self, database: &str) -> bool {
+        self.databases.iter().any(|d| d == database)
+    }
+}
+
+/// The `key=value` arguments of a directive; values may be double quoted
+pub fn arguments(arguments: &str) -> Result<Map<String, String>, DtMgrError> {
+    Ok(command_line::split_command_line(arguments)?
+        .into_iter()
+        .filter_map(|word| word.split_once('=').map(|(k, v)| (k.to_owned(), v.to_owned())))
+        .collect())
+}
+
+fn format(args: &mut Map<String, String>) -> Option<Format> {
+    Some(Format {
+        name: args.remove("name")?,
+        engine: args.remove("engine")?,
+        patterns: args.remove("patterns").unwrap_or_else(|| String::from("-")),
+        options: args.remove("options").unwrap_or_default(),
+        disabled: args.get("mode").is_some_and(|m| m == "disabled"),
+    })
+}
+
+fn hyphen(args: &mut Map<String, String>) -> Option<Hyphen> {
+    Some(Hyphen {
+        name: args.remove("name")?,
+        synonyms: args.remove("synonyms")
+            .map(|s| s.split(',').filter(|s| !s.is_empty()).map(String::from).collect())
+            .unwrap_or_default(),
+        lefthyphenmin: args.remove("lefthyphenmin").unwrap_or_default(),
+        righthyphenmin: args.remove("righthyphenmin").unwrap_or_default(),
+        file: args.remove("file")?,
+        file_patterns: args.remove("file_patterns").filter(|f| !f.is_empty()),
+        file_exceptions: args.remove("file_exceptions").filter(|f| !f.is_empty()),
+        luaspecial: args.remove("luaspecial"),
+        databases: args.remove("databases")
+            .unwrap_or_else(|| String::from("dat,def,lua"))
+            .split(',')
+            .map(String::from)
+            .collect(),
+    })
+}
+
+/// The content of the files `execute` directives contribute to, before their headers
+#[derive(Default)]
+struct Contributions {
+    formats: Vec<(String, Format)>,
+    maps: Vec<(String, String)>,
+    hyphens: Vec<(String, Hyphen)>,
+}
+
+fn collect(dep_tree: &Map<String, TlPObjInfo>) -> Contributions {
+    let mut result = Contributions::default();
+    for pkg in dep_tree.values() {
+        for execute in pkg.executes.iter().flatten() {
+            let (directive, rest) = execute.split_once(' ').unwrap_or((execute.as_str(), ""));
+            let understood = match directive {
+                "AddFormat" => arguments(rest).ok()
+                    .and_then(|mut args| format(&mut args))
+                    .map(|f| result.formats.push((pkg.name.clone(), f)))
+                    .is_some(),
+                "AddHyphen" => arguments(rest).ok()
+                    .and_then(|mut args| hyphen(&mut args))
+                    .map(|h| result.hyphens.push((pkg.name.clone(), h)))
+                    .is_some(),
+                "addMap" | "addMixedMap" | "addKanjiMap" => {
+                    let kind = match directive {
+                        "addMixedMap" => "MixedMap",
+                        "addKanjiMap" => "KanjiMap",
+                        _ => "Map",
+                    };
+                    result.maps.push((pkg.name.clone(), format!("{} {}", kind, rest.trim())));
+                    true
+                }
+                _ => {
+                    debug!("ignoring `execute {}` of {}", execute, pkg.name);
+                    true
+                }
+            };
+            if !understood {
+                warn!("unable to understand `execute {}` of {}, skipping it", execute, pkg.name);
+            }
+        }
+    }
+    result
+}
+
+fn has_directive(pkg: &TlPObjInfo, directives: &[&str]) -> bool {
+    pkg.executes.iter().flatten()
+        .any(|execute| directives.contains(&execute.split(' ').next().unwrap_or_default()))
+}
+
+/// Whether `pkg` adds a format or hyphenation patterns for `fmtutil-sys` to build
+pub fn contributes_formats(pkg: &TlPObjInfo) -> bool {
+    has_directive(pkg, &["AddFormat", "AddHyphen"])
+}
+
+/// Whether `pkg` adds a font map for `updmap-sys` to merge
+pub fn contributes_maps(pkg: &TlPObjInfo) -> bool {
+    has_directive(pkg, &["addMap", "addMixedMap", "addKanjiMap"])
+}
+
+/// The header file at `relative` in the environment, if a package linked it
+fn header(dot_dir: &Path, relative: &str) -> Result<String, DtMgrError> {
+    let path = dot_dir.join(relative);
+    if !path.exists() {
+        return Ok(String::new());
+    }
+    std::fs::read_to_string(&path)
+        .map_err(|e| DtMgrError::ReadFile { path, source: e })
+}
+
+fn write_file(dot_dir: &Path, relative: &str, content: String) -> Result<(), DtMgrError> {
+    let path = dot_dir.join(relative);
+    let parent = path.parent().expect("a path created by a join should have a parent");
+    std::fs::create_dir_all(parent)
+        .map_err(|e| DtMgrError::CreateDirectory { dir: parent.to_owned(), source: e })?;
+    std::fs::write(&path, content)
+        .map_err(|e| DtMgrError::WriteFile { file: path, source: e })
+}
+
+/// Whether `entries[index]` is the first of its package, which gets a comment naming it
+fn starts_package<T>(entries: &[(String, T)], index: usize) -> bool {
+    index == 0 || entries[index - 1].0 != entries[index].0
+}
+
+fn fmtutil_cnf(header: String, formats: &[(String, Format)]) -> String {
+    let mut result = header;
+    for (index, (package, format)) in formats.iter().enumerate() {
+        if starts_package(formats, index) {
+            let _ = writeln!(result, "#\n# from {}:", package);
+        }
+        let disabled = if format.disabled { "#! " } else { "" };
+        let _ = writeln!(result, "{}{} {} {} {}", disabled, format.name, format.engine, format.patterns, format.options);
+    }
+    result
+}
+
+fn updmap_cfg(header: String, maps: &[(String, String)]) -> String {
+    let mut result = header;
+    for (index, (package, map)) in maps.iter().enumerate() {
+        if starts_package(maps, index) {
+            let _ = writeln!(result, "#\n# from {}:", package);
+        }
+        let _ = writeln!(result, "{}", map);
+    }
+    result
+}
+
+fn language_dat(header: String, hyphens: &[(String, Hyphen)]) -> String {
+    let mut result = header;
+    for (package, hyphen) in hyphens.iter().filter(|(_, h)| h.in_database("dat")) {
+        let _ = writeln!(result, "% from {}:\n{} {}", package, hyphen.name, hyphen.file);
+        for synonym in hyphen.synonyms.iter() {
+            let _ = writeln!(result, "={}", synonym);
+        }
+    }
+    result
+}
+
+fn language_def(header: String, hyphens: &[(String, Hyphen)]) -> String {
+    let mut result = header;
+    for (package, hyphen) in hyphens.iter().filter(|(_, h)| h.in_database("def")) {
+        let _ = writeln!(result, "% from {}:", package);
+        for name in std::iter::once(&hyphen.name).chain(hyphen.synonyms.iter()) {
+            let _ = writeln!(
+                result,
+                "\\addlanguage{{{}}}{{{}}}{{}}{{{}}}{{{}}}",
+                name, hyphen.file, hyphen.lefthyphenmin, hyphen.righthyphenmin,
+            );
+        }
+    }
+    result
+}
+
+// the header is a whole Lua table, so entries go before its closing brace
+fn language_dat_lua(header: String, hyphens: &[(String, Hyphen)]) -> String {
+    let mut result = match header.trim_end().strip_suffix('}') {
+        Some(open) => String::from(open),
+        None => String::from("return {\n"),
+    };
+    for (package, hyphen) in hyphens.iter().filter(|(_, h)| h.in_database("lua")) {
+        let _ = writeln!(result, "-- from {}:", package);
+        let _ = writeln!(result, "\t['{}'] = {{", hyphen.name);
+        let _ = writeln!(result, "\t\tloader = '{}',", hyphen.file);
+        let _ = writeln!(result, "\t\tlefthyphenmin = {},", hyphen.lefthyphenmin);
+        let _ = writeln!(result, "\t\trighthyphenmin = {},", hyphen.righthyphenmin);
+        let synonyms: Vec<String> = hyphen.synonyms.iter().map(|s| format!("'{}'", s)).collect();
+        let _ = writeln!(result, "\t\tsynonyms = {{ {} }},", synonyms.join(", "));
+        if let Some(patterns) = &hyphen.file_patterns {
+            let _ = writeln!(result, "\t\tpatterns = '{}',", patterns);
+        }
+        if let Some(exceptions) = &hyphen.file_exceptions {
+            let _ = writeln!(result, "\t\thyphenation = '{}',", exceptions);
+        }
+        if let Some(special) = &hyphen.luaspecial {
+            let _ = writeln!(result, "\t\tspecial = '{}',", special);
+        }
+        let _ = writeln!(result, "\t}},");
+    }
+    result.push_str("}\n");
+    result
+}
+
+/// The environment's generated `updmap.cfg`, which `updmap-sys` is told to read instead of
+/// every `updmap.cfg` kpathsea finds, so that one in `texmf-local` or `extra-trees` adds no maps
+pub fn updmap_cfg_file(dot_dir: &Path) -> PathBuf {
+    dot_dir.join(UPDMAP_CFG)
+}
+
+/// Writes `fmtutil.cnf`, `updmap.cfg` and the hyphenation databases for exactly the packages in
+/// `dep_tree`, the way tlmgr generates them for a whole TeX Live installation
+pub fn write_generated_files(dot_dir: &Path, dep_tree: &Map<String, TlPObjInfo>) -> Result<(), DtMgrError> {
+    let contributions = collect(dep_tree);
+
+    write_file(dot_dir, FMTUTIL_CNF, fmtutil_cnf(header(dot_dir, FMTUTIL_HEADER)?, &contributions.formats))?;
+    write_file(dot_dir, UPDMAP_CFG, updmap_cfg(header(dot_dir, UPDMAP_HEADER)?, &contributions.maps))?;
+
+    write_file(dot_dir, LANGUAGE_DAT, language_dat(header(dot_dir, LANGUAGE_DAT_HEADER)?, &contributions.hyphens))?;
+    write_file(dot_dir, LANGUAGE_DEF, language_def(header(dot_dir, LANGUAGE_DEF_HEADER)?, &contributions.hyphens))?;
+    write_file(dot_dir, LANGUAGE_DAT_LUA, language_dat_lua(header(dot_dir, LANGUAGE_LUA_HEADER)?, &contributions.hyphens))
+}
@@ -0,0 +1,125 @@
+use std::collections::BTreeMap as Map;
+use std::collections::BTreeSet as Set;
+use clap::ValueEnum;
+use crate::{graph, TlPObjInfo};
+
+// TeX Live's own infrastructure, which every distribution's TeX Live has
+const INFRASTRUCTURE: &str = "texlive.infra";
+const SCHEME_PREFIX: &str = "scheme-";
+
+/// The Debian packages of TeX Live's collections, which Debian and Ubuntu ship whole
+const APT_COLLECTIONS: [(&str, &str); 22] = [
+    ("collection-basic", "texlive-base"),
+    ("collection-bibtexextra", "texlive-bibtex-extra"),
+    ("collection-binextra", "texlive-extra-utils"),
+    ("collection-context", "context"),
+    ("collection-fontsextra", "texlive-fonts-extra"),
+    ("collection-fontsrecommended", "texlive-fonts-recommended"),
+    ("collection-fontutils", "texlive-font-utils"),
+    ("collection-formatsextra", "texlive-formats-extra"),
+    ("collection-games", "texlive-games"),
+    ("collection-humanities", "texlive-humanities"),
+    ("collection-latex", "texlive-latex-base"),
+    ("collection-latexextra", "texlive-latex-extra"),
+    ("collection-latexrecommended", "texlive-latex-recommended"),
+    ("collection-luatex", "texlive-luatex"),
+    ("collection-mathscience", "texlive-science"),
+    ("collection-metapost", "texlive-metapost"),
+    ("collection-music", "texlive-music"),
+    ("collection-pictures", "texlive-pictures"),
+    ("collection-plaingeneric", "texlive-plain-generic"),
+    ("collection-pstricks", "texlive-pstricks"),
+    ("collection-publishers", "texlive-publishers"),
+    ("collection-xetex", "texlive-xetex"),
+];
+
+/// TeX Live packages Debian ships on their own rather than in their collection's package
+const APT_STANDALONE: [&str; 5] = ["biber", "chktex", "dvipng", "latexmk", "xindy"];
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum Distribution {
+    /// Debian and Ubuntu, which package TeX Live by collection
+    Apt,
+    /// Fedora, which packages every TeX Live package as `texlive-<name>`
+    Dnf,
+    /// Attribute names of `texlive.pkgs` in nixpkgs, for `texlive.withPackages`
+    NixpkgsNames,
+}
+
+impl Distribution {
+    pub fn name(self) -> &'static str {
+        match self {
+            Distribution::Apt => "apt",
+            Distribution::Dnf => "dnf",
+            Distribution::NixpkgsNames => "nixpkgs",
+        }
+    }
+}
+
+/// The distribution's packages that provide an environment, and the TeX Live packages none of
+/// them is known to provide
+#[derive(Debug, Default)]
+pub struct Exported {
+    pub packages: Set<String>,
+    pub unmapped: Vec<String>,
+}
+
+// the language collections all follow `collection-langcjk` -> `texlive-lang-cjk`
+fn apt_collection(collection: &str) -> Option<String> {
+    if let Some((_, name)) = APT_COLLECTIONS.iter().find(|(c, _)| *c == collection) {
+        return Some(String::from(*name));
+    }
+    collection.strip_prefix("collection-lang").map(|language| format!("texlive-lang-{}", language))
+}
+
+/// The collections of `tlpdb` that list each package, by package
+fn collections_of(tlpdb: &Map<String, TlPObjInfo>) -> Map<&str, Vec<&str>> {
+    let mut result: Map<&str, Vec<&str>> = Map::new();
+    for collection in tlpdb.values().filter(|pkg| graph::is_collection(&pkg.name)) {
+        for dep in collection.depends.iter().flatten() {
+            result.entry(dep.as_str()).or_default().push(collection.name.as_str());
+        }
+    }
+    result
+}
+
+// a name Nix takes as an attribute without quotes
+fn nix_attribute(name: &str) -> String {
+    let bare = name.chars().next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '\''));
+    if bare {
+        name.to_owned()
+    } else {
+        format!("\"{}\"", name)
+    }
+}
+
+/// Maps the packages of `dep_tree` to `distribution`'s, looking up the collections Debian
+/// packages by in `tlpdb`. Per-platform binary packages come with the packages they belong to
+/// everywhere, so they are left out.
+pub fn export(distribution: Distribution, dep_tree: &Map<String, TlPObjInfo>, tlpdb: &Map<String, TlPObjInfo>) -> Exported {
+    let collections = collections_of(tlpdb);
+    let mut exported = Exported::default();
+    for pkg in dep_tree.values().filter(|pkg| pkg.binary_platform().is_none()) {
+        let name = pkg.name.as_str();
+        let mapped = match distribution {
+            Distribution::Apt if APT_STANDALONE.contains(&name) => Some(name.to_owned()),
+            // Debian has no package for most schemes, and their collections are exported anyway
+            Distribution::Apt if name.starts_with(SCHEME_PREFIX) => continue,
+            Distribution::Apt if graph::is_collection(name) => apt_collection(name),
+            Distribution::Apt => collections.get(name).into_iter().flatten()
+                .find_map(|collection| apt_collection(collection)),
+            Distribution::Dnf if name == INFRASTRUCTURE => Some(String::from("texlive-base")),
+            Distribution::Dnf => Some(format!("texlive-{}", name)),
+            Distribution::NixpkgsNames if name == INFRASTRUCTURE => continue,
+            Distribution::NixpkgsNames => Some(nix_attribute(name)),
+        };
+        match mapped {
+            Some(mapped) => {
+                exported.packages.insert(mapped);
+            }
+            None => exported.unmapped.push(pkg.name.clone()),
+        }
+    }
+    exported
+}
@@ -0,0 +1,102 @@
+use std::collections::BTreeMap as Map;
+use std::ffi::OsString;
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use crate::{DtMgrError, TlPObjInfo};
+
+const LUAOTFLOAD_PACKAGE: &str = "luaotfload";
+/// fontconfig's configuration for the environment, read by XeTeX and luaotfload alike
+const FONTS_CONF: &str = "fonts.conf";
+/// fontconfig's own configuration, which lists the fonts installed on the system
+const SYSTEM_FONTS_CONF: &str = "/etc/fonts/fonts.conf";
+
+/// The `[fonts]` section of `dtmgr.toml`, for fonts found by name through luaotfload and fontconfig
+#[derive(Debug, Deserialize, JsonSchema, Serialize, Hash, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct FontsConfig {
+    /// Build luaotfload's font name database during `dtmgr install` instead of on the first
+    /// LuaLaTeX run that looks a font up
+    #[serde(default)]
+    pub prewarm: bool,
+    /// Whether the fonts installed on the system are found, besides TeX Live's and `dirs`
+    #[serde(default = "default_system")]
+    pub system: bool,
+    /// More directories to find fonts in, relative to the project directory
+    #[serde(default)]
+    pub dirs: Vec<PathBuf>,
+}
+
+fn default_system() -> bool {
+    true
+}
+
+impl Default for FontsConfig {
+    fn default() -> Self {
+        FontsConfig { prewarm: false, system: default_system(), dirs: Vec::new() }
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+fn unescape(text: &str) -> String {
+    text.replace("&lt;", "<").replace("&gt;", ">").replace("&amp;", "&")
+}
+
+impl FontsConfig {
+    pub fn is_default(&self) -> bool {
+        *self == FontsConfig::default()
+    }
+
+    /// The command filling luaotfload's font name database, if the environment has luaotfload
+    /// and the project wants the database built ahead of time
+    pub fn prewarm_command(&self, dep_tree: &Map<String, TlPObjInfo>) -> Option<[&'static str; 2]> {
+        (self.prewarm && dep_tree.contains_key(LUAOTFLOAD_PACKAGE)).then_some(["luaotfload-tool", "--update"])
+    }
+
+    /// Writes a `fonts.conf` into `dot_dir` listing `dirs`, and the system's fonts only if the
+    /// project allows them. Without one, fonts are found the way they are outside the environment.
+    pub fn write_fonts_conf(&self, dtmgr_directory: &Path, dot_dir: &Path) -> Result<(), DtMgrError> {
+        if self.system && self.dirs.is_empty() {
+            return Ok(());
+        }
+
+        let mut content = String::from("<?xml version=\"1.0\"?>\n<!DOCTYPE fontconfig SYSTEM \"fonts.dtd\">\n");
+        content.push_str("<!-- generated by dtmgr, changes are lost on the next install -->\n<fontconfig>\n");
+        if self.system {
+            let _ = writeln!(content, "  <include ignore_missing=\"yes\">{}</include>", SYSTEM_FONTS_CONF);
+        }
+        for dir in self.dirs.iter() {
+            let dir = dtmgr_directory.join(dir);
+            if !dir.is_dir() {
+                return Err(DtMgrError::FontDirectoryNotFound { dir });
+            }
+            let _ = writeln!(content, "  <dir>{}</dir>", escape(&dir.to_string_lossy()));
+        }
+        let cache = dot_dir.join("texmf-var").join("fontconfig");
+        let _ = writeln!(content, "  <cachedir>{}</cachedir>\n</fontconfig>", escape(&cache.to_string_lossy()));
+
+        let file = dot_dir.join(FONTS_CONF);
+        std::fs::write(&file, content)
+            .map_err(|e| DtMgrError::WriteFile { file, source: e })
+    }
+}
+
+/// `FONTCONFIG_FILE` and `OSFONTDIR` for the environment at `dot_dir`, if `dtmgr install` wrote a
+/// `fonts.conf` into it, so that neither a font directory of the host nor its fontconfig
+/// configuration comes in
+pub fn environment(dot_dir: &Path) -> Result<Vec<(&'static str, OsString)>, DtMgrError> {
+    let file = dot_dir.join(FONTS_CONF);
+    let Ok(content) = std::fs::read_to_string(&file) else {
+        return Ok(Vec::new());
+    };
+    let dirs: Vec<String> = content.lines()
+        .filter_map(|line| line.trim().strip_prefix("<dir>")?.strip_suffix("</dir>").map(unescape))
+        .collect();
+    let osfontdir = std::env::join_paths(dirs)
+        .map_err(|e| DtMgrError::JoinPaths { source: e })?;
+    Ok(vec![("FONTCONFIG_FILE", file.into_os_string()), ("OSFONTDIR", osfontdir)])
+}
@@ -0,0 +1,94 @@
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
+use clap::ValueEnum;
+use crate::build::BuildConfig;
+use crate::{signals, DtMgrError};
+
+pub const LATEXMKRC_FILE_NAME: &str = ".latexmkrc";
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum Generated {
+    /// A `.latexmkrc` running every tool through `dtmgr run`
+    Latexmkrc,
+    /// `post-merge` and `post-checkout` hooks warning when the environment is out of date
+    GitHooks,
+}
+
+impl Generated {
+    /// Whether the files have to be executable
+    pub fn executable(self) -> bool {
+        matches!(self, Generated::GitHooks)
+    }
+}
+
+// the programs latexmk calls through variables of the same name, with latexmk's default arguments
+const LATEXMK_PROGRAMS: [(&str, &str); 7] = [
+    ("pdflatex", "%O %S"), ("xelatex", "%O %S"), ("lualatex", "%O %S"), ("latex", "%O %S"),
+    ("bibtex", "%O %S"), ("biber", "%O %S"), ("makeindex", "%O -o %D %S"),
+];
+
+fn perl_quote(value: &str) -> String {
+    String::from("'") + &value.replace('\\', "\\\\").replace('\'', "\\'") + "'"
+}
+
+/// `.latexmkrc` contents matching the project's `[build]` section
+pub fn latexmkrc(build: &BuildConfig) -> String {
+    let mut result = String::from("# generated by `dtmgr generate latexmkrc`\n");
+    // `dtmgr run` works out the environment on every call, so this file can be committed
+    for (program, arguments) in LATEXMK_PROGRAMS {
+        result.push_str(&format!("${} = {};\n", program, perl_quote(&format!("dtmgr run {} {}", program, arguments))));
+    }
+    result.push_str(&format!("$pdf_mode = {};\n", build.engine().pdf_mode()));
+    if let Some(output_dir) = build.output_dir() {
+        result.push_str(&format!("$out_dir = {};\n", perl_quote(&output_dir.display().to_string())));
+    }
+    result
+}
+
+// after `git pull` and after switching branches, the two ways dtmgr.toml changes under a checkout
+const GIT_HOOKS: [&str; 2] = ["post-merge", "post-checkout"];
+
+fn git(dir: &Path, args: &[&str]) -> Result<String, DtMgrError> {
+    let mut exe_and_args = vec![OsStr::new("git"), OsStr::new("-C"), dir.as_os_str()];
+    exe_and_args.extend(args.iter().map(OsStr::new));
+    let out = signals::output(&mut crate::cmd_crossplatform_static_args(&exe_and_args))?;
+    if !out.status.success() {
+        return Err(DtMgrError::CommandStatus { command: format!("git {}", args.join(" ")), code: out.status.code(), stderr: crate::stderr_tail(&out.stderr) });
+    }
+    Ok(String::from_utf8_lossy(&out.stdout).trim().to_owned())
+}
+
+fn sh_quote(value: &str) -> String {
+    String::from("'") + &value.replace('\'', "'\\''") + "'"
+}
+
+/// The hooks of the git repository `dtmgr_directory` is in, by where they go, which run
+/// `dtmgr status --hook` for the project after git changed the checkout
+pub fn git_hooks(dtmgr_directory: &Path) -> Result<Vec<(PathBuf, String)>, DtMgrError> {
+    // also where `core.hooksPath` points, and in the main repository for a worktree
+    let hooks_dir = PathBuf::from(git(dtmgr_directory, &["rev-parse", "--path-format=absolute", "--git-path", "hooks"])?);
+    let toplevel = PathBuf::from(git(dtmgr_directory, &["rev-parse", "--show-toplevel"])?);
+    // hooks run at the top of the repository, which the project may be below
+    let project = std::fs::canonicalize(dtmgr_directory).ok()
+        .zip(std::fs::canonicalize(&toplevel).ok())
+        .and_then(|(project, toplevel)| project.strip_prefix(toplevel).ok().map(Path::to_owned))
+        .unwrap_or_default();
+    let project = project.to_string_lossy().replace('\\', "/");
+
+    Ok(GIT_HOOKS.iter()
+        .map(|hook| {
+            let mut content = String::from("#!/bin/sh\n# generated by `dtmgr generate git-hooks`\n");
+            if *hook == "post-checkout" {
+                // a checkout of single files leaves dtmgr.toml as it was
+                content.push_str("[ \"$3\" = 0 ] && exit 0\n");
+            }
+            content.push_str("command -v dtmgr >/dev/null 2>&1 || exit 0\n");
+            if !project.is_empty() {
+                content.push_str(&format!("cd {} || exit 0\n", sh_quote(&project)));
+            }
+            // warns, or installs with `auto-sync`; a hook failing would not undo the checkout
+            content.push_str("dtmgr status --hook || true\n");
+            (hooks_dir.join(hook), content)
+        })
+        .collect())
+}
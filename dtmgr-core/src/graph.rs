@@ -0,0 +1,405 @@
+use std::collections::BTreeMap as Map;
+use std::collections::BTreeSet as Set;
+use std::collections::VecDeque;
+use std::fmt;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use tracing::debug;
+use crate::{DtMgrConfig, DtMgrError, TlPObjInfo};
+
+/// Where resolving learns what a package is and what it depends on
+pub trait Backend {
+    /// What is known about `names`; names nothing is known about may be left out
+    fn packages(&self, names: &Set<String>) -> Result<Vec<TlPObjInfo>, DtMgrError>;
+}
+
+/// Reads TeX Live's own database and, unless offline, the repository's from `tlmgr dump-tlpdb`
+/// once, as `dtmgr` itself does
+pub struct TlmgrBackend;
+
+impl Backend for TlmgrBackend {
+    fn packages(&self, names: &Set<String>) -> Result<Vec<TlPObjInfo>, DtMgrError> {
+        crate::info_about_packages(names)
+    }
+}
+
+/// Packages loaded beforehand, e.g. by [`crate::tlpdb::load_tlpdbs`], so that resolving runs
+/// nothing
+pub struct TlpdbBackend {
+    packages: Map<String, TlPObjInfo>,
+}
+
+impl From<Map<String, TlPObjInfo>> for TlpdbBackend {
+    fn from(packages: Map<String, TlPObjInfo>) -> Self {
+        TlpdbBackend { packages }
+    }
+}
+
+impl Backend for TlpdbBackend {
+    fn packages(&self, names: &Set<String>) -> Result<Vec<TlPObjInfo>, DtMgrError> {
+        Ok(names.iter().filter_map(|name| self.packages.get(name)).cloned().collect())
+    }
+}
+
+/// The prefix of TeX Live's packages that only gather others
+const COLLECTION_PREFIX: &str = "collection-";
+
+/// How far dependencies on `collection-*` packages are followed
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Deserialize, JsonSchema, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Collections {
+    /// A collection brings in every collection it depends on, as with `tlmgr install`
+    #[default]
+    Full,
+    /// A collection brings in only its packages that are not collections themselves
+    Shallow,
+}
+
+impl Collections {
+    pub fn is_full(&self) -> bool {
+        *self == Collections::Full
+    }
+}
+
+pub fn is_collection(name: &str) -> bool {
+    name.starts_with(COLLECTION_PREFIX)
+}
+
+/// Why a package is part of the environment; a package can have several reasons
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(tag = "kind", content = "package", rename_all = "kebab-case")]
+pub enum Reason {
+    /// Listed in `dependencies` or `dev-dependencies`
+    Direct,
+    /// Needed by dtmgr itself, such as `kpathsea`
+    Base,
+    /// Depended on by this package
+    DependencyOf(String),
+}
+
+/// Something odd about TeX Live's dependencies that resolving worked around
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "kebab-case")]
+pub enum Anomaly {
+    /// A package listing itself among its dependencies, which is ignored
+    SelfDependency { package: String },
+    /// Packages depending on each other in a loop, with the first one repeated at the end
+    Cycle { chain: Vec<String> },
+    /// A dependency the backend knew nothing about and that is left out, after the chain of
+    /// dependencies leading to it from a root
+    Unavailable { chain: Vec<String> },
+    /// A package listed in `dependencies` that another listed package brings in anyway, after
+    /// the chain of dependencies from that package to it
+    Redundant { chain: Vec<String> },
+}
+
+impl fmt::Display for Anomaly {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Anomaly::SelfDependency { package } => write!(f, "`{}` depends on itself", package),
+            Anomaly::Cycle { chain } => write!(f, "dependency cycle: {}", chain.join(" -> ")),
+            Anomaly::Unavailable { chain } => {
+                let (package, dependents) = chain.split_last().expect("an unavailable package is in its chain");
+                if dependents.is_empty() {
+                    write!(f, "TeX Live has no package `{}`", package)
+                } else {
+                    write!(f, "TeX Live has no package `{}`, which {} depends on", package, dependents.join(" -> "))
+                }
+            }
+            Anomaly::Redundant { chain } => {
+                let package = chain.last().expect("a redundant package is in its chain");
+                write!(f, "`{}` need not be listed, `{}` brings it in already: {}", package, chain[0], chain.join(" -> "))
+            }
+        }
+    }
+}
+
+/// A package of a [`DependencyGraph`]
+#[derive(Clone, Copy, Debug)]
+pub struct Node<'a> {
+    info: &'a TlPObjInfo,
+    reasons: &'a [Reason],
+    platform: &'a str,
+}
+
+impl<'a> Node<'a> {
+    pub fn name(&self) -> &'a str {
+        &self.info.name
+    }
+
+    /// The installed revision, or the repository's if TeX Live does not have the package yet
+    pub fn revision(&self) -> Option<u64> {
+        self.info.lrev.or(self.info.rrev)
+    }
+
+    /// Every file the package puts into the environment, relative to TeX Live's root
+    pub fn files(&self) -> impl Iterator<Item = &'a str> + use<'a> {
+        self.info.files(self.platform)
+    }
+
+    pub fn reasons(&self) -> &'a [Reason] {
+        self.reasons
+    }
+
+    pub fn is_direct(&self) -> bool {
+        self.reasons.contains(&Reason::Direct)
+    }
+
+    /// The packages this one depends on, with `.ARCH` expanded to the graph's platform
+    pub fn dependencies(&self) -> Vec<String> {
+        self.info.dependencies(self.platform)
+    }
+
+    /// Everything `tlmgr` says about the package
+    pub fn info(&self) -> &'a TlPObjInfo {
+        self.info
+    }
+}
+
+/// Every package an environment needs, with why it needs each
+#[derive(Debug)]
+pub struct DependencyGraph {
+    platform: String,
+    packages: Map<String, TlPObjInfo>,
+    reasons: Map<String, Vec<Reason>>,
+    anomalies: Vec<Anomaly>,
+}
+
+impl DependencyGraph {
+    /// The platform whose binaries and `.ARCH` dependencies the graph includes
+    pub fn platform(&self) -> &str {
+        &self.platform
+    }
+
+    pub fn get(&self, name: &str) -> Option<Node<'_>> {
+        let info = self.packages.get(name)?;
+        let reasons = self.reasons.get(name).map(Vec::as_slice).unwrap_or_default();
+        Some(Node { info, reasons, platform: &self.platform })
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.packages.contains_key(name)
+    }
+
+    /// Every package, by name
+    pub fn iter(&self) -> impl Iterator<Item = Node<'_>> {
+        self.packages.keys().filter_map(|name| self.get(name))
+    }
+
+    pub fn len(&self) -> usize {
+        self.packages.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.packages.is_empty()
+    }
+
+    /// The packages that are there for their own sake rather than as a dependency
+    pub fn roots(&self) -> impl Iterator<Item = Node<'_>> {
+        self.iter().filter(|node| node.reasons.iter().any(|r| !matches!(r, Reason::DependencyOf(_))))
+    }
+
+    /// The packages that depend on `name` directly
+    pub fn dependents(&self, name: &str) -> Vec<&str> {
+        self.reasons.get(name).into_iter().flatten()
+            .filter_map(|reason| match reason {
+                Reason::DependencyOf(dependent) => Some(dependent.as_str()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Every package reachable from `roots`, including themselves
+    pub fn closure<'a>(&self, roots: impl IntoIterator<Item = &'a str>) -> Set<String> {
+        crate::dependency_closure(roots, &self.packages, &self.platform)
+    }
+
+    /// The shortest chain of dependencies from a root to `name`, root first, which answers why
+    /// `name` is in the environment
+    pub fn path_to(&self, name: &str) -> Option<Vec<&str>> {
+        self.path_from(name, |node| node.reasons.iter().any(|r| !matches!(r, Reason::DependencyOf(_))))
+    }
+
+    /// The shortest chain of dependencies to `name` from the nearest package `is_start` accepts,
+    /// which may be `name` itself, that package first
+    fn path_from(&self, name: &str, is_start: impl Fn(&Node<'_>) -> bool) -> Option<Vec<&str>> {
+        let (name, _) = self.packages.get_key_value(name)?;
+        let mut came_from: Map<&str, &str> = Map::new();
+        let mut queue = VecDeque::from([name.as_str()]);
+        while let Some(current) = queue.pop_front() {
+            if self.get(current).is_some_and(|node| is_start(&node)) {
+                let mut path = vec![current];
+                let mut at = current;
+                while let Some(next) = came_from.get(at) {
+                    path.push(next);
+                    at = next;
+                }
+                return Some(path);
+            }
+            for dependent in self.dependents(current) {
+                if dependent != name && !came_from.contains_key(dependent) {
+                    came_from.insert(dependent, current);
+                    queue.push_back(dependent);
+                }
+            }
+        }
+        None
+    }
+
+    /// The listed dependencies another listed dependency brings in, each with the chain from
+    /// that one to it. Of several that bring in each other, the first by name is kept.
+    fn redundant(&self, dependencies: &Set<String>) -> Vec<Vec<String>> {
+        let mut redundant: Set<&str> = Set::new();
+        let mut chains = Vec::new();
+        for dep in dependencies.iter() {
+            let chain = self.path_from(dep, |node| node.name() != dep && node.is_direct() && !redundant.contains(node.name()));
+            if let Some(chain) = chain {
+                chains.push(chain.into_iter().map(String::from).collect());
+                redundant.insert(dep.as_str());
+            }
+        }
+        chains
+    }
+
+    /// The odd edges resolving came across, which the graph works around
+    pub fn anomalies(&self) -> &[Anomaly] {
+        &self.anomalies
+    }
+
+    /// Every dependency cycle, each found once from the package where it is first entered
+    fn cycles(&self) -> Vec<Vec<String>> {
+        fn visit<'a>(graph: &'a DependencyGraph, name: &'a str, stack: &mut Vec<&'a str>, done: &mut Set<&'a str>, cycles: &mut Vec<Vec<String>>) {
+            stack.push(name);
+            if let Some(node) = graph.get(name) {
+                for dep in node.info.dependencies(&graph.platform) {
+                    let Some((dep, _)) = graph.packages.get_key_value(&dep) else {
+                        continue;
+                    };
+                    if dep == name || done.contains(dep.as_str()) {
+                        continue;
+                    }
+                    if let Some(start) = stack.iter().position(|on_stack| *on_stack == dep) {
+                        let mut chain: Vec<String> = stack[start..].iter().map(|s| s.to_string()).collect();
+                        chain.push(dep.clone());
+                        cycles.push(chain);
+                    } else {
+                        visit(graph, dep, stack, done, cycles);
+                    }
+                }
+            }
+            stack.pop();
+            done.insert(name);
+        }
+
+        let mut cycles = Vec::new();
+        let mut done = Set::new();
+        for name in self.packages.keys() {
+            if !done.contains(name.as_str()) {
+                visit(self, name, &mut Vec::new(), &mut done, &mut cycles);
+            }
+        }
+        cycles
+    }
+
+    /// The packages by name, as the rest of dtmgr takes them
+    pub fn packages(&self) -> &Map<String, TlPObjInfo> {
+        &self.packages
+    }
+
+    pub fn into_packages(self) -> Map<String, TlPObjInfo> {
+        self.packages
+    }
+}
+
+/// The platforms besides `platform` whose binaries `config` wants too
+pub fn extra_platforms<'a>(config: &'a DtMgrConfig, platform: &str) -> Vec<&'a str> {
+    config.platforms.iter()
+        .map(String::as_str)
+        .filter(|p| *p != platform)
+        .collect()
+}
+
+/// Every TeX Live package `config` needs on `platform`, following dependencies through
+/// `backend`. Packages that `[sources]` provide are left out, along with what only they need,
+/// and so are the collections other collections depend on if `collections` is shallow.
+///
+/// `.ARCH` dependencies are expanded for the extra `platforms` as well; a package without
+/// binaries for one of them is skipped quietly, since TeX Live does not build everything
+/// everywhere.
+pub fn resolve(config: &DtMgrConfig, platform: &str, backend: &impl Backend) -> Result<DependencyGraph, DtMgrError> {
+    let mut reasons: Map<String, Vec<Reason>> = Map::new();
+    for base in crate::base_packages() {
+        reasons.entry(base).or_default().push(Reason::Base);
+    }
+    for dep in config.dependencies.iter().chain(config.dev_dependencies.iter()) {
+        reasons.entry(dep.clone()).or_default().push(Reason::Direct);
+    }
+    reasons.retain(|name, _| !config.sources.contains_key(name));
+
+    let mut queue: Set<String> = reasons.keys().cloned().collect();
+    let mut packages: Map<String, TlPObjInfo> = Map::new();
+    let mut anomalies = Vec::new();
+    // names the backend was asked about, so that one it knows nothing about is asked only once
+    let mut asked: Set<String> = Set::new();
+    let extra_platforms = extra_platforms(config, platform);
+    // binary packages wanted only for an extra platform, which may not exist
+    let mut optional: Set<String> = Set::new();
+    while !queue.is_empty() {
+        let info = backend.packages(&queue)?;
+        asked.append(&mut queue);
+
+        for tlpobjinfo in info.into_iter() {
+            let extra_binaries: Vec<String> = tlpobjinfo.depends.iter().flatten()
+                .filter_map(|dep| dep.strip_suffix(".ARCH"))
+                .flat_map(|stem| extra_platforms.iter().map(move |extra| format!("{}.{}", stem, extra)))
+                .collect();
+            optional.extend(extra_binaries.iter().cloned());
+            for dep in tlpobjinfo.dependencies(platform).into_iter().chain(extra_binaries) {
+                if config.sources.contains_key(&dep) {
+                    continue;
+                }
+                if config.collections == Collections::Shallow && is_collection(&tlpobjinfo.name) && is_collection(&dep) {
+                    continue;
+                }
+                if dep == tlpobjinfo.name {
+                    anomalies.push(Anomaly::SelfDependency { package: dep });
+                    continue;
+                }
+                reasons.entry(dep.clone()).or_default().push(Reason::DependencyOf(tlpobjinfo.name.clone()));
+                if !packages.contains_key(&dep) && !asked.contains(&dep) {
+                    queue.insert(dep);
+                }
+            }
+            packages.insert(tlpobjinfo.name.clone(), tlpobjinfo);
+        }
+    }
+
+    // what the backend knew nothing about
+    let (reasons, unavailable): (Map<String, Vec<Reason>>, Map<String, Vec<Reason>>) = reasons.into_iter()
+        .partition(|(name, _)| packages.contains_key(name));
+    let mut graph = DependencyGraph { platform: platform.to_owned(), packages, reasons, anomalies };
+    for (name, why) in unavailable {
+        if optional.contains(&name) {
+            debug!("TeX Live has no {}, skipping it", name);
+            continue;
+        }
+        let mut chain: Vec<String> = why.iter()
+            .find_map(|reason| match reason {
+                Reason::DependencyOf(dependent) => graph.path_to(dependent),
+                _ => None,
+            })
+            .unwrap_or_default()
+            .into_iter()
+            .map(String::from)
+            .collect();
+        chain.push(name);
+        graph.anomalies.push(Anomaly::Unavailable { chain });
+    }
+    for chain in graph.cycles() {
+        graph.anomalies.push(Anomaly::Cycle { chain });
+    }
+    for chain in graph.redundant(&config.dependencies) {
+        graph.anomalies.push(Anomaly::Redundant { chain });
+    }
+    Ok(graph)
+}
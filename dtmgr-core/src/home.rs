@@ -0,0 +1,72 @@
+use std::path::PathBuf;
+use serde::Deserialize;
+use tracing::debug;
+use crate::{config_span, network, DtMgrError};
+
+/// Puts all of dtmgr's global directories below one, e.g. for a portable install or CI
+const HOME_VARIABLE: &str = "DTMGR_HOME";
+const DIR_NAME: &str = "dtmgr";
+
+// a variable naming a directory, which like the XDG variables only counts if it is absolute
+fn dir_variable(name: &str) -> Option<PathBuf> {
+    std::env::var_os(name)
+        .map(PathBuf::from)
+        .filter(|path| path.is_absolute())
+}
+
+// `DTMGR_HOME/<inner>`, then `$<xdg>/dtmgr` off Windows (where `dirs` ignores it on macOS), then
+// the system's own directory, which on Windows is a Known Folder
+fn global_dir(inner: &str, xdg: &str, system: fn() -> Option<PathBuf>) -> Option<PathBuf> {
+    if let Some(home) = dir_variable(HOME_VARIABLE) {
+        return Some(home.join(inner));
+    }
+    let xdg = if cfg!(windows) { None } else { dir_variable(xdg) };
+    xdg.or_else(system).map(|dir| dir.join(DIR_NAME))
+}
+
+/// Where dtmgr keeps what it downloads, shared by every project of this user; everything in it
+/// can be downloaded again
+pub fn cache_dir() -> Result<PathBuf, DtMgrError> {
+    global_dir("cache", "XDG_CACHE_HOME", dirs::cache_dir).ok_or(DtMgrError::NoCacheDirectory)
+}
+
+/// Where dtmgr keeps what it cannot make again, like the list of projects
+pub fn data_dir() -> Result<PathBuf, DtMgrError> {
+    global_dir("data", "XDG_DATA_HOME", dirs::data_local_dir).ok_or(DtMgrError::NoDataDirectory)
+}
+
+/// Where the user's own settings for dtmgr are, if the system has such a place
+pub fn config_dir() -> Option<PathBuf> {
+    global_dir("config", "XDG_CONFIG_HOME", dirs::config_dir)
+}
+
+/// Settings of this machine rather than the project, such as a proxy and its password, in
+/// `config.toml` in [`config_dir`]
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct UserConfig {
+    #[serde(default)]
+    pub network: network::UserNetworkConfig,
+    /// The TeX Live to use in projects that do not name one, instead of the one on PATH
+    #[serde(rename = "texlive-root")]
+    pub texlive_root: Option<PathBuf>,
+}
+
+/// Where [`UserConfig`] is read from, if the system has a configuration directory
+pub fn user_config_file() -> Option<PathBuf> {
+    config_dir().map(|dir| dir.join("config.toml"))
+}
+
+pub fn read_user_config() -> Result<UserConfig, DtMgrError> {
+    let Some(path) = user_config_file().filter(|path| path.is_file()) else {
+        return Ok(UserConfig::default());
+    };
+    debug!("reading {}", path.display());
+    let content = std::fs::read_to_string(&path)
+        .map_err(|e| DtMgrError::ReadFile { path: path.clone(), source: e })?;
+    toml::from_str(&content)
+        .map_err(|e| {
+            let span = e.span().map(|span| Box::new(config_span::ConfigSpan::new(&path, &content, span)));
+            DtMgrError::ParseConfig { source: e, span }
+        })
+}
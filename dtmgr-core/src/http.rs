@@ -0,0 +1,85 @@
+use std::io::Read;
+use std::time::{Duration, Instant};
+use tracing::debug;
+use crate::{network, DtMgrError};
+
+// the default agent, which reads the proxy variables, unless a proxy is configured for `url`
+fn agent(url: &str) -> ureq::Agent {
+    match network::proxy_for(url) {
+        Some(proxy) => ureq::Agent::new_with_config(ureq::Agent::config_builder().proxy(Some(proxy)).build()),
+        None => ureq::Agent::new_with_defaults(),
+    }
+}
+
+/// The body of `url`, however large
+pub fn get(url: &str) -> Result<Vec<u8>, DtMgrError> {
+    debug!("downloading {}", url);
+    let mut response = agent(url).get(url)
+        .call()
+        .map_err(|e| DtMgrError::Download { url: url.to_owned(), source: Box::new(e) })?;
+    response.body_mut()
+        .with_config()
+        .limit(u64::MAX)
+        .read_to_vec()
+        .map_err(|e| DtMgrError::Download { url: url.to_owned(), source: Box::new(e) })
+}
+
+/// The body of `url`, or `None` if the server does not have it
+pub fn get_if_exists(url: &str, authorization: Option<&str>) -> Result<Option<Vec<u8>>, DtMgrError> {
+    debug!("downloading {}", url);
+    let mut request = agent(url).get(url);
+    if let Some(authorization) = authorization {
+        request = request.header("Authorization", authorization);
+    }
+    let mut response = match request.call() {
+        Ok(response) => response,
+        Err(ureq::Error::StatusCode(404)) => return Ok(None),
+        Err(e) => return Err(DtMgrError::Download { url: url.to_owned(), source: Box::new(e) }),
+    };
+    response.body_mut()
+        .with_config()
+        .limit(u64::MAX)
+        .read_to_vec()
+        .map(Some)
+        .map_err(|e| DtMgrError::Download { url: url.to_owned(), source: Box::new(e) })
+}
+
+/// How many bytes of `url` arrive within `duration`, reading at most `limit` of them, and how
+/// long that took
+pub fn sample(url: &str, limit: u64, duration: Duration) -> Result<(u64, Duration), DtMgrError> {
+    debug!("sampling {}", url);
+    let download_error = |e| DtMgrError::Download { url: url.to_owned(), source: Box::new(e) };
+    let mut config = ureq::Agent::config_builder()
+        // a server that stops sending altogether is given up on a little after `duration`
+        .timeout_global(Some(duration * 2));
+    if let Some(proxy) = network::proxy_for(url) {
+        config = config.proxy(Some(proxy));
+    }
+    let start = Instant::now();
+    let mut response = ureq::Agent::new_with_config(config.build()).get(url)
+        .call()
+        .map_err(download_error)?;
+    let mut reader = response.body_mut().as_reader();
+    let mut buffer = [0; 16 * 1024];
+    let mut received = 0;
+    while received < limit && start.elapsed() < duration {
+        match reader.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(read) => received += read as u64,
+            Err(e) => return Err(download_error(ureq::Error::Io(e))),
+        }
+    }
+    Ok((received, start.elapsed()))
+}
+
+/// Uploads `body` to `url` with a PUT request
+pub fn put(url: &str, authorization: Option<&str>, body: &[u8]) -> Result<(), DtMgrError> {
+    debug!("uploading {} bytes to {}", body.len(), url);
+    let mut request = agent(url).put(url);
+    if let Some(authorization) = authorization {
+        request = request.header("Authorization", authorization);
+    }
+    request.send(body)
+        .map(|_| ())
+        .map_err(|e| DtMgrError::Upload { url: url.to_owned(), source: Box::new(e) })
+}
@@ -0,0 +1,259 @@
+use std::collections::BTreeSet as Set;
+use std::path::{Path, PathBuf};
+use crate::DtMgrError;
+use crate::provides::{font_candidates, Requirement};
+
+const SOURCE_EXTENSIONS: [&str; 3] = ["tex", "sty", "cls"];
+
+/// Removes `%` comments, keeping escaped `\%`
+fn strip_comments(source: &str) -> String {
+    let mut result = String::with_capacity(source.len());
+    for line in source.lines() {
+        let mut escaped = false;
+        for c in line.chars() {
+            if c == '%' && !escaped {
+                break;
+            }
+            escaped = c == '\\' && !escaped;
+            result.push(c);
+        }
+        result.push('\n');
+    }
+    result
+}
+
+/// Skips whitespace, then a balanced group opened by `open` if one starts here
+fn skip_group(chars: &[char], mut idx: usize, open: char, close: char) -> (usize, Option<String>) {
+    while idx < chars.len() && chars[idx].is_whitespace() {
+        idx += 1;
+    }
+    if idx >= chars.len() || chars[idx] != open {
+        return (idx, None);
+    }
+
+    let mut depth = 0;
+    let start = idx + 1;
+    while idx < chars.len() {
+        if chars[idx] == open {
+            depth += 1;
+        } else if chars[idx] == close {
+            depth -= 1;
+            if depth == 0 {
+                return (idx + 1, Some(chars[start..idx].iter().collect()));
+            }
+        }
+        idx += 1;
+    }
+    (idx, None)
+}
+
+/// The mandatory arguments following each `\name`, after skipping `skip` leading tokens and optional arguments
+fn macro_arguments(source: &[char], name: &str, skip: usize, count: usize) -> Vec<Vec<String>> {
+    let pattern: Vec<char> = name.chars().collect();
+    let mut result = Vec::new();
+
+    let mut idx = 0;
+    while idx + pattern.len() <= source.len() {
+        let is_macro = source[idx] == '\\'
+            && source[idx + 1..idx + pattern.len()] == pattern[1..]
+            && source.get(idx + pattern.len()).is_none_or(|c| !c.is_ascii_alphabetic());
+        if !is_macro {
+            idx += 1;
+            continue;
+        }
+
+        let mut pos = idx + pattern.len();
+        // e.g. the command being defined by \newfontfamily\foo{Font}
+        for _ in 0..skip {
+            while pos < source.len() && source[pos].is_whitespace() {
+                pos += 1;
+            }
+            if source.get(pos) == Some(&'\\') {
+                pos += 1;
+                while pos < source.len() && source[pos].is_ascii_alphabetic() {
+                    pos += 1;
+                }
+            } else {
+                pos = skip_group(source, pos, '{', '}').0;
+            }
+        }
+
+        let mut args = Vec::new();
+        loop {
+            let (after_optional, optional) = skip_group(source, pos, '[', ']');
+            pos = after_optional;
+            if optional.is_some() {
+                continue;
+            }
+            let (after_mandatory, mandatory) = skip_group(source, pos, '{', '}');
+            match mandatory {
+                Some(arg) => {
+                    pos = after_mandatory;
+                    args.push(arg);
+                    if args.len() == count {
+                        break;
+                    }
+                }
+                None => break,
+            }
+        }
+        if args.len() == count {
+            result.push(args);
+        }
+        idx = pos.max(idx + 1);
+    }
+
+    result
+}
+
+fn split_list(arg: &str) -> impl Iterator<Item = &str> {
+    arg.split(',').map(str::trim).filter(|s| !s.is_empty())
+}
+
+/// Everything a project's sources load from the TeX tree
+#[derive(Debug, Default)]
+pub struct SourceScan {
+    pub requirements: Set<Requirement>,
+    pub visited: Vec<PathBuf>,
+}
+
+struct Scanner<'a> {
+    project_dir: &'a Path,
+    scan: SourceScan,
+    seen: Set<PathBuf>,
+}
+
+impl Scanner<'_> {
+    /// A project file named by `\input`/`\include`/`\usepackage`, if it exists locally
+    fn local_file(&self, relative_to: &Path, name: &str, extension: &str) -> Option<PathBuf> {
+        let mut candidates = Vec::new();
+        for base in [relative_to, self.project_dir] {
+            candidates.push(base.join(name));
+            candidates.push(base.join(String::from(name) + "." + extension));
+        }
+        candidates.into_iter().find(|c| c.is_file())
+    }
+
+    fn require(&mut self, requirement: Requirement) {
+        self.scan.requirements.insert(requirement);
+    }
+
+    fn scan_file(&mut self, path: &Path) -> Result<(), DtMgrError> {
+        let canonical = path.canonicalize().unwrap_or_else(|_| path.to_owned());
+        if !self.seen.insert(canonical) {
+            return Ok(());
+        }
+
+        let content = std::fs::read(path)
+            .map_err(|e| DtMgrError::ReadFile { path: path.to_owned(), source: e })?;
+        let source: Vec<char> = strip_comments(&String::from_utf8_lossy(&content)).chars().collect();
+        let dir = path.parent().unwrap_or(self.project_dir).to_owned();
+        self.scan.visited.push(path.to_owned());
+
+        for name in ["\\usepackage", "\\RequirePackage", "\\RequirePackageWithOptions"] {
+            for args in macro_arguments(&source, name, 0, 1) {
+                for package in split_list(&args[0]) {
+                    match self.local_file(&dir, package, "sty") {
+                        Some(local) => self.scan_file(&local)?,
+                        None => self.require(Requirement::file(&(String::from(package) + ".sty"))),
+                    }
+                }
+            }
+        }
+
+        for name in ["\\documentclass", "\\LoadClass", "\\LoadClassWithOptions"] {
+            for args in macro_arguments(&source, name, 0, 1) {
+                let class = args[0].trim();
+                match self.local_file(&dir, class, "cls") {
+                    Some(local) => self.scan_file(&local)?,
+                    None => self.require(Requirement::file(&(String::from(class) + ".cls"))),
+                }
+            }
+        }
+
+        for args in macro_arguments(&source, "\\usetikzlibrary", 0, 1) {
+            for library in split_list(&args[0]) {
+                self.require(Requirement {
+                    description: format!("tikz library `{}`", library),
+                    candidates: vec![format!("tikzlibrary{}.code.tex", library), format!("pgflibrary{}.code.tex", library)],
+                });
+            }
+        }
+        for args in macro_arguments(&source, "\\usepgfplotslibrary", 0, 1) {
+            for library in split_list(&args[0]) {
+                self.require(Requirement::file(&format!("pgfplotslibrary{}.code.tex", library)));
+            }
+        }
+
+        let font_macros = [
+            ("\\setmainfont", 0), ("\\setsansfont", 0), ("\\setmonofont", 0), ("\\setmathfont", 0),
+            ("\\fontspec", 0), ("\\newfontfamily", 1), ("\\newfontface", 1),
+        ];
+        for (name, skip) in font_macros {
+            for args in macro_arguments(&source, name, skip, 1) {
+                let font = args[0].trim();
+                // fonts given by a macro or a path are out of reach
+                if !font.is_empty() && !font.contains('\\') && !font.contains('/') {
+                    self.require(Requirement { description: format!("font \"{}\"", font), candidates: font_candidates(font) });
+                }
+            }
+        }
+
+        for name in ["\\input", "\\include", "\\subfile", "\\InputIfFileExists"] {
+            for args in macro_arguments(&source, name, 0, 1) {
+                let file = args[0].trim();
+                match self.local_file(&dir, file, "tex") {
+                    Some(local) => self.scan_file(&local)?,
+                    // \input{tikz} and friends load from the tree
+                    None if !file.is_empty() && !file.contains('\\') => {
+                        let name = if Path::new(file).extension().is_some() { file.to_owned() } else { String::from(file) + ".tex" };
+                        self.require(Requirement::file(&name));
+                    }
+                    None => {}
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn project_sources(dir: &Path, result: &mut Vec<PathBuf>) -> Result<(), DtMgrError> {
+    let entries = std::fs::read_dir(dir)
+        .map_err(|e| DtMgrError::ReadFile { path: dir.to_owned(), source: e })?;
+    let mut paths: Vec<PathBuf> = entries.filter_map(|e| e.ok()).map(|e| e.path()).collect();
+    paths.sort();
+
+    for path in paths {
+        let hidden = path.file_name()
+            .and_then(|n| n.to_str())
+            .is_some_and(|n| n.starts_with('.'));
+        if hidden {
+            continue;
+        }
+        if path.is_dir() {
+            project_sources(&path, result)?;
+        } else if path.extension().and_then(|e| e.to_str()).is_some_and(|e| SOURCE_EXTENSIONS.contains(&e)) {
+            result.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// Collects what the project loads, starting at `entry` or from every source file in the project
+pub fn scan_sources(project_dir: &Path, entry: Option<&Path>) -> Result<SourceScan, DtMgrError> {
+    let mut scanner = Scanner { project_dir, scan: SourceScan::default(), seen: Set::new() };
+
+    match entry {
+        Some(entry) => scanner.scan_file(entry)?,
+        None => {
+            let mut sources = Vec::new();
+            project_sources(project_dir, &mut sources)?;
+            for source in sources {
+                scanner.scan_file(&source)?;
+            }
+        }
+    }
+
+    Ok(scanner.scan)
+}
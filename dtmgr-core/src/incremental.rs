@@ -0,0 +1,150 @@
+use std::collections::BTreeMap as Map;
+use std::collections::BTreeSet as Set;
+use std::path::{Path, PathBuf};
+use serde::Serialize;
+use sha3::{Digest, Sha3_256};
+use tracing::info;
+use crate::manifest::Manifest;
+use crate::{executes, link, overrides, package_options, sources, texmf_cnf, DtMgrConfig, DtMgrError, TlPObjInfo};
+
+// where fmtutil-sys and updmap-sys write their output inside the environment
+const FORMATS_DIR: &str = "texmf-var/web2c";
+const FONT_MAPS_DIR: &str = "texmf-var/fonts/map";
+// the trees of the environment mktexlsr writes an ls-R into
+const DATABASE_TREES: [&str; 3] = ["texmf-dist", "texmf-config", "texmf-var"];
+
+/// The settings besides the packages that decide which files an install links
+#[derive(Serialize)]
+struct Linking<'a> {
+    exclude: &'a [String],
+    package_exclude: &'a Map<String, Vec<String>>,
+    sources: &'a Map<String, sources::Source>,
+    overrides: &'a Map<String, overrides::Override>,
+    use_texmflocal: bool,
+    use_texmfhome: bool,
+    extra_trees: &'a [PathBuf],
+    // formats keep the memory sizes they were built with, so they cannot be reused across a change
+    kpathsea: &'a Map<String, texmf_cnf::KpathseaValue>,
+    #[serde(skip_serializing_if = "Map::is_empty")]
+    package_options: &'a Map<String, package_options::PackageOptions>,
+}
+
+/// A hash of what decides which files `config` links, apart from the packages and their revisions
+pub fn linking_hash(config: &DtMgrConfig) -> Result<String, DtMgrError> {
+    let linking = Linking {
+        exclude: &config.exclude,
+        package_exclude: &config.package_exclude,
+        sources: &config.sources,
+        overrides: &config.overrides,
+        use_texmflocal: config.use_texmflocal,
+        use_texmfhome: config.use_texmfhome,
+        extra_trees: &config.extra_trees,
+        kpathsea: &config.kpathsea,
+        package_options: &config.package_options,
+    };
+    let bytes = postcard::to_stdvec(&linking)
+        .map_err(|e| DtMgrError::HashConfig { source: e })?;
+    Ok(hex::encode(Sha3_256::digest(bytes)))
+}
+
+/// Which of the post-install steps' output the previous environment has just as they would
+/// make it again
+#[derive(Debug, Default)]
+pub struct Reuse {
+    pub filename_databases: bool,
+    pub formats: bool,
+    pub font_maps: bool,
+}
+
+fn same_files(previous_dot_dir: &Path, dot_dir: &Path, files: &[&str]) -> bool {
+    files.iter().all(|file| {
+        match (std::fs::read(previous_dot_dir.join(file)), std::fs::read(dot_dir.join(file))) {
+            (Ok(previous), Ok(new)) => previous == new,
+            _ => false,
+        }
+    })
+}
+
+/// `roots` and everything they depend on in `dep_tree`
+pub fn closure<'a>(dep_tree: &'a Map<String, TlPObjInfo>, platform: &str, roots: impl Iterator<Item = &'a TlPObjInfo>) -> Set<&'a str> {
+    let mut result = Set::new();
+    let mut pending: Vec<&TlPObjInfo> = roots.collect();
+    while let Some(pkg) = pending.pop() {
+        if result.insert(pkg.name.as_str()) {
+            pending.extend(pkg.dependencies(platform).iter().filter_map(|dep| dep_tree.get(dep)));
+        }
+    }
+    result
+}
+
+impl Reuse {
+    /// Compares the environment being built in `dot_dir` from `dep_tree` with the previous one.
+    ///
+    /// Formats are kept if the packages that make them and everything those depend on are
+    /// unchanged, font maps if the packages adding maps are, and the filename databases only if
+    /// no package changed at all.
+    pub fn compare(previous_dot_dir: &Path, previous: &Manifest, dot_dir: &Path, new: &Manifest, dep_tree: &Map<String, TlPObjInfo>, platform: &str) -> Reuse {
+        if previous.linking.is_none() || previous.linking != new.linking || previous.platform != new.platform {
+            return Reuse::default();
+        }
+
+        let diff = previous.diff(new);
+        let changed: Set<&str> = diff.added.iter().map(String::as_str)
+            .chain(diff.changed.iter().map(|change| change.name.as_str()))
+            .collect();
+
+        let format_packages = closure(dep_tree, platform, dep_tree.values().filter(|pkg| executes::contributes_formats(pkg)));
+        let formats = same_files(previous_dot_dir, dot_dir, &executes::FORMAT_FILES)
+            && changed.iter().all(|name| !format_packages.contains(name));
+        let font_maps = same_files(previous_dot_dir, dot_dir, &executes::MAP_FILES)
+            && changed.iter().all(|name| !dep_tree.get(*name).is_some_and(executes::contributes_maps));
+        let filename_databases = formats && font_maps && changed.is_empty() && diff.removed.is_empty();
+        Reuse { filename_databases, formats, font_maps }
+    }
+
+    /// Copies what is reused from `previous_dot_dir`, which stays as it is in case the install
+    /// fails and it has to be put back
+    pub fn copy(&self, previous_dot_dir: &Path, dot_dir: &Path) -> Result<(), DtMgrError> {
+        if self.formats {
+            info!("formats are unchanged, keeping them");
+            copy_dir(&previous_dot_dir.join(FORMATS_DIR), &dot_dir.join(FORMATS_DIR))?;
+        }
+        if self.font_maps {
+            info!("font maps are unchanged, keeping them");
+            copy_dir(&previous_dot_dir.join(FONT_MAPS_DIR), &dot_dir.join(FONT_MAPS_DIR))?;
+        }
+        if self.filename_databases {
+            info!("no package changed, keeping the filename databases");
+            for tree in DATABASE_TREES {
+                let from = previous_dot_dir.join(tree).join("ls-R");
+                let to = dot_dir.join(tree).join("ls-R");
+                if from.is_file() {
+                    link::copy_file(&from, &to)
+                        .map_err(|e| DtMgrError::WriteFile { file: to, source: e })?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+fn copy_dir(from: &Path, to: &Path) -> Result<(), DtMgrError> {
+    if !from.is_dir() {
+        return Ok(());
+    }
+    std::fs::create_dir_all(to)
+        .map_err(|e| DtMgrError::CreateDirectory { dir: to.to_owned(), source: e })?;
+    let entries = std::fs::read_dir(from)
+        .map_err(|e| DtMgrError::ReadFile { path: from.to_owned(), source: e })?;
+    for entry in entries {
+        let entry = entry.map_err(|e| DtMgrError::ReadFile { path: from.to_owned(), source: e })?;
+        let target = to.join(entry.file_name());
+        if entry.path().is_dir() {
+            copy_dir(&entry.path(), &target)?;
+        } else {
+            link::copy_file(&entry.path(), &target)
+                .map_err(|e| DtMgrError::WriteFile { file: target, source: e })?;
+        }
+    }
+    Ok(())
+}
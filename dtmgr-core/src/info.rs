@@ -0,0 +1,151 @@
+use std::collections::BTreeMap as Map;
+use serde::Serialize;
+use crate::exclude::Exclusions;
+use crate::output::{self, OutputFormat};
+use crate::{executes, resolve_alias, size, texlive_platform, DtMgrConfig, DtMgrError, TlPObjInfo};
+
+/// What linking a package puts into an environment, by kind
+#[derive(Debug, Default, Serialize)]
+pub struct LinkedFiles<'a> {
+    /// Including those of the binary packages for the platform it depends on, like
+    /// `pdftex.x86_64-linux` for `pdftex`
+    pub binfiles: Vec<&'a str>,
+    pub runfiles: Vec<&'a str>,
+    pub docfiles: Vec<&'a str>,
+    pub srcfiles: Vec<&'a str>,
+    /// Files the project's `exclude` leaves out, which are in none of the lists
+    pub excluded: usize,
+}
+
+/// What `dtmgr info` shows about a package
+#[derive(Debug, Serialize)]
+pub struct PackageInfo<'a> {
+    pub name: &'a str,
+    pub description: Option<&'a str>,
+    pub category: Option<&'a str>,
+    /// As the CTAN catalogue has it
+    pub version: Option<&'a str>,
+    pub licenses: Vec<&'a str>,
+    pub installed_revision: Option<u64>,
+    pub available_revision: Option<u64>,
+    /// Direct dependencies, with `.ARCH` expanded to the platform
+    pub dependencies: Vec<String>,
+    /// Bytes the package takes up in an environment for the platform
+    pub size: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub files: Option<LinkedFiles<'a>>,
+}
+
+/// The files of `pkg` and the binary packages among `binaries` it depends on that an environment
+/// for `platform` links, without those `exclusions` leave out
+fn linked_files<'a>(pkg: &'a TlPObjInfo, binaries: &[&'a TlPObjInfo], platform: &str, exclusions: &Exclusions) -> LinkedFiles<'a> {
+    let mut files = LinkedFiles::default();
+    let mut excluded = 0;
+    for owner in std::iter::once(pkg).chain(binaries.iter().copied()) {
+        let owner_platform = owner.binary_platform().unwrap_or(platform);
+        let binfiles = owner.binfiles.as_ref().and_then(|b| b.get(owner_platform)).into_iter().flatten();
+        for file in binfiles {
+            if exclusions.excludes(&owner.name, file) {
+                excluded += 1;
+            } else {
+                files.binfiles.push(file);
+            }
+        }
+    }
+    // written by dtmgr for the whole environment rather than linked
+    let runfiles = pkg.runfiles.iter().flatten().filter(|file| !executes::GENERATED_RUNFILES.contains(&file.as_str()));
+    for (list, candidates) in [
+        (&mut files.runfiles, runfiles.map(String::as_str).collect::<Vec<_>>()),
+        (&mut files.docfiles, pkg.docfiles.iter().flatten().map(|doc| doc.file.as_str()).collect()),
+        (&mut files.srcfiles, pkg.srcfiles.iter().flatten().map(String::as_str).collect()),
+    ] {
+        for file in candidates {
+            if exclusions.excludes(&pkg.name, file) {
+                excluded += 1;
+            } else {
+                list.push(file);
+            }
+        }
+    }
+    files.excluded = excluded;
+    files
+}
+
+/// What TeX Live and its repository know about `name`, with the files an environment for
+/// `platform` would link if `with_files` is set
+fn package_info<'a>(name: &str, database: &'a Map<String, TlPObjInfo>, platform: &str, exclusions: &Exclusions, with_files: bool) -> Result<PackageInfo<'a>, DtMgrError> {
+    let Some(pkg) = database.get(name) else {
+        return Err(DtMgrError::UnknownPackage { package: name.to_owned() });
+    };
+    let dependencies = pkg.dependencies(platform);
+    let binaries: Vec<&TlPObjInfo> = pkg.depends.iter().flatten()
+        .filter_map(|dep| dep.strip_suffix(".ARCH"))
+        .filter_map(|stem| database.get(&format!("{}.{}", stem, platform)))
+        .collect();
+    let size = std::iter::once(pkg).chain(binaries.iter().copied())
+        .map(|pkg| size::package_size(pkg, platform))
+        .sum();
+    Ok(PackageInfo {
+        name: &pkg.name,
+        description: pkg.shortdesc.as_deref(),
+        category: pkg.category.as_deref(),
+        version: pkg.catalogue_version(),
+        licenses: pkg.licenses(),
+        installed_revision: pkg.lrev,
+        available_revision: pkg.rrev,
+        dependencies,
+        size,
+        files: with_files.then(|| linked_files(pkg, &binaries, platform, exclusions)),
+    })
+}
+
+/// Prints what `dtmgr info` shows about `name`, or its alias in `config`, for the project's
+/// platform
+pub fn show(config: &DtMgrConfig, name: &str, with_files: bool, format: OutputFormat) -> Result<(), DtMgrError> {
+    let database = crate::package_database()?;
+    let platform = texlive_platform(config)?;
+    let exclusions = Exclusions::new(config)?;
+    let info = package_info(resolve_alias(&config.aliases, name), &database, &platform, &exclusions, with_files)?;
+    match format {
+        OutputFormat::Human => info.print(),
+        OutputFormat::Json => output::print_json(&info)?,
+    }
+    Ok(())
+}
+
+impl PackageInfo<'_> {
+    pub fn print(&self) {
+        println!("{}{}", self.name, self.description.map(|d| format!(": {}", d)).unwrap_or_default());
+        if let Some(category) = self.category {
+            println!("category: {}", category);
+        }
+        if let Some(version) = self.version {
+            println!("version: {}", version);
+        }
+        if !self.licenses.is_empty() {
+            println!("licenses: {}", self.licenses.join(", "));
+        }
+        let revision = |revision: Option<u64>| revision.map(|r| format!("r{}", r)).unwrap_or_else(|| String::from("none"));
+        println!("installed: {}, available: {}", revision(self.installed_revision), revision(self.available_revision));
+        if !self.dependencies.is_empty() {
+            println!("depends on: {}", self.dependencies.join(", "));
+        }
+        println!("size: {}", size::format_size(self.size));
+
+        let Some(files) = &self.files else {
+            return;
+        };
+        for (kind, list) in [("binfiles", &files.binfiles), ("runfiles", &files.runfiles), ("docfiles", &files.docfiles), ("srcfiles", &files.srcfiles)] {
+            if list.is_empty() {
+                continue;
+            }
+            println!("{} ({}):", kind, list.len());
+            for file in list.iter() {
+                println!("  {}", file);
+            }
+        }
+        if files.excluded > 0 {
+            println!("{} files left out by `exclude`", files.excluded);
+        }
+    }
+}
@@ -0,0 +1,34 @@
+use std::fmt;
+use std::io::{BufReader, Read};
+use serde::de::{Deserializer, SeqAccess, Visitor};
+use crate::TlPObjInfo;
+
+// takes one package at a time off the array, so tlmgr's output is never held all at once
+struct Packages;
+
+impl<'de> Visitor<'de> for Packages {
+    type Value = Vec<TlPObjInfo>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("an array of TeX Live packages")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let mut result = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+        while let Some(pkg) = seq.next_element::<TlPObjInfo>()? {
+            result.push(pkg.unrelocate());
+        }
+        Ok(result)
+    }
+}
+
+/// Parses the output of `tlmgr info --json` while tlmgr writes it, keeping only the fields of
+/// [`TlPObjInfo`]
+pub fn read_packages(mut reader: impl Read) -> Result<Vec<TlPObjInfo>, serde_json::Error> {
+    let mut deserializer = serde_json::Deserializer::from_reader(BufReader::new(&mut reader));
+    let packages = deserializer.deserialize_seq(Packages)
+        .and_then(|packages| deserializer.end().map(|()| packages));
+    // after a parse error, tlmgr would otherwise block writing the rest into a full pipe
+    let _ = std::io::copy(&mut reader, &mut std::io::sink());
+    packages
+}
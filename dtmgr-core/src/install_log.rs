@@ -0,0 +1,130 @@
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, ExitStatus, Stdio};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Instant, SystemTime};
+use crate::{events, report, signals, DtMgrError};
+
+pub const LOGS_DIR_NAME: &str = "logs";
+
+// older logs are deleted when a new install starts
+const KEPT_LOGS: usize = 10;
+
+static QUIET: AtomicBool = AtomicBool::new(false);
+
+/// Keeps tools' output out of the terminal (`--quiet`)
+pub fn set_quiet() {
+    QUIET.store(true, Ordering::Relaxed);
+}
+
+pub fn quiet() -> bool {
+    QUIET.load(Ordering::Relaxed)
+}
+
+/// The output of every tool one `dtmgr install` ran, in `.dtmgr/logs`
+pub struct InstallLog {
+    path: PathBuf,
+    file: Mutex<File>,
+}
+
+fn rotate_logs(logs_dir: &Path) {
+    let Ok(entries) = std::fs::read_dir(logs_dir) else {
+        return;
+    };
+    let mut logs: Vec<PathBuf> = entries.filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| p.file_name().is_some_and(|n| n.to_string_lossy().starts_with("install-")))
+        .collect();
+    // timestamps in the names sort chronologically
+    logs.sort();
+
+    let excess = (logs.len() + 1).saturating_sub(KEPT_LOGS);
+    for old in logs.iter().take(excess) {
+        let _ = std::fs::remove_file(old);
+    }
+}
+
+/// Copies `from` into the log and, if given, to the terminal and `captured`
+fn tee(mut from: impl Read, log: &Mutex<File>, mut echo: Option<impl Write>, mut captured: Option<&mut Vec<u8>>) {
+    let mut buffer = [0u8; 8192];
+    while let Ok(n) = from.read(&mut buffer) {
+        if n == 0 {
+            break;
+        }
+        let chunk = &buffer[..n];
+        if let Ok(mut file) = log.lock() {
+            let _ = file.write_all(chunk);
+        }
+        if let Some(echo) = echo.as_mut() {
+            let _ = echo.write_all(chunk);
+            let _ = echo.flush();
+        }
+        if let Some(captured) = captured.as_mut() {
+            captured.extend_from_slice(chunk);
+        }
+    }
+}
+
+impl InstallLog {
+    /// Starts a new log named after the current time, removing the oldest ones
+    pub fn create(dot_dir: &Path) -> Result<InstallLog, DtMgrError> {
+        let logs_dir = dot_dir.join(LOGS_DIR_NAME);
+        std::fs::create_dir_all(&logs_dir)
+            .map_err(|e| DtMgrError::CreateDirectory { dir: logs_dir.clone(), source: e })?;
+        rotate_logs(&logs_dir);
+
+        // `:` is not allowed in file names on Windows
+        let timestamp = humantime::format_rfc3339_seconds(SystemTime::now()).to_string().replace(':', "-");
+        let path = logs_dir.join(format!("install-{}.log", timestamp));
+        let file = File::create(&path)
+            .map_err(|e| DtMgrError::WriteFile { file: path.clone(), source: e })?;
+        Ok(InstallLog { path, file: Mutex::new(file) })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Runs `cmd` with its stdout and stderr going to the log, and to the terminal as well if `echo`.
+    ///
+    /// Returns the exit status and everything the command wrote to stderr. During an install, the
+    /// command is added to its report as a step.
+    pub fn run(&self, description: &str, cmd: &mut Command, input: Option<&[u8]>, echo: bool) -> Result<(ExitStatus, Vec<u8>), DtMgrError> {
+        if let Ok(mut file) = self.file.lock() {
+            let _ = writeln!(file, "$ {}", description);
+        }
+
+        let started = Instant::now();
+        cmd.stdin(if input.is_some() { Stdio::piped() } else { Stdio::inherit() })
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+        let mut child = cmd.spawn()
+            .map_err(|e| DtMgrError::CommandExecution { source: e })?;
+        let _running = signals::Running::new(&child);
+        if let Some(input) = input {
+            child.stdin.take()
+                .expect("stdin was piped")
+                .write_all(input)
+                .map_err(|e| DtMgrError::CommandExecution { source: e })?;
+        }
+
+        let stdout = child.stdout.take().expect("stdout was piped");
+        let stderr = child.stderr.take().expect("stderr was piped");
+        let mut captured = Vec::new();
+        let status = std::thread::scope(|scope| {
+            scope.spawn(|| tee(stdout, &self.file, echo.then(std::io::stdout), None));
+            scope.spawn(|| tee(stderr, &self.file, echo.then(std::io::stderr), Some(&mut captured)));
+            signals::wait(&mut child, cmd)
+        })?;
+
+        if let Ok(mut file) = self.file.lock() {
+            let _ = writeln!(file, "[{}]\n", status);
+        }
+        let seconds = started.elapsed().as_secs_f64();
+        report::record_step(description, seconds, status.code());
+        events::emit(events::Event::CommandFinished { command: description.to_owned(), seconds, status: status.code() });
+        Ok((status, captured))
+    }
+}
@@ -0,0 +1,223 @@
+use std::collections::BTreeMap as Map;
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
+use tracing::debug;
+use crate::DtMgrError;
+
+// the program TeX Live for Windows copies as the `.exe` of each of its scripts, which looks for the
+// script and its interpreter relative to where TeX Live is installed
+const RUNSCRIPT: &str = "runscript.exe";
+// below `.dtmgr`, as `texmf-dist/scripts` in TeX Live
+const SCRIPTS_DIR: &str = "texmf-dist/scripts";
+// what runscript finds Perl in when TeX Live brings its own
+const TLPERL: &str = "tlpkg/tlperl/bin/perl.exe";
+// the extensions runscript tries, in its order, with the interpreter each needs and its options
+const SCRIPT_INTERPRETERS: [(&str, &str, &str); 8] = [
+    ("tlu", "texlua", ""),
+    ("texlua", "texlua", ""),
+    ("lua", "texlua", ""),
+    ("pl", "perl", ""),
+    ("py", "python", ""),
+    ("rb", "ruby", ""),
+    ("tcl", "tclsh", ""),
+    ("jar", "java", "-jar "),
+];
+
+// the variables a launcher sets are paths, which it has to spell out as text
+fn utf8<'a>(what: &str, value: &'a OsStr) -> Result<&'a str, DtMgrError> {
+    value.to_str().ok_or_else(|| DtMgrError::NotUnicode { what: String::from(what) })
+}
+
+/// The launcher for `tool` in `platform_bin` and the name to give it, if `tool` is a program
+#[cfg(unix)]
+fn launcher(tool: &Path, platform_bin: &str, texmfcnf: &str) -> Option<(String, String)> {
+    use crate::env::shell_quote;
+    if !crate::which::is_executable(tool) {
+        return None;
+    }
+    let name = tool.file_name()?.to_str()?;
+    let script = format!(
+        "#!/bin/sh\n\
+        # written by dtmgr, runs {name} in the environment\n\
+        PATH={bin}:\"$PATH\"\n\
+        TEXMFCNF={texmfcnf}\n\
+        export PATH TEXMFCNF\n\
+        exec {program} \"$@\"\n",
+        name = name,
+        bin = shell_quote(platform_bin),
+        texmfcnf = shell_quote(texmfcnf),
+        program = shell_quote(&format!("{}/{}", platform_bin, name)),
+    );
+    Some((String::from(name), script))
+}
+/// The launcher for `tool` in `platform_bin` and the name to give it, if `tool` is a program
+#[cfg(windows)]
+fn launcher(tool: &Path, platform_bin: &str, texmfcnf: &str) -> Option<(String, String)> {
+    let extension = tool.extension()?.to_str()?.to_ascii_lowercase();
+    if !["exe", "bat", "cmd"].contains(&extension.as_str()) {
+        return None;
+    }
+    let file_name = tool.file_name()?.to_str()?;
+    let stem = tool.file_stem()?.to_str()?;
+    let script = format!(
+        "@echo off\r\n\
+        rem written by dtmgr, runs {stem} in the environment\r\n\
+        setlocal\r\n\
+        set \"PATH={bin};%PATH%\"\r\n\
+        set \"TEXMFCNF={texmfcnf}\"\r\n\
+        \"{bin}\\{file_name}\" %*\r\n",
+        stem = stem,
+        bin = platform_bin,
+        texmfcnf = texmfcnf,
+        file_name = file_name,
+    );
+    Some((String::from(stem) + ".cmd", script))
+}
+
+/// Where the shims for TeX Live's scripts are, which go on `PATH` in front of the stubs they stand
+/// in for
+pub fn shims_dir(dot_dir: &Path) -> PathBuf {
+    dot_dir.join("bin").join("shims")
+}
+
+// each script below `dir` runscript could run, by its name without the extension
+fn find_scripts(dir: &Path, scripts: &mut Map<String, (usize, PathBuf)>) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+    for path in entries.filter_map(|entry| entry.ok()).map(|entry| entry.path()) {
+        if path.is_dir() {
+            find_scripts(&path, scripts);
+            continue;
+        }
+        let (Some(stem), Some(extension)) = (path.file_stem().and_then(OsStr::to_str), path.extension().and_then(OsStr::to_str)) else {
+            continue;
+        };
+        let Some(rank) = SCRIPT_INTERPRETERS.iter().position(|(known, _, _)| extension.eq_ignore_ascii_case(known)) else {
+            continue;
+        };
+        let stem = stem.to_ascii_lowercase();
+        if scripts.get(&stem).is_none_or(|(found, _)| rank < *found) {
+            scripts.insert(stem, (rank, path));
+        }
+    }
+}
+
+/// Writes a `.cmd` into [`shims_dir`] for each runscript stub in `platform_dir`, which runs the
+/// stub's script from the environment with the environment's interpreter, and returns them by
+/// the stub's name.
+///
+/// The stubs look for both next to TeX Live's own `bin` directory, which the environment does not
+/// have in the same place. Stubs whose script has another name, like runscript's aliases, are left
+/// as they are.
+fn write_script_shims(dot_dir: &Path, platform_dir: &Path, platform_bin: &str, texmfcnf: &str) -> Result<Map<String, String>, DtMgrError> {
+    let mut shims = Map::new();
+    let Ok(runscript) = std::fs::read(platform_dir.join(RUNSCRIPT)) else {
+        return Ok(shims);
+    };
+    let mut scripts = Map::new();
+    find_scripts(&dot_dir.join(SCRIPTS_DIR), &mut scripts);
+
+    let shims_dir = shims_dir(dot_dir);
+    let shims_bin = utf8("the environment's shims directory", shims_dir.as_os_str())?;
+    let entries = std::fs::read_dir(platform_dir)
+        .map_err(|e| DtMgrError::ReadFile { path: platform_dir.to_owned(), source: e })?;
+    for tool in entries.filter_map(|entry| entry.ok()).map(|entry| entry.path()) {
+        let Some(stem) = tool.file_stem().and_then(OsStr::to_str) else {
+            continue;
+        };
+        let is_stub = tool.extension().is_some_and(|extension| extension.eq_ignore_ascii_case("exe"))
+            && tool.metadata().is_ok_and(|metadata| metadata.len() == runscript.len() as u64)
+            && std::fs::read(&tool).is_ok_and(|content| content == runscript);
+        let Some((rank, script)) = scripts.get(&stem.to_ascii_lowercase()).filter(|_| is_stub) else {
+            continue;
+        };
+        let (_, interpreter, options) = SCRIPT_INTERPRETERS[*rank];
+        let interpreter = match interpreter {
+            "texlua" if platform_dir.join("texlua.exe").is_file() => platform_dir.join("texlua.exe"),
+            "perl" if dot_dir.join(TLPERL).is_file() => dot_dir.join(TLPERL),
+            // from PATH, as runscript takes them
+            other => PathBuf::from(other),
+        };
+        let shim = format!(
+            "@echo off\r\n\
+            rem written by dtmgr, runs the script of {stem} in the environment\r\n\
+            setlocal\r\n\
+            set \"PATH={shims};{bin};%PATH%\"\r\n\
+            set \"TEXMFCNF={texmfcnf}\"\r\n\
+            \"{interpreter}\" {options}\"{script}\" %*\r\n",
+            stem = stem,
+            shims = shims_bin,
+            bin = platform_bin,
+            texmfcnf = texmfcnf,
+            interpreter = utf8("the script's interpreter", interpreter.as_os_str())?,
+            options = options,
+            script = utf8("the script's path", script.as_os_str())?,
+        );
+        shims.insert(String::from(stem), shim);
+    }
+
+    if !shims.is_empty() {
+        debug!("writing shims for {} scripts", shims.len());
+        std::fs::create_dir_all(&shims_dir)
+            .map_err(|e| DtMgrError::CreateDirectory { dir: shims_dir.clone(), source: e })?;
+        for (stem, shim) in shims.iter() {
+            let file = shims_dir.join(format!("{}.cmd", stem));
+            std::fs::write(&file, shim)
+                .map_err(|e| DtMgrError::WriteFile { file: file.clone(), source: e })?;
+        }
+    }
+    Ok(shims)
+}
+
+/// Lets `path` be run as a program, which on Windows its extension does
+#[cfg(unix)]
+pub fn make_executable(path: &Path) -> Result<(), DtMgrError> {
+    use std::os::unix::fs::PermissionsExt;
+    std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o755))
+        .map_err(|e| DtMgrError::WriteFile { file: path.to_owned(), source: e })
+}
+#[cfg(not(unix))]
+pub fn make_executable(_path: &Path) -> Result<(), DtMgrError> {
+    Ok(())
+}
+
+/// Writes a launcher into `.dtmgr/bin` for each program in `.dtmgr/bin/<platform>`, which sets up
+/// the environment the way `dtmgr run` does and then starts the program.
+///
+/// With `.dtmgr/bin` on `PATH`, or an editor pointed at a launcher, tools run in the environment
+/// without going through dtmgr. The launchers hold absolute paths, so a moved project needs
+/// another `dtmgr install`. On Windows, the launcher of a script TeX Live starts through a
+/// runscript stub is its shim, see [`shims_dir`].
+pub fn write_launchers(dot_dir: &Path, platform: &str, texmfcnf: &OsStr) -> Result<(), DtMgrError> {
+    let bin_dir = dot_dir.join("bin");
+    let platform_dir = bin_dir.join(platform);
+    let platform_bin = utf8("the environment's bin directory", platform_dir.as_os_str())?;
+    let texmfcnf = utf8("TEXMFCNF", texmfcnf)?;
+
+    if !platform_dir.is_dir() {
+        // none of the packages has binaries
+        return Ok(());
+    }
+    let shims = match cfg!(windows) {
+        true => write_script_shims(dot_dir, &platform_dir, platform_bin, texmfcnf)?,
+        false => Map::new(),
+    };
+    let entries = std::fs::read_dir(&platform_dir)
+        .map_err(|e| DtMgrError::ReadFile { path: platform_dir.clone(), source: e })?;
+    for entry in entries.filter_map(|entry| entry.ok()) {
+        let Some((name, script)) = launcher(&entry.path(), platform_bin, texmfcnf) else {
+            continue;
+        };
+        let script = entry.path().file_stem()
+            .and_then(OsStr::to_str)
+            .and_then(|stem| shims.get(stem))
+            .cloned()
+            .unwrap_or(script);
+        let file = bin_dir.join(name);
+        std::fs::write(&file, script)
+            .map_err(|e| DtMgrError::WriteFile { file: file.clone(), source: e })?;
+        make_executable(&file)?;
+    }
+    Ok(())
+}
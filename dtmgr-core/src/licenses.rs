@@ -0,0 +1,84 @@
+use std::collections::BTreeMap as Map;
+use std::collections::BTreeSet as Set;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use crate::{DtMgrError, TlPObjInfo};
+
+/// License policy declared under `[licenses]` in `dtmgr.toml`.
+///
+/// Keys are CTAN catalogue license keys (`lppl1.3c`, `gpl2+`, `nosell`, ...).
+/// If `allow` is non-empty every license of a package must be listed in it;
+/// any license listed in `deny` is rejected regardless.
+#[derive(Debug, Default, Deserialize, JsonSchema, Serialize, Hash)]
+#[serde(deny_unknown_fields)]
+pub struct LicensePolicy {
+    #[serde(default)]
+    allow: Set<String>,
+    #[serde(default)]
+    deny: Set<String>,
+}
+
+impl LicensePolicy {
+    pub fn is_empty(&self) -> bool {
+        self.allow.is_empty() && self.deny.is_empty()
+    }
+
+    fn permits(&self, license: &str) -> bool {
+        !self.deny.contains(license) && (self.allow.is_empty() || self.allow.contains(license))
+    }
+}
+
+fn violations<'a>(policy: &LicensePolicy, packages: &'a Map<String, TlPObjInfo>) -> Vec<(&'a str, &'a str)> {
+    let mut result = Vec::new();
+    for pkg in packages.values() {
+        for license in pkg.licenses() {
+            if !policy.permits(license) {
+                result.push((pkg.name.as_str(), license));
+            }
+        }
+    }
+    result
+}
+
+pub fn check_license_policy(policy: &LicensePolicy, packages: &Map<String, TlPObjInfo>) -> Result<(), DtMgrError> {
+    let found = violations(policy, packages);
+    if found.is_empty() {
+        return Ok(());
+    }
+
+    let described: Vec<String> = found.iter()
+        .map(|(pkg, license)| format!("{} ({})", pkg, license))
+        .collect();
+    Err(DtMgrError::LicensePolicy { violations: described.join(", ") })
+}
+
+pub fn print_license_report(policy: &LicensePolicy, packages: &Map<String, TlPObjInfo>) {
+    let mut by_license: Map<&str, Vec<&str>> = Map::new();
+    let mut unknown: Vec<&str> = Vec::new();
+
+    for pkg in packages.values() {
+        let licenses = pkg.licenses();
+        if licenses.is_empty() {
+            unknown.push(pkg.name.as_str());
+        }
+        for license in licenses {
+            by_license.entry(license).or_default().push(pkg.name.as_str());
+        }
+    }
+
+    for (license, pkgs) in by_license.iter() {
+        let marker = if policy.permits(license) { "" } else { " [disallowed]" };
+        println!("{} ({}){}", license, pkgs.len(), marker);
+        for pkg in pkgs {
+            println!("    {}", pkg);
+        }
+    }
+
+    // collections, schemes and per-platform binary packages carry no catalogue data
+    if !unknown.is_empty() {
+        println!("unknown ({})", unknown.len());
+        for pkg in unknown {
+            println!("    {}", pkg);
+        }
+    }
+}
@@ -0,0 +1,147 @@
+use std::path::Path;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use tracing::trace;
+use crate::long_path;
+
+/// How files from TeX Live are put into an environment
+#[derive(Clone, Copy, Debug, Default, Deserialize, JsonSchema, Serialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "kebab-case")]
+pub enum LinkStrategy {
+    #[default]
+    Symlink,
+    /// Hard links (or copies, across volumes) for files and junctions for directories, for
+    /// Windows users who may not create symlinks
+    Hardlink,
+    /// Copies, cloned where the file system can; only ever asked for by a package's `link`
+    /// option, for packages that misbehave behind links
+    Copy,
+}
+
+#[cfg(windows)]
+fn create_symlink(target: &Path, name: &Path) -> std::io::Result<()> {
+    if target.is_dir() {
+        std::os::windows::fs::symlink_dir(target, name)
+    } else {
+        std::os::windows::fs::symlink_file(target, name)
+    }
+}
+#[cfg(unix)]
+fn create_symlink(target: &Path, name: &Path) -> std::io::Result<()> {
+    std::os::unix::fs::symlink(target, name)
+}
+
+/// Symlinks if this user may create them in `dot_dir`, which on Windows needs Developer Mode
+/// or the "Create symbolic links" right
+#[cfg(windows)]
+pub fn detect_strategy(dot_dir: &Path) -> LinkStrategy {
+    use tracing::{debug, info};
+
+    let target = dot_dir.join(".link-probe-target");
+    let probe = dot_dir.join(".link-probe");
+    let result = std::fs::write(&target, b"")
+        .and_then(|()| create_symlink(&target, &probe));
+    let _ = std::fs::remove_file(&probe);
+    let _ = std::fs::remove_file(&target);
+
+    match result {
+        Ok(()) => LinkStrategy::Symlink,
+        Err(e) => {
+            debug!("unable to create a symlink: {}", e);
+            info!("symlinks are not available, using hard links and junctions instead");
+            LinkStrategy::Hardlink
+        }
+    }
+}
+#[cfg(unix)]
+pub fn detect_strategy(_dot_dir: &Path) -> LinkStrategy {
+    LinkStrategy::Symlink
+}
+
+// `mklink /J` needs no special rights, unlike directory symlinks
+#[cfg(windows)]
+fn create_junction(target: &Path, name: &Path) -> std::io::Result<()> {
+    // cmd.exe takes no `\\?\` paths, and no paths beyond `MAX_PATH` either way
+    let out = std::process::Command::new("cmd")
+        .arg("/c")
+        .arg("mklink")
+        .arg("/J")
+        .arg(long_path::plain(name))
+        .arg(long_path::plain(target))
+        .output()?;
+    if out.status.success() {
+        Ok(())
+    } else {
+        Err(std::io::Error::other(String::from_utf8_lossy(&out.stderr).trim().to_owned()))
+    }
+}
+#[cfg(unix)]
+fn create_junction(target: &Path, name: &Path) -> std::io::Result<()> {
+    create_symlink(target, name)
+}
+
+// FICLONE shares the extents on btrfs and XFS, and fails elsewhere or across file systems
+#[cfg(target_os = "linux")]
+fn clone_file(from: &Path, to: &Path) -> std::io::Result<()> {
+    use std::os::fd::AsRawFd;
+
+    let source = std::fs::File::open(from)?;
+    let permissions = source.metadata()?.permissions();
+    let target = std::fs::File::create(to)?;
+    // SAFETY: both descriptors stay open for the call
+    if unsafe { libc::ioctl(target.as_raw_fd(), libc::FICLONE, source.as_raw_fd()) } != 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    target.set_permissions(permissions)
+}
+// clonefile shares the blocks on APFS; it never replaces a file, so one in the way is copied over
+#[cfg(target_os = "macos")]
+fn clone_file(from: &Path, to: &Path) -> std::io::Result<()> {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+
+    if to.symlink_metadata().is_ok() {
+        return Err(std::io::Error::from(std::io::ErrorKind::AlreadyExists));
+    }
+    let source = CString::new(from.as_os_str().as_bytes())?;
+    let target = CString::new(to.as_os_str().as_bytes())?;
+    // SAFETY: both paths are NUL-terminated and outlive the call
+    if unsafe { libc::clonefile(source.as_ptr(), target.as_ptr(), 0) } != 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(())
+}
+// since Windows 11 24H2, the `CopyFileEx` that `std::fs::copy` calls clones blocks on ReFS by itself
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+fn clone_file(_from: &Path, _to: &Path) -> std::io::Result<()> {
+    Err(std::io::Error::from(std::io::ErrorKind::Unsupported))
+}
+
+/// Copies `from` to `to`, sharing the data with `from` where the file system can (reflinks on
+/// btrfs and XFS, clones on APFS and ReFS), so that a copy costs next to no time or space
+pub fn copy_file(from: &Path, to: &Path) -> std::io::Result<()> {
+    let (from, to) = (&long_path::extended(from), &long_path::extended(to));
+    match clone_file(from, to) {
+        Ok(()) => Ok(()),
+        Err(e) => {
+            trace!("unable to clone {}, copying it: {}", from.display(), e);
+            std::fs::copy(from, to).map(|_| ())
+        }
+    }
+}
+
+/// Makes `name` refer to `target` the way `strategy` says
+pub fn create_link(strategy: LinkStrategy, target: &Path, name: &Path) -> std::io::Result<()> {
+    // a link keeps its target as given, which Windows only follows past `MAX_PATH` with `\\?\`;
+    // a relative one is as long as it was in TeX Live
+    let target = if target.is_absolute() { long_path::extended(target) } else { target.to_owned() };
+    let (target, name) = (target.as_path(), &long_path::extended(name));
+    match strategy {
+        LinkStrategy::Symlink => create_symlink(target, name),
+        LinkStrategy::Hardlink if target.is_dir() => create_junction(target, name),
+        // hard links cannot cross volumes
+        LinkStrategy::Hardlink => std::fs::hard_link(target, name)
+            .or_else(|_| copy_file(target, name)),
+        LinkStrategy::Copy => copy_file(target, name),
+    }
+}
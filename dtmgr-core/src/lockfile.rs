@@ -0,0 +1,45 @@
+use std::collections::BTreeMap as Map;
+use std::path::Path;
+use serde::{Deserialize, Serialize};
+use crate::DtMgrError;
+
+pub const LOCKFILE_NAME: &str = "dtmgr.lock";
+
+/// A download dtmgr made for the project, so that later ones can be checked against it
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct LockedArchive {
+    pub version: String,
+    pub url: String,
+    #[serde(rename = "sha3-256")]
+    pub sha3_256: String,
+}
+
+/// `dtmgr.lock`, next to `dtmgr.toml` and meant to be committed with it
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct Lockfile {
+    /// `[sources]` entries downloaded from CTAN, by name
+    #[serde(default, skip_serializing_if = "Map::is_empty")]
+    pub ctan: Map<String, LockedArchive>,
+}
+
+impl Lockfile {
+    /// The project's lockfile, or an empty one if there is none yet
+    pub fn read(dtmgr_directory: &Path) -> Result<Lockfile, DtMgrError> {
+        let path = dtmgr_directory.join(LOCKFILE_NAME);
+        if !path.is_file() {
+            return Ok(Lockfile::default());
+        }
+        let content = std::fs::read_to_string(&path)
+            .map_err(|e| DtMgrError::ReadFile { path: path.clone(), source: e })?;
+        toml::from_str(&content)
+            .map_err(|e| DtMgrError::ParseLockfile { source: e })
+    }
+
+    pub fn write(&self, dtmgr_directory: &Path) -> Result<(), DtMgrError> {
+        let file = dtmgr_directory.join(LOCKFILE_NAME);
+        let content = String::from("# written by dtmgr, do not edit\n")
+            + &toml::to_string(self).map_err(|e| DtMgrError::SerializeLockfile { source: e })?;
+        std::fs::write(&file, content)
+            .map_err(|e| DtMgrError::WriteFile { file, source: e })
+    }
+}
@@ -0,0 +1,56 @@
+use std::path::{Path, PathBuf};
+
+// the longest path Windows takes without the `\\?\` prefix, less the 12 characters
+// `CreateDirectoryW` keeps free for a file name of 8.3 characters
+#[cfg(windows)]
+const MAX_PATH: usize = 248;
+
+#[cfg(windows)]
+const VERBATIM_PREFIX: &str = r"\\?\";
+#[cfg(windows)]
+const VERBATIM_UNC_PREFIX: &str = r"\\?\UNC\";
+
+/// `path` in the form Windows takes beyond `MAX_PATH` if it is that long: absolute, with `/`,
+/// `.` and `..` resolved and `\\?\` in front. Shorter paths are left as they are, so that links
+/// to them look the same as ever.
+#[cfg(windows)]
+pub fn extended(path: &Path) -> PathBuf {
+    let Some(text) = path.to_str() else {
+        return path.to_owned();
+    };
+    if text.len() < MAX_PATH || text.starts_with(VERBATIM_PREFIX) {
+        return path.to_owned();
+    }
+    // a verbatim path is taken as it is, so it has to be normalized first
+    let Some(absolute) = std::path::absolute(path).ok().and_then(|absolute| absolute.to_str().map(str::to_owned)) else {
+        return path.to_owned();
+    };
+    match absolute.strip_prefix(r"\\") {
+        Some(unc) => PathBuf::from(format!("{}{}", VERBATIM_UNC_PREFIX, unc)),
+        None => PathBuf::from(format!("{}{}", VERBATIM_PREFIX, absolute)),
+    }
+}
+#[cfg(not(windows))]
+pub fn extended(path: &Path) -> PathBuf {
+    path.to_owned()
+}
+
+/// `path` without the `\\?\` that [`extended`] or `canonicalize` put in front, for tools such
+/// as kpathsea that only read plain paths
+#[cfg(windows)]
+pub fn plain(path: &Path) -> PathBuf {
+    let Some(text) = path.to_str() else {
+        return path.to_owned();
+    };
+    if let Some(unc) = text.strip_prefix(VERBATIM_UNC_PREFIX) {
+        PathBuf::from(format!(r"\\{}", unc))
+    } else if let Some(local) = text.strip_prefix(VERBATIM_PREFIX) {
+        PathBuf::from(local)
+    } else {
+        path.to_owned()
+    }
+}
+#[cfg(not(windows))]
+pub fn plain(path: &Path) -> PathBuf {
+    path.to_owned()
+}
@@ -0,0 +1,47 @@
+use std::fmt::Write as _;
+use std::path::Path;
+use crate::DtMgrError;
+
+const LS_R: &str = "ls-R";
+/// The first line kpathsea looks for before trusting a database
+const MAGIC: &str = "% ls-R -- filename database for kpathsea; do not change this line.";
+
+/// Lists `dir` and everything below it the way `ls -R` does, with `relative` as its name
+fn list(dir: &Path, relative: &str, result: &mut String) -> Result<(), DtMgrError> {
+    let mut entries: Vec<(String, bool)> = std::fs::read_dir(dir)
+        .map_err(|e| DtMgrError::ReadFile { path: dir.to_owned(), source: e })?
+        .filter_map(|entry| entry.ok())
+        // follows links, as directories may be junctions on Windows
+        .map(|entry| (entry.file_name().to_string_lossy().into_owned(), entry.path().is_dir()))
+        .collect();
+    entries.sort();
+
+    // directories after the first are separated by an empty line
+    if relative != "./" {
+        result.push('\n');
+    }
+    let _ = writeln!(result, "{}:", relative);
+    for (name, _) in entries.iter() {
+        let _ = writeln!(result, "{}", name);
+    }
+    // like mktexlsr, leaves out what is inside hidden directories such as `.git`
+    for (name, _) in entries.iter().filter(|(name, is_dir)| *is_dir && !name.starts_with('.')) {
+        list(&dir.join(name), &format!("{}/{}", relative.trim_end_matches('/'), name), result)?;
+    }
+    Ok(())
+}
+
+/// Writes the `ls-R` of `tree`, as `mktexlsr` would but without starting it, and skipping
+/// trees that do not exist
+pub fn write(tree: &Path) -> Result<(), DtMgrError> {
+    if !tree.is_dir() {
+        return Ok(());
+    }
+    let mut content = String::from(MAGIC);
+    content.push('\n');
+    list(tree, "./", &mut content)?;
+
+    let file = tree.join(LS_R);
+    std::fs::write(&file, content)
+        .map_err(|e| DtMgrError::WriteFile { file, source: e })
+}
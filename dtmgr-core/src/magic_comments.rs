@@ -0,0 +1,168 @@
+use std::collections::BTreeMap as Map;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use serde::{Deserialize, Serialize};
+use tracing::{debug, warn};
+use crate::CONFIG_FILE_NAME;
+
+const SOURCE_EXTENSIONS: [&str; 3] = ["tex", "sty", "cls"];
+// like TeXShop's `% !TEX program = ...`, which editors already leave alone
+const MAGIC: &str = "!dtmgr";
+// in `.dtmgr`, so that every config parse, e.g. for `dtmgr run` or a shell prompt, only reads
+// the sources changed since
+const CACHE_FILE_NAME: &str = "magic-comments.json";
+
+/// A package a `% !dtmgr dep: name` comment in one of the project's sources declares
+#[derive(Debug)]
+pub struct Declared {
+    pub package: String,
+    /// Relative to the project
+    pub file: PathBuf,
+    pub line: usize,
+}
+
+impl Declared {
+    /// Where the comment is, e.g. `chapters/results.tex:12`
+    pub fn location(&self) -> String {
+        format!("{}:{}", self.file.display(), self.line)
+    }
+}
+
+/// The `% !dtmgr` comments of one source, by line number
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct MagicComments {
+    /// The packages `% !dtmgr dep:` declares. A comment may declare several, separated by
+    /// commas or spaces.
+    pub declared: Vec<(usize, String)>,
+    /// Comments starting with `!dtmgr` that are not `dep:`, as they are written
+    pub unknown: Vec<(usize, String)>,
+}
+
+/// The `% !dtmgr` comments of `content`
+pub fn parse_magic_comments(content: &str) -> MagicComments {
+    let mut result = MagicComments::default();
+    for (idx, line) in content.lines().enumerate() {
+        let Some(comment) = line.trim_start().strip_prefix('%') else {
+            continue;
+        };
+        let Some(directive) = comment.trim_start().strip_prefix(MAGIC) else {
+            continue;
+        };
+        match directive.trim_start().split_once(':') {
+            Some(("dep", packages)) => {
+                let packages = packages.split([',', ' ', '\t']).filter(|package| !package.is_empty());
+                result.declared.extend(packages.map(|package| (idx + 1, package.to_owned())));
+            }
+            _ => result.unknown.push((idx + 1, format!("%{}", comment))),
+        }
+    }
+    result
+}
+
+// what a source had when it was last read, valid while its size and modification time stay the
+// same
+#[derive(Deserialize, Serialize)]
+struct CachedSource {
+    modified: SystemTime,
+    len: u64,
+    comments: MagicComments,
+}
+
+type Cache = Map<PathBuf, CachedSource>;
+
+// the sources below `dir` with their metadata, leaving out hidden directories such as
+// `.dtmgr` and projects of their own. Symlinked directories are not followed, so that a link
+// to a parent cannot send this around in circles.
+fn project_sources(dir: &Path, result: &mut Vec<(PathBuf, std::fs::Metadata)>) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        debug!("unable to read {} for dtmgr comments", dir.display());
+        return;
+    };
+    let mut entries: Vec<std::fs::DirEntry> = entries.filter_map(|e| e.ok()).collect();
+    entries.sort_by_key(|e| e.path());
+
+    for entry in entries {
+        let hidden = entry.file_name().to_str().is_some_and(|n| n.starts_with('.'));
+        if hidden {
+            continue;
+        }
+        let Ok(file_type) = entry.file_type() else {
+            continue;
+        };
+        let path = entry.path();
+        if file_type.is_dir() {
+            if !path.join(CONFIG_FILE_NAME).is_file() {
+                project_sources(&path, result);
+            }
+        } else if path.extension().and_then(|e| e.to_str()).is_some_and(|e| SOURCE_EXTENSIONS.contains(&e))
+            && let Ok(metadata) = std::fs::metadata(&path) {
+            result.push((path, metadata));
+        }
+    }
+}
+
+fn read_cache(dot_dir: &Path) -> Cache {
+    std::fs::read(dot_dir.join(CACHE_FILE_NAME)).ok()
+        .and_then(|content| serde_json::from_slice(&content).ok())
+        .unwrap_or_default()
+}
+
+// only into an environment that is already there; parsing a config does not create one
+fn write_cache(dot_dir: &Path, cache: &Cache) {
+    if !dot_dir.is_dir() {
+        return;
+    }
+    let written = serde_json::to_vec(cache).map_err(std::io::Error::other)
+        .and_then(|content| std::fs::write(dot_dir.join(CACHE_FILE_NAME), content));
+    if let Err(e) = written {
+        debug!("unable to write the dtmgr comment cache: {}", e);
+    }
+}
+
+/// Every package the sources of the project in `project_dir` declare with `% !dtmgr dep:`, in
+/// the order of the files and lines. A source that cannot be read declares nothing.
+///
+/// What each source declares is kept in `.dtmgr` and only read again once the source changes.
+pub fn declared_dependencies(project_dir: &Path) -> Vec<Declared> {
+    // the directory of a `dtmgr.toml` given without one
+    let project_dir = if project_dir.as_os_str().is_empty() { Path::new(".") } else { project_dir };
+    let dot_dir = project_dir.join(".dtmgr");
+    let mut sources = Vec::new();
+    project_sources(project_dir, &mut sources);
+
+    let mut cache = read_cache(&dot_dir);
+    let cached_sources = cache.len();
+    let mut changed = false;
+    let mut result = Vec::new();
+    let mut seen = Cache::new();
+    for (source, metadata) in sources {
+        let file = source.strip_prefix(project_dir).unwrap_or(&source).to_owned();
+        let modified = metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+        let cached = cache.remove(&file)
+            .filter(|cached| cached.modified == modified && cached.len == metadata.len());
+        let cached = match cached {
+            Some(cached) => cached,
+            None => {
+                let Ok(content) = std::fs::read(&source) else {
+                    debug!("unable to read {} for dtmgr comments", source.display());
+                    continue;
+                };
+                changed = true;
+                let comments = parse_magic_comments(&String::from_utf8_lossy(&content));
+                CachedSource { modified, len: metadata.len(), comments }
+            }
+        };
+        for (line, comment) in cached.comments.unknown.iter() {
+            warn!("{}:{}: ignoring `{}`, dtmgr only knows `% !dtmgr dep: <packages>`", file.display(), line, comment);
+        }
+        for (line, package) in cached.comments.declared.iter() {
+            result.push(Declared { package: package.clone(), file: file.clone(), line: *line });
+        }
+        seen.insert(file, cached);
+    }
+    // sources that were removed leave the cache too
+    if changed || seen.len() != cached_sources {
+        write_cache(&dot_dir, &seen);
+    }
+    result
+}
@@ -0,0 +1,167 @@
+use std::collections::BTreeMap as Map;
+use std::path::Path;
+use serde::{Deserialize, Serialize};
+use crate::link::LinkStrategy;
+use crate::status::EnvironmentState;
+use crate::{DtMgrError, TlPObjInfo};
+
+const MANIFEST_FILE_NAME: &str = "packages.json";
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct LinkedPackage {
+    pub revision: Option<u64>,
+    /// In bytes, see [`crate::size::package_size`]
+    pub size: u64,
+    /// The checksum of the package's container in TeX Live's database as of the install
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub containerchecksum: Option<String>,
+    /// See [`crate::verify::file_set_checksum`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub files_sha3: Option<String>,
+}
+
+/// The packages `dtmgr install` linked into `.dtmgr`, so later commands can
+/// compare against them without asking tlmgr
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct Manifest {
+    pub packages: Map<String, LinkedPackage>,
+    /// Older manifests were all written by symlinking installs
+    #[serde(default)]
+    pub link_strategy: LinkStrategy,
+    /// The TeX Live platform whose binaries were linked
+    #[serde(default)]
+    pub platform: Option<String>,
+    /// Platforms whose binaries were linked as well
+    #[serde(default)]
+    pub extra_platforms: Vec<String>,
+    /// When TeX Live's database was last written as of the install, see [`crate::tlpdb::modified`]
+    #[serde(default)]
+    pub tlpdb_modified: Option<u64>,
+    /// See [`crate::incremental::linking_hash`]
+    #[serde(default)]
+    pub linking: Option<String>,
+}
+
+impl Manifest {
+    pub fn from_tree(dep_tree: &Map<String, TlPObjInfo>, platform: &str) -> Manifest {
+        let packages = dep_tree.values()
+            .map(|pkg| (pkg.name.clone(), LinkedPackage {
+                revision: pkg.lrev.or(pkg.rrev),
+                size: crate::size::package_size(pkg, platform),
+                containerchecksum: pkg.containerchecksum.clone(),
+                files_sha3: None,
+            }))
+            .collect();
+        Manifest { packages, link_strategy: LinkStrategy::default(), platform: None, extra_platforms: Vec::new(), tlpdb_modified: None, linking: None }
+    }
+
+    /// The manifest of the environment in `dot_dir`, if it has one
+    pub fn read(dot_dir: &Path) -> Result<Option<Manifest>, DtMgrError> {
+        let path = dot_dir.join(MANIFEST_FILE_NAME);
+        if !path.is_file() {
+            return Ok(None);
+        }
+        let content = std::fs::read(&path)
+            .map_err(|e| DtMgrError::ReadFile { path: path.clone(), source: e })?;
+        serde_json::from_slice(&content)
+            .map(Some)
+            .map_err(|e| DtMgrError::JsonParse { source: e })
+    }
+
+    pub fn write(&self, dot_dir: &Path) -> Result<(), DtMgrError> {
+        let path = dot_dir.join(MANIFEST_FILE_NAME);
+        let content = serde_json::to_string_pretty(self)
+            .map_err(|e| DtMgrError::JsonSerialize { source: e })?;
+        std::fs::write(&path, content)
+            .map_err(|e| DtMgrError::WriteFile { file: path, source: e })
+    }
+
+    pub fn total_size(&self) -> u64 {
+        self.packages.values().map(|p| p.size).sum()
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct RevisionChange {
+    pub name: String,
+    pub from: Option<u64>,
+    pub to: Option<u64>,
+}
+
+/// How one set of linked packages differs from another
+#[derive(Debug, Default, Serialize)]
+pub struct ManifestDiff {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    pub changed: Vec<RevisionChange>,
+}
+
+pub fn format_revision(revision: Option<u64>) -> String {
+    revision.map(|r| format!("r{}", r)).unwrap_or_else(|| String::from("?"))
+}
+
+impl Manifest {
+    pub fn diff(&self, new: &Manifest) -> ManifestDiff {
+        let mut result = ManifestDiff::default();
+        for (name, pkg) in new.packages.iter() {
+            match self.packages.get(name) {
+                None => result.added.push(name.clone()),
+                Some(old) if old.revision != pkg.revision => result.changed.push(RevisionChange {
+                    name: name.clone(),
+                    from: old.revision,
+                    to: pkg.revision,
+                }),
+                Some(_) => {}
+            }
+        }
+        result.removed = self.packages.keys()
+            .filter(|name| !new.packages.contains_key(*name))
+            .cloned()
+            .collect();
+        result
+    }
+}
+
+impl ManifestDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+
+    /// One line per package: `+` added, `-` removed, `~` at another revision
+    pub fn print(&self, new: &Manifest) {
+        for name in self.added.iter() {
+            let revision = new.packages.get(name).and_then(|p| p.revision);
+            println!("+ {} {}", name, format_revision(revision));
+        }
+        for name in self.removed.iter() {
+            println!("- {}", name);
+        }
+        for change in self.changed.iter() {
+            println!("~ {} {} -> {}", change.name, format_revision(change.from), format_revision(change.to));
+        }
+    }
+}
+
+/// What `dtmgr install` would change in the current environment
+#[derive(Debug, Serialize)]
+pub struct EnvironmentDiff {
+    pub environment: EnvironmentState,
+    /// False if the environment was made before dtmgr recorded what it linked
+    pub previous_known: bool,
+    #[serde(flatten)]
+    pub diff: ManifestDiff,
+}
+
+impl EnvironmentDiff {
+    pub fn print(&self, new: &Manifest) {
+        match (self.environment, self.previous_known) {
+            (EnvironmentState::Missing, _) => println!("no environment yet; `dtmgr install` would add:"),
+            (_, false) => println!("the environment has no record of its packages; run `dtmgr install` to compare next time"),
+            _ if self.diff.is_empty() => println!("the environment matches {}", crate::CONFIG_FILE_NAME),
+            _ => {}
+        }
+        if self.previous_known {
+            self.diff.print(new);
+        }
+    }
+}
@@ -0,0 +1,205 @@
+use std::process::Command;
+use std::time::Duration;
+use clap::Subcommand;
+use serde::Serialize;
+use toml_edit::{DocumentMut, Item};
+use tracing::{debug, info};
+use crate::output::{self, OutputFormat};
+use crate::size::format_size;
+use crate::{home, http, network, tlpdb, DtMgrError};
+
+// where TeX Live's repository is below a CTAN mirror's root
+const TLNET_PATH: &str = "systems/texlive/tlnet";
+// a few hundred bytes that every mirror carrying TeX Live has, for the time to answer
+const LATENCY_FILE: &str = "tlpkg/texlive.tlpdb.sha512";
+// several megabytes, of which the first ones tell how fast the mirror sends
+const THROUGHPUT_FILE: &str = "tlpkg/texlive.tlpdb.xz";
+const LATENCY_TIMEOUT: Duration = Duration::from_secs(5);
+const THROUGHPUT_DURATION: Duration = Duration::from_secs(5);
+const THROUGHPUT_LIMIT: u64 = 4 * 1024 * 1024;
+
+// spread over the continents, as the nearest mirror is usually the fastest
+const DEFAULT_MIRRORS: [&str; 9] = [
+    "https://ctan.math.illinois.edu",
+    "https://mirrors.mit.edu/CTAN",
+    "https://mirror.math.princeton.edu/pub/CTAN",
+    "https://ftp.fau.de/ctan",
+    "https://ftp.gwdg.de/pub/ctan",
+    "https://mirror.init7.net/ctan",
+    "https://mirrors.tuna.tsinghua.edu.cn/CTAN",
+    "https://ftp.jaist.ac.jp/pub/CTAN",
+    "https://mirror.aarnet.edu.au/pub/CTAN",
+];
+
+#[derive(Subcommand)]
+pub enum MirrorCommand {
+    /// Probe CTAN mirrors and record the fastest in the user's `config.toml`
+    Select {
+        /// A mirror to probe instead of dtmgr's list, as the URL of its CTAN root
+        #[arg(long = "mirror", value_name = "URL")]
+        mirrors: Vec<String>,
+        /// Only show how the mirrors did
+        #[arg(long)]
+        dry_run: bool,
+        #[arg(long, value_enum, default_value_t)]
+        format: OutputFormat,
+    },
+    /// Show the selected mirror
+    Show,
+    /// Forget the selected mirror, leaving the choice to `mirrors.ctan.org` again
+    Clear,
+}
+
+/// How a mirror did when probed
+#[derive(Debug, Serialize)]
+pub struct Probe {
+    pub mirror: String,
+    /// Seconds until a small file had arrived
+    pub latency: Option<f64>,
+    /// Bytes per second a larger download arrived at
+    pub throughput: Option<f64>,
+    /// Why the mirror could not be probed
+    pub error: Option<String>,
+}
+
+fn probe(mirror: &str) -> Probe {
+    let base = format!("{}/{}", mirror, TLNET_PATH);
+    let sampled = http::sample(&format!("{}/{}", base, LATENCY_FILE), u64::MAX, LATENCY_TIMEOUT)
+        .and_then(|(_, latency)| {
+            let (received, elapsed) = http::sample(&format!("{}/{}", base, THROUGHPUT_FILE), THROUGHPUT_LIMIT, THROUGHPUT_DURATION)?;
+            Ok((latency, received as f64 / elapsed.as_secs_f64().max(f64::EPSILON)))
+        });
+    match sampled {
+        Ok((latency, throughput)) => Probe { mirror: mirror.to_owned(), latency: Some(latency.as_secs_f64()), throughput: Some(throughput), error: None },
+        Err(e) => {
+            // the download error names the URL, its source what went wrong
+            let error = std::error::Error::source(&e).map_or_else(|| e.to_string(), |source| source.to_string());
+            Probe { mirror: mirror.to_owned(), latency: None, throughput: None, error: Some(error) }
+        }
+    }
+}
+
+/// Probes each of `mirrors` in turn, so they do not compete for the connection, fastest first
+/// and those that could not be reached last
+pub fn probe_mirrors(mirrors: &[String]) -> Vec<Probe> {
+    let mut probes: Vec<Probe> = mirrors.iter()
+        .map(|mirror| {
+            info!("probing {}", mirror);
+            probe(mirror.trim_end_matches('/'))
+        })
+        .collect();
+    probes.sort_by(|a, b| b.throughput.unwrap_or(-1.0).total_cmp(&a.throughput.unwrap_or(-1.0)));
+    probes
+}
+
+fn print_probes(probes: &[Probe]) {
+    let width = probes.iter().map(|probe| probe.mirror.len()).max().unwrap_or(0);
+    for probe in probes {
+        match (probe.latency, probe.throughput, &probe.error) {
+            (Some(latency), Some(throughput), _) => println!(
+                "{:<width$}  {:>6.0} ms  {:>10}/s",
+                probe.mirror, latency * 1000.0, format_size(throughput as u64),
+            ),
+            (_, _, error) => println!("{:<width$}  {}", probe.mirror, error.as_deref().unwrap_or("unreachable")),
+        }
+    }
+}
+
+/// Sets `[network] mirror` in the user's `config.toml`, or removes it, keeping the rest of the
+/// file as it is
+fn write_mirror(mirror: Option<&str>) -> Result<(), DtMgrError> {
+    let path = home::user_config_file().ok_or(DtMgrError::NoConfigDirectory)?;
+    // a file dtmgr cannot read is reported as such, rather than edited
+    home::read_user_config()?;
+    let content = if path.is_file() {
+        std::fs::read_to_string(&path)
+            .map_err(|e| DtMgrError::ReadFile { path: path.clone(), source: e })?
+    } else {
+        String::new()
+    };
+    let mut document: DocumentMut = content.parse()
+        .map_err(|e| DtMgrError::EditConfig { source: e })?;
+    match mirror {
+        Some(mirror) => document["network"]["mirror"] = toml_edit::value(mirror),
+        None => {
+            if let Some(network) = document.get_mut("network").and_then(Item::as_table_like_mut) {
+                network.remove("mirror");
+            }
+        }
+    }
+    let dir = path.parent().expect("config.toml is in the configuration directory");
+    std::fs::create_dir_all(dir)
+        .map_err(|e| DtMgrError::CreateDirectory { dir: dir.to_owned(), source: e })?;
+    std::fs::write(&path, document.to_string())
+        .map_err(|e| DtMgrError::WriteFile { file: path, source: e })
+}
+
+/// TeX Live's repository on the selected mirror
+pub fn texlive_repository() -> Option<String> {
+    network::mirror().map(|mirror| format!("{}/{}", mirror, TLNET_PATH))
+}
+
+// `tlmgr option repository` leaves `ctan` for the multiplexer, the installer its URL
+fn is_multiplexer(location: &str) -> bool {
+    location == "ctan" || location.contains("://mirror.ctan.org/") || location.contains("://mirrors.ctan.org/")
+}
+
+/// Points `cmd`, a `tlmgr` that downloads, at the selected mirror.
+///
+/// A TeX Live that was set to a repository of its own, such as a historic release or a local
+/// copy, keeps it.
+pub fn apply_repository(cmd: &mut Command) {
+    let Some(repository) = texlive_repository() else {
+        return;
+    };
+    let location = crate::get_texlive_root()
+        .and_then(|root| {
+            let path = tlpdb::local_tlpdb(root);
+            std::fs::read_to_string(&path).map_err(|e| DtMgrError::ReadFile { path, source: e })
+        })
+        .map(|content| tlpdb::repository(&content).map(str::to_owned));
+    match location {
+        Ok(Some(location)) if !is_multiplexer(&location) => {
+            debug!("leaving tlmgr at its repository, {}", location);
+        }
+        Err(e) => debug!("leaving tlmgr at its repository, as it is unknown: {}", e),
+        Ok(_) => {
+            cmd.arg("--repository").arg(repository);
+        }
+    }
+}
+
+pub fn run_mirror_command(command: MirrorCommand) -> Result<(), DtMgrError> {
+    match command {
+        MirrorCommand::Select { mirrors, dry_run, format } => {
+            if network::offline() {
+                return Err(DtMgrError::Offline { needed: String::from("the mirrors") });
+            }
+            let mirrors = if mirrors.is_empty() {
+                DEFAULT_MIRRORS.iter().map(|mirror| mirror.to_string()).collect()
+            } else {
+                mirrors
+            };
+            let probes = probe_mirrors(&mirrors);
+            match format {
+                OutputFormat::Human => print_probes(&probes),
+                OutputFormat::Json => output::print_json(&probes)?,
+            }
+            let Some(fastest) = probes.first().filter(|probe| probe.throughput.is_some()) else {
+                return Err(DtMgrError::NoMirrorReachable { count: probes.len() });
+            };
+            if !dry_run {
+                write_mirror(Some(&fastest.mirror))?;
+                if format == OutputFormat::Human {
+                    println!("selected {}", fastest.mirror);
+                }
+            }
+        }
+        MirrorCommand::Show => match network::mirror() {
+            Some(mirror) => println!("{}", mirror),
+            None => println!("no mirror is selected, mirrors.ctan.org picks one"),
+        },
+        MirrorCommand::Clear => write_mirror(None)?,
+    }
+    Ok(())
+}
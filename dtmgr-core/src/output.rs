@@ -0,0 +1,19 @@
+use clap::ValueEnum;
+use serde::Serialize;
+use crate::DtMgrError;
+
+/// How inspection commands report their results
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    #[default]
+    Human,
+    /// One JSON object on stdout; fields are only ever added, never renamed or removed
+    Json,
+}
+
+pub fn print_json(value: &impl Serialize) -> Result<(), DtMgrError> {
+    let json = serde_json::to_string_pretty(value)
+        .map_err(|e| DtMgrError::JsonSerialize { source: e })?;
+    println!("{}", json);
+    Ok(())
+}
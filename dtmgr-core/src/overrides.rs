@@ -0,0 +1,88 @@
+use std::path::{Component, Path, PathBuf};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use sha3::{Digest, Sha3_256};
+use tracing::{info, warn};
+use crate::DtMgrError;
+
+/// A replacement for one file of the environment, an entry of `[overrides]` keyed by the file's
+/// path in `texmf-dist`
+#[derive(Debug, Deserialize, JsonSchema, Serialize, Hash)]
+#[serde(deny_unknown_fields)]
+pub struct Override {
+    /// A file to use instead, relative to the project
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    file: Option<PathBuf>,
+    /// A unified diff to apply to the package's file, relative to the project
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    patch: Option<PathBuf>,
+    /// Of `file` or `patch`, filled in when the config is read so that edits to it invalidate the
+    /// environment
+    #[serde(skip_deserializing, skip_serializing_if = "Option::is_none")]
+    content_hash: Option<String>,
+}
+
+impl Override {
+    fn source(&self, target: &str) -> Result<&Path, DtMgrError> {
+        let invalid = |problem| DtMgrError::InvalidOverride { target: target.to_owned(), problem };
+        if !Path::new(target).components().all(|c| matches!(c, Component::Normal(_))) {
+            return Err(invalid("the file has to be a relative path inside `texmf-dist`"));
+        }
+        match (&self.file, &self.patch) {
+            (Some(file), None) => Ok(file),
+            (None, Some(patch)) => Ok(patch),
+            _ => Err(invalid("needs either `file` or `patch`")),
+        }
+    }
+
+    /// Checks the entry and hashes the project's file
+    pub fn resolve(&mut self, target: &str, project_dir: &Path) -> Result<(), DtMgrError> {
+        let path = project_dir.join(self.source(target)?);
+        let content = std::fs::read(&path)
+            .map_err(|e| DtMgrError::ReadFile { path, source: e })?;
+        let hash: [u8; 32] = Sha3_256::digest(content).into();
+        self.content_hash = Some(hex::encode(hash));
+        Ok(())
+    }
+
+    /// Puts the replacement for `target` into the environment's `texmf-dist` as a file of its own,
+    /// so the linked original is left alone
+    pub fn apply(&self, target: &str, project_dir: &Path, dot_dir: &Path) -> Result<(), DtMgrError> {
+        let source = project_dir.join(self.source(target)?);
+        let full_new = dot_dir.join("texmf-dist").join(target);
+        let read = |path: &Path| std::fs::read(path)
+            .map_err(|e| DtMgrError::ReadFile { path: path.to_owned(), source: e });
+
+        let content = match &self.patch {
+            Some(_) => {
+                info!("patching {}", target);
+                if !full_new.exists() {
+                    return Err(DtMgrError::InvalidOverride { target: target.to_owned(), problem: "no package provides the file to patch" });
+                }
+                let diff = read(&source)?;
+                let patch = diffy::Patch::from_bytes(&diff)
+                    .map_err(|e| DtMgrError::ParsePatch { patch: source.clone(), source: e })?;
+                diffy::apply_bytes(&read(&full_new)?, &patch)
+                    .map_err(|e| DtMgrError::ApplyPatch { patch: source.clone(), source: e })?
+            }
+            None => {
+                info!("overriding {}", target);
+                if !full_new.exists() {
+                    warn!("no package provides {}, adding it", target);
+                }
+                read(&source)?
+            }
+        };
+
+        // writing through the link would change TeX Live's own copy
+        if full_new.symlink_metadata().is_ok() {
+            std::fs::remove_file(&full_new)
+                .map_err(|e| DtMgrError::WriteFile { file: full_new.clone(), source: e })?;
+        }
+        let parent = full_new.parent().expect("a path created by a join should have a parent");
+        std::fs::create_dir_all(parent)
+            .map_err(|e| DtMgrError::CreateDirectory { dir: parent.to_owned(), source: e })?;
+        std::fs::write(&full_new, content)
+            .map_err(|e| DtMgrError::WriteFile { file: full_new, source: e })
+    }
+}
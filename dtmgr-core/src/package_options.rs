@@ -0,0 +1,83 @@
+use std::collections::BTreeMap as Map;
+use std::collections::BTreeSet as Set;
+use schemars::JsonSchema;
+use serde::de::value::MapAccessDeserializer;
+use serde::de::{MapAccess, Visitor};
+use serde::{Deserialize, Deserializer, Serialize};
+use crate::link::LinkStrategy;
+
+/// The options of a [`ListedPackage`], without its name
+#[derive(Clone, Debug, Default, JsonSchema, Serialize, Hash)]
+pub struct PackageOptions {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub link: Option<LinkStrategy>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub docs: Option<bool>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub exclude: Vec<String>,
+}
+
+/// A package with options for how it is linked
+#[derive(Debug, Deserialize, JsonSchema, Hash)]
+#[serde(deny_unknown_fields)]
+pub struct ListedPackage {
+    pub name: String,
+    /// How to put the package's files into the environment instead of the way the install does,
+    /// e.g. `copy` for a package that misbehaves behind symlinks
+    #[serde(default)]
+    pub link: Option<LinkStrategy>,
+    /// `true` links the package's documentation even where `exclude` leaves documentation out,
+    /// `false` leaves it out
+    #[serde(default)]
+    pub docs: Option<bool>,
+    /// Globs of the package's files not to link, like its entry of `[package-exclude]`
+    #[serde(default)]
+    pub exclude: Vec<String>,
+}
+
+/// An entry of `dependencies` or `dev-dependencies`: a package's name, or a table with its name
+/// and options such as `{ name = "pgf", link = "copy" }`
+#[derive(Debug, JsonSchema, Hash)]
+#[serde(untagged)]
+pub enum Dependency {
+    Name(String),
+    WithOptions(ListedPackage),
+}
+
+// by hand rather than untagged, so that a mistyped option is named in the error
+impl<'de> Deserialize<'de> for Dependency {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct DependencyVisitor;
+
+        impl<'de> Visitor<'de> for DependencyVisitor {
+            type Value = Dependency;
+
+            fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+                formatter.write_str("a package name or a table with its `name` and options")
+            }
+
+            fn visit_str<E: serde::de::Error>(self, name: &str) -> Result<Dependency, E> {
+                Ok(Dependency::Name(name.to_owned()))
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, map: A) -> Result<Dependency, A::Error> {
+                ListedPackage::deserialize(MapAccessDeserializer::new(map)).map(Dependency::WithOptions)
+            }
+        }
+
+        deserializer.deserialize_any(DependencyVisitor)
+    }
+}
+
+/// The names of `listed`, with the options of those that have any added to `options`
+pub fn split(listed: Vec<Dependency>, options: &mut Map<String, PackageOptions>) -> Set<String> {
+    listed.into_iter()
+        .map(|dependency| match dependency {
+            Dependency::Name(name) => name,
+            Dependency::WithOptions(ListedPackage { name, link, docs, exclude }) => {
+                options.insert(name.clone(), PackageOptions { link, docs, exclude });
+                name
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,214 @@
+use std::collections::BTreeMap as Map;
+use std::collections::BTreeSet as Set;
+use clap::ValueEnum;
+use serde::Serialize;
+use crate::{size, DtMgrConfig, TlPObjInfo};
+
+/// How `dtmgr tree` prints the dependencies
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum TreeFormat {
+    #[default]
+    Human,
+    Json,
+    /// A Graphviz graph, e.g. for `dot -Tsvg`
+    Dot,
+    /// A Mermaid flowchart, for Markdown that renders it
+    Mermaid,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PackageEntry<'a> {
+    pub name: &'a str,
+    pub revision: Option<u64>,
+    pub category: Option<&'a str>,
+    /// Listed in `dependencies` rather than pulled in by another package
+    pub direct: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PackageList<'a> {
+    pub packages: Vec<PackageEntry<'a>>,
+}
+
+pub fn package_list<'a>(config: &DtMgrConfig, dep_tree: &'a Map<String, TlPObjInfo>) -> PackageList<'a> {
+    let packages = dep_tree.values()
+        .map(|pkg| PackageEntry {
+            name: &pkg.name,
+            revision: pkg.lrev,
+            category: pkg.category.as_deref(),
+            direct: config.is_direct(&pkg.name),
+        })
+        .collect();
+    PackageList { packages }
+}
+
+impl PackageList<'_> {
+    pub fn print(&self) {
+        for entry in self.packages.iter() {
+            let revision = entry.revision.map(|r| format!(" r{}", r)).unwrap_or_default();
+            let direct = if entry.direct { " (direct)" } else { "" };
+            println!("{}{}{}", entry.name, revision, direct);
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct TreeNode {
+    pub name: String,
+    pub revision: Option<u64>,
+    /// Shown in full elsewhere in the tree, so `dependencies` is left empty here
+    pub repeated: bool,
+    pub dependencies: Vec<TreeNode>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DependencyTree {
+    pub dependencies: Vec<TreeNode>,
+}
+
+fn tree_node(name: &str, dep_tree: &Map<String, TlPObjInfo>, platform: &str, seen: &mut Set<String>) -> TreeNode {
+    let pkg = dep_tree.get(name);
+    let mut node = TreeNode {
+        name: name.to_owned(),
+        revision: pkg.and_then(|p| p.lrev),
+        repeated: false,
+        dependencies: Vec::new(),
+    };
+
+    if !seen.insert(name.to_owned()) {
+        node.repeated = true;
+        return node;
+    }
+    if let Some(pkg) = pkg {
+        for dep in pkg.dependencies(platform) {
+            node.dependencies.push(tree_node(&dep, dep_tree, platform, seen));
+        }
+    }
+    node
+}
+
+/// Every direct dependency with what it pulls in, expanding each package once
+pub fn dependency_tree(config: &DtMgrConfig, dep_tree: &Map<String, TlPObjInfo>, platform: &str) -> DependencyTree {
+    let mut seen = Set::new();
+    let dependencies = config.dependencies.iter()
+        .map(|dep| tree_node(dep, dep_tree, platform, &mut seen))
+        .collect();
+    DependencyTree { dependencies }
+}
+
+fn print_tree_node(node: &TreeNode, prefix: &str, last: bool) {
+    let (branch, continuation) = if last { ("└── ", "    ") } else { ("├── ", "│   ") };
+    let repeated = if node.repeated { " (*)" } else { "" };
+    println!("{}{}{}{}", prefix, branch, node.name, repeated);
+
+    let child_prefix = String::from(prefix) + continuation;
+    for (idx, child) in node.dependencies.iter().enumerate() {
+        print_tree_node(child, &child_prefix, idx + 1 == node.dependencies.len());
+    }
+}
+
+impl DependencyTree {
+    pub fn print(&self) {
+        for root in self.dependencies.iter() {
+            let repeated = if root.repeated { " (*)" } else { "" };
+            println!("{}{}", root.name, repeated);
+            for (idx, child) in root.dependencies.iter().enumerate() {
+                print_tree_node(child, "", idx + 1 == root.dependencies.len());
+            }
+        }
+    }
+}
+
+/// Each package and the packages it depends on directly, labelled with its size
+fn graph_edges<'a>(dep_tree: &'a Map<String, TlPObjInfo>, platform: &str) -> Vec<(&'a str, String, Vec<&'a str>)> {
+    dep_tree.values()
+        .map(|pkg| {
+            let label = format!("{}\\n{}", pkg.name, size::format_size(size::package_size(pkg, platform)));
+            let dependencies = pkg.dependencies(platform).iter()
+                .filter_map(|dep| dep_tree.get_key_value(dep).map(|(name, _)| name.as_str()))
+                .filter(|dep| *dep != pkg.name)
+                .collect();
+            (pkg.name.as_str(), label, dependencies)
+        })
+        .collect()
+}
+
+/// The resolved packages as a Graphviz digraph, direct dependencies in bold
+pub fn render_dot(config: &DtMgrConfig, dep_tree: &Map<String, TlPObjInfo>, platform: &str) -> String {
+    let mut out = String::from("digraph dependencies {\n    rankdir=LR;\n    node [shape=box];\n");
+    for (name, label, dependencies) in graph_edges(dep_tree, platform) {
+        let style = if config.is_direct(name) { ", style=bold" } else { "" };
+        out.push_str(&format!("    \"{}\" [label=\"{}\"{}];\n", name, label, style));
+        for dep in dependencies {
+            out.push_str(&format!("    \"{}\" -> \"{}\";\n", name, dep));
+        }
+    }
+    out.push_str("}\n");
+    out
+}
+
+/// The resolved packages as a Mermaid flowchart, direct dependencies in bold
+pub fn render_mermaid(config: &DtMgrConfig, dep_tree: &Map<String, TlPObjInfo>, platform: &str) -> String {
+    // names like `texlive.infra` are not valid node ids, so nodes are numbered
+    let ids: Map<&str, usize> = dep_tree.keys().enumerate().map(|(idx, name)| (name.as_str(), idx)).collect();
+    let mut out = String::from("flowchart LR\n");
+    let mut direct = Vec::new();
+    for (name, label, dependencies) in graph_edges(dep_tree, platform) {
+        let id = ids[name];
+        out.push_str(&format!("    n{}[\"{}\"]\n", id, label.replace("\\n", "<br/>")));
+        for dep in dependencies {
+            out.push_str(&format!("    n{} --> n{}\n", id, ids[dep]));
+        }
+        if config.is_direct(name) {
+            direct.push(format!("n{}", id));
+        }
+    }
+    if !direct.is_empty() {
+        out.push_str("    classDef direct stroke-width:3px\n");
+        out.push_str(&format!("    class {} direct\n", direct.join(",")));
+    }
+    out
+}
+
+#[derive(Debug, Serialize)]
+pub struct OutdatedPackage<'a> {
+    pub name: &'a str,
+    pub installed_revision: Option<u64>,
+    pub available_revision: u64,
+    pub direct: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct OutdatedReport<'a> {
+    pub packages: Vec<OutdatedPackage<'a>>,
+}
+
+/// Packages whose repository revision is newer than the one installed in TeX Live
+pub fn outdated_packages<'a>(config: &DtMgrConfig, dep_tree: &'a Map<String, TlPObjInfo>) -> OutdatedReport<'a> {
+    let packages = dep_tree.values()
+        .filter_map(|pkg| {
+            let available = pkg.rrev?;
+            (pkg.lrev.is_none_or(|installed| installed < available)).then(|| OutdatedPackage {
+                name: &pkg.name,
+                installed_revision: pkg.lrev,
+                available_revision: available,
+                direct: config.is_direct(&pkg.name),
+            })
+        })
+        .collect();
+    OutdatedReport { packages }
+}
+
+impl OutdatedReport<'_> {
+    pub fn print(&self) {
+        if self.packages.is_empty() {
+            println!("every package is at the repository's revision");
+            return;
+        }
+        for pkg in self.packages.iter() {
+            let installed = pkg.installed_revision.map(|r| format!("r{}", r)).unwrap_or_else(|| String::from("not installed"));
+            println!("{}: {} -> r{}", pkg.name, installed, pkg.available_revision);
+        }
+        println!("run `dtmgr update` to update them and reinstall the environment");
+    }
+}
@@ -0,0 +1,79 @@
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use crate::{DtMgrError, KPSE_SEPARATOR};
+
+/// The `[paths]` section of `dtmgr.toml`: directories searched after kpathsea's own for
+/// each kind of input, relative to the project unless absolute
+#[derive(Debug, Default, Deserialize, JsonSchema, Hash)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct PathsConfig {
+    #[serde(default)]
+    texinputs: Vec<PathBuf>,
+    #[serde(default)]
+    bibinputs: Vec<PathBuf>,
+    #[serde(default)]
+    bstinputs: Vec<PathBuf>,
+}
+
+impl PathsConfig {
+    fn variables(&self) -> [(&'static str, &[PathBuf]); 3] {
+        [("TEXINPUTS", &self.texinputs), ("BIBINPUTS", &self.bibinputs), ("BSTINPUTS", &self.bstinputs)]
+    }
+
+    /// The directories of each variable that lists any, joined by kpathsea's separator
+    fn joined(&self, project_dir: &Path) -> Result<Vec<(&'static str, OsString)>, DtMgrError> {
+        let mut result = Vec::new();
+        for (name, dirs) in self.variables() {
+            if dirs.is_empty() {
+                continue;
+            }
+            let mut value = OsString::new();
+            for (idx, dir) in dirs.iter().enumerate() {
+                let dir = project_dir.join(dir);
+                // kpathsea has no way to escape its separator inside a path
+                if dir.as_os_str().to_string_lossy().contains(KPSE_SEPARATOR) {
+                    return Err(DtMgrError::PathContainsSeparator { path: dir, separator: KPSE_SEPARATOR });
+                }
+                if idx > 0 {
+                    value.push(KPSE_SEPARATOR.to_string());
+                }
+                value.push(dir);
+            }
+            result.push((name, value));
+        }
+        Ok(result)
+    }
+
+    /// The variables as `dtmgr env` prints them; the leading separator stands for kpathsea's
+    /// default path, so the project's directories come after it
+    pub fn environment(&self, project_dir: &Path) -> Result<Vec<(&'static str, OsString)>, DtMgrError> {
+        Ok(self.joined(project_dir)?.into_iter()
+            .map(|(name, dirs)| {
+                let mut value = OsString::from(KPSE_SEPARATOR.to_string());
+                value.push(dirs);
+                (name, value)
+            })
+            .collect())
+    }
+
+    /// Sets the variables for `cmd`, after whatever `cmd` already searches for them, such as
+    /// the project directory `[build]` adds with an `output-dir`
+    pub fn apply_environment(&self, cmd: &mut Command, project_dir: &Path) -> Result<(), DtMgrError> {
+        for (name, dirs) in self.joined(project_dir)? {
+            let mut value = cmd.get_envs()
+                .find(|(n, _)| *n == name)
+                .and_then(|(_, value)| value.map(OsString::from))
+                .unwrap_or_default();
+            // an empty entry stands for kpathsea's default path, which `[build]` ends its value with
+            if !value.to_string_lossy().ends_with(KPSE_SEPARATOR) {
+                value.push(KPSE_SEPARATOR.to_string());
+            }
+            value.push(dirs);
+            cmd.env(name, value);
+        }
+        Ok(())
+    }
+}
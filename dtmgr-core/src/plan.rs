@@ -0,0 +1,138 @@
+use std::collections::BTreeMap as Map;
+use serde::Serialize;
+use crate::manifest::{format_revision, Manifest, RevisionChange};
+use crate::status::EnvironmentState;
+use crate::{context, DtMgrConfig, TlPObjInfo};
+
+// what `dtmgr install` runs once everything is linked
+const POST_INSTALL_STEPS: [&str; 2] = [
+    "fmtutil-sys --missing --no-strict",
+    "updmap-sys --cnffile texmf-dist/web2c/updmap.cfg",
+];
+
+#[derive(Debug, Serialize)]
+pub struct PlannedPackage<'a> {
+    pub name: &'a str,
+    pub revision: Option<u64>,
+    pub direct: bool,
+    /// Whether TeX Live already has it; if not, `tlmgr install` runs first
+    pub installed: bool,
+}
+
+/// What `dtmgr install` would do, without doing it
+#[derive(Debug, Serialize)]
+pub struct InstallPlan<'a> {
+    pub environment: EnvironmentState,
+    /// Every package the environment would contain
+    pub packages: Vec<PlannedPackage<'a>>,
+    /// Packages `tlmgr install` would add to TeX Live
+    pub install_globally: Vec<&'a str>,
+    /// Compared to the current environment, which is empty if there is none
+    pub link: Vec<String>,
+    pub unlink: Vec<String>,
+    pub revision_changes: Vec<RevisionChange>,
+    /// False for environments made before dtmgr recorded what it linked,
+    /// in which case every package is listed under `link`
+    pub previous_known: bool,
+    /// Commands that would rebuild the formats and font maps, and prepare
+    /// ConTeXt if the environment has it
+    pub post_install: Vec<&'static str>,
+    pub size_before: u64,
+    pub size_after: u64,
+}
+
+pub fn install_plan<'a>(
+    config: &DtMgrConfig,
+    environment: EnvironmentState,
+    previous: Option<&Manifest>,
+    dep_tree: &'a Map<String, TlPObjInfo>,
+    platform: &str,
+) -> InstallPlan<'a> {
+    let packages: Vec<PlannedPackage> = dep_tree.values()
+        .map(|pkg| PlannedPackage {
+            name: &pkg.name,
+            revision: pkg.lrev.or(pkg.rrev),
+            direct: config.is_direct(&pkg.name),
+            installed: pkg.installed.unwrap_or(pkg.lrev.is_some()),
+        })
+        .collect();
+    let up_to_date = environment == EnvironmentState::UpToDate;
+    let install_globally = if up_to_date {
+        Vec::new()
+    } else {
+        packages.iter().filter(|p| !p.installed).map(|p| p.name).collect()
+    };
+
+    let empty = Manifest::default();
+    let before = match environment {
+        EnvironmentState::Missing | EnvironmentState::Incompatible => &empty,
+        _ => previous.unwrap_or(&empty),
+    };
+    let after = Manifest::from_tree(dep_tree, platform);
+
+    let diff = before.diff(&after);
+
+    let mut post_install = Vec::new();
+    if !up_to_date {
+        post_install.extend(POST_INSTALL_STEPS);
+        if context::in_closure(dep_tree) {
+            post_install.extend(["mtxrun --generate", "context --make"]);
+        }
+        if config.fonts.prewarm_command(dep_tree).is_some() {
+            post_install.push("luaotfload-tool --update");
+        }
+    }
+
+    InstallPlan {
+        environment,
+        packages,
+        install_globally,
+        link: diff.added,
+        unlink: diff.removed,
+        revision_changes: diff.changed,
+        // an incompatible environment is rebuilt from nothing
+        previous_known: matches!(environment, EnvironmentState::Missing | EnvironmentState::Incompatible) || previous.is_some(),
+        post_install,
+        size_before: before.total_size(),
+        size_after: after.total_size(),
+    }
+}
+
+impl InstallPlan<'_> {
+    pub fn print(&self) {
+        if self.environment == EnvironmentState::UpToDate {
+            println!("environment is up to date, nothing to do");
+            return;
+        }
+
+        if !self.install_globally.is_empty() {
+            println!("would install into TeX Live: {}", self.install_globally.join(", "));
+        }
+        if !self.previous_known {
+            println!("the current environment has no record of its packages, so all are listed as new");
+        }
+        for name in self.link.iter() {
+            println!("  + {}", name);
+        }
+        for name in self.unlink.iter() {
+            println!("  - {}", name);
+        }
+        for change in self.revision_changes.iter() {
+            println!("  ~ {} {} -> {}", change.name, format_revision(change.from), format_revision(change.to));
+        }
+        println!("would relink {} packages and then run {}", self.packages.len(), self.post_install.join(", "));
+
+        let (sign, delta) = if self.size_after >= self.size_before {
+            ("+", self.size_after - self.size_before)
+        } else {
+            ("-", self.size_before - self.size_after)
+        };
+        println!(
+            "size: {} -> {} ({}{})",
+            crate::size::format_size(self.size_before),
+            crate::size::format_size(self.size_after),
+            sign,
+            crate::size::format_size(delta),
+        );
+    }
+}
@@ -0,0 +1,57 @@
+use std::collections::BTreeMap as Map;
+use std::ffi::OsString;
+use std::path::Path;
+use tracing::warn;
+use crate::{executes, TlPObjInfo};
+
+/// A `script` postaction, run in the environment like tlmgr runs it after installing the package
+pub struct Script {
+    pub package: String,
+    pub command: Vec<OsString>,
+}
+
+// tlmgr passes the action and the TeX Live root; here the environment is the root
+fn script_command(file: &str, dot_dir: &Path) -> Vec<OsString> {
+    let path = dot_dir.join(file);
+    let mut command = if file.ends_with(".pl") {
+        vec![OsString::from("perl"), path.into_os_string()]
+    } else if file.ends_with(".texlua") {
+        vec![OsString::from("texlua"), path.into_os_string()]
+    } else {
+        vec![path.into_os_string()]
+    };
+    command.push(OsString::from("install"));
+    command.push(dot_dir.as_os_str().to_owned());
+    command
+}
+
+/// The `script` postactions of `dep_tree`'s packages, warning about every other kind.
+///
+/// Shortcuts, file types and file associations change the desktop rather than TeX Live, so they
+/// have no place in an environment.
+pub fn scripts(dot_dir: &Path, dep_tree: &Map<String, TlPObjInfo>) -> Vec<Script> {
+    let mut result = Vec::new();
+    let mut unhandled = Vec::new();
+    for pkg in dep_tree.values() {
+        for postaction in pkg.postactions.iter().flatten() {
+            let (kind, rest) = postaction.split_once(' ').unwrap_or((postaction.as_str(), ""));
+            let file = executes::arguments(rest).ok().and_then(|mut args| {
+                let windows_file = args.remove("filew32");
+                let file = args.remove("file");
+                if cfg!(windows) { windows_file.or(file) } else { file }
+            });
+            match (kind, file) {
+                ("script", Some(file)) => result.push(Script {
+                    package: pkg.name.clone(),
+                    command: script_command(&file, dot_dir),
+                }),
+                _ => unhandled.push(format!("{} ({})", pkg.name, postaction)),
+            }
+        }
+    }
+
+    if !unhandled.is_empty() {
+        warn!("not carrying out these postactions: {}", unhandled.join(", "));
+    }
+    result
+}
@@ -0,0 +1,50 @@
+use std::io::IsTerminal;
+use std::sync::atomic::{AtomicBool, Ordering};
+use indicatif::{ProgressBar, ProgressDrawTarget, ProgressStyle};
+
+static ENABLED: AtomicBool = AtomicBool::new(true);
+
+/// For `--no-progress`, and when log messages would interleave with the bars
+pub fn disable() {
+    ENABLED.store(false, Ordering::Relaxed);
+}
+
+/// Whether bars are drawn; they only are on a terminal
+pub fn enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed) && std::io::stderr().is_terminal()
+}
+
+/// Counts linked files, with the current package as its message
+pub fn link_bar(files: u64) -> ProgressBar {
+    if !enabled() {
+        return ProgressBar::hidden();
+    }
+    let style = ProgressStyle::with_template("linking [{bar:30}] {pos}/{len} files ({msg})")
+        .expect("template is valid")
+        .progress_chars("=> ");
+    ProgressBar::new(files).with_style(style)
+}
+
+/// Counts the sources fetched so far, of all those downloading at once
+pub fn download_bar(sources: u64) -> ProgressBar {
+    if !enabled() {
+        return ProgressBar::hidden();
+    }
+    let style = ProgressStyle::with_template("fetching [{bar:30}] {pos}/{len} sources")
+        .expect("template is valid")
+        .progress_chars("=> ");
+    ProgressBar::new(sources).with_style(style)
+}
+
+/// A spinner showing which of `total` steps is running
+pub fn steps(total: u64) -> ProgressBar {
+    if !enabled() {
+        // still counting, for the install's events
+        return ProgressBar::with_draw_target(Some(total), ProgressDrawTarget::hidden());
+    }
+    let style = ProgressStyle::with_template("{spinner} [{pos}/{len}] {msg}")
+        .expect("template is valid");
+    let bar = ProgressBar::new(total).with_style(style);
+    bar.enable_steady_tick(std::time::Duration::from_millis(100));
+    bar
+}
@@ -0,0 +1,208 @@
+use std::collections::BTreeMap as Map;
+use std::collections::BTreeSet as Set;
+use std::path::{Path, PathBuf};
+use clap::Subcommand;
+use serde::{Deserialize, Deserializer, Serialize};
+use tracing::warn;
+use crate::color::{self, Stream};
+use crate::state::State;
+use crate::{home, parse_config, status, DtMgrError, CONFIG_FILE_NAME};
+
+const REGISTRY_FILE_NAME: &str = "projects.json";
+
+#[derive(Subcommand)]
+pub enum ProjectsCommand {
+    /// List every project `dtmgr install` ran in, with the state of its environment
+    List,
+    /// Drop projects from the registry, so `upgrade-texlive` and `prune-global` leave them out
+    Forget {
+        /// The projects' directories
+        #[arg(required_unless_present = "gone")]
+        dirs: Vec<PathBuf>,
+        /// Drop every project whose `dtmgr.toml` is gone
+        #[arg(long)]
+        gone: bool,
+    },
+}
+
+/// What the registry knows about a project as of its last finished install; empty for
+/// projects that were registered before installs were recorded or that never finished one
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct Project {
+    /// See [`crate::hash_config`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub config_hash: Option<String>,
+    /// When the install finished, in RFC 3339
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub installed: Option<String>,
+    /// The TeX Live the environment links to
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub texlive_root: Option<PathBuf>,
+    /// Every package the environment links, by name
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub packages: Option<Set<String>>,
+}
+
+impl Project {
+    pub fn from_state(state: &State) -> Project {
+        Project {
+            config_hash: Some(state.config_hash.clone()),
+            installed: Some(state.finished.clone()),
+            texlive_root: state.texlive_root.clone(),
+            packages: Some(state.packages.keys().cloned().collect()),
+        }
+    }
+
+    /// This, or for a project registered before installs were recorded, what its environment's
+    /// `state.json` says
+    pub fn or_environment(self, dtmgr_directory: &Path) -> Result<Project, DtMgrError> {
+        if self.packages.is_some() {
+            return Ok(self);
+        }
+        Ok(State::read(&dtmgr_directory.join(".dtmgr"))?
+            .map(|state| Project::from_state(&state))
+            .unwrap_or(self))
+    }
+}
+
+/// Every project `dtmgr install` ran in, so that changes to TeX Live can be carried over to all
+/// of their environments
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct Registry {
+    #[serde(deserialize_with = "read_projects")]
+    pub projects: Map<PathBuf, Project>,
+}
+
+// `projects.json` only listed the directories before installs were recorded in it
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum StoredProjects {
+    Directories(Set<PathBuf>),
+    Projects(Map<PathBuf, Project>),
+}
+
+fn read_projects<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Map<PathBuf, Project>, D::Error> {
+    Ok(match StoredProjects::deserialize(deserializer)? {
+        StoredProjects::Directories(dirs) => dirs.into_iter().map(|dir| (dir, Project::default())).collect(),
+        StoredProjects::Projects(projects) => projects,
+    })
+}
+
+// with the user's data rather than in the cache, which `dtmgr cache clean` removes
+fn registry_file() -> Result<PathBuf, DtMgrError> {
+    home::data_dir().map(|dir| dir.join(REGISTRY_FILE_NAME))
+}
+
+impl Registry {
+    pub fn read() -> Result<Registry, DtMgrError> {
+        let path = registry_file()?;
+        if !path.is_file() {
+            return Ok(Registry::default());
+        }
+        let content = std::fs::read(&path)
+            .map_err(|e| DtMgrError::ReadFile { path: path.clone(), source: e })?;
+        serde_json::from_slice(&content)
+            .map_err(|e| DtMgrError::JsonParse { source: e })
+    }
+
+    pub fn write(&self) -> Result<(), DtMgrError> {
+        let path = registry_file()?;
+        let dir = path.parent().expect("the registry is in dtmgr's data directory");
+        std::fs::create_dir_all(dir)
+            .map_err(|e| DtMgrError::CreateDirectory { dir: dir.to_owned(), source: e })?;
+        let content = serde_json::to_string_pretty(self)
+            .map_err(|e| DtMgrError::JsonSerialize { source: e })?;
+        // written aside and renamed, so that two installs at once never leave half a file
+        let partial = path.with_extension("json.partial");
+        std::fs::write(&partial, content)
+            .map_err(|e| DtMgrError::WriteFile { file: partial.clone(), source: e })?;
+        std::fs::rename(&partial, &path)
+            .map_err(|e| DtMgrError::WriteFile { file: path, source: e })
+    }
+}
+
+// the same project is found under the same name from anywhere
+fn registry_key(dtmgr_directory: &Path) -> PathBuf {
+    dtmgr_directory.canonicalize()
+        .unwrap_or_else(|_| dtmgr_directory.to_owned())
+}
+
+/// Adds the project in `dtmgr_directory` to the registry, unless it is there already
+pub fn register(dtmgr_directory: &Path) -> Result<(), DtMgrError> {
+    let mut registry = Registry::read()?;
+    if let std::collections::btree_map::Entry::Vacant(entry) = registry.projects.entry(registry_key(dtmgr_directory)) {
+        entry.insert(Project::default());
+        registry.write()?;
+    }
+    Ok(())
+}
+
+/// Records the install that just finished in `dtmgr_directory`, from its `state.json`
+pub fn record_install(dtmgr_directory: &Path) -> Result<(), DtMgrError> {
+    let Some(state) = State::read(&dtmgr_directory.join(".dtmgr"))? else {
+        return Ok(());
+    };
+    let mut registry = Registry::read()?;
+    registry.projects.insert(registry_key(dtmgr_directory), Project::from_state(&state));
+    registry.write()
+}
+
+/// One line about a registered project for `dtmgr projects list`
+fn describe(dir: &Path, project: Project) -> String {
+    let config_file = dir.join(CONFIG_FILE_NAME);
+    if !config_file.is_file() {
+        return color::warning(&format!("gone, it has no {} anymore", CONFIG_FILE_NAME), Stream::Stdout);
+    }
+    let state = parse_config(config_file)
+        .and_then(|config| status::environment_state(&dir.join(".dtmgr"), &config));
+    let mut description = match state {
+        Ok(state) => state.painted(Stream::Stdout),
+        Err(e) => color::warning(&e.to_string(), Stream::Stdout),
+    };
+    if let Ok(project) = project.or_environment(dir) {
+        if let Some(packages) = &project.packages {
+            description.push_str(&format!(", {} packages", packages.len()));
+        }
+        if let Some(installed) = &project.installed {
+            description.push_str(&format!(", installed {}", installed));
+        }
+    }
+    description
+}
+
+pub fn run_projects_command(command: ProjectsCommand) -> Result<(), DtMgrError> {
+    let mut registry = Registry::read()?;
+    match command {
+        ProjectsCommand::List => {
+            for (dir, project) in registry.projects {
+                println!("{}\t{}", dir.display(), describe(&dir, project));
+            }
+        }
+        ProjectsCommand::Forget { dirs, gone } => {
+            let mut forgotten = Vec::new();
+            for dir in dirs.iter() {
+                match registry.projects.remove_entry(&registry_key(dir)) {
+                    Some((dir, _)) => forgotten.push(dir),
+                    None => warn!("{} is not a registered project", dir.display()),
+                }
+            }
+            if gone {
+                let gone: Vec<PathBuf> = registry.projects.keys()
+                    .filter(|dir| !dir.join(CONFIG_FILE_NAME).is_file())
+                    .cloned()
+                    .collect();
+                for dir in gone {
+                    registry.projects.remove(&dir);
+                    forgotten.push(dir);
+                }
+            }
+            if !forgotten.is_empty() {
+                registry.write()?;
+            }
+            for dir in forgotten {
+                println!("forgot {}", dir.display());
+            }
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,56 @@
+use std::io::{BufRead, IsTerminal, Write};
+
+/// Whether questions can be asked on this terminal at all
+pub fn interactive() -> bool {
+    std::io::stdin().is_terminal() && std::io::stderr().is_terminal()
+}
+
+fn read_answer(question: &str) -> Option<String> {
+    eprint!("{} ", question);
+    std::io::stderr().flush().ok()?;
+    let mut answer = String::new();
+    std::io::stdin().lock().read_line(&mut answer).ok()?;
+    Some(answer.trim().to_owned())
+}
+
+/// Asks a yes-or-no question, where anything but yes is no
+pub fn confirm(question: &str) -> bool {
+    read_answer(&format!("{} [y/N]", question))
+        .is_some_and(|answer| answer.eq_ignore_ascii_case("y") || answer.eq_ignore_ascii_case("yes"))
+}
+
+/// Asks for one of `options` by number; an empty answer picks nothing
+pub fn choose<'a>(question: &str, options: &'a [String]) -> Option<&'a String> {
+    for (idx, option) in options.iter().enumerate() {
+        eprintln!("  {}) {}", idx + 1, option);
+    }
+    let answer = read_answer(&format!("{} [1-{}, empty to skip]", question, options.len()))?;
+    let idx: usize = answer.parse().ok()?;
+    options.get(idx.checked_sub(1)?)
+}
+
+/// Asks for any number of `options` by number, like `1 3` or `2-4`, and returns their indices;
+/// an empty or unreadable answer picks nothing
+pub fn choose_many(question: &str, options: &[String]) -> Vec<usize> {
+    for (idx, option) in options.iter().enumerate() {
+        eprintln!("  {}) {}", idx + 1, option);
+    }
+    let Some(answer) = read_answer(&format!("{} [e.g. 1 3 or 2-4, empty to skip]", question)) else {
+        return Vec::new();
+    };
+
+    let mut chosen = Vec::new();
+    for part in answer.split(|c: char| c == ',' || c.is_whitespace()).filter(|p| !p.is_empty()) {
+        let (first, last) = part.split_once('-').unwrap_or((part, part));
+        let (Ok(first), Ok(last)) = (first.parse::<usize>(), last.parse::<usize>()) else {
+            return Vec::new();
+        };
+        if first == 0 || last > options.len() {
+            return Vec::new();
+        }
+        chosen.extend((first..=last).map(|n| n - 1));
+    }
+    chosen.sort();
+    chosen.dedup();
+    chosen
+}
@@ -0,0 +1,138 @@
+use std::collections::BTreeMap as Map;
+use std::collections::BTreeSet as Set;
+use crate::{cmd_crossplatform_static_args, mirror, network, signals, stderr_tail, DtMgrError, TlPObjInfo};
+
+/// A package shipping a file matching the query, and the file's path in the tree
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Provider {
+    pub package: String,
+    pub file: String,
+}
+
+/// Something a document needs, and the file names any of which would satisfy it
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Requirement {
+    pub description: String,
+    pub candidates: Vec<String>,
+}
+
+impl Requirement {
+    pub fn file(name: &str) -> Requirement {
+        Requirement { description: name.to_owned(), candidates: vec![name.to_owned()] }
+    }
+}
+
+/// fontspec works with font names rather than files, so guess the usual file naming schemes
+pub fn font_candidates(font: &str) -> Vec<String> {
+    let squashed: String = font.chars()
+        .filter(|c| !c.is_whitespace())
+        .collect::<String>()
+        .to_lowercase();
+    let mut result = Vec::new();
+    for stem in [squashed.clone(), squashed.clone() + "-regular"] {
+        for ext in ["otf", "ttf"] {
+            result.push(format!("{}.{}", stem, ext));
+        }
+    }
+    // names given as file names already, e.g. "lmroman10-regular.otf"
+    if font.contains('.') {
+        result.insert(0, font.to_owned());
+    }
+    result
+}
+
+/// Matches either an exact file name (`standalone.cls`) or a path suffix (`latex/standalone/standalone.cls`)
+fn file_matches(file: &str, query: &str) -> bool {
+    file == query || file.ends_with(&(String::from("/") + query))
+}
+
+pub fn find_in_tlpdb(tlpdb: &Map<String, TlPObjInfo>, query: &str) -> Vec<Provider> {
+    let mut result = Vec::new();
+    for pkg in tlpdb.values() {
+        if let Some(runfiles) = &pkg.runfiles {
+            for file in runfiles.iter() {
+                if file_matches(file, query) {
+                    result.push(Provider { package: pkg.name.clone(), file: file.clone() });
+                }
+            }
+        }
+    }
+    result
+}
+
+// `tlmgr search --file --global` prints `package:` followed by tab-indented file names
+pub fn find_with_tlmgr(query: &str) -> Result<Vec<Provider>, DtMgrError> {
+    let mut cmd = cmd_crossplatform_static_args(["tlmgr", "search", "--file", "--global", query]);
+    network::apply_proxy(&mut cmd);
+    mirror::apply_repository(&mut cmd);
+    let out = signals::output(&mut cmd)?;
+
+    if !out.status.success() {
+        return Err(DtMgrError::CommandStatus { command: "tlmgr search --file --global ".to_owned() + query, code: out.status.code(), stderr: stderr_tail(&out.stderr) });
+    }
+
+    let stdout = String::from_utf8_lossy(&out.stdout);
+    let mut result = Vec::new();
+    let mut current: Option<&str> = None;
+    for line in stdout.lines() {
+        if line.starts_with(char::is_whitespace) {
+            let file = line.trim();
+            if let Some(package) = current
+                && file_matches(file, query) {
+                result.push(Provider { package: package.to_owned(), file: file.to_owned() });
+            }
+        } else if let Some(package) = line.strip_suffix(':') {
+            current = Some(package);
+        } else {
+            current = None;
+        }
+    }
+    Ok(result)
+}
+
+/// Looks `query` up in the local databases first, and asks the repository if they know nothing
+pub fn find_providers(tlpdb: &Map<String, TlPObjInfo>, query: &str) -> Result<Vec<Provider>, DtMgrError> {
+    let local = find_in_tlpdb(tlpdb, query);
+    if !local.is_empty() || network::offline() {
+        return Ok(local);
+    }
+    find_with_tlmgr(query)
+}
+
+/// Providers of the first candidate of `requirement` that any package ships
+pub fn find_requirement_providers(tlpdb: &Map<String, TlPObjInfo>, requirement: &Requirement) -> Result<Vec<Provider>, DtMgrError> {
+    for candidate in requirement.candidates.iter() {
+        let providers = find_providers(tlpdb, candidate)?;
+        if !providers.is_empty() {
+            return Ok(providers);
+        }
+    }
+    Ok(Vec::new())
+}
+
+/// Distinct package names among `providers`, in order
+pub fn provider_packages(providers: &[Provider]) -> Vec<String> {
+    let set: Set<&str> = providers.iter().map(|p| p.package.as_str()).collect();
+    set.into_iter().map(String::from).collect()
+}
+
+/// Strips the quoting LaTeX puts around file names, as in ``File `xstring.sty' not found.``
+pub fn clean_file_name(raw: &str) -> &str {
+    raw.trim().trim_end_matches('.').trim_matches(|c| c == '`' || c == '\'' || c == '"')
+}
+
+/// Picks the single package to depend on for `file`, asking when several packages ship it
+pub fn pick_package(file: &str, providers: &[Provider]) -> Result<String, DtMgrError> {
+    let packages = provider_packages(providers);
+    match packages.len() {
+        0 => Err(DtMgrError::NoProvider { file: file.to_owned() }),
+        1 => Ok(packages[0].clone()),
+        _ => {
+            if crate::prompt::interactive()
+                && let Some(package) = crate::prompt::choose(&format!("`{}` is shipped by several packages, which one?", file), &packages) {
+                return Ok(package.clone());
+            }
+            Err(DtMgrError::AmbiguousProvider { file: file.to_owned(), packages: packages.join(", ") })
+        }
+    }
+}
@@ -0,0 +1,168 @@
+use std::collections::BTreeMap as Map;
+use std::collections::BTreeSet as Set;
+use std::path::{Path, PathBuf};
+use serde::{Deserialize, Serialize};
+use tracing::debug;
+use crate::projects::Registry;
+use crate::{cmd_crossplatform_static_args, home, signals, size, tlpdb, DtMgrError, CONFIG_FILE_NAME};
+
+const INSTALLED_FILE_NAME: &str = "installed.json";
+
+/// The packages `dtmgr install` had tlmgr add to each TeX Live, by its root. Only these are
+/// ever offered for removal: whatever the user installed themselves stays.
+#[derive(Debug, Default, Deserialize, Serialize)]
+struct Installed {
+    roots: Map<PathBuf, Set<String>>,
+}
+
+fn installed_file() -> Result<PathBuf, DtMgrError> {
+    home::data_dir().map(|dir| dir.join(INSTALLED_FILE_NAME))
+}
+
+impl Installed {
+    fn read() -> Result<Installed, DtMgrError> {
+        let path = installed_file()?;
+        if !path.is_file() {
+            return Ok(Installed::default());
+        }
+        let content = std::fs::read(&path)
+            .map_err(|e| DtMgrError::ReadFile { path: path.clone(), source: e })?;
+        serde_json::from_slice(&content)
+            .map_err(|e| DtMgrError::JsonParse { source: e })
+    }
+
+    fn write(&self) -> Result<(), DtMgrError> {
+        let path = installed_file()?;
+        let dir = path.parent().expect("the list is in dtmgr's data directory");
+        std::fs::create_dir_all(dir)
+            .map_err(|e| DtMgrError::CreateDirectory { dir: dir.to_owned(), source: e })?;
+        let content = serde_json::to_string_pretty(self)
+            .map_err(|e| DtMgrError::JsonSerialize { source: e })?;
+        // written aside and renamed, like the project registry
+        let partial = path.with_extension("json.partial");
+        std::fs::write(&partial, content)
+            .map_err(|e| DtMgrError::WriteFile { file: partial.clone(), source: e })?;
+        std::fs::rename(&partial, &path)
+            .map_err(|e| DtMgrError::WriteFile { file: path, source: e })
+    }
+}
+
+fn read_local_tlpdb(root: &Path) -> Result<String, DtMgrError> {
+    let path = tlpdb::local_tlpdb(root);
+    std::fs::read_to_string(&path)
+        .map_err(|e| DtMgrError::ReadFile { path, source: e })
+}
+
+/// The name of every package the TeX Live in `root` has installed
+pub fn installed_packages(root: &Path) -> Result<Set<String>, DtMgrError> {
+    let content = read_local_tlpdb(root)?;
+    Ok(tlpdb::revisions(&content).into_keys().map(str::to_owned).collect())
+}
+
+/// Remembers the packages the TeX Live in `root` has now but did not have `before`, as the
+/// ones dtmgr installed
+pub fn record_installed(root: &Path, before: &Set<String>) -> Result<(), DtMgrError> {
+    let added: Set<String> = installed_packages(root)?.difference(before).cloned().collect();
+    if added.is_empty() {
+        return Ok(());
+    }
+    debug!("tlmgr installed {}", added.iter().cloned().collect::<Vec<_>>().join(", "));
+    let mut installed = Installed::read()?;
+    installed.roots.entry(root.to_owned()).or_default().extend(added);
+    installed.write()
+}
+
+/// A package dtmgr installed that no registered project links anymore
+pub struct Unneeded {
+    pub name: String,
+    /// Bytes it takes up in TeX Live, with the binaries of every platform
+    pub size: u64,
+}
+
+pub struct Pruning {
+    pub packages: Vec<Unneeded>,
+    /// Registered projects without an installed environment, whose packages are kept as dtmgr
+    /// cannot tell which they need
+    pub unknown: Vec<PathBuf>,
+}
+
+/// The packages dtmgr installed into the TeX Live in `root` that neither a registered project's
+/// environment links nor anything else TeX Live keeps depends on
+pub fn unneeded_packages(root: &Path) -> Result<Pruning, DtMgrError> {
+    let database = tlpdb::parse_tlpdb(&read_local_tlpdb(root)?, tlpdb::TlpdbOrigin::Local);
+    let mut installed = Installed::read()?;
+    let mut candidates: Set<String> = installed.roots.remove(root).unwrap_or_default().into_iter()
+        .filter(|name| database.contains_key(name))
+        .collect();
+    for base in crate::base_packages() {
+        candidates.remove(&base);
+    }
+
+    let mut unknown = Vec::new();
+    for (dir, project) in Registry::read()?.projects {
+        // `dtmgr upgrade-texlive` forgets projects that are gone
+        if !dir.join(CONFIG_FILE_NAME).is_file() {
+            continue;
+        }
+        let project = project.or_environment(&dir)?;
+        // an environment linked to another TeX Live needs nothing from this one
+        if project.texlive_root.as_deref().is_some_and(|linked| linked != root) {
+            continue;
+        }
+        match project.packages {
+            Some(packages) => {
+                for name in packages.iter() {
+                    candidates.remove(name);
+                }
+            }
+            None => unknown.push(dir),
+        }
+    }
+    if !unknown.is_empty() {
+        candidates.clear();
+    }
+
+    // what stays keeps its dependencies, which may in turn keep others
+    let mut kept: Vec<String> = database.keys().filter(|name| !candidates.contains(*name)).cloned().collect();
+    while let Some(name) = kept.pop() {
+        for dep in database.get(&name).and_then(|pkg| pkg.depends.as_ref()).into_iter().flatten() {
+            let needed: Vec<String> = match dep.strip_suffix(".ARCH") {
+                Some(stem) => candidates.iter().filter(|c| c.strip_prefix(stem).is_some_and(|rest| rest.starts_with('.'))).cloned().collect(),
+                None => candidates.iter().filter(|c| *c == dep).cloned().collect(),
+            };
+            for name in needed {
+                candidates.remove(&name);
+                kept.push(name);
+            }
+        }
+    }
+
+    let packages = candidates.into_iter()
+        .map(|name| {
+            let size = database.get(&name).map(size::installed_size).unwrap_or(0);
+            Unneeded { name, size }
+        })
+        .collect();
+    Ok(Pruning { packages, unknown })
+}
+
+/// Removes `packages` from the TeX Live in `root` with `tlmgr remove`, showing its output
+pub fn remove(root: &Path, packages: &[String]) -> Result<(), DtMgrError> {
+    let command = format!("tlmgr remove --no-depends {}", packages.join(" "));
+    let mut cmd = cmd_crossplatform_static_args(["tlmgr", "remove", "--no-depends"].into_iter().chain(packages.iter().map(String::as_str)));
+    let status = signals::status(&mut cmd)
+        .map_err(|e| DtMgrError::CommandExecution { source: e })?;
+    crate::forget_package_database();
+    if !status.success() {
+        return Err(DtMgrError::CommandStatus { command, code: status.code(), stderr: String::new() });
+    }
+
+    let mut installed = Installed::read()?;
+    if let Some(recorded) = installed.roots.get_mut(root) {
+        for package in packages {
+            recorded.remove(package);
+        }
+        installed.write()?;
+    }
+    Ok(())
+}
@@ -0,0 +1,121 @@
+use std::collections::BTreeMap as Map;
+use std::path::Path;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use sha3::{Digest, Sha3_256};
+use tracing::{debug, info, warn};
+use crate::link::LinkStrategy;
+use crate::{archive, DtMgrConfig, DtMgrError, TlPObjInfo};
+
+// a bearer token for the remote, kept out of dtmgr.toml
+const TOKEN_VARIABLE: &str = "DTMGR_REMOTE_CACHE_TOKEN";
+// set (e.g. in CI) to upload environments the remote does not have yet
+const PUSH_VARIABLE: &str = "DTMGR_REMOTE_CACHE_PUSH";
+
+/// The `[remote-cache]` section of `dtmgr.toml`
+#[derive(Debug, Default, Deserialize, JsonSchema, Hash)]
+#[serde(deny_unknown_fields)]
+pub struct RemoteCacheConfig {
+    /// Where built environments are kept, each as `<url>/<key>.tar.gz`
+    #[serde(default)]
+    url: Option<String>,
+}
+
+// why an environment for `config` would not work on another machine, if it would not
+fn unshareable(config: &DtMgrConfig, dep_tree: &Map<String, TlPObjInfo>) -> Option<&'static str> {
+    // these link into the user's home or cache directory
+    if dep_tree.values().any(|pkg| pkg.relocated == Some(true)) {
+        Some("it has packages installed in TEXMFHOME")
+    } else if config.use_texmfhome {
+        Some("it uses TEXMFHOME")
+    } else if config.sources.values().any(|source| !source.is_local()) {
+        Some("it has [sources] from git or CTAN")
+    } else {
+        None
+    }
+}
+
+fn authorization() -> Option<String> {
+    std::env::var(TOKEN_VARIABLE).ok()
+        .filter(|token| !token.is_empty())
+        .map(|token| format!("Bearer {}", token))
+}
+
+impl RemoteCacheConfig {
+    /// Identifies the environment for `config` on `platform` in the remote, or `None` if there
+    /// is no remote or the environment cannot be shared.
+    ///
+    /// Its links lead into the TeX Live at `root`, so that is part of the key as much as the
+    /// revisions of the packages in `dep_tree`.
+    pub fn key(&self, config: &DtMgrConfig, strategy: LinkStrategy, platform: &str, root: &Path, dep_tree: &Map<String, TlPObjInfo>) -> Result<Option<String>, DtMgrError> {
+        if self.url.is_none() || crate::network::offline() {
+            return Ok(None);
+        }
+        if let Some(reason) = unshareable(config, dep_tree) {
+            info!("not using the remote cache, as {}", reason);
+            return Ok(None);
+        }
+
+        let mut hasher = Sha3_256::new();
+        hasher.update(crate::hash_config(config, strategy)?.as_bytes());
+        hasher.update([0]);
+        hasher.update(root.as_os_str().as_encoded_bytes());
+        hasher.update([0]);
+        for pkg in dep_tree.values() {
+            hasher.update(format!("{} {}\n", pkg.name, pkg.lrev.unwrap_or_default()).as_bytes());
+        }
+        let hash: [u8; 32] = hasher.finalize().into();
+        Ok(Some(format!("{}-{}", platform, &hex::encode(hash)[..32])))
+    }
+
+    fn archive_url(&self, key: &str) -> Option<String> {
+        self.url.as_ref().map(|url| format!("{}/{}.tar.gz", url.trim_end_matches('/'), key))
+    }
+
+    /// Fills the fresh `dot_dir` with the environment the remote has under `key`, returning
+    /// whether it did.
+    ///
+    /// A remote that is unreachable or holds a broken archive only costs a local build.
+    pub fn pull(&self, key: &str, project_dir: &Path, dot_dir: &Path) -> Result<bool, DtMgrError> {
+        let Some(url) = self.archive_url(key) else {
+            return Ok(false);
+        };
+        let archive = match crate::http::get_if_exists(&url, authorization().as_deref()) {
+            Ok(Some(archive)) => archive,
+            Ok(None) => {
+                debug!("the remote cache has no {}", url);
+                return Ok(false);
+            }
+            Err(e) => {
+                warn!("{}, building the environment here", e);
+                return Ok(false);
+            }
+        };
+
+        info!("using the environment from {}", url);
+        if let Err(e) = archive::unpack(archive.as_slice(), project_dir, dot_dir) {
+            warn!("{}, building the environment here", e);
+            crate::clear_dot_dir(dot_dir)?;
+            return Ok(false);
+        }
+        Ok(true)
+    }
+
+    /// Uploads the environment in `dot_dir` under `key` if `DTMGR_REMOTE_CACHE_PUSH` is set
+    pub fn push(&self, key: &str, project_dir: &Path, dot_dir: &Path) -> Result<(), DtMgrError> {
+        let Some(url) = self.archive_url(key) else {
+            return Ok(());
+        };
+        if std::env::var_os(PUSH_VARIABLE).is_none_or(|v| v.is_empty()) {
+            return Ok(());
+        }
+
+        info!("uploading the environment to {}", url);
+        let archive = archive::pack(project_dir, dot_dir, false, Vec::new())?;
+        // the environment is in place either way
+        if let Err(e) = crate::http::put(&url, authorization().as_deref(), &archive) {
+            warn!("{}", e);
+        }
+        Ok(())
+    }
+}
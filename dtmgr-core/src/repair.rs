@@ -0,0 +1,276 @@
+use std::collections::BTreeMap as Map;
+use std::collections::BTreeSet as Set;
+use std::ffi::OsString;
+use std::path::Path;
+use std::time::SystemTime;
+use tracing::{debug, info, warn};
+use crate::manifest::Manifest;
+use crate::state::State;
+use crate::status::{self, EnvironmentState};
+use crate::{
+    context, cmd_crossplatform_static_args, executes, exclude, incremental, install_log, ls_r, mirror, network,
+    progress, sources, stderr_tail, texmf_cnf, tlpdb, verify, DtMgrConfig, DtMgrError, TlPObjInfo,
+};
+
+/// Why a package of the environment has to be linked again
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Damage {
+    /// tlmgr updated it since the install, so its files may have changed
+    Updated,
+    /// A package it depends on now, which the environment does not have yet
+    Added,
+    /// Some of its files are missing from the environment or from TeX Live
+    Missing,
+    /// Its files in TeX Live changed without tlmgr updating it, see [`verify::modified_packages`]
+    Modified,
+}
+
+impl Damage {
+    pub fn describe(self) -> &'static str {
+        match self {
+            Damage::Updated => "updated in TeX Live",
+            Damage::Added => "newly needed",
+            Damage::Missing => "files missing",
+            Damage::Modified => "files changed in TeX Live",
+        }
+    }
+
+    // the package's files in TeX Live are themselves broken, which only tlmgr can put right
+    fn needs_reinstall(self) -> bool {
+        matches!(self, Damage::Missing | Damage::Modified)
+    }
+}
+
+/// What the environment in `dot_dir` was made from, or why it cannot be repaired
+fn repairable(dot_dir: &Path, config: &DtMgrConfig, platform: &str) -> Result<(State, Manifest), String> {
+    let environment = status::environment_state(dot_dir, config).map_err(|e| e.to_string())?;
+    match environment {
+        EnvironmentState::Missing => return Err(String::from("there is none")),
+        EnvironmentState::Incompatible => return Err(String::from("it is from another dtmgr release")),
+        EnvironmentState::Outdated | EnvironmentState::UpToDate => {}
+    }
+    let (Ok(Some(state)), Ok(Some(manifest))) = (State::read(dot_dir), Manifest::read(dot_dir)) else {
+        return Err(String::from("it has no record of what it linked"));
+    };
+    if !status::config_matches(&state.config_hash, state.without_dev, config, state.link_strategy).unwrap_or(false) {
+        return Err(format!("{} changed since it was installed", crate::CONFIG_FILE_NAME));
+    }
+    if state.platform != platform {
+        return Err(format!("it was installed for {}", state.platform));
+    }
+    Ok((state, manifest))
+}
+
+// binaries for another platform that TeX Live does not have, which an install leaves out
+fn is_skipped(pkg: &TlPObjInfo, platform: &str) -> bool {
+    pkg.binary_platform().is_some_and(|p| p != platform) && pkg.lrev.is_none()
+}
+
+/// The packages of `dep_tree` the environment's `manifest` no longer matches, with why
+fn damaged<'a>(dot_dir: &Path, manifest: &Manifest, dep_tree: &'a Map<String, TlPObjInfo>, platform: &str, exclusions: &exclude::Exclusions) -> Result<Map<&'a str, Damage>, DtMgrError> {
+    let mut damaged = Map::new();
+    let linked = dep_tree.values().filter(|pkg| !is_skipped(pkg, platform));
+    for pkg in linked {
+        match manifest.packages.get(&pkg.name) {
+            None => {
+                damaged.insert(pkg.name.as_str(), Damage::Added);
+            }
+            Some(linked) if linked.revision != pkg.lrev.or(pkg.rrev) => {
+                damaged.insert(pkg.name.as_str(), Damage::Updated);
+            }
+            Some(_) => {}
+        }
+    }
+    let report = verify::verify_environment(dot_dir, EnvironmentState::UpToDate, dep_tree, platform, exclusions);
+    for problem in report.problems.iter().filter(|problem| !dep_tree.get(problem.package).is_some_and(|pkg| is_skipped(pkg, platform))) {
+        damaged.entry(problem.package).or_insert(Damage::Missing);
+    }
+    for package in verify::modified_packages(manifest, dep_tree, platform, exclusions)? {
+        damaged.entry(package).or_insert(Damage::Modified);
+    }
+    Ok(damaged)
+}
+
+fn reinstall(packages: &[&str], log: &install_log::InstallLog) -> Result<(), DtMgrError> {
+    let command = format!("tlmgr install --reinstall {}", packages.join(" "));
+    let mut cmd = cmd_crossplatform_static_args(["tlmgr", "install", "--reinstall"].into_iter().chain(packages.iter().copied()));
+    network::apply_proxy(&mut cmd);
+    mirror::apply_repository(&mut cmd);
+    let echo = !install_log::quiet();
+    network::retry(|| {
+        let (out, stderr) = log.run(&command, &mut cmd, None, echo)?;
+        if out.success() {
+            Ok(())
+        } else {
+            Err(DtMgrError::CommandStatus { command: command.clone(), code: out.code(), stderr: stderr_tail(&stderr) })
+        }
+    })
+}
+
+/// Removes whatever the environment has at each of `pkg`'s files, so they can be linked again
+fn unlink(dot_dir: &Path, pkg: &TlPObjInfo, platform: &str, exclusions: &exclude::Exclusions) -> Result<(), DtMgrError> {
+    let files = pkg.files(platform)
+        .filter(|f| !exclusions.excludes(&pkg.name, f) && !executes::GENERATED_RUNFILES.contains(f));
+    for file in files {
+        let path = dot_dir.join(file);
+        if path.symlink_metadata().is_ok_and(|m| !m.is_dir()) {
+            std::fs::remove_file(&path)
+                .map_err(|e| DtMgrError::WriteFile { file: path, source: e })?;
+        }
+    }
+    Ok(())
+}
+
+// links below `dir` whose target is gone, such as files an updated package no longer has
+fn remove_dangling(dir: &Path) -> Result<usize, DtMgrError> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Ok(0);
+    };
+    let mut removed = 0;
+    for entry in entries.filter_map(|entry| entry.ok()) {
+        let path = entry.path();
+        match entry.file_type() {
+            Ok(t) if t.is_symlink() && path.metadata().is_err() => {
+                std::fs::remove_file(&path)
+                    .map_err(|e| DtMgrError::WriteFile { file: path, source: e })?;
+                removed += 1;
+            }
+            Ok(t) if t.is_dir() => removed += remove_dangling(&path)?,
+            _ => {}
+        }
+    }
+    Ok(removed)
+}
+
+fn read_all(dot_dir: &Path, files: &[&str]) -> Vec<Option<Vec<u8>>> {
+    files.iter().map(|file| std::fs::read(dot_dir.join(file)).ok()).collect()
+}
+
+/// Links the packages of the environment of the project in `dtmgr_directory` that TeX Live
+/// changed or broke underneath it again, reinstalling those whose files TeX Live lost or that
+/// were edited there, and reruns only the finishing steps they affect.
+///
+/// Returns the packages it repaired, nothing if the environment was intact. An environment that
+/// is missing, from another release or from another configuration cannot be repaired.
+pub fn repair(dtmgr_directory: &Path) -> Result<Map<String, Damage>, DtMgrError> {
+    let started = SystemTime::now();
+    let config = crate::parse_config(dtmgr_directory.join(crate::CONFIG_FILE_NAME))?;
+    let dot_dir = dtmgr_directory.join(".dtmgr");
+    let root = crate::get_texlive_root()?;
+    let platform = crate::texlive_platform(&config)?;
+    let (state, previous) = repairable(&dot_dir, &config, &platform)
+        .map_err(|reason| DtMgrError::CannotRepair { reason })?;
+
+    let mut dep_tree = crate::build_dependency_tree(&config, &platform)?;
+    let gone: Vec<&str> = previous.packages.keys()
+        .filter(|name| !dep_tree.contains_key(*name))
+        .map(String::as_str)
+        .collect();
+    if !gone.is_empty() {
+        return Err(DtMgrError::CannotRepair { reason: format!("it no longer needs {}", gone.join(", ")) });
+    }
+    let exclusions = exclude::Exclusions::new(&config)?;
+    let damage: Map<String, Damage> = damaged(&dot_dir, &previous, &dep_tree, &platform, &exclusions)?.into_iter()
+        .map(|(name, damage)| (name.to_owned(), damage))
+        .collect();
+    if damage.is_empty() {
+        return Ok(damage);
+    }
+    for (name, damage) in damage.iter() {
+        info!("{}: {}", name, damage.describe());
+    }
+
+    let log = install_log::InstallLog::create(&dot_dir)?;
+    let broken: Vec<&str> = damage.iter()
+        .filter(|(_, damage)| damage.needs_reinstall())
+        .map(|(name, _)| name.as_str())
+        .collect();
+    if !broken.is_empty() && network::offline() {
+        warn!("not reinstalling {} offline, linking them as they are", broken.join(", "));
+    } else if !broken.is_empty() {
+        reinstall(&broken, &log)?;
+        // tlmgr may have brought them to a newer revision
+        dep_tree = crate::build_dependency_tree(&config, &platform)?;
+    }
+
+    let strategy = state.link_strategy;
+    let user_tree = crate::user_tree(&dep_tree)?;
+    // the packages a file of the project replaces, which are linked again before it is
+    let overridden: Set<&str> = dep_tree.values()
+        .filter(|pkg| config.overrides.keys().any(|target| pkg.files(&platform).any(|f| f.strip_prefix("texmf-dist/") == Some(target))))
+        .map(|pkg| pkg.name.as_str())
+        .collect();
+    let relinked: Vec<&TlPObjInfo> = dep_tree.values()
+        .filter(|pkg| damage.contains_key(&pkg.name) || overridden.contains(pkg.name.as_str()))
+        .collect();
+    let formats_before = read_all(&dot_dir, &executes::FORMAT_FILES);
+    let maps_before = read_all(&dot_dir, &executes::MAP_FILES);
+    for pkg in relinked.iter() {
+        debug!("linking {} again", pkg.name);
+        unlink(&dot_dir, pkg, &platform, &exclusions)?;
+        let relocated_root = crate::relocated_root(&root, user_tree.as_deref(), pkg);
+        let package_strategy = config.package_options.get(&pkg.name).and_then(|options| options.link).unwrap_or(strategy);
+        crate::do_symlinks(package_strategy, &root, relocated_root, &dot_dir, &platform, pkg, &exclusions)?;
+    }
+    crate::relink_dangling_binfiles(&root, &dot_dir)?;
+    let removed = remove_dangling(&dot_dir)?;
+    if removed > 0 {
+        debug!("removed {} links to files TeX Live no longer has", removed);
+    }
+    // in the order of an install, as a source or override may replace a file linked again
+    let trees = sources::fetch_all(&config.sources, dtmgr_directory)?;
+    for (name, source) in config.sources.iter() {
+        source.link(name, &trees[name], strategy, &dot_dir)?;
+    }
+    executes::write_generated_files(&dot_dir, &dep_tree)?;
+    for (target, file_override) in config.overrides.iter() {
+        file_override.apply(target, dtmgr_directory, &dot_dir)?;
+    }
+
+    let changed: Set<&str> = relinked.iter().map(|pkg| pkg.name.as_str()).collect();
+    let format_packages = incremental::closure(&dep_tree, &platform, dep_tree.values().filter(|pkg| executes::contributes_formats(pkg)));
+    let formats = formats_before != read_all(&dot_dir, &executes::FORMAT_FILES)
+        || changed.iter().any(|name| format_packages.contains(name));
+    let font_maps = maps_before != read_all(&dot_dir, &executes::MAP_FILES)
+        || changed.iter().any(|name| dep_tree.get(*name).is_some_and(executes::contributes_maps));
+    let mut cnf = texmf_cnf::TexmfCnf::for_environment(&dot_dir);
+    if let Some(tree) = &config.texmf {
+        cnf.add_tree(&crate::project_tree(dtmgr_directory, tree)?);
+    }
+    let database_trees = cnf.database_trees(&dot_dir);
+    if crate::platform_override(&config).is_some() && crate::get_texlive_platform()? != platform {
+        warn!("{} binaries cannot run here; finish the environment on that platform with `dtmgr run fmtutil-sys --missing`", platform);
+        for tree in database_trees.iter() {
+            ls_r::write(tree)?;
+        }
+    } else {
+        let context_steps: &[(&str, &[&str])] = if context::in_closure(&dep_tree) { &context::STEPS } else { &[] };
+        let steps = progress::steps((1 + usize::from(formats) + usize::from(font_maps) + context_steps.len()) as u64);
+        crate::native_step(&steps, "updating the filename databases");
+        for tree in database_trees.iter() {
+            ls_r::write(tree)?;
+        }
+        if formats {
+            crate::post_install_step(dtmgr_directory, &steps, &log, "building formats", &["fmtutil-sys", "--all", "--no-strict"])?;
+        }
+        if font_maps {
+            let updmap = [OsString::from("updmap-sys"), OsString::from("--cnffile"), executes::updmap_cfg_file(&dot_dir).into_os_string()];
+            crate::post_install_step(dtmgr_directory, &steps, &log, "regenerating font maps", &updmap)?;
+        }
+        for (description, command) in context_steps.iter() {
+            crate::post_install_step(dtmgr_directory, &steps, &log, description, command)?;
+        }
+        steps.finish_and_clear();
+    }
+
+    let mut manifest = Manifest::from_tree(&dep_tree, &platform);
+    manifest.link_strategy = strategy;
+    manifest.platform = previous.platform;
+    manifest.extra_platforms = previous.extra_platforms;
+    manifest.linking = previous.linking;
+    verify::record_checksums(&mut manifest, &root, user_tree.as_deref(), &dep_tree, &platform, &exclusions);
+    manifest.tlpdb_modified = tlpdb::modified(&tlpdb::local_tlpdb(&root));
+    manifest.write(&dot_dir)?;
+    State::new(&config, dtmgr_directory, &manifest, &platform, &root, started)?.write(&dot_dir)?;
+    Ok(damage)
+}
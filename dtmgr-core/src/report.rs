@@ -0,0 +1,164 @@
+use std::collections::BTreeMap as Map;
+use std::fmt::Debug;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::SystemTime;
+use serde::{Deserialize, Serialize};
+use tracing::field::{Field, Visit};
+use tracing::{debug, Event, Level, Subscriber};
+use tracing_subscriber::layer::{Context, Layer};
+use crate::link::LinkStrategy;
+use crate::{events, DtMgrError, TlPObjInfo};
+
+pub const REPORT_FILE_NAME: &str = "report.json";
+
+// installs run one after another, so one report is collected at a time
+static RECORDING: Mutex<Option<InstallReport>> = Mutex::new(None);
+
+/// How many files linking put into the environment, by how
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct FileCounts {
+    /// Symlinked or hard linked
+    pub linked: u64,
+    /// Copied, for packages whose `link` option asks for it
+    pub copied: u64,
+}
+
+/// A tool the install ran
+#[derive(Debug, Deserialize, Serialize)]
+pub struct Step {
+    pub command: String,
+    pub seconds: f64,
+    /// The exit code, missing if the tool was ended by a signal
+    pub status: Option<i32>,
+}
+
+/// What one `dtmgr install` did, written to `.dtmgr/report.json` whether or not it succeeded
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct InstallReport {
+    pub succeeded: bool,
+    /// Why the install failed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// When the install started and finished, in RFC 3339
+    pub started: String,
+    pub finished: String,
+    /// The revision of every resolved package, by name
+    pub packages: Map<String, Option<u64>>,
+    pub files: FileCounts,
+    /// In the order they ran, including retries
+    pub steps: Vec<Step>,
+    /// Every warning the install logged
+    pub warnings: Vec<String>,
+}
+
+/// The gist of the last install, as `dtmgr status` shows it
+#[derive(Debug, Serialize)]
+pub struct Summary {
+    pub succeeded: bool,
+    pub error: Option<String>,
+    pub finished: String,
+    pub packages: usize,
+    pub warnings: usize,
+}
+
+fn with_recording(record: impl FnOnce(&mut InstallReport)) {
+    if let Ok(mut recording) = RECORDING.lock()
+        && let Some(report) = recording.as_mut() {
+        record(report);
+    }
+}
+
+/// Starts collecting the report of an install, forgetting any unfinished one
+pub(crate) fn start() {
+    let report = InstallReport {
+        started: humantime::format_rfc3339_seconds(SystemTime::now()).to_string(),
+        ..InstallReport::default()
+    };
+    if let Ok(mut recording) = RECORDING.lock() {
+        *recording = Some(report);
+    }
+}
+
+pub(crate) fn record_step(command: &str, seconds: f64, status: Option<i32>) {
+    with_recording(|report| report.steps.push(Step { command: command.to_owned(), seconds, status }));
+}
+
+pub(crate) fn record_packages(dep_tree: &Map<String, TlPObjInfo>) {
+    with_recording(|report| report.packages = dep_tree.iter().map(|(name, pkg)| (name.clone(), pkg.lrev)).collect());
+}
+
+pub(crate) fn record_files(strategy: LinkStrategy, count: u64) {
+    with_recording(|report| match strategy {
+        LinkStrategy::Copy => report.files.copied += count,
+        LinkStrategy::Symlink | LinkStrategy::Hardlink => report.files.linked += count,
+    });
+}
+
+/// Ends the report [`start`] began with how the install ended, and writes it to `dot_dir`.
+/// An install is not failed over its report, which is only logged if it cannot be written.
+pub(crate) fn finish(dot_dir: &Path, result: &Result<(), DtMgrError>) {
+    let Some(mut report) = RECORDING.lock().ok().and_then(|mut recording| recording.take()) else {
+        return;
+    };
+    report.succeeded = result.is_ok();
+    report.error = result.as_ref().err().map(|e| e.to_string());
+    report.finished = humantime::format_rfc3339_seconds(SystemTime::now()).to_string();
+    if let Err(e) = report.write(dot_dir) {
+        debug!("unable to write the install report: {}", e);
+    }
+}
+
+impl InstallReport {
+    /// The report of the last install in `dot_dir`, if it left one that this release can read
+    pub fn read(dot_dir: &Path) -> Option<InstallReport> {
+        let content = std::fs::read(dot_dir.join(REPORT_FILE_NAME)).ok()?;
+        serde_json::from_slice(&content).ok()
+    }
+
+    pub fn write(&self, dot_dir: &Path) -> Result<(), DtMgrError> {
+        let path = dot_dir.join(REPORT_FILE_NAME);
+        let content = serde_json::to_string_pretty(self)
+            .map_err(|e| DtMgrError::JsonSerialize { source: e })?;
+        std::fs::write(&path, content)
+            .map_err(|e| DtMgrError::WriteFile { file: path, source: e })
+    }
+
+    pub fn summary(&self) -> Summary {
+        Summary {
+            succeeded: self.succeeded,
+            error: self.error.clone(),
+            finished: self.finished.clone(),
+            packages: self.packages.len(),
+            warnings: self.warnings.len(),
+        }
+    }
+}
+
+struct MessageVisitor(Option<String>);
+
+impl Visit for MessageVisitor {
+    fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+        if field.name() == "message" {
+            self.0 = Some(format!("{:?}", value));
+        }
+    }
+}
+
+/// A `tracing` layer that adds the warnings logged during an install to its report and passes
+/// every warning on as an [`events::Event::Warning`]; without it, neither gets any
+pub struct Warnings;
+
+impl<S: Subscriber> Layer<S> for Warnings {
+    fn on_event(&self, event: &Event<'_>, _: Context<'_, S>) {
+        if *event.metadata().level() != Level::WARN {
+            return;
+        }
+        let mut visitor = MessageVisitor(None);
+        event.record(&mut visitor);
+        if let Some(message) = visitor.0 {
+            with_recording(|report| report.warnings.push(message.clone()));
+            events::emit(events::Event::Warning { message });
+        }
+    }
+}
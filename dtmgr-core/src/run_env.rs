@@ -0,0 +1,137 @@
+use std::collections::BTreeMap as Map;
+use std::ffi::{OsStr, OsString};
+use std::path::Path;
+use std::process::Command;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use crate::{texmf_cnf, DtMgrError, KPSE_SEPARATOR};
+
+/// Which of dtmgr's own environment variables tools see
+#[derive(Clone, Copy, Debug, Default, Deserialize, JsonSchema, Hash, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum EnvPassthrough {
+    #[default]
+    All,
+    /// Only the variables tools generally need and `allow-env`, so e.g. a `TEXINPUTS` set on the
+    /// host cannot change what the environment finds
+    Allowlist,
+}
+
+// what tools generally need to run at all; a trailing `*` matches any suffix
+const ALWAYS_ALLOWED: [&str; 23] = [
+    "HOME", "USER", "LOGNAME", "SHELL", "LANG", "LANGUAGE", "LC_*", "TERM", "TZ", "TMPDIR", "TMP", "TEMP",
+    "DISPLAY", "WAYLAND_DISPLAY", "XDG_RUNTIME_DIR",
+    "SYSTEMROOT", "WINDIR", "COMSPEC", "PATHEXT", "USERPROFILE", "APPDATA", "LOCALAPPDATA", "SYSTEMDRIVE",
+];
+
+// all a hermetic run passes on from the host: where tools may keep files, what Windows needs to
+// start a process at all, and PATH for `run --keep-path`
+const HERMETIC_ALLOWED: [&str; 15] = [
+    "PATH", "HOME", "USER", "LOGNAME", "TMPDIR", "TMP", "TEMP",
+    "SYSTEMROOT", "WINDIR", "COMSPEC", "PATHEXT", "USERPROFILE", "APPDATA", "LOCALAPPDATA", "SYSTEMDRIVE",
+];
+
+/// The `[run]` section of `dtmgr.toml`, for the tools `run`, `build`, `test` and `run-script` start
+#[derive(Debug, Default, Deserialize, JsonSchema, Hash)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct RunConfig {
+    #[serde(default)]
+    env: EnvPassthrough,
+    /// More variables to pass through with `env = "allowlist"`
+    #[serde(default)]
+    allow_env: Vec<String>,
+    /// Start tools with nothing of the host's environment but PATH, the home and temporary
+    /// directories and what Windows needs, besides dtmgr's own variables and `[env]`
+    #[serde(default)]
+    pub hermetic: bool,
+    /// Run tools as `dtmgr run --restricted` does, for projects that build sources from
+    /// elsewhere
+    #[serde(default)]
+    pub restricted: bool,
+}
+
+fn matches(pattern: &str, name: &str) -> bool {
+    // variable names are case-insensitive on Windows
+    let (pattern, name) = if cfg!(windows) {
+        (pattern.to_ascii_uppercase(), name.to_ascii_uppercase())
+    } else {
+        (pattern.to_owned(), name.to_owned())
+    };
+    match pattern.strip_suffix('*') {
+        Some(prefix) => name.starts_with(prefix),
+        None => name == pattern,
+    }
+}
+
+impl RunConfig {
+    fn allows(&self, name: &OsStr) -> bool {
+        let name = name.to_string_lossy();
+        ALWAYS_ALLOWED.iter().copied()
+            .chain(self.allow_env.iter().map(String::as_str))
+            .any(|pattern| matches(pattern, &name))
+    }
+
+    /// Keeps the variables dtmgr inherited out of `cmd` unless they are allowed, leaving alone the
+    /// ones set on `cmd` itself, then sets the project's `[env]`
+    pub fn filter_environment(&self, cmd: &mut Command, env: &Map<String, String>) {
+        if self.hermetic {
+            let set: Vec<(OsString, OsString)> = cmd.get_envs()
+                .filter_map(|(name, value)| Some((name.to_owned(), value?.to_owned())))
+                .collect();
+            cmd.env_clear();
+            for (name, value) in std::env::vars_os() {
+                if HERMETIC_ALLOWED.iter().any(|pattern| matches(pattern, &name.to_string_lossy())) {
+                    cmd.env(name, value);
+                }
+            }
+            cmd.envs(set);
+        } else if self.env == EnvPassthrough::Allowlist {
+            let set: Vec<_> = cmd.get_envs().map(|(name, _)| name.to_owned()).collect();
+            for (name, _) in std::env::vars_os() {
+                if !self.allows(&name) && !set.contains(&name) {
+                    cmd.env_remove(name);
+                }
+            }
+        }
+        cmd.envs(env);
+    }
+
+    /// With `restricted` set, puts the environment's restricted `texmf.cnf` in front of `cmd`'s
+    /// TEXMFCNF, and keeps out variables that would loosen it again, such as a `shell_escape=t`
+    /// or `openout_any.pdftex=a` kpathsea would take over any `texmf.cnf`, whether inherited or
+    /// set on `cmd`, e.g. from `[env]`. Call it last, once everything else is set.
+    pub fn restrict(&self, cmd: &mut Command, dtmgr_directory: &Path) -> Result<(), DtMgrError> {
+        if !self.restricted {
+            return Ok(());
+        }
+        let mut texmfcnf = OsString::from(texmf_cnf::write_restricted(&dtmgr_directory.join(".dtmgr"))?);
+        let environment = cmd.get_envs().find_map(|(name, value)| (name == "TEXMFCNF").then_some(value)).flatten();
+        if let Some(environment) = environment.map(OsStr::to_owned) {
+            texmfcnf.push(KPSE_SEPARATOR.to_string());
+            texmfcnf.push(environment);
+        }
+        cmd.env("TEXMFCNF", texmfcnf);
+        let set: Vec<OsString> = cmd.get_envs()
+            .filter(|(_, value)| value.is_some())
+            .map(|(name, _)| name.to_owned())
+            .collect();
+        for name in std::env::vars_os().map(|(name, _)| name).chain(set) {
+            let name_str = name.to_string_lossy();
+            // kpathsea also looks for `NAME.program` and `NAME_program`
+            let loosens = texmf_cnf::RESTRICTED_VARIABLES.iter()
+                .any(|variable| matches(&format!("{}*", variable), &name_str));
+            if loosens {
+                cmd.env_remove(name);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Parses a `KEY=VALUE` argument of `dtmgr run -e`
+pub fn parse_assignment(assignment: &str) -> Result<(String, String), String> {
+    match assignment.split_once('=') {
+        Some((name, value)) if !name.is_empty() => Ok((name.to_owned(), value.to_owned())),
+        _ => Err(format!("`{}` is not of the form KEY=VALUE", assignment)),
+    }
+}
@@ -0,0 +1,308 @@
+use std::collections::BTreeMap as Map;
+use clap::ValueEnum;
+use serde::Serialize;
+use crate::TlPObjInfo;
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum SbomFormat {
+    Cyclonedx,
+    Spdx,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct CycloneDxBom {
+    bom_format: &'static str,
+    spec_version: &'static str,
+    version: u32,
+    metadata: CycloneDxMetadata,
+    components: Vec<CycloneDxComponent>,
+}
+
+#[derive(Serialize)]
+struct CycloneDxMetadata {
+    timestamp: String,
+    tools: CycloneDxTools,
+    component: CycloneDxComponent,
+}
+
+#[derive(Serialize)]
+struct CycloneDxTools {
+    components: Vec<CycloneDxComponent>,
+}
+
+#[derive(Serialize)]
+struct CycloneDxComponent {
+    #[serde(rename = "type")]
+    component_type: &'static str,
+    #[serde(rename = "bom-ref", skip_serializing_if = "Option::is_none")]
+    bom_ref: Option<String>,
+    name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    version: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    description: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    licenses: Vec<CycloneDxLicenseChoice>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    hashes: Vec<CycloneDxHash>,
+}
+
+#[derive(Serialize)]
+#[serde(untagged)]
+enum CycloneDxLicenseChoice {
+    License { license: CycloneDxLicense },
+    Expression { expression: String },
+}
+
+#[derive(Serialize)]
+#[serde(untagged)]
+enum CycloneDxLicense {
+    Id { id: String },
+    Name { name: String },
+}
+
+#[derive(Serialize)]
+struct CycloneDxHash {
+    alg: &'static str,
+    content: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SpdxDocument {
+    spdx_version: &'static str,
+    data_license: &'static str,
+    #[serde(rename = "SPDXID")]
+    spdx_id: &'static str,
+    name: String,
+    document_namespace: String,
+    creation_info: SpdxCreationInfo,
+    packages: Vec<SpdxPackage>,
+    relationships: Vec<SpdxRelationship>,
+}
+
+#[derive(Serialize)]
+struct SpdxCreationInfo {
+    created: String,
+    creators: Vec<String>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SpdxPackage {
+    name: String,
+    #[serde(rename = "SPDXID")]
+    spdx_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    version_info: Option<String>,
+    download_location: &'static str,
+    files_analyzed: bool,
+    license_concluded: &'static str,
+    license_declared: String,
+    copyright_text: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    summary: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    checksums: Vec<SpdxChecksum>,
+}
+
+#[derive(Serialize)]
+struct SpdxChecksum {
+    algorithm: &'static str,
+    #[serde(rename = "checksumValue")]
+    checksum_value: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SpdxRelationship {
+    spdx_element_id: String,
+    relationship_type: &'static str,
+    related_spdx_element: String,
+}
+
+// Catalogue license keys are listed at https://ctan.org/license; only the ones
+// with an exact SPDX counterpart are mapped, the rest become LicenseRefs. Keys
+// without a version, such as `lppl` or `fdl`, are not exact.
+fn spdx_license_id(catalogue_license: &str) -> Option<&'static str> {
+    match catalogue_license {
+        "lppl1.3c" => Some("LPPL-1.3c"),
+        "lppl1.3a" => Some("LPPL-1.3a"),
+        "lppl1.2" => Some("LPPL-1.2"),
+        "lppl1.1" => Some("LPPL-1.1"),
+        "lppl1" => Some("LPPL-1.0"),
+        "gpl1" => Some("GPL-1.0-only"),
+        "gpl1+" => Some("GPL-1.0-or-later"),
+        "gpl2" => Some("GPL-2.0-only"),
+        "gpl2+" => Some("GPL-2.0-or-later"),
+        "gpl3" => Some("GPL-3.0-only"),
+        "gpl3+" => Some("GPL-3.0-or-later"),
+        "lgpl2" => Some("LGPL-2.0-only"),
+        "lgpl2.1" => Some("LGPL-2.1-only"),
+        "lgpl3" => Some("LGPL-3.0-only"),
+        "agpl3" => Some("AGPL-3.0-only"),
+        "apache2" => Some("Apache-2.0"),
+        "artistic2" => Some("Artistic-2.0"),
+        "bsd2" => Some("BSD-2-Clause"),
+        "bsd3" => Some("BSD-3-Clause"),
+        "bsd4" => Some("BSD-4-Clause"),
+        "mit" => Some("MIT"),
+        "isc" => Some("ISC"),
+        "cc0" => Some("CC0-1.0"),
+        "cc-by-1" => Some("CC-BY-1.0"),
+        "cc-by-2" => Some("CC-BY-2.0"),
+        "cc-by-3" => Some("CC-BY-3.0"),
+        "cc-by-4" => Some("CC-BY-4.0"),
+        "cc-by-sa-1" => Some("CC-BY-SA-1.0"),
+        "cc-by-sa-2" => Some("CC-BY-SA-2.0"),
+        "cc-by-sa-3" => Some("CC-BY-SA-3.0"),
+        "cc-by-sa-4" => Some("CC-BY-SA-4.0"),
+        "knuth" => Some("Knuth-CTAN"),
+        _ => None,
+    }
+}
+
+fn spdx_license_expression(licenses: &[&str]) -> String {
+    if licenses.is_empty() {
+        return String::from("NOASSERTION");
+    }
+
+    let ids: Vec<String> = licenses.iter()
+        .map(|l| match spdx_license_id(l) {
+            Some(id) => id.to_owned(),
+            None => spdx_license_ref(l),
+        })
+        .collect();
+    ids.join(" AND ")
+}
+
+fn spdx_license_ref(catalogue_license: &str) -> String {
+    String::from("LicenseRef-ctan-") + &spdx_sanitize(catalogue_license)
+}
+
+// SPDX identifiers may only contain letters, numbers, `.` and `-`
+fn spdx_sanitize(s: &str) -> String {
+    s.chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '.' || c == '-' { c } else { '-' })
+        .collect()
+}
+
+fn package_revision(pkg: &TlPObjInfo) -> Option<String> {
+    pkg.lrev.or(pkg.rrev).map(|r| r.to_string())
+}
+
+fn cyclonedx_component(pkg: &TlPObjInfo) -> CycloneDxComponent {
+    let licenses = pkg.licenses();
+    let license_choices = if licenses.len() > 1 {
+        vec![CycloneDxLicenseChoice::Expression { expression: spdx_license_expression(&licenses) }]
+    } else {
+        licenses.iter()
+            .map(|l| CycloneDxLicenseChoice::License {
+                license: match spdx_license_id(l) {
+                    Some(id) => CycloneDxLicense::Id { id: id.to_owned() },
+                    None => CycloneDxLicense::Name { name: (*l).to_owned() },
+                }
+            })
+            .collect()
+    };
+
+    CycloneDxComponent {
+        component_type: "library",
+        bom_ref: Some(pkg.name.clone()),
+        name: pkg.name.clone(),
+        version: package_revision(pkg),
+        description: pkg.shortdesc.clone(),
+        licenses: license_choices,
+        hashes: pkg.containerchecksum.iter()
+            .map(|c| CycloneDxHash { alg: "SHA-512", content: c.clone() })
+            .collect(),
+    }
+}
+
+fn cyclonedx(project_name: &str, packages: &Map<String, TlPObjInfo>, timestamp: String) -> CycloneDxBom {
+    CycloneDxBom {
+        bom_format: "CycloneDX",
+        spec_version: "1.5",
+        version: 1,
+        metadata: CycloneDxMetadata {
+            timestamp,
+            tools: CycloneDxTools {
+                components: vec![CycloneDxComponent {
+                    component_type: "application",
+                    bom_ref: None,
+                    name: String::from(env!("CARGO_PKG_NAME")),
+                    version: Some(String::from(env!("CARGO_PKG_VERSION"))),
+                    description: None,
+                    licenses: Vec::new(),
+                    hashes: Vec::new(),
+                }],
+            },
+            component: CycloneDxComponent {
+                component_type: "application",
+                bom_ref: None,
+                name: project_name.to_owned(),
+                version: None,
+                description: None,
+                licenses: Vec::new(),
+                hashes: Vec::new(),
+            },
+        },
+        components: packages.values().map(cyclonedx_component).collect(),
+    }
+}
+
+fn spdx(project_name: &str, config_hash: &str, packages: &Map<String, TlPObjInfo>, timestamp: String) -> SpdxDocument {
+    let mut spdx_packages = Vec::new();
+    let mut relationships = Vec::new();
+
+    for pkg in packages.values() {
+        let spdx_id = String::from("SPDXRef-Package-") + &spdx_sanitize(&pkg.name);
+        relationships.push(SpdxRelationship {
+            spdx_element_id: String::from("SPDXRef-DOCUMENT"),
+            relationship_type: "DESCRIBES",
+            related_spdx_element: spdx_id.clone(),
+        });
+        spdx_packages.push(SpdxPackage {
+            name: pkg.name.clone(),
+            spdx_id,
+            version_info: package_revision(pkg),
+            download_location: "NOASSERTION",
+            files_analyzed: false,
+            license_concluded: "NOASSERTION",
+            license_declared: spdx_license_expression(&pkg.licenses()),
+            copyright_text: "NOASSERTION",
+            summary: pkg.shortdesc.clone(),
+            checksums: pkg.containerchecksum.iter()
+                .map(|c| SpdxChecksum { algorithm: "SHA512", checksum_value: c.clone() })
+                .collect(),
+        });
+    }
+
+    SpdxDocument {
+        spdx_version: "SPDX-2.3",
+        data_license: "CC0-1.0",
+        spdx_id: "SPDXRef-DOCUMENT",
+        name: project_name.to_owned(),
+        document_namespace: format!("https://spdx.org/spdxdocs/dtmgr-{}-{}", spdx_sanitize(project_name), config_hash),
+        creation_info: SpdxCreationInfo {
+            created: timestamp,
+            creators: vec![format!("Tool: {}-{}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION"))],
+        },
+        packages: spdx_packages,
+        relationships,
+    }
+}
+
+pub fn render_sbom(
+    format: SbomFormat,
+    project_name: &str,
+    config_hash: &str,
+    packages: &Map<String, TlPObjInfo>,
+) -> Result<String, serde_json::Error> {
+    let timestamp = humantime::format_rfc3339_seconds(std::time::SystemTime::now()).to_string();
+    match format {
+        SbomFormat::Cyclonedx => serde_json::to_string_pretty(&cyclonedx(project_name, packages, timestamp)),
+        SbomFormat::Spdx => serde_json::to_string_pretty(&spdx(project_name, config_hash, packages, timestamp)),
+    }
+}
@@ -0,0 +1,90 @@
+use std::collections::BTreeSet as Set;
+use crate::provides::{font_candidates, Requirement};
+
+// TeX wraps log lines at this many characters (max_print_line)
+const LOG_LINE_WIDTH: usize = 79;
+
+/// Joins lines TeX broke at `max_print_line` so file names are not split
+fn unwrap_log_lines(log: &str) -> Vec<String> {
+    let mut result = Vec::new();
+    let mut current = String::new();
+    for line in log.lines() {
+        current.push_str(line);
+        if line.chars().count() != LOG_LINE_WIDTH {
+            result.push(std::mem::take(&mut current));
+        }
+    }
+    if !current.is_empty() {
+        result.push(current);
+    }
+    result
+}
+
+fn between<'a>(haystack: &'a str, start: &str, end: &str) -> Option<&'a str> {
+    let from = haystack.find(start)? + start.len();
+    let len = haystack[from..].find(end)?;
+    Some(&haystack[from..from + len])
+}
+
+// LaTeX quotes as `name' while newer kernels and packages use 'name'
+fn quoted(haystack: &str) -> Option<&str> {
+    between(haystack, "`", "'").or_else(|| between(haystack, "'", "'"))
+}
+
+fn parse_line(line: &str) -> Option<Requirement> {
+    // ! LaTeX Error: File `xstring.sty' not found.
+    if line.contains("Error: File") && line.contains("not found") {
+        return quoted(line.split_once("Error: File")?.1).map(Requirement::file);
+    }
+
+    // ! I can't find file `chapter1'.
+    if let Some(rest) = line.strip_prefix("! I can't find file") {
+        return quoted(rest).map(Requirement::file);
+    }
+
+    // ! Font \OT1/cmr/m/n/10=cmr10 at 10.0pt not loadable: Metric (TFM) file not found.
+    if line.starts_with("! Font ") && line.contains("Metric (TFM) file not found") {
+        let font = between(line, "=", " not loadable")?
+            .split_whitespace()
+            .next()?;
+        return Some(Requirement::file(&(font.to_owned() + ".tfm")));
+    }
+
+    // ! Package fontspec Error: The font "TeX Gyre Pagella" cannot be found.
+    if line.contains("Package fontspec Error: The font") && line.contains("cannot be found") {
+        let font = between(line, "\"", "\"")?;
+        return Some(Requirement { description: format!("font \"{}\"", font), candidates: font_candidates(font) });
+    }
+
+    // ! Package babel Error: Unknown option 'ngerman'. Either you misspelled it
+    // (babel) or the language definition file ngerman.ldf was not found.
+    if line.contains("Package babel Error: Unknown option") {
+        let language = quoted(line.split_once("Unknown option")?.1)?;
+        return Some(Requirement {
+            description: format!("babel language `{}`", language),
+            candidates: vec![language.to_owned() + ".ldf", format!("babel-{}.tex", language)],
+        });
+    }
+
+    // ! Package tikz Error: I did not find the tikz library 'arrows.meta'.
+    if line.contains("Package tikz Error: I did not find the tikz library") {
+        let library = quoted(line.split_once("tikz library")?.1)?;
+        return Some(Requirement {
+            description: format!("tikz library `{}`", library),
+            candidates: vec![format!("tikzlibrary{}.code.tex", library), format!("pgflibrary{}.code.tex", library)],
+        });
+    }
+
+    None
+}
+
+/// Every distinct missing resource reported in a LaTeX, fontspec or babel log
+pub fn scan_log(log: &str) -> Vec<Requirement> {
+    let mut result: Set<Requirement> = Set::new();
+    for line in unwrap_log_lines(log) {
+        if let Some(missing) = parse_line(&line) {
+            result.insert(missing);
+        }
+    }
+    result.into_iter().collect()
+}
@@ -0,0 +1,40 @@
+use std::collections::BTreeMap as Map;
+use crate::TlPObjInfo;
+
+/// How well a package matches a query, best first
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum Match {
+    Name,
+    NamePrefix,
+    InName,
+    InDescription,
+}
+
+fn match_of(pkg: &TlPObjInfo, query: &str) -> Option<Match> {
+    let name = pkg.name.to_lowercase();
+    let description = pkg.shortdesc.as_deref().unwrap_or_default().to_lowercase();
+    if name == query {
+        Some(Match::Name)
+    } else if name.starts_with(query) {
+        Some(Match::NamePrefix)
+    } else if name.contains(query) {
+        Some(Match::InName)
+    } else if description.contains(query) {
+        Some(Match::InDescription)
+    } else {
+        None
+    }
+}
+
+/// The packages of `tlpdb` whose name or short description contains `query`, ignoring case,
+/// those matching by name first. Platform-specific binary packages like `xetex.x86_64-linux`
+/// are left out, as the package they belong to brings them in.
+pub fn search<'a>(tlpdb: &'a Map<String, TlPObjInfo>, query: &str) -> Vec<&'a TlPObjInfo> {
+    let query = query.trim().to_lowercase();
+    let mut found: Vec<(Match, &TlPObjInfo)> = tlpdb.values()
+        .filter(|pkg| !pkg.name.contains('.'))
+        .filter_map(|pkg| match_of(pkg, &query).map(|m| (m, pkg)))
+        .collect();
+    found.sort_by(|a, b| a.0.cmp(&b.0).then_with(|| a.1.name.cmp(&b.1.name)));
+    found.into_iter().map(|(_, pkg)| pkg).collect()
+}
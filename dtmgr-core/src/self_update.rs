@@ -0,0 +1,110 @@
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use semver::Version;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use tracing::info;
+use crate::{http, network, DtMgrError};
+
+const LATEST_RELEASE: &str = "https://api.github.com/repos/s5bug/dtmgr/releases/latest";
+/// The release asset listing `sha256sum` output for every binary
+const CHECKSUMS: &str = "SHA256SUMS";
+
+// https://docs.github.com/en/rest/releases/releases, only the fields dtmgr reads
+#[derive(Debug, Deserialize)]
+struct Release {
+    tag_name: String,
+    assets: Vec<Asset>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Asset {
+    name: String,
+    browser_download_url: String,
+}
+
+/// A release newer than the running dtmgr
+#[derive(Debug)]
+pub struct Update {
+    pub current: Version,
+    pub latest: Version,
+    release: Release,
+}
+
+/// The name of the release asset built for the platform dtmgr runs on, such as
+/// `dtmgr-x86_64-linux` or `dtmgr-aarch64-macos`
+pub fn asset_name() -> String {
+    format!("dtmgr-{}-{}{}", std::env::consts::ARCH, std::env::consts::OS, std::env::consts::EXE_SUFFIX)
+}
+
+/// The latest release on GitHub, if it is newer than `current`
+pub fn check(current: &str) -> Result<Option<Update>, DtMgrError> {
+    if network::offline() {
+        return Err(DtMgrError::Offline { needed: "the list of dtmgr releases".to_owned() });
+    }
+    let current = Version::parse(current)
+        .map_err(|_| DtMgrError::InvalidReleaseVersion { version: current.to_owned() })?;
+
+    let body = network::retry(|| http::get(LATEST_RELEASE))?;
+    let release: Release = serde_json::from_slice(&body)
+        .map_err(|e| DtMgrError::JsonParse { source: e })?;
+    let latest = Version::parse(release.tag_name.trim_start_matches('v'))
+        .map_err(|_| DtMgrError::InvalidReleaseVersion { version: release.tag_name.clone() })?;
+
+    Ok((latest > current).then_some(Update { current, latest, release }))
+}
+
+impl Update {
+    fn asset_url(&self, name: &str) -> Result<&str, DtMgrError> {
+        self.release.assets.iter()
+            .find(|asset| asset.name == name)
+            .map(|asset| asset.browser_download_url.as_str())
+            .ok_or_else(|| DtMgrError::MissingReleaseAsset { version: self.latest.to_string(), asset: name.to_owned() })
+    }
+
+    /// Downloads the binary for this platform, checks it against the release's checksums and
+    /// puts it in place of the running executable.
+    ///
+    /// The checksums come from the same release as the binary, so they only catch a corrupted
+    /// download; they do not show that the release is authentic.
+    pub fn install(&self) -> Result<(), DtMgrError> {
+        let asset = asset_name();
+        let binary_url = self.asset_url(&asset)?;
+        let checksums_url = self.asset_url(CHECKSUMS)?;
+
+        let checksums = network::retry(|| http::get(checksums_url))?;
+        let expected = String::from_utf8_lossy(&checksums).lines()
+            .filter_map(|line| line.split_once(char::is_whitespace))
+            .find(|(_, name)| name.trim_start().trim_start_matches('*') == asset)
+            .map(|(hash, _)| hash.to_ascii_lowercase())
+            .ok_or_else(|| DtMgrError::MissingReleaseAsset { version: self.latest.to_string(), asset: format!("a checksum for {}", asset) })?;
+
+        info!("downloading dtmgr {}", self.latest);
+        let binary = network::retry(|| http::get(binary_url))?;
+        let downloaded = hex::encode(Sha256::digest(&binary));
+        if downloaded != expected {
+            return Err(DtMgrError::ReleaseChecksum { url: binary_url.to_owned(), expected, downloaded });
+        }
+
+        // created anew under a random name next to the executable rather than at a predictable
+        // path in a shared temporary directory, where another user could put a link or swap the
+        // checked binary for their own
+        let exe = current_exe();
+        let exe_dir = exe.parent().unwrap_or(Path::new("."));
+        let mut new_exe = tempfile::Builder::new()
+            .prefix(&format!(".{}-{}", asset, self.latest))
+            .tempfile_in(exe_dir)
+            .map_err(|e| DtMgrError::WriteFile { file: exe_dir.to_owned(), source: e })?;
+        new_exe.write_all(&binary)
+            .and_then(|()| new_exe.flush())
+            .map_err(|e| DtMgrError::WriteFile { file: new_exe.path().to_owned(), source: e })?;
+        // takes over the running executable's permissions, and works while it runs on Windows too;
+        // the temporary file is removed when `new_exe` is dropped
+        self_replace::self_replace(new_exe.path())
+            .map_err(|e| DtMgrError::ReplaceExecutable { exe, source: e })
+    }
+}
+
+fn current_exe() -> PathBuf {
+    std::env::current_exe().unwrap_or_else(|_| PathBuf::from("dtmgr"))
+}
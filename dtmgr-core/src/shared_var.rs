@@ -0,0 +1,62 @@
+use std::path::{Path, PathBuf};
+use sha3::{Digest, Sha3_256};
+use tracing::debug;
+use crate::link::LinkStrategy;
+use crate::{cache, executes, home, link, tlpdb, DtMgrError};
+
+/// The cache area the shared directories are in, one for each platform and TeX Live revision
+pub const AREA: &str = "texmf-var";
+// what fmtutil-sys builds, shared by environments whose formats are made from the same files
+const FORMATS_DIR: &str = "web2c";
+// caches that TeX and its tools fill on demand, by font name or path, while documents build;
+// luaotfload's name database lists the fonts of one environment, so `luatex-cache` stays
+const CACHE_DIRS: [&str; 3] = ["fontconfig", "fonts/pk", "fonts/tfm"];
+
+/// The directory below the cache that environments for `platform` in the TeX Live at `root`
+/// share, as its formats and caches are only valid for those binaries
+fn revision_dir(root: &Path, platform: &str) -> Result<PathBuf, DtMgrError> {
+    let revision = std::fs::read_to_string(tlpdb::local_tlpdb(root)).ok()
+        .and_then(|content| tlpdb::texlive_revision(&content))
+        .map_or_else(|| String::from("unknown"), |revision| revision.to_string());
+    Ok(home::cache_dir()?.join(AREA).join(format!("{}-{}", platform, revision)))
+}
+
+// the generated files the formats are built from, which differ with the hyphenation patterns
+// and formats of the packages
+fn formats_key(dot_dir: &Path) -> String {
+    let mut hasher = Sha3_256::new();
+    for file in executes::FORMAT_FILES {
+        hasher.update(std::fs::read(dot_dir.join(file)).unwrap_or_default());
+        hasher.update([0]);
+    }
+    hex::encode(hasher.finalize())[..16].to_owned()
+}
+
+fn link_dir(strategy: LinkStrategy, target: &Path, name: &Path) -> Result<(), DtMgrError> {
+    std::fs::create_dir_all(target)
+        .map_err(|e| DtMgrError::CreateDirectory { dir: target.to_owned(), source: e })?;
+    if let Some(parent) = name.parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(|e| DtMgrError::CreateDirectory { dir: parent.to_owned(), source: e })?;
+    }
+    link::create_link(strategy, target, name)
+        .map_err(|e| DtMgrError::CreateSymlink { src: target.to_owned(), dst: name.to_owned(), source: e })
+}
+
+/// Links the formats and font caches of the environment in `dot_dir` to the ones every
+/// environment of this user with the same TeX Live shares, so each is built once rather than
+/// per project.
+///
+/// Formats are only shared between environments whose generated files make the same formats.
+/// The caches are shared by all of them, so one environment may see what another cached.
+pub fn link(strategy: LinkStrategy, dot_dir: &Path, root: &Path, platform: &str) -> Result<(), DtMgrError> {
+    let shared = revision_dir(root, platform)?;
+    debug!("sharing formats and font caches in {}", shared.display());
+    let var = dot_dir.join("texmf-var");
+    link_dir(strategy, &shared.join(format!("formats-{}", formats_key(dot_dir))), &var.join(FORMATS_DIR))?;
+    for dir in CACHE_DIRS {
+        link_dir(strategy, &shared.join(dir), &var.join(dir))?;
+    }
+    cache::mark_used(&shared);
+    Ok(())
+}
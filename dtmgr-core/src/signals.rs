@@ -0,0 +1,213 @@
+use std::collections::BTreeMap as Map;
+use std::io::{self, Read};
+use std::process::{Child, Command, ExitStatus, Output, Stdio};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use serde::{Deserialize, Deserializer};
+use tracing::{debug, warn};
+use crate::DtMgrError;
+
+// tools dtmgr is waiting for, by process id
+static CHILDREN: Mutex<Vec<u32>> = Mutex::new(Vec::new());
+
+// how long each tool may run, by program name, from `[timeouts]`
+static TIMEOUTS: Mutex<Map<String, Duration>> = Mutex::new(Map::new());
+
+// how often a tool with a timeout is checked on
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+fn children() -> Vec<u32> {
+    CHILDREN.lock().map(|c| c.clone()).unwrap_or_default()
+}
+
+/// Stops interrupts from killing dtmgr while it waits for a tool.
+///
+/// The tool gets the signal instead and dtmgr carries on once it exits, so a tool stopping
+/// halfway is handled like any other failure (an install is rolled back) and nothing is left
+/// running in the background. Without a tool running, signals end dtmgr as usual.
+#[cfg(unix)]
+pub fn init() {
+    use signal_hook::consts::{SIGHUP, SIGINT, SIGTERM};
+    use signal_hook::iterator::SignalsInfo;
+    use signal_hook::iterator::exfiltrator::WithOrigin;
+
+    let mut signals = match SignalsInfo::<WithOrigin>::new([SIGINT, SIGTERM, SIGHUP]) {
+        Ok(signals) => signals,
+        Err(e) => {
+            warn!("unable to handle interrupts, tools may outlive dtmgr: {}", e);
+            return;
+        }
+    };
+    std::thread::spawn(move || {
+        for origin in signals.forever() {
+            let children = children();
+            if children.is_empty() {
+                let _ = signal_hook::low_level::emulate_default_handler(origin.signal);
+                continue;
+            }
+            // Ctrl-C on the terminal already reaches the tools, which share dtmgr's process group
+            if origin.signal == SIGINT && origin.process.is_none() {
+                continue;
+            }
+            for pid in children {
+                debug!("forwarding signal {} to {}", origin.signal, pid);
+                // SAFETY: kill has no memory safety requirements
+                unsafe { libc::kill(pid as libc::pid_t, origin.signal) };
+            }
+        }
+    });
+}
+
+/// Stops console Ctrl events from killing dtmgr while it waits for a tool.
+///
+/// Every process attached to the console gets the event, so the tool sees it on its own;
+/// dtmgr only has to stay around until the tool exits.
+#[cfg(windows)]
+pub fn init() {
+    // what Windows reports for a process ended by Ctrl-C
+    const STATUS_CONTROL_C_EXIT: u32 = 0xC000013A;
+
+    let handled = ctrlc::set_handler(|| {
+        if children().is_empty() {
+            std::process::exit(STATUS_CONTROL_C_EXIT as i32);
+        }
+    });
+    if let Err(e) = handled {
+        warn!("unable to handle interrupts, tools may outlive dtmgr: {}", e);
+    }
+}
+
+/// Marks a tool as running until dropped, so interrupts go to it rather than dtmgr
+pub struct Running(u32);
+
+impl Running {
+    pub fn new(child: &Child) -> Running {
+        if let Ok(mut children) = CHILDREN.lock() {
+            children.push(child.id());
+        }
+        Running(child.id())
+    }
+}
+
+impl Drop for Running {
+    fn drop(&mut self) {
+        if let Ok(mut children) = CHILDREN.lock() {
+            children.retain(|pid| *pid != self.0);
+        }
+    }
+}
+
+/// [`Command::status`], keeping dtmgr alive until the tool exits
+pub fn status(cmd: &mut Command) -> io::Result<ExitStatus> {
+    let mut child = cmd.spawn()?;
+    let _running = Running::new(&child);
+    child.wait()
+}
+
+/// Ends dtmgr the way a tool ended: with its full exit code, which `ExitCode` would cut down to
+/// a byte, or on Unix by the same signal, so that whoever started dtmgr sees what the tool did
+#[cfg(unix)]
+pub fn exit_like(status: ExitStatus) -> ! {
+    use std::os::unix::process::ExitStatusExt;
+    if let Some(signal) = status.signal() {
+        let _ = signal_hook::low_level::emulate_default_handler(signal);
+        // the signal does not end processes by default, or could not be raised; shells report
+        // a tool ended by a signal the same way
+        std::process::exit(128 + signal);
+    }
+    std::process::exit(status.code().unwrap_or(1))
+}
+#[cfg(not(unix))]
+pub fn exit_like(status: ExitStatus) -> ! {
+    std::process::exit(status.code().unwrap_or(1))
+}
+
+/// The `[timeouts]` section of `dtmgr.toml`, e.g. `tlmgr = "20min"`
+pub fn deserialize_timeouts<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Map<String, Duration>, D::Error> {
+    let texts = Map::<String, String>::deserialize(deserializer)?;
+    texts.into_iter()
+        .map(|(program, text)| humantime::parse_duration(&text)
+            .map(|timeout| (program, timeout))
+            .map_err(serde::de::Error::custom))
+        .collect()
+}
+
+/// Makes the project's `[timeouts]` apply to every tool started from here on
+pub fn set_timeouts(timeouts: &Map<String, Duration>) {
+    if let Ok(mut current) = TIMEOUTS.lock() {
+        *current = timeouts.clone();
+    }
+}
+
+// by the program's name without directory or extension, so `tlmgr` also covers `tlmgr.bat`
+fn timeout(cmd: &Command) -> Option<Duration> {
+    let program = std::path::Path::new(cmd.get_program()).file_stem()?.to_str()?;
+    TIMEOUTS.lock().ok()?.get(program).copied()
+}
+
+fn describe(cmd: &Command) -> String {
+    std::iter::once(cmd.get_program())
+        .chain(cmd.get_args())
+        .map(|a| a.to_string_lossy())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Waits for `child`, started from `cmd`, killing it once it runs longer than its `[timeouts]`
+/// entry allows
+pub fn wait(child: &mut Child, cmd: &Command) -> Result<ExitStatus, DtMgrError> {
+    let Some(timeout) = timeout(cmd) else {
+        return child.wait().map_err(|e| DtMgrError::CommandExecution { source: e });
+    };
+    let deadline = Instant::now() + timeout;
+    loop {
+        if let Some(status) = child.try_wait().map_err(|e| DtMgrError::CommandExecution { source: e })? {
+            return Ok(status);
+        }
+        let now = Instant::now();
+        if now >= deadline {
+            debug!("stopping {} after {}", describe(cmd), humantime::format_duration(timeout));
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(DtMgrError::CommandTimeout { command: describe(cmd), timeout });
+        }
+        std::thread::sleep(POLL_INTERVAL.min(deadline - now));
+    }
+}
+
+/// [`Command::output`], keeping dtmgr alive until the tool exits or runs out of time
+pub fn output(cmd: &mut Command) -> Result<Output, DtMgrError> {
+    let (status, stdout, stderr) = output_with(cmd, |mut pipe| {
+        let mut content = Vec::new();
+        let _ = pipe.read_to_end(&mut content);
+        content
+    })?;
+    Ok(Output { status, stdout, stderr })
+}
+
+/// Like [`output`], but hands the tool's standard output to `read_stdout` as it is written
+/// rather than collecting all of it first
+pub fn output_with<T, F>(cmd: &mut Command, read_stdout: F) -> Result<(ExitStatus, T, Vec<u8>), DtMgrError>
+where
+    T: Send + 'static,
+    F: FnOnce(Box<dyn Read + Send>) -> T + Send + 'static {
+    cmd.stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    let mut child = cmd.spawn()
+        .map_err(|e| DtMgrError::CommandExecution { source: e })?;
+    let _running = Running::new(&child);
+
+    // not scoped: a killed tool's own children may keep the pipes open
+    let stdout_pipe: Box<dyn Read + Send> = Box::new(child.stdout.take().expect("stdout was piped"));
+    let stdout = std::thread::spawn(move || read_stdout(stdout_pipe));
+    let mut stderr_pipe = child.stderr.take().expect("stderr was piped");
+    let stderr = std::thread::spawn(move || {
+        let mut content = Vec::new();
+        let _ = stderr_pipe.read_to_end(&mut content);
+        content
+    });
+    let status = wait(&mut child, cmd)?;
+    let stdout = stdout.join().unwrap_or_else(|panic| std::panic::resume_unwind(panic));
+    Ok((status, stdout, stderr.join().unwrap_or_default()))
+}
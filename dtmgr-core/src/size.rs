@@ -0,0 +1,124 @@
+use std::collections::BTreeMap as Map;
+use std::collections::VecDeque;
+use crate::{DtMgrConfig, TlPObjInfo};
+
+// tlpdb sizes are counted in blocks of this many bytes (TeXLive::TLConfig::BlockSize)
+const TLPDB_BLOCK_SIZE: u64 = 4096;
+
+const BASE_GROUP: &str = "(dtmgr base)";
+
+type SizedPackage<'a> = (&'a str, u64);
+
+/// Bytes a package occupies in an environment built for `platform`
+pub fn package_size(pkg: &TlPObjInfo, platform: &str) -> u64 {
+    let binsize = pkg.binsize.as_ref()
+        .and_then(|b| b.get(platform).copied())
+        .unwrap_or(0);
+    let blocks = pkg.runsize.unwrap_or(0) + pkg.docsize.unwrap_or(0) + pkg.srcsize.unwrap_or(0) + binsize;
+    blocks * TLPDB_BLOCK_SIZE
+}
+
+/// Bytes a package occupies in TeX Live itself, with the binaries of every platform it has
+pub fn installed_size(pkg: &TlPObjInfo) -> u64 {
+    let binsize: u64 = pkg.binsize.iter().flat_map(|b| b.values()).sum();
+    let blocks = pkg.runsize.unwrap_or(0) + pkg.docsize.unwrap_or(0) + pkg.srcsize.unwrap_or(0) + binsize;
+    blocks * TLPDB_BLOCK_SIZE
+}
+
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} {}", bytes, UNITS[0])
+    } else {
+        format!("{:.1} {}", value, UNITS[unit])
+    }
+}
+
+/// Assigns every package to the direct dependency with the shortest path to it.
+///
+/// Packages only reachable from the packages dtmgr always adds are grouped under
+/// a pseudo-dependency. Ties are broken by the order of the direct dependencies.
+fn attribute_packages(
+    config: &DtMgrConfig,
+    packages: &Map<String, TlPObjInfo>,
+    platform: &str,
+) -> Map<String, String> {
+    let mut owner: Map<String, String> = Map::new();
+    let mut queue: VecDeque<String> = VecDeque::new();
+
+    for dep in config.dependencies.iter().chain(config.dev_dependencies.iter()) {
+        if packages.contains_key(dep) && !owner.contains_key(dep) {
+            owner.insert(dep.clone(), dep.clone());
+            queue.push_back(dep.clone());
+        }
+    }
+    for base in crate::base_packages() {
+        if packages.contains_key(&base) && !owner.contains_key(&base) {
+            owner.insert(base.clone(), String::from(BASE_GROUP));
+            queue.push_back(base);
+        }
+    }
+
+    while let Some(name) = queue.pop_front() {
+        let group = owner[&name].clone();
+        if let Some(pkg) = packages.get(&name) {
+            for dep in pkg.dependencies(platform) {
+                if packages.contains_key(&dep) && !owner.contains_key(&dep) {
+                    owner.insert(dep.clone(), group.clone());
+                    queue.push_back(dep);
+                }
+            }
+        }
+    }
+
+    owner
+}
+
+pub fn print_size_report(packages: &Map<String, TlPObjInfo>, platform: &str) {
+    let mut sizes: Vec<SizedPackage> = packages.values()
+        .map(|p| (p.name.as_str(), package_size(p, platform)))
+        .collect();
+    sizes.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+
+    let total: u64 = sizes.iter().map(|(_, s)| s).sum();
+    for (name, size) in sizes.iter() {
+        println!("{:>10}  {}", format_size(*size), name);
+    }
+    println!("{:>10}  total", format_size(total));
+}
+
+pub fn print_size_tree(config: &DtMgrConfig, packages: &Map<String, TlPObjInfo>, platform: &str) {
+    let owner = attribute_packages(config, packages, platform);
+
+    let mut groups: Map<&str, Vec<SizedPackage>> = Map::new();
+    for (name, group) in owner.iter() {
+        if let Some(pkg) = packages.get(name) {
+            groups.entry(group.as_str()).or_default().push((name.as_str(), package_size(pkg, platform)));
+        }
+    }
+
+    let mut group_totals: Vec<(&str, u64, Vec<SizedPackage>)> = groups.into_iter()
+        .map(|(group, mut members)| {
+            members.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+            let total = members.iter().map(|(_, s)| s).sum();
+            (group, total, members)
+        })
+        .collect();
+    group_totals.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+
+    let mut total = 0;
+    for (group, group_total, members) in group_totals.iter() {
+        total += group_total;
+        println!("{:>10}  {}", format_size(*group_total), group);
+        for (name, size) in members.iter() {
+            println!("{:>10}      {}", format_size(*size), name);
+        }
+    }
+    println!("{:>10}  total", format_size(total));
+}
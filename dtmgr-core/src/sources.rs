@@ -0,0 +1,291 @@
+use std::collections::BTreeMap as Map;
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, PoisonError};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use sha3::{Digest, Sha3_256};
+use tracing::{debug, info};
+use crate::{cache, ctan, cmd_crossplatform_static_args, create_texlive_symlink, depends_txt, home, link, network, progress, signals, stderr_tail, DtMgrError};
+
+/// Where a package from outside TeX Live comes from, an entry of `[sources]`
+#[derive(Debug, Deserialize, JsonSchema, Serialize, Hash)]
+#[serde(deny_unknown_fields)]
+pub struct Source {
+    /// A directory laid out like a TEXMF tree, relative to the project
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    path: Option<PathBuf>,
+    /// A repository to clone, holding either a TEXMF tree or an l3build project
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    git: Option<String>,
+    /// The commit, tag or branch of `git` to use
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    rev: Option<String>,
+    /// A package to download from CTAN
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    ctan: Option<String>,
+    /// The version of `ctan` to expect; otherwise whichever the lockfile records
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    version: Option<String>,
+    /// Of the files in `path`, filled in when the config is read so that edits to them invalidate
+    /// the environment
+    #[serde(skip_deserializing, skip_serializing_if = "Option::is_none")]
+    content_hash: Option<String>,
+}
+
+// like a TEXMF tree, only files in subdirectories (tex/, doc/, ...) belong to the package
+fn tree_files(root: &Path, dir: &Path, result: &mut Vec<PathBuf>) -> Result<(), DtMgrError> {
+    let entries = std::fs::read_dir(dir)
+        .map_err(|e| DtMgrError::ReadFile { path: dir.to_owned(), source: e })?;
+    let mut paths: Vec<PathBuf> = entries.filter_map(|e| e.ok()).map(|e| e.path()).collect();
+    paths.sort();
+
+    for path in paths {
+        let hidden = path.file_name()
+            .and_then(|n| n.to_str())
+            .is_some_and(|n| n.starts_with('.'));
+        if hidden {
+            continue;
+        }
+        if path.is_dir() {
+            tree_files(root, &path, result)?;
+        } else if dir != root {
+            let relative = path.strip_prefix(root)
+                .expect("read_dir returns paths inside the directory");
+            result.push(relative.to_owned());
+        }
+    }
+    Ok(())
+}
+
+/// The files of the TEXMF tree at `dir`, relative to it
+fn files(dir: &Path) -> Result<Vec<PathBuf>, DtMgrError> {
+    if !dir.is_dir() {
+        return Err(DtMgrError::TreeNotFound { dir: dir.to_owned() });
+    }
+    let mut result = Vec::new();
+    tree_files(dir, dir, &mut result)?;
+    Ok(result)
+}
+
+fn run<S: AsRef<OsStr>>(exe_and_args: &[S], current_dir: Option<&Path>) -> Result<String, DtMgrError> {
+    let mut cmd = cmd_crossplatform_static_args(exe_and_args);
+    network::apply_proxy(&mut cmd);
+    if let Some(dir) = current_dir {
+        cmd.current_dir(dir);
+    }
+    let out = signals::output(&mut cmd)?;
+
+    if !out.status.success() {
+        let command: Vec<_> = exe_and_args.iter().map(|a| a.as_ref().to_string_lossy()).collect();
+        return Err(DtMgrError::CommandStatus { command: command.join(" "), code: out.status.code(), stderr: stderr_tail(&out.stderr) });
+    }
+    Ok(String::from_utf8_lossy(&out.stdout).trim().to_owned())
+}
+
+/// The commit `rev` names in the bare repository `db`, if it has it
+fn resolve_rev(db: &Path, rev: &str) -> Option<String> {
+    let spec = format!("{}^{{commit}}", rev);
+    run(&[OsStr::new("git"), OsStr::new("-C"), db.as_os_str(), OsStr::new("rev-parse"), OsStr::new("--verify"), OsStr::new("--quiet"), OsStr::new(&spec)], None).ok()
+}
+
+/// Clones or updates `url` in the cache and checks out `rev`, returning the checkout
+pub(crate) fn git_checkout(name: &str, url: &str, rev: &str) -> Result<PathBuf, DtMgrError> {
+    let git_dir = home::cache_dir()?.join("git");
+    let key = cache::url_key(name, url);
+    let db = git_dir.join("db").join(&key);
+
+    let cloned = !db.is_dir();
+    if cloned && network::offline() {
+        return Err(DtMgrError::Offline { needed: format!("`{}` from {}", name, url) });
+    }
+    if cloned {
+        info!("cloning {}", url);
+        network::retry(|| run(&[OsStr::new("git"), OsStr::new("clone"), OsStr::new("--bare"), OsStr::new("--quiet"), OsStr::new(url), db.as_os_str()], None))?;
+    }
+
+    // branches and tags can move, so only a full commit hash is trusted without fetching
+    let commit = match resolve_rev(&db, rev) {
+        Some(commit) if cloned || commit == rev => commit,
+        // a branch or tag stays where it was last fetched
+        Some(commit) if network::offline() => commit,
+        None if network::offline() => {
+            return Err(DtMgrError::Offline { needed: format!("`{}` at {} from {}", name, rev, url) });
+        }
+        _ => {
+            info!("fetching {}", url);
+            network::retry(|| run(&[
+                OsStr::new("git"), OsStr::new("-C"), db.as_os_str(), OsStr::new("fetch"), OsStr::new("--quiet"),
+                OsStr::new("--force"), OsStr::new("--tags"), OsStr::new(url), OsStr::new("+refs/heads/*:refs/heads/*"),
+            ], None))?;
+            resolve_rev(&db, rev)
+                .ok_or_else(|| DtMgrError::RevisionNotFound { url: url.to_owned(), rev: rev.to_owned() })?
+        }
+    };
+
+    let checkout = git_dir.join("checkouts").join(format!("{}-{}", key, &commit[..12]));
+    if !checkout.is_dir() {
+        debug!("checking out {} of {}", commit, url);
+        run(&[
+            OsStr::new("git"), OsStr::new("-C"), db.as_os_str(), OsStr::new("worktree"), OsStr::new("add"),
+            OsStr::new("--quiet"), OsStr::new("--detach"), checkout.as_os_str(), OsStr::new(&commit),
+        ], None)?;
+    }
+    cache::mark_used(&db);
+    cache::mark_used(&checkout);
+    Ok(checkout)
+}
+
+/// The TEXMF tree of a package's sources: the sources themselves if they are one, or where
+/// `l3build install` puts the package otherwise
+pub fn package_tree(checkout: &Path) -> Result<PathBuf, DtMgrError> {
+    if checkout.join("tex").is_dir() {
+        return Ok(checkout.to_owned());
+    }
+    if !checkout.join("build.lua").is_file() {
+        return Err(DtMgrError::UnknownSourceLayout { dir: checkout.to_owned() });
+    }
+
+    let mut tree_name = checkout.file_name().expect("checkouts are named").to_owned();
+    tree_name.push(".tds");
+    let tree = checkout.with_file_name(tree_name);
+    if !tree.is_dir() {
+        info!("installing {} with l3build", checkout.display());
+        let result = run(&[OsStr::new("l3build"), OsStr::new("install"), OsStr::new("--texmfhome"), tree.as_os_str()], Some(checkout));
+        if let Err(e) = result {
+            // a half-installed tree would be taken for a finished one next time
+            let _ = std::fs::remove_dir_all(&tree);
+            return Err(e);
+        }
+    }
+    cache::mark_used(&tree);
+    Ok(tree)
+}
+
+/// What a [`Source`] is, once its keys are checked
+enum Kind<'a> {
+    Path(&'a Path),
+    Git { url: &'a str, rev: &'a str },
+    Ctan { package: &'a str, version: Option<&'a str> },
+}
+
+impl Source {
+    fn kind(&self, name: &str) -> Result<Kind<'_>, DtMgrError> {
+        let invalid = |problem| DtMgrError::InvalidSource { name: name.to_owned(), problem };
+        match (&self.path, &self.git, &self.ctan) {
+            (Some(path), None, None) if self.rev.is_none() && self.version.is_none() => Ok(Kind::Path(path)),
+            (None, Some(url), None) if self.version.is_none() => match &self.rev {
+                Some(rev) => Ok(Kind::Git { url, rev }),
+                None => Err(invalid("`git` needs a `rev`")),
+            },
+            (None, None, Some(package)) if self.rev.is_none() => Ok(Kind::Ctan { package, version: self.version.as_deref() }),
+            (None, None, None) => Err(invalid("needs one of `path`, `git` or `ctan`")),
+            (Some(_), None, None) | (None, Some(_), None) | (None, None, Some(_)) => Err(invalid("`rev` goes with `git` and `version` with `ctan`")),
+            _ => Err(invalid("only one of `path`, `git` or `ctan` may be given")),
+        }
+    }
+
+    /// Whether the package comes from the project rather than from a download
+    pub fn is_local(&self) -> bool {
+        self.path.is_some()
+    }
+
+    /// The CTAN package the entry downloads, if it is one
+    pub fn ctan_package(&self) -> Option<&str> {
+        self.ctan.as_deref()
+    }
+
+    /// Where the entry takes its package from, the same for two entries taking the same package
+    pub fn origin(&self) -> String {
+        match (&self.path, &self.git, &self.ctan) {
+            (Some(path), _, _) => format!("the directory {}", path.display()),
+            (_, Some(url), _) => format!("the repository {}", url),
+            (_, _, Some(package)) => format!("`{}` from CTAN", package),
+            _ => String::new(),
+        }
+    }
+
+    /// Checks the entry, hashes a local package's files and returns the TeX Live packages its
+    /// `DEPENDS.txt` lists, if it has one
+    pub fn resolve(&mut self, name: &str, project_dir: &Path) -> Result<Vec<String>, DtMgrError> {
+        let dir = match self.kind(name)? {
+            Kind::Path(path) => project_dir.join(path),
+            Kind::Git { .. } | Kind::Ctan { .. } => return Ok(Vec::new()),
+        };
+        let mut hasher = Sha3_256::new();
+        for relative in files(&dir)? {
+            let path = dir.join(&relative);
+            let content = std::fs::read(&path)
+                .map_err(|e| DtMgrError::ReadFile { path, source: e })?;
+            // separators differ between platforms, the files do not
+            let components: Vec<_> = relative.components().map(|c| c.as_os_str().to_string_lossy()).collect();
+            hasher.update(components.join("/").as_bytes());
+            hasher.update((content.len() as u64).to_le_bytes());
+            hasher.update(content);
+        }
+        let hash: [u8; 32] = hasher.finalize().into();
+        self.content_hash = Some(hex::encode(hash));
+
+        let depends_txt = dir.join("DEPENDS.txt");
+        if depends_txt.is_file() {
+            depends_txt::read_depends_txt(depends_txt)
+        } else {
+            Ok(Vec::new())
+        }
+    }
+
+    /// The TEXMF tree holding the package, fetching and installing it first unless it is local
+    fn tree(&self, name: &str, project_dir: &Path) -> Result<PathBuf, DtMgrError> {
+        match self.kind(name)? {
+            Kind::Path(path) => Ok(project_dir.join(path)),
+            Kind::Git { url, rev } => package_tree(&git_checkout(name, url, rev)?),
+            Kind::Ctan { package, version } => package_tree(&ctan::fetch(name, package, version, project_dir)?),
+        }
+    }
+
+    /// Links the package's files in `dir`, its tree from [`fetch_all`], into the environment's
+    /// `texmf-dist`, in place of any a TeX Live package put there
+    pub fn link(&self, name: &str, dir: &Path, strategy: link::LinkStrategy, dot_dir: &Path) -> Result<(), DtMgrError> {
+        info!("linking {} from {}", name, dir.display());
+        let new_root = dot_dir.join("texmf-dist");
+        for relative in files(dir)? {
+            let full_new = new_root.join(&relative);
+            if full_new.symlink_metadata().is_ok() {
+                debug!("{} replaces {}", name, full_new.display());
+                std::fs::remove_file(&full_new)
+                    .map_err(|e| DtMgrError::WriteFile { file: full_new, source: e })?;
+            }
+            create_texlive_symlink(strategy, dir.join(&relative), &new_root, relative)?;
+        }
+        Ok(())
+    }
+}
+
+/// Fetches and installs every source, up to `[network] parallel-downloads` at a time, and
+/// returns the TEXMF tree of each by name
+pub fn fetch_all(sources: &Map<String, Source>, project_dir: &Path) -> Result<Map<String, PathBuf>, DtMgrError> {
+    let downloads = sources.values().filter(|source| !source.is_local()).count();
+    let bar = progress::download_bar(downloads as u64);
+    let pending = Mutex::new(sources.iter().collect::<Vec<_>>());
+    let trees = Mutex::new(Map::new());
+    std::thread::scope(|scope| {
+        for _ in 0..network::parallel_downloads().min(downloads).max(1) {
+            scope.spawn(|| {
+                while let Some((name, source)) = pending.lock().ok().and_then(|mut pending| pending.pop()) {
+                    let tree = source.tree(name, project_dir);
+                    if !source.is_local() {
+                        bar.inc(1);
+                    }
+                    if let Ok(mut trees) = trees.lock() {
+                        trees.insert(name.clone(), tree);
+                    }
+                }
+            });
+        }
+    });
+    bar.finish_and_clear();
+    // the first failure by name, whichever happened first
+    trees.into_inner().unwrap_or_else(PoisonError::into_inner).into_iter()
+        .map(|(name, tree)| tree.map(|tree| (name, tree)))
+        .collect()
+}
@@ -0,0 +1,147 @@
+use std::collections::BTreeMap as Map;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use serde::{Deserialize, Serialize};
+use crate::link::LinkStrategy;
+use crate::lockfile::Lockfile;
+use crate::manifest::Manifest;
+use crate::timings::Timings;
+use crate::{tlpdb, DtMgrConfig, DtMgrError};
+
+const STATE_FILE_NAME: &str = "state.json";
+// where dtmgr kept only the config hash before there was a state file
+const LEGACY_VERSION_FILE_NAME: &str = "version";
+
+/// How `.dtmgr` is laid out, raised whenever its files change in a way another release would
+/// misread. There are no migrations between layouts: an environment of another layout is rebuilt.
+pub const LAYOUT_VERSION: u32 = 1;
+// the layout of environments with only a `version` file
+const LEGACY_LAYOUT: u32 = 0;
+
+// state files from before the layout was recorded all have the first with one
+fn first_layout() -> u32 {
+    1
+}
+
+/// Just the layout of a state file, which reads whatever else a future layout put in it
+#[derive(Deserialize)]
+struct Layout {
+    #[serde(default = "first_layout")]
+    layout: u32,
+}
+
+/// What `dtmgr install` made the environment in `.dtmgr` from, written last so that an
+/// environment without it was never finished
+#[derive(Debug, Deserialize, Serialize)]
+pub struct State {
+    /// See [`LAYOUT_VERSION`]
+    #[serde(default = "first_layout")]
+    pub layout: u32,
+    /// See [`crate::hash_config`]
+    pub config_hash: String,
+    /// The revision of every linked package, by name
+    pub packages: Map<String, Option<u64>>,
+    /// The project's `dtmgr.lock` as of the install
+    pub lock: Lockfile,
+    pub platform: String,
+    /// Where the TeX Live the environment links to is installed, so running tools need not ask
+    /// `kpsewhich` again; missing from older state files
+    #[serde(default)]
+    pub texlive_root: Option<PathBuf>,
+    /// TeX Live's own revision, from its `00texlive.config`
+    pub texlive_revision: Option<u64>,
+    pub link_strategy: LinkStrategy,
+    /// The dtmgr that installed the environment
+    pub dtmgr_version: String,
+    /// When the install started and finished, in RFC 3339
+    pub started: String,
+    pub finished: String,
+    /// How long each phase of the install took
+    #[serde(default, skip_serializing_if = "Timings::is_empty")]
+    pub timings: Timings,
+    /// Whether `install --no-dev` left out the `dev-dependencies`
+    #[serde(default, skip_serializing_if = "crate::is_false")]
+    pub without_dev: bool,
+}
+
+impl State {
+    /// The state of an install of `config` that started at `started` and linked `manifest`
+    pub fn new(config: &DtMgrConfig, dtmgr_directory: &Path, manifest: &Manifest, platform: &str, root: &Path, started: SystemTime) -> Result<State, DtMgrError> {
+        let texlive_revision = std::fs::read_to_string(tlpdb::local_tlpdb(root)).ok()
+            .and_then(|content| tlpdb::texlive_revision(&content));
+        Ok(State {
+            layout: LAYOUT_VERSION,
+            config_hash: crate::hash_config(config, manifest.link_strategy)?,
+            packages: manifest.packages.iter().map(|(name, pkg)| (name.clone(), pkg.revision)).collect(),
+            lock: Lockfile::read(dtmgr_directory)?,
+            platform: platform.to_owned(),
+            texlive_root: Some(root.to_owned()),
+            texlive_revision,
+            link_strategy: manifest.link_strategy,
+            dtmgr_version: env!("CARGO_PKG_VERSION").to_owned(),
+            started: humantime::format_rfc3339_seconds(started).to_string(),
+            finished: humantime::format_rfc3339_seconds(SystemTime::now()).to_string(),
+            timings: Timings::default(),
+            without_dev: crate::no_dev(),
+        })
+    }
+
+    /// The state of the environment in `dot_dir`, if it was finished and has this release's
+    /// layout
+    pub fn read(dot_dir: &Path) -> Result<Option<State>, DtMgrError> {
+        let Some(content) = read_state_file(dot_dir)? else {
+            return Ok(None);
+        };
+        let layout: Layout = serde_json::from_slice(&content)
+            .map_err(|e| DtMgrError::JsonParse { source: e })?;
+        if layout.layout != LAYOUT_VERSION {
+            return Ok(None);
+        }
+        serde_json::from_slice(&content)
+            .map(Some)
+            .map_err(|e| DtMgrError::JsonParse { source: e })
+    }
+
+    pub fn write(&self, dot_dir: &Path) -> Result<(), DtMgrError> {
+        let path = dot_dir.join(STATE_FILE_NAME);
+        let content = serde_json::to_string_pretty(self)
+            .map_err(|e| DtMgrError::JsonSerialize { source: e })?;
+        std::fs::write(&path, content)
+            .map_err(|e| DtMgrError::WriteFile { file: path, source: e })
+    }
+}
+
+fn read_state_file(dot_dir: &Path) -> Result<Option<Vec<u8>>, DtMgrError> {
+    let path = dot_dir.join(STATE_FILE_NAME);
+    if !path.is_file() {
+        return Ok(None);
+    }
+    std::fs::read(&path)
+        .map(Some)
+        .map_err(|e| DtMgrError::ReadFile { path, source: e })
+}
+
+/// The layout of the finished environment in `dot_dir`, if there is one
+pub fn layout(dot_dir: &Path) -> Result<Option<u32>, DtMgrError> {
+    if let Some(content) = read_state_file(dot_dir)? {
+        let layout: Layout = serde_json::from_slice(&content)
+            .map_err(|e| DtMgrError::JsonParse { source: e })?;
+        return Ok(Some(layout.layout));
+    }
+    Ok(dot_dir.join(LEGACY_VERSION_FILE_NAME).is_file().then_some(LEGACY_LAYOUT))
+}
+
+/// The config hash the environment in `dot_dir` was installed with, also from environments
+/// older dtmgr releases installed
+pub fn config_hash(dot_dir: &Path) -> Result<Option<String>, DtMgrError> {
+    if let Some(state) = State::read(dot_dir)? {
+        return Ok(Some(state.config_hash));
+    }
+    let version_file = dot_dir.join(LEGACY_VERSION_FILE_NAME);
+    if !version_file.is_file() {
+        return Ok(None);
+    }
+    std::fs::read_to_string(&version_file)
+        .map(Some)
+        .map_err(|e| DtMgrError::ReadFile { path: version_file, source: e })
+}
@@ -0,0 +1,184 @@
+use std::path::{Path, PathBuf};
+use serde::Serialize;
+use crate::color::{self, Stream};
+use crate::link::LinkStrategy;
+use crate::manifest::Manifest;
+use crate::report::Summary;
+use crate::state::State;
+use crate::{state, tlpdb, DtMgrConfig, DtMgrError};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum EnvironmentState {
+    /// There is no `.dtmgr`, or it was never finished
+    Missing,
+    /// `.dtmgr` was made from a different configuration, for another platform or from packages
+    /// TeX Live has updated since
+    Outdated,
+    /// `.dtmgr` was laid out by a release that stores it differently, see
+    /// [`state::LAYOUT_VERSION`]
+    Incompatible,
+    UpToDate,
+}
+
+impl EnvironmentState {
+    pub fn describe(self) -> &'static str {
+        match self {
+            EnvironmentState::Missing => "not installed, run `dtmgr install`",
+            EnvironmentState::Outdated => "outdated, run `dtmgr install`",
+            EnvironmentState::Incompatible => "from another dtmgr release, run `dtmgr install` to rebuild it",
+            EnvironmentState::UpToDate => "up to date",
+        }
+    }
+
+    /// [`Self::describe`], green if up to date and yellow otherwise
+    pub fn painted(self, stream: Stream) -> String {
+        match self {
+            EnvironmentState::UpToDate => color::good(self.describe(), stream),
+            _ => color::warning(self.describe(), stream),
+        }
+    }
+}
+
+// whether the environment in `dot_dir` no longer matches the TeX Live it was linked from
+fn texlive_changed(dot_dir: &Path, config: &DtMgrConfig) -> Result<bool, DtMgrError> {
+    let Some(mut manifest) = Manifest::read(dot_dir)? else {
+        return Ok(false);
+    };
+    // e.g. a project directory synced between machines
+    if crate::platform_override(config).is_none()
+        && let Some(platform) = &manifest.platform
+        && crate::platform_runs_here(platform) == Some(false) {
+        return Ok(true);
+    }
+
+    // without a TeX Live to compare with, as after `dtmgr restore`, the environment is all there is
+    let root = match crate::environment_texlive_root(dot_dir) {
+        Ok(root) => root,
+        // a pinned TeX Live that is not there is a mistake rather than a missing TeX Live
+        Err(e @ (DtMgrError::TexliveRootNotFound { .. } | DtMgrError::ToolchainNotInstalled { .. })) => return Err(e),
+        Err(_) => return Ok(false),
+    };
+    let tlpdb_file = tlpdb::local_tlpdb(&root);
+    let Some(modified) = tlpdb::modified(&tlpdb_file) else {
+        return Ok(false);
+    };
+    if manifest.tlpdb_modified == Some(modified) {
+        return Ok(false);
+    }
+
+    let content = std::fs::read_to_string(&tlpdb_file)
+        .map_err(|e| DtMgrError::ReadFile { path: tlpdb_file.clone(), source: e })?;
+    let installed = tlpdb::revisions(&content);
+    let updated = manifest.packages.iter()
+        .any(|(name, pkg)| match (installed.get(name.as_str()), pkg.revision) {
+            (Some(now), Some(linked)) => *now != linked,
+            _ => false,
+        });
+    if !updated {
+        // tlmgr changed other packages; this spares reading the database again next time
+        manifest.tlpdb_modified = Some(modified);
+        let _ = manifest.write(dot_dir);
+    }
+    Ok(updated)
+}
+
+/// The TeX Live revisions the environment in `dot_dir` was installed from and that TeX Live has
+/// now, if tlmgr updated it since. TeX Live's database is only read if it was written after the
+/// install, and the root is the one the install recorded, so this is cheap enough for every
+/// `dtmgr run`.
+pub fn texlive_drift(dot_dir: &Path) -> Option<(u64, u64)> {
+    let manifest = Manifest::read(dot_dir).ok()??;
+    let tlpdb_file = tlpdb::local_tlpdb(&crate::environment_texlive_root(dot_dir).ok()?);
+    if manifest.tlpdb_modified == tlpdb::modified(&tlpdb_file) {
+        return None;
+    }
+    let installed = State::read(dot_dir).ok()??.texlive_revision?;
+    let now = tlpdb::texlive_revision(&std::fs::read_to_string(&tlpdb_file).ok()?)?;
+    (installed != now).then_some((installed, now))
+}
+
+/// Whether an environment installed with `config_hash`, and without `dev-dependencies` if
+/// `without_dev` is set, was made from `config` linking with `strategy`.
+///
+/// Tools can run in an environment `install --no-dev` made without being told `--no-dev` too.
+pub fn config_matches(config_hash: &str, without_dev: bool, config: &DtMgrConfig, strategy: LinkStrategy) -> Result<bool, DtMgrError> {
+    Ok(config_hash == crate::hash_config(config, strategy)?
+        || (without_dev && config_hash == crate::hash_config_without_dev(config, strategy)?))
+}
+
+/// Compares the layout and hash `dtmgr install` left in `dot_dir` to this release's and the
+/// current configuration, and the packages it linked to the ones TeX Live has now
+pub fn environment_state(dot_dir: &Path, config: &DtMgrConfig) -> Result<EnvironmentState, DtMgrError> {
+    match state::layout(dot_dir)? {
+        None => return Ok(EnvironmentState::Missing),
+        Some(layout) if layout != state::LAYOUT_VERSION => return Ok(EnvironmentState::Incompatible),
+        Some(_) => {}
+    }
+    let Some(config_hash) = state::config_hash(dot_dir)? else {
+        return Ok(EnvironmentState::Missing);
+    };
+    // a later install would link the way this system allows now
+    let strategy = crate::link::detect_strategy(dot_dir);
+    let without_dev = State::read(dot_dir)?.is_some_and(|state| state.without_dev);
+    if !config_matches(&config_hash, without_dev, config, strategy)? || texlive_changed(dot_dir, config)? {
+        Ok(EnvironmentState::Outdated)
+    } else {
+        Ok(EnvironmentState::UpToDate)
+    }
+}
+
+// the links below `dir` whose target is gone, without following links to directories
+fn broken_links(dir: &Path) -> usize {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return 0;
+    };
+    entries.filter_map(|entry| entry.ok())
+        .map(|entry| match entry.file_type() {
+            Ok(t) if t.is_symlink() => usize::from(entry.path().metadata().is_err()),
+            Ok(t) if t.is_dir() => broken_links(&entry.path()),
+            _ => 0,
+        })
+        .sum()
+}
+
+/// One word for the state of the environment in `dot_dir`, for shell prompts and scripts:
+/// `missing`, `stale`, `broken-links:<count>` or `ok`.
+///
+/// Only an otherwise up-to-date environment is searched for broken links, which takes a look at
+/// every link but never starts tlmgr.
+pub fn porcelain(dot_dir: &Path, config: &DtMgrConfig) -> Result<String, DtMgrError> {
+    let word = match environment_state(dot_dir, config)? {
+        EnvironmentState::Missing => String::from("missing"),
+        EnvironmentState::Outdated | EnvironmentState::Incompatible => String::from("stale"),
+        EnvironmentState::UpToDate => match broken_links(dot_dir) {
+            0 => String::from("ok"),
+            count => format!("broken-links:{}", count),
+        },
+    };
+    Ok(word)
+}
+
+#[derive(Debug, Serialize)]
+pub struct Status<'a> {
+    pub project_dir: PathBuf,
+    pub environment: EnvironmentState,
+    pub dependencies: Vec<&'a str>,
+    /// From the report the last install left in `.dtmgr`
+    pub last_install: Option<Summary>,
+}
+
+impl Status<'_> {
+    pub fn print(&self) {
+        println!("project: {}", self.project_dir.display());
+        println!("environment: {}", self.environment.painted(Stream::Stdout));
+        println!("dependencies: {}", self.dependencies.join(", "));
+        if let Some(last) = &self.last_install {
+            let outcome = if last.succeeded { "succeeded" } else { "failed" };
+            println!("last install: {} at {}, {} packages, {} warnings", outcome, last.finished, last.packages, last.warnings);
+            if let Some(error) = &last.error {
+                println!("  {}", error);
+            }
+        }
+    }
+}
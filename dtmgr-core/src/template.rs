@@ -0,0 +1,187 @@
+use std::path::Path;
+use std::time::SystemTime;
+use tracing::debug;
+use crate::{cache, config_edit, signals, sources, DtMgrError, CONFIG_FILE_NAME};
+
+/// A template dtmgr ships, for the kinds of documents projects are most often started for
+struct BuiltinTemplate {
+    name: &'static str,
+    files: &'static [(&'static str, &'static str)],
+    dependencies: &'static [&'static str],
+}
+
+const ARTICLE_MAIN: &str = r"\documentclass{article}
+\usepackage{amsmath}
+\usepackage{hyperref}
+
+\title{{{name}}}
+\author{{{author}}}
+\date{{{date}}}
+
+\begin{document}
+\maketitle
+
+\end{document}
+";
+
+const IEEE_MAIN: &str = r"\documentclass[conference]{IEEEtran}
+\usepackage{amsmath}
+\usepackage{graphicx}
+\usepackage{cite}
+
+\begin{document}
+\title{{{name}}}
+\author{\IEEEauthorblockN{{{author}}}}
+\maketitle
+
+\begin{abstract}
+\end{abstract}
+
+\section{Introduction}
+
+\bibliographystyle{IEEEtran}
+\bibliography{references}
+\end{document}
+";
+
+const BEAMER_MAIN: &str = r"\documentclass{beamer}
+
+\title{{{name}}}
+\author{{{author}}}
+\date{{{date}}}
+
+\begin{document}
+\frame{\titlepage}
+
+\begin{frame}{Outline}
+\end{frame}
+\end{document}
+";
+
+const BUILTIN_TEMPLATES: [BuiltinTemplate; 3] = [
+    BuiltinTemplate {
+        name: "article",
+        files: &[("main.tex", ARTICLE_MAIN)],
+        dependencies: &["latex-bin", "latexmk", "amsmath", "hyperref"],
+    },
+    BuiltinTemplate {
+        name: "ieee",
+        files: &[("main.tex", IEEE_MAIN), ("references.bib", "")],
+        dependencies: &["latex-bin", "latexmk", "ieeetran", "amsmath", "graphics", "cite"],
+    },
+    BuiltinTemplate {
+        name: "beamer",
+        files: &[("main.tex", BEAMER_MAIN)],
+        dependencies: &["latex-bin", "latexmk", "beamer", "pgf", "xcolor", "translator", "hyperref", "amsmath"],
+    },
+];
+
+pub const DEFAULT_TEMPLATE: &str = "article";
+
+/// The values of the `{{...}}` placeholders in a template's files and their names
+struct Variables {
+    name: String,
+    author: String,
+    date: String,
+}
+
+impl Variables {
+    fn new(name: &str) -> Variables {
+        // like commits, documents are signed with the name git knows the user by
+        let author = signals::output(&mut crate::cmd_crossplatform_static_args(["git", "config", "user.name"]))
+            .ok()
+            .filter(|out| out.status.success())
+            .map(|out| String::from_utf8_lossy(&out.stdout).trim().to_owned())
+            .filter(|author| !author.is_empty())
+            .or_else(|| std::env::var("USER").or_else(|_| std::env::var("USERNAME")).ok())
+            .unwrap_or_default();
+        let date = humantime::format_rfc3339_seconds(SystemTime::now()).to_string()[..10].to_owned();
+        Variables { name: name.to_owned(), author, date }
+    }
+
+    fn substitute(&self, text: &str) -> String {
+        text.replace("{{name}}", &self.name)
+            .replace("{{author}}", &self.author)
+            .replace("{{date}}", &self.date)
+    }
+}
+
+// what `--template` takes for a repository rather than the name of a built-in template
+fn is_git_url(template: &str) -> bool {
+    template.contains("://") || template.starts_with("git@") || template.ends_with(".git")
+}
+
+fn write_file(path: &Path, content: &[u8]) -> Result<(), DtMgrError> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)
+            .map_err(|e| DtMgrError::CreateDirectory { dir: dir.to_owned(), source: e })?;
+    }
+    std::fs::write(path, content)
+        .map_err(|e| DtMgrError::WriteFile { file: path.to_owned(), source: e })
+}
+
+/// Copies the files of `checkout` below `from` to `dir`, substituting `variables` in their names
+/// and, unless they are binary, their contents
+fn instantiate_tree(checkout: &Path, from: &Path, dir: &Path, variables: &Variables) -> Result<(), DtMgrError> {
+    let entries = std::fs::read_dir(from)
+        .map_err(|e| DtMgrError::ReadFile { path: from.to_owned(), source: e })?;
+    for entry in entries {
+        let entry = entry.map_err(|e| DtMgrError::ReadFile { path: from.to_owned(), source: e })?;
+        let path = entry.path();
+        let file_name = entry.file_name();
+        // the checkout's link to the cached repository and the cache's own marker, and an
+        // environment committed by mistake
+        if file_name == ".git" || file_name == cache::LAST_USED_NAME || file_name == ".dtmgr" {
+            continue;
+        }
+        let target = dir.join(variables.substitute(&file_name.to_string_lossy()));
+        if path.is_dir() {
+            instantiate_tree(checkout, &path, &target, variables)?;
+            continue;
+        }
+        let content = std::fs::read(&path)
+            .map_err(|e| DtMgrError::ReadFile { path: path.clone(), source: e })?;
+        debug!("instantiating {}", path.strip_prefix(checkout).unwrap_or(&path).display());
+        match String::from_utf8(content) {
+            Ok(text) => write_file(&target, variables.substitute(&text).as_bytes())?,
+            Err(e) => write_file(&target, e.as_bytes())?,
+        }
+    }
+    Ok(())
+}
+
+/// Starts a project in `dir` from `template`, a built-in template or a git repository at `rev`,
+/// and returns the dependencies of the `dtmgr.toml` it wrote
+pub fn new_project(dir: &Path, template: &str, rev: Option<&str>) -> Result<Vec<String>, DtMgrError> {
+    let is_empty = std::fs::read_dir(dir).map_or(true, |mut entries| entries.next().is_none());
+    if !is_empty {
+        return Err(DtMgrError::DirectoryNotEmpty { dir: dir.to_owned() });
+    }
+    std::fs::create_dir_all(dir)
+        .map_err(|e| DtMgrError::CreateDirectory { dir: dir.to_owned(), source: e })?;
+    let name = dir.file_name().map_or_else(|| String::from("project"), |name| name.to_string_lossy().into_owned());
+    let variables = Variables::new(&name);
+    let config_file = dir.join(CONFIG_FILE_NAME);
+
+    if let Some(builtin) = BUILTIN_TEMPLATES.iter().find(|builtin| builtin.name == template) {
+        for (file, content) in builtin.files {
+            write_file(&dir.join(file), variables.substitute(content).as_bytes())?;
+        }
+        config_edit::write_new_config(&config_file, builtin.dependencies)?;
+        return Ok(builtin.dependencies.iter().map(|dep| dep.to_string()).collect());
+    }
+    if !is_git_url(template) {
+        let available: Vec<&str> = BUILTIN_TEMPLATES.iter().map(|builtin| builtin.name).collect();
+        return Err(DtMgrError::UnknownTemplate { name: template.to_owned(), available: available.join(", ") });
+    }
+
+    // `HEAD` of the cached bare clone is the repository's default branch
+    let checkout = sources::git_checkout("template", template, rev.unwrap_or("HEAD"))?;
+    instantiate_tree(&checkout, &checkout, dir, &variables)?;
+    if !config_file.is_file() {
+        config_edit::write_new_config::<&str>(&config_file, &[])?;
+        return Ok(Vec::new());
+    }
+    let config = crate::parse_config(&config_file)?;
+    Ok(config.dependencies.into_iter().collect())
+}
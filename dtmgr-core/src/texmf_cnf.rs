@@ -0,0 +1,215 @@
+use std::collections::BTreeMap as Map;
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use crate::{long_path, DtMgrError};
+
+/// The environment's own `texmf.cnf`, which comes first in `TEXMFCNF`
+const TEXMF_CNF: &str = "texmf.cnf";
+/// The same for ConTeXt, whose `mtxrun` reads Lua configuration files rather than `texmf.cnf`
+const TEXMFCNF_LUA: &str = "texmfcnf.lua";
+/// Where the `texmf.cnf` of restricted runs is, which comes before the environment's
+const RESTRICTED_DIR: &str = "restricted";
+// no shell escape at all, rather than TeX Live's list of trusted programs, and no reading or
+// writing of dot files, parent directories or absolute paths outside TEXMFOUTPUT
+const RESTRICTED_CNF: &str = "% generated by dtmgr for restricted runs, changes are lost on the next install
+shell_escape = f
+openin_any = p
+openout_any = p
+";
+
+/// The variables the restricted `texmf.cnf` sets, which kpathsea would rather take from the
+/// environment
+pub const RESTRICTED_VARIABLES: [&str; 3] = ["shell_escape", "openin_any", "openout_any"];
+
+// what dtmgr sets itself to lay out the environment's trees
+const MANAGED_VARIABLES: [&str; 12] = [
+    "TEXMFROOT", "TEXMFDIST", "TEXMFMAIN", "TEXMFLOCAL", "TEXMFSYSVAR", "TEXMFSYSCONFIG", "TEXMFVAR",
+    "TEXMFCONFIG", "TEXMFHOME", "TEXMFCACHE", "TEXMF", "TEXMFDBS",
+];
+
+/// The value of an entry of `[kpathsea]`, e.g. `shell_escape = "p"` or `max_strings = 500000`
+#[derive(Clone, Debug, Deserialize, JsonSchema, Serialize, Hash)]
+#[serde(untagged)]
+pub enum KpathseaValue {
+    Integer(i64),
+    Boolean(bool),
+    String(String),
+}
+
+impl KpathseaValue {
+    // kpathsea takes `t`/`f` as well as `true`/`false` for the likes of `shell_escape`
+    fn render(&self) -> String {
+        match self {
+            KpathseaValue::Integer(value) => value.to_string(),
+            KpathseaValue::Boolean(value) => String::from(if *value { "t" } else { "f" }),
+            KpathseaValue::String(value) => value.clone(),
+        }
+    }
+}
+
+/// Checks an entry of `[kpathsea]`, which may be limited to one program as in `max_print_line.bibtex`
+pub fn check_variable(name: &str, value: &KpathseaValue) -> Result<(), DtMgrError> {
+    let invalid = |problem| DtMgrError::InvalidKpathseaVariable { name: name.to_owned(), problem };
+    let variable = name.split_once('.').map_or(name, |(variable, _)| variable);
+    if variable.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '-')) {
+        return Err(invalid("not a kpathsea variable name"));
+    }
+    if MANAGED_VARIABLES.contains(&variable) {
+        return Err(invalid("dtmgr sets this itself; use `texmf`, `extra-trees` or `use-texmfhome` instead"));
+    }
+    if let KpathseaValue::String(value) = value
+        && (value.contains('\n') || value.ends_with('\\')) {
+        // a backslash at the end of a line continues the value on the next
+        return Err(invalid("the value has to be on one line"));
+    }
+    Ok(())
+}
+
+/// Settings for the environment's `texmf.cnf`.
+///
+/// kpathsea uses the first value it finds for a variable, so these override TeX Live's
+/// `web2c/texmf.cnf`, which would otherwise derive the trees from wherever its binaries live.
+pub struct TexmfCnf {
+    variables: Vec<(String, String)>,
+    /// Trees searched before the environment's own
+    trees: Vec<String>,
+    /// Trees searched after the environment's own
+    appended_trees: Vec<String>,
+    /// The project's `[kpathsea]`, only for kpathsea's own programs
+    overrides: Vec<(String, String)>,
+}
+
+// kpathsea reads forward slashes on every platform, and a backslash ending a value continues it
+pub fn cnf_path(path: &Path) -> String {
+    let path = long_path::plain(path);
+    let path = path.to_string_lossy();
+    if cfg!(windows) { path.replace('\\', "/") } else { path.into_owned() }
+}
+
+impl TexmfCnf {
+    /// The trees of the environment at `dot_dir`, leaving out the user's own and TeX Live's
+    /// `texmf-local`
+    pub fn for_environment(dot_dir: &Path) -> TexmfCnf {
+        let mut result = TexmfCnf { variables: Vec::new(), trees: Vec::new(), appended_trees: Vec::new(), overrides: Vec::new() };
+        result.set("TEXMFROOT", cnf_path(dot_dir));
+        result.set("TEXMFDIST", "$TEXMFROOT/texmf-dist");
+        result.set("TEXMFMAIN", "$TEXMFDIST");
+        result.set("TEXMFLOCAL", "$TEXMFROOT/texmf-local");
+        result.set("TEXMFSYSVAR", "$TEXMFROOT/texmf-var");
+        result.set("TEXMFSYSCONFIG", "$TEXMFROOT/texmf-config");
+        result.set("TEXMFVAR", "$TEXMFSYSVAR");
+        result.set("TEXMFCONFIG", "$TEXMFSYSCONFIG");
+        result.set("TEXMFHOME", "$TEXMFROOT/texmf-home");
+        // luaotfload's font name database and ConTeXt's file database, kept with the formats
+        result.set("TEXMFCACHE", "$TEXMFSYSVAR");
+        result
+    }
+
+    /// Sets `name`, replacing an earlier value but keeping its place in the file
+    pub fn set(&mut self, name: impl Into<String>, value: impl Into<String>) {
+        let name = name.into();
+        let value = value.into();
+        match self.variables.iter_mut().find(|(n, _)| *n == name) {
+            Some((_, old)) => *old = value,
+            None => self.variables.push((name, value)),
+        }
+    }
+
+    /// Adds the project's `[kpathsea]` settings, which [`check_variable`] has checked
+    pub fn set_overrides(&mut self, overrides: &Map<String, KpathseaValue>) {
+        self.overrides = overrides.iter().map(|(name, value)| (name.clone(), value.render())).collect();
+    }
+
+    /// Searches `tree` before the environment's trees and gives it an `ls-R`.
+    ///
+    /// `dtmgr install` writes the `ls-R` into the tree, but files added since are still found, as the
+    /// tree is searched on disk too.
+    pub fn add_tree(&mut self, tree: &Path) {
+        self.trees.push(cnf_path(tree));
+    }
+
+    /// Searches `tree` after the environment's trees, on disk only: dtmgr never writes to it
+    pub fn append_tree(&mut self, tree: &Path) {
+        self.appended_trees.push(cnf_path(tree));
+    }
+
+    // TeX Live's order, except that TEXMFLOCAL may be TeX Live's own, which has an ls-R that is
+    // not the environment's to rewrite, so it is searched on disk instead
+    fn texmf(&self) -> Vec<&str> {
+        self.trees.iter().map(String::as_str)
+            .chain(["$TEXMFCONFIG", "$TEXMFVAR", "$TEXMFHOME", "$TEXMFLOCAL", "!!$TEXMFSYSCONFIG", "!!$TEXMFSYSVAR", "!!$TEXMFDIST"])
+            .chain(self.appended_trees.iter().map(String::as_str))
+            .collect()
+    }
+
+    /// The trees of `TEXMFDBS`, which get an `ls-R`
+    pub fn database_trees(&self, dot_dir: &Path) -> Vec<PathBuf> {
+        self.trees.iter().map(PathBuf::from)
+            .chain(["texmf-config", "texmf-var", "texmf-dist"].map(|tree| dot_dir.join(tree)))
+            .collect()
+    }
+
+    fn render(&self) -> String {
+        let mut result = String::from("% generated by dtmgr, changes are lost on the next install\n");
+        for (name, value) in self.variables.iter() {
+            let _ = writeln!(result, "{} = {}", name, value);
+        }
+
+        let _ = writeln!(result, "TEXMF = {{{}}}", self.texmf().join(","));
+        let databases: Vec<&str> = self.trees.iter().map(String::as_str)
+            .chain(["!!$TEXMFSYSCONFIG", "!!$TEXMFSYSVAR", "!!$TEXMFDIST"])
+            .collect();
+        let _ = writeln!(result, "TEXMFDBS = {{{}}}", databases.join(","));
+        for (name, value) in self.overrides.iter() {
+            let _ = writeln!(result, "{} = {}", name, value);
+        }
+        result
+    }
+
+    pub fn write(&self, dot_dir: &Path) -> Result<(), DtMgrError> {
+        let file = dot_dir.join(TEXMF_CNF);
+        std::fs::write(&file, self.render())
+            .map_err(|e| DtMgrError::WriteFile { file, source: e })
+    }
+
+    // mtxrun takes each variable from the first configuration file that sets it, so the rest
+    // still comes from TeX Live's `web2c/texmfcnf.lua`. It always builds its own file database,
+    // so the `!!` that keeps kpathsea to `ls-R` is left out.
+    fn render_lua(&self) -> String {
+        let mut result = String::from("-- generated by dtmgr, changes are lost on the next install\n");
+        result.push_str("return {\n    type = \"configuration\",\n    content = {\n        variables = {\n");
+        let texmf: Vec<&str> = self.texmf().into_iter().map(|tree| tree.trim_start_matches("!!")).collect();
+        let texmf = format!("{{{}}}", texmf.join(","));
+        let variables = self.variables.iter().map(|(n, v)| (n.as_str(), v.as_str()));
+        for (name, value) in variables.chain([("TEXMF", texmf.as_str())]) {
+            let value = value.replace('\\', "\\\\").replace('"', "\\\"");
+            let _ = writeln!(result, "            {} = \"{}\",", name, value);
+        }
+        result.push_str("        },\n    },\n}\n");
+        result
+    }
+
+    /// Writes the settings for ConTeXt as well, into a `texmfcnf.lua` next to `texmf.cnf`
+    pub fn write_lua(&self, dot_dir: &Path) -> Result<(), DtMgrError> {
+        let file = dot_dir.join(TEXMFCNF_LUA);
+        std::fs::write(&file, self.render_lua())
+            .map_err(|e| DtMgrError::WriteFile { file, source: e })
+    }
+}
+
+/// Writes the `texmf.cnf` that restricted runs put first in `TEXMFCNF`, unless it is there, and
+/// returns its directory
+pub fn write_restricted(dot_dir: &Path) -> Result<PathBuf, DtMgrError> {
+    let dir = dot_dir.join(RESTRICTED_DIR);
+    let file = dir.join(TEXMF_CNF);
+    if file.is_file() {
+        return Ok(dir);
+    }
+    std::fs::create_dir_all(&dir)
+        .map_err(|e| DtMgrError::CreateDirectory { dir: dir.clone(), source: e })?;
+    std::fs::write(&file, RESTRICTED_CNF)
+        .map_err(|e| DtMgrError::WriteFile { file, source: e })?;
+    Ok(dir)
+}
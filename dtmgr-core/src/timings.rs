@@ -0,0 +1,74 @@
+use std::collections::BTreeMap as Map;
+use std::time::Instant;
+use serde::{Deserialize, Serialize};
+
+/// A part of `dtmgr install` whose duration is recorded, in the order they run
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Phase {
+    /// `tlmgr install` of the dependencies into TeX Live
+    GlobalInstall,
+    /// Working out every package the environment needs
+    Resolution,
+    /// Linking packages and `[sources]` into the environment
+    Linking,
+    /// Recording the checksums `dtmgr verify` compares against
+    Checksums,
+    /// Packages' own install scripts
+    InstallScripts,
+    /// Writing the `ls-R` filename databases
+    Mktexlsr,
+    /// Building formats with `fmtutil-sys`
+    Fmtutil,
+    /// Regenerating font maps with `updmap-sys`
+    Updmap,
+    /// ConTeXt's own databases
+    Context,
+    /// Building the font name database `[fonts] prewarm` asks for
+    FontCache,
+}
+
+impl Phase {
+    pub fn describe(self) -> &'static str {
+        match self {
+            Phase::GlobalInstall => "global install",
+            Phase::Resolution => "resolution",
+            Phase::Linking => "linking",
+            Phase::Checksums => "checksums",
+            Phase::InstallScripts => "install scripts",
+            Phase::Mktexlsr => "mktexlsr",
+            Phase::Fmtutil => "fmtutil",
+            Phase::Updmap => "updmap",
+            Phase::Context => "context",
+            Phase::FontCache => "font cache",
+        }
+    }
+}
+
+/// How long the phases of an install took, in seconds; phases an install skipped, such as the
+/// formats it could reuse, are missing
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct Timings {
+    pub phases: Map<Phase, f64>,
+    /// The whole install, including what no phase covers
+    pub total: f64,
+}
+
+impl Timings {
+    /// Adds the time since `start` to `phase`
+    pub fn record(&mut self, phase: Phase, start: Instant) {
+        *self.phases.entry(phase).or_default() += start.elapsed().as_secs_f64();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.phases.is_empty()
+    }
+
+    pub fn print(&self) {
+        let width = self.phases.keys().map(|phase| phase.describe().len()).max().unwrap_or(0).max("total".len());
+        for (phase, seconds) in self.phases.iter() {
+            println!("{:<width$}  {:>8.2}s", phase.describe(), seconds);
+        }
+        println!("{:<width$}  {:>8.2}s", "total", self.total);
+    }
+}
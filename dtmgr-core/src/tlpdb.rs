@@ -0,0 +1,258 @@
+use std::collections::BTreeMap as Map;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+use crate::{DtMgrError, TlPObjDocFile, TlPObjInfo, TlPObjInfoCatalogueData};
+
+// https://tug.org/texlive/doc/tlpkg/TeXLive/TLPOBJ.html describes the text format
+
+/// Which side of `lrev`/`rrev` the revisions of a parsed database belong to
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TlpdbOrigin {
+    /// `tlpkg/texlive.tlpdb`, describing what is installed
+    Local,
+    /// `tlpkg/texlive.tlpdb.main.*`, tlmgr's cache of the repository database, or the
+    /// repository's as `tlmgr dump-tlpdb --remote` prints it
+    Remote,
+}
+
+enum FileSection {
+    None,
+    Run,
+    Doc,
+    Src,
+    Bin(String),
+}
+
+pub fn parse_tlpdb(content: &str, origin: TlpdbOrigin) -> Map<String, TlPObjInfo> {
+    let mut result = Map::new();
+
+    for block in content.split("\n\n") {
+        if let Some(pkg) = parse_tlpobj(block, origin) {
+            result.insert(pkg.name.clone(), pkg);
+        }
+    }
+
+    result
+}
+
+fn push_to(list: &mut Option<Vec<String>>, value: String) {
+    list.get_or_insert_with(Vec::new).push(value);
+}
+
+fn size_attribute<'a>(attrs: impl Iterator<Item = &'a str>) -> Option<u64> {
+    attrs.filter_map(|a| a.strip_prefix("size="))
+        .find_map(|s| s.parse().ok())
+}
+
+// the `key="value"` attributes after a doc file, e.g. `details="Package documentation"
+// language="en"`, whose quoted values may hold spaces
+fn file_attributes(mut attrs: &str) -> Vec<(&str, &str)> {
+    let mut result = Vec::new();
+    loop {
+        attrs = attrs.trim_start();
+        let Some((key, rest)) = attrs.split_once('=') else {
+            return result;
+        };
+        let (value, rest) = match rest.strip_prefix('"') {
+            Some(quoted) => quoted.split_once('"').unwrap_or((quoted, "")),
+            None => rest.split_once(' ').unwrap_or((rest, "")),
+        };
+        result.push((key, value));
+        attrs = rest;
+    }
+}
+
+fn parse_tlpobj(block: &str, origin: TlpdbOrigin) -> Option<TlPObjInfo> {
+    let mut pkg = TlPObjInfo { available: true, ..TlPObjInfo::default() };
+    let mut catalogue = TlPObjInfoCatalogueData::default();
+    let mut has_catalogue = false;
+    let mut section = FileSection::None;
+
+    for line in block.lines() {
+        if let Some(file_line) = line.strip_prefix(' ') {
+            let (file, attrs) = file_line.split_once(' ').unwrap_or((file_line, ""));
+            let file = file.to_owned();
+            match &section {
+                FileSection::None => {}
+                FileSection::Run => push_to(&mut pkg.runfiles, file),
+                FileSection::Src => push_to(&mut pkg.srcfiles, file),
+                FileSection::Doc => {
+                    let mut doc = TlPObjDocFile { file, lang: None, detail: None };
+                    for (key, value) in file_attributes(attrs) {
+                        match key {
+                            "language" => doc.lang = Some(value.to_owned()),
+                            "details" => doc.detail = Some(value.to_owned()),
+                            _ => {}
+                        }
+                    }
+                    pkg.docfiles.get_or_insert_with(Vec::new).push(doc);
+                }
+                FileSection::Bin(arch) => {
+                    pkg.binfiles.get_or_insert_with(Map::new).entry(arch.clone()).or_default().push(file);
+                }
+            }
+            continue;
+        }
+
+        section = FileSection::None;
+        let (key, value) = line.split_once(' ').unwrap_or((line, ""));
+        match key {
+            "name" => pkg.name = value.to_owned(),
+            "category" => pkg.category = Some(value.to_owned()),
+            "revision" => {
+                let revision = value.parse().ok();
+                match origin {
+                    TlpdbOrigin::Local => pkg.lrev = revision,
+                    TlpdbOrigin::Remote => pkg.rrev = revision,
+                }
+            }
+            "shortdesc" => pkg.shortdesc = Some(value.to_owned()),
+            "depend" => push_to(&mut pkg.depends, value.to_owned()),
+            "execute" => push_to(&mut pkg.executes, value.to_owned()),
+            "postaction" => push_to(&mut pkg.postactions, value.to_owned()),
+            "relocated" => pkg.relocated = Some(value == "1"),
+            "containerchecksum" => pkg.containerchecksum = Some(value.to_owned()),
+            "runfiles" => {
+                pkg.runsize = size_attribute(value.split(' '));
+                section = FileSection::Run;
+            }
+            "docfiles" => {
+                pkg.docsize = size_attribute(value.split(' '));
+                section = FileSection::Doc;
+            }
+            "srcfiles" => {
+                pkg.srcsize = size_attribute(value.split(' '));
+                section = FileSection::Src;
+            }
+            "binfiles" => {
+                let arch = value.split(' ')
+                    .find_map(|a| a.strip_prefix("arch="))
+                    .unwrap_or_default()
+                    .to_owned();
+                if let Some(size) = size_attribute(value.split(' ')) {
+                    pkg.binsize.get_or_insert_with(Map::new).insert(arch.clone(), size);
+                }
+                section = FileSection::Bin(arch);
+            }
+            catalogue_key if catalogue_key.starts_with("catalogue-") => {
+                has_catalogue = true;
+                let value = Some(value.to_owned());
+                match &catalogue_key["catalogue-".len()..] {
+                    "version" => catalogue.version = value,
+                    "license" => catalogue.license = value,
+                    "ctan" => catalogue.ctan = value,
+                    "topics" => catalogue.topics = value,
+                    _ => {}
+                }
+            }
+            _ => {}
+        }
+    }
+
+    if has_catalogue {
+        pkg.cataloguedata = Some(catalogue);
+    }
+    if origin == TlpdbOrigin::Local {
+        pkg.installed = Some(true);
+    }
+
+    if pkg.name.is_empty() { None } else { Some(pkg.unrelocate()) }
+}
+
+/// The installation's own database plus any repository databases tlmgr has cached
+pub fn tlpdb_files(texlive_root: impl AsRef<Path>) -> Vec<(PathBuf, TlpdbOrigin)> {
+    let tlpkg = texlive_root.as_ref().join("tlpkg");
+    let mut result = vec![(tlpkg.join("texlive.tlpdb"), TlpdbOrigin::Local)];
+
+    if let Ok(entries) = std::fs::read_dir(&tlpkg) {
+        let mut cached: Vec<PathBuf> = entries
+            .filter_map(|e| e.ok())
+            .map(|e| e.path())
+            .filter(|p| p.file_name()
+                .and_then(|n| n.to_str())
+                .is_some_and(|n| n.starts_with("texlive.tlpdb.main.")))
+            .collect();
+        cached.sort();
+        result.extend(cached.into_iter().map(|p| (p, TlpdbOrigin::Remote)));
+    }
+
+    result
+}
+
+/// Loads every readable database under `texlive_root`, later ones not overriding earlier ones
+pub fn load_tlpdbs(texlive_root: impl AsRef<Path>) -> Result<Map<String, TlPObjInfo>, DtMgrError> {
+    let mut result: Map<String, TlPObjInfo> = Map::new();
+
+    for (path, origin) in tlpdb_files(texlive_root) {
+        if !path.is_file() {
+            continue;
+        }
+        let content = std::fs::read_to_string(&path)
+            .map_err(|e| DtMgrError::ReadFile { path: path.clone(), source: e })?;
+        merge(&mut result, parse_tlpdb(&content, origin));
+    }
+
+    Ok(result)
+}
+
+/// Adds the packages of another database to `result`, where the ones it has already only take
+/// the repository revision, if they lack one
+pub fn merge(result: &mut Map<String, TlPObjInfo>, packages: Map<String, TlPObjInfo>) {
+    for (name, pkg) in packages {
+        match result.get_mut(&name) {
+            Some(existing) => {
+                if existing.rrev.is_none() {
+                    existing.rrev = pkg.rrev;
+                }
+            }
+            None => {
+                result.insert(name, pkg);
+            }
+        }
+    }
+}
+
+/// The installation's own database, which every `tlmgr install` or `update` rewrites
+pub fn local_tlpdb(texlive_root: impl AsRef<Path>) -> PathBuf {
+    texlive_root.as_ref().join("tlpkg").join("texlive.tlpdb")
+}
+
+/// When `tlpdb` was last written, in nanoseconds since the epoch, as precisely as the file
+/// system tells
+pub fn modified(tlpdb: &Path) -> Option<u64> {
+    let modified = tlpdb.metadata().ok()?.modified().ok()?;
+    u64::try_from(modified.duration_since(UNIX_EPOCH).ok()?.as_nanos()).ok()
+}
+
+/// TeX Live's own revision, which `00texlive.config` lists as `depend revision/NNNNN`
+pub fn texlive_revision(content: &str) -> Option<u64> {
+    let (_, config) = content.split_once("name 00texlive.config\n")?;
+    config.lines()
+        .take_while(|line| !line.is_empty())
+        .find_map(|line| line.strip_prefix("depend revision/"))
+        .and_then(|revision| revision.parse().ok())
+}
+
+/// The repository `tlmgr` installs from, which `00texlive.installation` lists as
+/// `depend opt_location:URL`
+pub fn repository(content: &str) -> Option<&str> {
+    let (_, installation) = content.split_once("name 00texlive.installation\n")?;
+    installation.lines()
+        .take_while(|line| !line.is_empty())
+        .find_map(|line| line.strip_prefix("depend opt_location:"))
+}
+
+/// The revision of each package in a database, without parsing the rest of it
+pub fn revisions(content: &str) -> Map<&str, u64> {
+    let mut result = Map::new();
+    let mut name = None;
+    for line in content.lines() {
+        if let Some(value) = line.strip_prefix("name ") {
+            name = Some(value);
+        } else if let Some(value) = line.strip_prefix("revision ")
+            && let (Some(name), Ok(revision)) = (name, value.parse()) {
+            result.insert(name, revision);
+        }
+    }
+    result
+}
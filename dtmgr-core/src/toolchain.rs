@@ -0,0 +1,226 @@
+use std::io::{Cursor, Read};
+use std::path::{Path, PathBuf};
+use clap::Subcommand;
+use flate2::read::GzDecoder;
+use tracing::{debug, info};
+use crate::{cmd_crossplatform_in_path, home, http, mirror, network, signals, tlpdb, DtMgrError};
+
+// TeX Live's current release, from whichever CTAN mirror is near
+const CURRENT_REPOSITORY: &str = "https://mirror.ctan.org/systems/texlive/tlnet";
+// every earlier release as it was when it was frozen, which CTAN no longer carries
+const HISTORIC_REPOSITORY: &str = "https://ftp.math.utah.edu/pub/tex/historic/systems/texlive";
+#[cfg(windows)]
+const INSTALLER: &str = "install-tl.zip";
+#[cfg(not(windows))]
+const INSTALLER: &str = "install-tl-unx.tar.gz";
+#[cfg(windows)]
+const INSTALLER_SCRIPT: &str = "install-tl-windows.bat";
+#[cfg(not(windows))]
+const INSTALLER_SCRIPT: &str = "install-tl";
+// the first line of which names the release, e.g. `TeX Live (https://tug.org/texlive) version 2024`
+const RELEASE_FILE: &str = "release-texlive.txt";
+// below the data directory, as installations cannot be downloaded again once their year is over
+const TOOLCHAINS_DIR: &str = "toolchains";
+
+#[derive(Subcommand)]
+pub enum ToolchainCommand {
+    /// Install a TeX Live release for projects whose `toolchain` names it
+    Install {
+        /// e.g. `2024`
+        release: String,
+    },
+    /// List the installed TeX Live releases
+    List,
+    /// Delete an installed TeX Live release
+    Remove {
+        release: String,
+    },
+}
+
+/// Where dtmgr installs TeX Live releases, one directory each
+pub fn toolchains_dir() -> Result<PathBuf, DtMgrError> {
+    Ok(home::data_dir()?.join(TOOLCHAINS_DIR))
+}
+
+fn check_release(release: &str) -> Result<(), DtMgrError> {
+    if release.len() == 4 && release.bytes().all(|b| b.is_ascii_digit()) {
+        Ok(())
+    } else {
+        Err(DtMgrError::InvalidToolchain { release: release.to_owned() })
+    }
+}
+
+/// Where the TeX Live `release` is, or would be, installed
+pub fn root(release: &str) -> Result<PathBuf, DtMgrError> {
+    check_release(release)?;
+    Ok(toolchains_dir()?.join(release))
+}
+
+/// The release of the TeX Live at `root`, if dtmgr installed it
+pub fn release_of(root: &Path) -> Option<String> {
+    let toolchains = toolchains_dir().ok()?;
+    (root.parent() == Some(toolchains.as_path()))
+        .then(|| root.file_name())
+        .flatten()
+        .map(|name| name.to_string_lossy().into_owned())
+}
+
+/// The installed releases, oldest first
+pub fn installed() -> Result<Vec<String>, DtMgrError> {
+    let Ok(entries) = std::fs::read_dir(toolchains_dir()?) else {
+        return Ok(Vec::new());
+    };
+    let mut releases: Vec<String> = entries.filter_map(|entry| entry.ok())
+        .filter(|entry| tlpdb::local_tlpdb(entry.path()).is_file())
+        .map(|entry| entry.file_name().to_string_lossy().into_owned())
+        .collect();
+    releases.sort();
+    Ok(releases)
+}
+
+// what `install-tl` is told; the scheme has the tools dtmgr runs, and projects install the rest
+fn profile(root: &Path) -> String {
+    let root = root.display();
+    format!("selected_scheme scheme-minimal
+TEXDIR {root}
+TEXMFLOCAL {root}/texmf-local
+instopt_adjustpath 0
+instopt_adjustrepo 1
+instopt_letter 0
+instopt_portable 0
+tlpdbopt_autobackup 0
+tlpdbopt_desktop_integration 0
+tlpdbopt_file_assocs 0
+tlpdbopt_install_docfiles 0
+tlpdbopt_install_srcfiles 0
+")
+}
+
+fn extract(archive: &[u8], url: &str, dir: &Path) -> Result<(), DtMgrError> {
+    if INSTALLER.ends_with(".zip") {
+        zip::ZipArchive::new(Cursor::new(archive))
+            .and_then(|mut zip| zip.extract(dir))
+            .map_err(|e| DtMgrError::Extract { url: url.to_owned(), source: e })
+    } else {
+        tar::Archive::new(GzDecoder::new(archive)).unpack(dir)
+            .map_err(|e| DtMgrError::UnpackArchive { dir: dir.to_owned(), source: e })
+    }
+}
+
+// the installer's archive holds a single `install-tl-<date>` directory
+fn installer_dir(extracted: &Path) -> Option<PathBuf> {
+    std::fs::read_dir(extracted).ok()?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .find(|path| path.join(INSTALLER_SCRIPT).is_file())
+}
+
+fn installer_release(installer: &Path) -> Option<String> {
+    let mut content = String::new();
+    std::fs::File::open(installer.join(RELEASE_FILE)).ok()?
+        .read_to_string(&mut content).ok()?;
+    content.lines().next()?
+        .split_whitespace()
+        .find(|word| check_release(word).is_ok())
+        .map(str::to_owned)
+}
+
+/// The repository `release` is installed from and its installer, extracted into `dir`: the
+/// historic archive for a finished year, CTAN for the current one
+fn download_installer(release: &str, dir: &Path) -> Result<(String, PathBuf), DtMgrError> {
+    let historic = format!("{}/{}/tlnet-final", HISTORIC_REPOSITORY, release);
+    let url = format!("{}/{}", historic, INSTALLER);
+    if let Some(archive) = network::retry(|| http::get_if_exists(&url, None))? {
+        extract(&archive, &url, dir)?;
+        let installer = installer_dir(dir).ok_or_else(|| DtMgrError::UnknownToolchain { release: release.to_owned(), current: None })?;
+        return Ok((historic, installer));
+    }
+
+    let repository = mirror::texlive_repository().unwrap_or_else(|| String::from(CURRENT_REPOSITORY));
+    let url = format!("{}/{}", repository, INSTALLER);
+    let archive = network::retry(|| http::get(&url))?;
+    extract(&archive, &url, dir)?;
+    let installer = installer_dir(dir);
+    let current = installer.as_deref().and_then(installer_release);
+    match installer {
+        Some(installer) if current.as_deref() == Some(release) => Ok((repository, installer)),
+        _ => Err(DtMgrError::UnknownToolchain { release: release.to_owned(), current }),
+    }
+}
+
+/// Installs the TeX Live `release` with `install-tl`, unless it is installed already, and
+/// returns its root.
+///
+/// Only a minimal scheme is installed; `dtmgr install` adds the packages each project needs
+/// with the release's own `tlmgr`.
+pub fn install(release: &str) -> Result<PathBuf, DtMgrError> {
+    let root = root(release)?;
+    if tlpdb::local_tlpdb(&root).is_file() {
+        debug!("TeX Live {} is installed in {}", release, root.display());
+        return Ok(root);
+    }
+    if network::offline() {
+        return Err(DtMgrError::Offline { needed: format!("TeX Live {}", release) });
+    }
+
+    // next to the releases, and removed whether the install works out or not
+    let work_dir = toolchains_dir()?.join(format!(".install-{}", release));
+    let _ = std::fs::remove_dir_all(&work_dir);
+    std::fs::create_dir_all(&work_dir)
+        .map_err(|e| DtMgrError::CreateDirectory { dir: work_dir.clone(), source: e })?;
+    let installed = run_installer(release, &root, &work_dir);
+    let _ = std::fs::remove_dir_all(&work_dir);
+    if installed.is_err() {
+        // a release without its database is not installed, but would be in the way of the next try
+        let _ = std::fs::remove_dir_all(&root);
+    }
+    installed.map(|()| root)
+}
+
+fn run_installer(release: &str, root: &Path, work_dir: &Path) -> Result<(), DtMgrError> {
+    info!("downloading the installer of TeX Live {}", release);
+    let (repository, installer) = download_installer(release, work_dir)?;
+    let profile_file = work_dir.join("dtmgr.profile");
+    std::fs::write(&profile_file, profile(root))
+        .map_err(|e| DtMgrError::WriteFile { file: profile_file.clone(), source: e })?;
+
+    info!("installing TeX Live {} into {}", release, root.display());
+    let script = installer.join(INSTALLER_SCRIPT);
+    let mut cmd = cmd_crossplatform_in_path([script.as_os_str(), "-profile".as_ref(), profile_file.as_os_str(), "-repository".as_ref(), repository.as_ref()], None);
+    cmd.current_dir(&installer);
+    network::apply_proxy(&mut cmd);
+    let status = signals::status(&mut cmd)
+        .map_err(|e| DtMgrError::CommandExecution { source: e })?;
+    if !status.success() {
+        return Err(DtMgrError::CommandStatus { command: format!("{} -profile {}", INSTALLER_SCRIPT, profile_file.display()), code: status.code(), stderr: String::new() });
+    }
+    if !tlpdb::local_tlpdb(root).is_file() {
+        return Err(DtMgrError::TexliveRootNotFound { dir: root.to_owned() });
+    }
+    Ok(())
+}
+
+pub fn run_toolchain_command(command: ToolchainCommand) -> Result<(), DtMgrError> {
+    match command {
+        ToolchainCommand::Install { release } => {
+            let root = install(&release)?;
+            println!("TeX Live {} is installed in {}", release, root.display());
+        }
+        ToolchainCommand::List => {
+            let toolchains = toolchains_dir()?;
+            for release in installed()? {
+                println!("{}\t{}", release, toolchains.join(&release).display());
+            }
+        }
+        ToolchainCommand::Remove { release } => {
+            let root = root(&release)?;
+            if !root.is_dir() {
+                return Err(DtMgrError::ToolchainNotInstalled { release });
+            }
+            std::fs::remove_dir_all(&root)
+                .map_err(|e| DtMgrError::RemoveDirectory { dir: root, source: e })?;
+            println!("removed TeX Live {}", release);
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,143 @@
+use std::collections::BTreeMap as Map;
+use std::collections::BTreeSet as Set;
+use std::path::{Component, Path, PathBuf};
+use std::time::SystemTime;
+use crate::{DtMgrConfig, DtMgrError, TlPObjInfo};
+
+/// The files a `-recorder` run read, as absolute paths
+pub fn read_fls(path: impl AsRef<Path>) -> Result<Set<PathBuf>, DtMgrError> {
+    let content = std::fs::read(path.as_ref())
+        .map_err(|e| DtMgrError::ReadFile { path: path.as_ref().to_owned(), source: e })?;
+    let content = String::from_utf8_lossy(&content);
+
+    let mut pwd = path.as_ref().parent().map(Path::to_owned).unwrap_or_default();
+    let mut result = Set::new();
+    for line in content.lines() {
+        if let Some(dir) = line.strip_prefix("PWD ") {
+            pwd = PathBuf::from(dir);
+        } else if let Some(file) = line.strip_prefix("INPUT ") {
+            result.insert(pwd.join(file));
+        }
+    }
+    Ok(result)
+}
+
+// TeX breaks its log at `max_print_line`, which TeX Live's texmf.cnf sets to 79 characters
+const LOG_LINE_WIDTH: usize = 79;
+
+/// The files a build's `.log` shows it opening, as absolute paths: `(file` for inputs,
+/// `<file>` for fonts and images and `{file}` for font maps. Less complete than a recorder
+/// file, as TeX only logs some of what it reads, and only files that still exist are kept.
+pub fn read_log(path: impl AsRef<Path>) -> Result<Set<PathBuf>, DtMgrError> {
+    let content = std::fs::read(path.as_ref())
+        .map_err(|e| DtMgrError::ReadFile { path: path.as_ref().to_owned(), source: e })?;
+    let content = String::from_utf8_lossy(&content);
+
+    // a full line continues on the next one, wherever in a path that falls
+    let mut unwrapped = String::new();
+    for line in content.lines() {
+        unwrapped.push_str(line);
+        if line.chars().count() != LOG_LINE_WIDTH {
+            unwrapped.push('\n');
+        }
+    }
+
+    let dir = path.as_ref().parent().map(Path::to_owned).unwrap_or_default();
+    let mut result = Set::new();
+    for (idx, _) in unwrapped.match_indices(['(', '<', '{']) {
+        let rest = &unwrapped[idx + 1..];
+        let end = rest.find(|c: char| c.is_whitespace() || "()<>{}".contains(c)).unwrap_or(rest.len());
+        if end == 0 {
+            continue;
+        }
+        let file = dir.join(&rest[..end]);
+        if file.is_file() {
+            result.insert(file);
+        }
+    }
+    Ok(result)
+}
+
+/// The newest `.fls` in `dir` written after `since`
+pub fn find_fls(dir: impl AsRef<Path>, since: SystemTime) -> Option<PathBuf> {
+    std::fs::read_dir(dir.as_ref()).ok()?
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| p.extension().is_some_and(|e| e == "fls"))
+        .filter_map(|p| {
+            let modified = p.metadata().ok()?.modified().ok()?;
+            (modified >= since).then_some((modified, p))
+        })
+        .max()
+        .map(|(_, p)| p)
+}
+
+// tlpdb paths always use `/`, whatever the platform
+fn tree_relative_key(path: &Path) -> String {
+    let parts: Vec<String> = path.components()
+        .filter_map(|c| match c {
+            Component::Normal(part) => Some(part.to_string_lossy().into_owned()),
+            _ => None,
+        })
+        .collect();
+    parts.join("/")
+}
+
+fn file_index(dep_tree: &Map<String, TlPObjInfo>) -> Map<&str, &str> {
+    let mut result = Map::new();
+    for pkg in dep_tree.values() {
+        for file in pkg.runfiles.iter().flatten() {
+            result.insert(file.as_str(), pkg.name.as_str());
+        }
+    }
+    result
+}
+
+/// Maps recorded inputs under any of `roots` back to the packages shipping them
+pub fn accessed_packages(inputs: &Set<PathBuf>, roots: &[&Path], dep_tree: &Map<String, TlPObjInfo>) -> Set<String> {
+    let index = file_index(dep_tree);
+    let mut result = Set::new();
+
+    for input in inputs.iter() {
+        for root in roots.iter() {
+            if let Ok(relative) = input.strip_prefix(root)
+                && let Some(pkg) = index.get(tree_relative_key(relative).as_str()) {
+                result.insert((*pkg).to_owned());
+                break;
+            }
+        }
+    }
+
+    result
+}
+
+/// The recorder never sees executables or what was compiled into a format, so
+/// packages shipping either always count as used
+fn unobservable(pkg: &TlPObjInfo, platform: &str) -> bool {
+    let has_binaries = pkg.binfiles.as_ref()
+        .and_then(|b| b.get(platform))
+        .is_some_and(|files| !files.is_empty());
+    let feeds_formats = pkg.runfiles.iter().flatten()
+        .any(|f| f.ends_with(".ini") || f.ends_with(".ltx"));
+    has_binaries || feeds_formats
+}
+
+/// Declared dependencies none of whose packages were read during the build
+pub fn unused_dependencies(
+    config: &DtMgrConfig,
+    dep_tree: &Map<String, TlPObjInfo>,
+    platform: &str,
+    accessed: &Set<String>,
+) -> Vec<String> {
+    let mut result = Vec::new();
+    for dep in config.dependencies.iter() {
+        let closure = crate::dependency_closure([dep.as_str()], dep_tree, platform);
+        let used = closure.iter().any(|name| {
+            accessed.contains(name) || dep_tree.get(name).is_some_and(|pkg| unobservable(pkg, platform))
+        });
+        if !used {
+            result.push(dep.clone());
+        }
+    }
+    result
+}
@@ -0,0 +1,121 @@
+use std::collections::BTreeMap as Map;
+use std::collections::BTreeSet as Set;
+use std::path::Path;
+use serde::Serialize;
+use tracing::debug;
+use crate::state::State;
+use crate::{cmd_crossplatform_static_args, mirror, network, prune, signals, size, DtMgrConfig, DtMgrError, TlPObjInfo};
+
+/// A package of the environment the repository has a newer revision of
+#[derive(Debug, Serialize)]
+pub struct PackageUpdate<'a> {
+    pub name: &'a str,
+    /// The revision the environment links, or TeX Live has if there is no environment yet
+    pub current_revision: Option<u64>,
+    pub available_revision: u64,
+    pub direct: bool,
+    /// How many bytes more the new revision takes up in the environment, negative if fewer
+    pub size_delta: i64,
+    /// Files the new revision has that the current one does not, and the other way around
+    pub added_files: usize,
+    pub removed_files: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct UpdatePreview<'a> {
+    pub packages: Vec<PackageUpdate<'a>>,
+}
+
+/// What updating the packages of `dep_tree` to the repository's revisions would change, compared
+/// to the environment in `dot_dir`
+pub fn preview<'a>(config: &DtMgrConfig, dot_dir: &Path, dep_tree: &'a Map<String, TlPObjInfo>, platform: &str) -> Result<UpdatePreview<'a>, DtMgrError> {
+    if network::offline() {
+        return Err(DtMgrError::Offline { needed: String::from("the repository's revisions") });
+    }
+    let linked = State::read(dot_dir)?.map(|state| state.packages);
+    let repository = crate::remote_tlpdb()?;
+
+    let mut packages = Vec::new();
+    for pkg in dep_tree.values() {
+        let Some(available) = repository.get(&pkg.name) else {
+            continue;
+        };
+        let Some(available_revision) = available.rrev else {
+            continue;
+        };
+        let current_revision = match &linked {
+            Some(linked) => linked.get(&pkg.name).copied().flatten(),
+            None => pkg.lrev,
+        };
+        if current_revision.is_some_and(|current| current >= available_revision) {
+            continue;
+        }
+
+        // what TeX Live has is what the environment links, unless it is not installed yet
+        let current_files: Set<&str> = if pkg.lrev.is_some() { pkg.files(platform).collect() } else { Set::new() };
+        let available_files: Set<&str> = available.files(platform).collect();
+        let current_size = if pkg.lrev.is_some() { size::package_size(pkg, platform) } else { 0 };
+        packages.push(PackageUpdate {
+            name: &pkg.name,
+            current_revision,
+            available_revision,
+            direct: config.is_direct(&pkg.name),
+            size_delta: size::package_size(available, platform) as i64 - current_size as i64,
+            added_files: available_files.difference(&current_files).count(),
+            removed_files: current_files.difference(&available_files).count(),
+        });
+    }
+    Ok(UpdatePreview { packages })
+}
+
+fn format_delta(bytes: i64) -> String {
+    let sign = if bytes < 0 { "-" } else { "+" };
+    format!("{}{}", sign, size::format_size(bytes.unsigned_abs()))
+}
+
+impl UpdatePreview<'_> {
+    pub fn print(&self) {
+        if self.packages.is_empty() {
+            println!("every package is at the repository's revision");
+            return;
+        }
+        for pkg in self.packages.iter() {
+            let current = pkg.current_revision.map(|r| format!("r{}", r)).unwrap_or_else(|| String::from("not installed"));
+            println!(
+                "{}: {} -> r{}, {}, {} files added, {} removed",
+                pkg.name, current, pkg.available_revision, format_delta(pkg.size_delta), pkg.added_files, pkg.removed_files,
+            );
+        }
+        let total: i64 = self.packages.iter().map(|pkg| pkg.size_delta).sum();
+        println!("{} packages, {} in total", self.packages.len(), format_delta(total));
+    }
+
+    /// The packages TeX Live itself has older revisions of, which `tlmgr update` has to fetch;
+    /// the others only need to be linked again
+    pub fn outdated_in_texlive<'b>(&'b self, dep_tree: &'b Map<String, TlPObjInfo>) -> Vec<&'b str> {
+        self.packages.iter()
+            .filter(|pkg| dep_tree.get(pkg.name).is_some_and(|info| info.lrev.is_some_and(|installed| installed < pkg.available_revision)))
+            .map(|pkg| pkg.name)
+            .collect()
+    }
+}
+
+/// Updates `packages` in the TeX Live in `root` with `tlmgr update`, showing its output
+pub fn update_packages(root: &Path, packages: &[&str]) -> Result<(), DtMgrError> {
+    let command = format!("tlmgr update {}", packages.join(" "));
+    let mut cmd = cmd_crossplatform_static_args(["tlmgr", "update"].into_iter().chain(packages.iter().copied()));
+    network::apply_proxy(&mut cmd);
+    mirror::apply_repository(&mut cmd);
+    let before = prune::installed_packages(root);
+    let status = signals::status(&mut cmd)
+        .map_err(|e| DtMgrError::CommandExecution { source: e })?;
+    crate::forget_package_database();
+    if !status.success() {
+        return Err(DtMgrError::CommandStatus { command, code: status.code(), stderr: String::new() });
+    }
+    // updates can bring in new dependencies, which are dtmgr's to prune like the ones it installs
+    if let Err(e) = before.and_then(|before| prune::record_installed(root, &before)) {
+        debug!("unable to record the packages tlmgr installed: {}", e);
+    }
+    Ok(())
+}
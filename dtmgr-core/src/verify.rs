@@ -0,0 +1,137 @@
+use std::collections::BTreeMap as Map;
+use std::path::Path;
+use serde::Serialize;
+use sha3::{Digest, Sha3_256};
+use crate::color::{self, Stream};
+use crate::manifest::Manifest;
+use crate::status::EnvironmentState;
+use crate::exclude::Exclusions;
+use crate::{executes, DtMgrError, TlPObjInfo};
+
+#[derive(Clone, Copy, Debug, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ProblemKind {
+    /// Nothing was linked at the path
+    Missing,
+    /// A link is there but what it points to in TeX Live is gone
+    Dangling,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Problem<'a> {
+    pub package: &'a str,
+    pub file: &'a str,
+    pub kind: ProblemKind,
+}
+
+#[derive(Debug, Serialize)]
+pub struct VerifyReport<'a> {
+    pub environment: EnvironmentState,
+    pub ok: bool,
+    pub problems: Vec<Problem<'a>>,
+    /// With `--deep`, the packages whose files in TeX Live changed since the install
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub modified: Option<Vec<&'a str>>,
+}
+
+/// Checks that every file of every package in `dep_tree` is reachable in `dot_dir`, apart from
+/// those `exclusions` left out
+pub fn verify_environment<'a>(
+    dot_dir: &Path,
+    environment: EnvironmentState,
+    dep_tree: &'a Map<String, TlPObjInfo>,
+    platform: &str,
+    exclusions: &Exclusions,
+) -> VerifyReport<'a> {
+    let mut problems = Vec::new();
+    for pkg in dep_tree.values() {
+        for file in pkg.files(platform).filter(|f| !exclusions.excludes(&pkg.name, f)) {
+            let path = dot_dir.join(file);
+            // `exists` follows links, `symlink_metadata` does not
+            let kind = if path.exists() {
+                continue;
+            } else if path.symlink_metadata().is_ok() {
+                ProblemKind::Dangling
+            } else {
+                ProblemKind::Missing
+            };
+            problems.push(Problem { package: &pkg.name, file, kind });
+        }
+    }
+
+    let ok = environment == EnvironmentState::UpToDate && problems.is_empty();
+    VerifyReport { environment, ok, problems, modified: None }
+}
+
+/// A hash of the names and contents of the files `pkg` links from TeX Live, below `root` or
+/// `relocated_root`, which changes whenever one of them is edited, replaced or deleted there
+pub fn file_set_checksum(root: &Path, relocated_root: Option<&Path>, pkg: &TlPObjInfo, platform: &str, exclusions: &Exclusions) -> String {
+    let mut hasher = Sha3_256::new();
+    let files = pkg.files(platform)
+        .filter(|f| !exclusions.excludes(&pkg.name, f) && !executes::GENERATED_RUNFILES.contains(f));
+    for file in files {
+        hasher.update(file.as_bytes());
+        hasher.update([0]);
+        match std::fs::read(crate::texlive_file(root, relocated_root, Path::new(file))) {
+            Ok(content) => {
+                hasher.update((content.len() as u64).to_le_bytes());
+                hasher.update(content);
+            }
+            Err(_) => hasher.update(u64::MAX.to_le_bytes()),
+        }
+    }
+    hex::encode(hasher.finalize())
+}
+
+/// Records the [`file_set_checksum`] of every package of `dep_tree` in `manifest`
+pub fn record_checksums(manifest: &mut Manifest, root: &Path, user_tree: Option<&Path>, dep_tree: &Map<String, TlPObjInfo>, platform: &str, exclusions: &Exclusions) {
+    for pkg in dep_tree.values() {
+        if let Some(linked) = manifest.packages.get_mut(&pkg.name) {
+            let relocated_root = crate::relocated_root(root, user_tree, pkg);
+            linked.files_sha3 = Some(file_set_checksum(root, relocated_root, pkg, platform, exclusions));
+        }
+    }
+}
+
+/// The packages of `dep_tree` whose files no longer hash to what the install recorded in
+/// `manifest`.
+///
+/// A package tlmgr has updated since, whose container checksum differs, is left to
+/// `dtmgr status`, as is one from an install that recorded no checksums.
+pub fn modified_packages<'a>(manifest: &Manifest, dep_tree: &'a Map<String, TlPObjInfo>, platform: &str, exclusions: &Exclusions) -> Result<Vec<&'a str>, DtMgrError> {
+    let root = crate::get_texlive_root()?;
+    let user_tree = crate::user_tree(dep_tree)?;
+    let mut modified = Vec::new();
+    for pkg in dep_tree.values() {
+        let Some(linked) = manifest.packages.get(&pkg.name) else {
+            continue;
+        };
+        let Some(recorded) = linked.files_sha3.as_ref().filter(|_| linked.containerchecksum == pkg.containerchecksum) else {
+            continue;
+        };
+        let relocated_root = crate::relocated_root(&root, user_tree.as_deref(), pkg);
+        if *recorded != file_set_checksum(&root, relocated_root, pkg, platform, exclusions) {
+            modified.push(pkg.name.as_str());
+        }
+    }
+    Ok(modified)
+}
+
+impl VerifyReport<'_> {
+    pub fn print(&self) {
+        println!("environment: {}", self.environment.painted(Stream::Stdout));
+        for problem in self.problems.iter() {
+            let kind = match problem.kind {
+                ProblemKind::Missing => "missing",
+                ProblemKind::Dangling => "dangling link",
+            };
+            println!("{} ({}): {}", problem.file, problem.package, color::error(kind, Stream::Stdout));
+        }
+        for package in self.modified.iter().flatten() {
+            println!("{}: {}", package, color::error("files changed in TeX Live since the install", Stream::Stdout));
+        }
+        if self.ok {
+            println!("{}", color::good("every file is in place", Stream::Stdout));
+        }
+    }
+}
@@ -0,0 +1,100 @@
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::time::Duration;
+use notify::{RecursiveMode, Watcher};
+use crate::{DtMgrError, CONFIG_FILE_NAME};
+
+// editors tend to write a file several times in a row when saving
+const DEBOUNCE: Duration = Duration::from_millis(300);
+
+// files latexmk and the engines write next to the sources
+const ARTIFACT_EXTENSIONS: [&str; 22] = [
+    "aux", "bbl", "bcf", "blg", "dvi", "fdb_latexmk", "fls", "glg", "glo", "gls", "idx", "ilg",
+    "ind", "lof", "log", "lot", "nav", "out", "pdf", "run.xml", "synctex.gz", "toc",
+];
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum Change {
+    Config,
+    Sources,
+}
+
+fn is_artifact(path: &Path) -> bool {
+    let name = path.file_name().map(|n| n.to_string_lossy()).unwrap_or_default();
+    ARTIFACT_EXTENSIONS.iter().any(|ext| name.ends_with(&(String::from(".") + ext)))
+}
+
+struct Filter {
+    project_dir: PathBuf,
+    ignored_dirs: Vec<PathBuf>,
+}
+
+impl Filter {
+    fn classify(&self, path: &Path) -> Option<Change> {
+        let relative = path.strip_prefix(&self.project_dir).ok()?;
+        if relative == Path::new(CONFIG_FILE_NAME) {
+            return Some(Change::Config);
+        }
+
+        let hidden = relative.components()
+            .any(|c| c.as_os_str().to_string_lossy().starts_with('.'));
+        let ignored = self.ignored_dirs.iter().any(|d| path.starts_with(d));
+        if hidden || ignored || is_artifact(path) {
+            None
+        } else {
+            Some(Change::Sources)
+        }
+    }
+}
+
+/// Calls `on_change` whenever something in `project_dir` changes, until it fails.
+///
+/// Hidden directories (including `.dtmgr`), `ignored_dirs`, and build artifacts are
+/// not watched. A config change takes precedence over source changes in the same burst.
+pub fn watch_project(
+    project_dir: &Path,
+    ignored_dirs: &[PathBuf],
+    mut on_change: impl FnMut(Change) -> Result<(), DtMgrError>,
+) -> Result<(), DtMgrError> {
+    let (tx, rx) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(tx)
+        .map_err(|e| DtMgrError::Watch { source: e })?;
+    watcher.watch(project_dir, RecursiveMode::Recursive)
+        .map_err(|e| DtMgrError::Watch { source: e })?;
+
+    let filter = Filter {
+        project_dir: project_dir.to_owned(),
+        ignored_dirs: ignored_dirs.iter().map(|d| project_dir.join(d)).collect(),
+    };
+
+    let classify_event = |event: notify::Result<notify::Event>| -> Option<Change> {
+        let event = event.ok()?;
+        if event.kind.is_access() {
+            return None;
+        }
+        let mut result = None;
+        for path in event.paths.iter() {
+            match filter.classify(path) {
+                Some(Change::Config) => return Some(Change::Config),
+                Some(Change::Sources) => result = Some(Change::Sources),
+                None => {}
+            }
+        }
+        result
+    };
+
+    // the sender lives inside the watcher, so this only ends with an error
+    while let Ok(event) = rx.recv() {
+        let Some(mut change) = classify_event(event) else {
+            continue;
+        };
+        while let Ok(event) = rx.recv_timeout(DEBOUNCE) {
+            if classify_event(event) == Some(Change::Config) {
+                change = Change::Config;
+            }
+        }
+        on_change(change)?;
+    }
+
+    Ok(())
+}
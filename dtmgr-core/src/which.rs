@@ -0,0 +1,83 @@
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
+
+// how close a name has to be to the one asked for to be suggested
+const SIMILARITY_THRESHOLD: f64 = 0.8;
+
+/// Whether `program` is a bare name, to be looked up in PATH
+pub fn is_bare_name(program: &OsStr) -> bool {
+    Path::new(program).components().count() == 1
+}
+
+/// The extensions Windows tries on a bare program name, in order
+#[cfg(windows)]
+fn executable_extensions() -> Vec<std::ffi::OsString> {
+    let pathext = std::env::var_os("PATHEXT")
+        .unwrap_or_else(|| std::ffi::OsString::from(".COM;.EXE;.BAT;.CMD"));
+    pathext.to_string_lossy()
+        .split(';')
+        .filter(|e| !e.is_empty())
+        .map(std::ffi::OsString::from)
+        .collect()
+}
+
+#[cfg(windows)]
+fn candidates_in(dir: &Path, program: &OsStr) -> Vec<PathBuf> {
+    // like cmd.exe, a name that already has an extension is also tried as is
+    let mut result = Vec::new();
+    if Path::new(program).extension().is_some() {
+        result.push(dir.join(program));
+    }
+    for extension in executable_extensions() {
+        let mut name = program.to_owned();
+        name.push(extension);
+        result.push(dir.join(name));
+    }
+    result
+}
+#[cfg(unix)]
+fn candidates_in(dir: &Path, program: &OsStr) -> Vec<PathBuf> {
+    vec![dir.join(program)]
+}
+
+#[cfg(windows)]
+pub fn is_executable(path: &Path) -> bool {
+    path.is_file()
+}
+#[cfg(unix)]
+pub fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    path.metadata().is_ok_and(|m| m.is_file() && m.permissions().mode() & 0o111 != 0)
+}
+
+/// The file a bare `program` name runs when looked up in `search_path`, honouring PATHEXT on Windows
+pub fn find_program(program: &OsStr, search_path: &OsStr) -> Option<PathBuf> {
+    std::env::split_paths(search_path)
+        .flat_map(|dir| candidates_in(&dir, program))
+        .find(|candidate| is_executable(candidate))
+}
+
+#[cfg(windows)]
+fn program_name(path: &Path) -> Option<String> {
+    path.file_stem().map(|s| s.to_string_lossy().into_owned())
+}
+#[cfg(unix)]
+fn program_name(path: &Path) -> Option<String> {
+    path.file_name().map(|s| s.to_string_lossy().into_owned())
+}
+
+/// Executables in `dirs` with names like `program`, without their extensions
+pub fn similar_programs(program: &OsStr, dirs: &[PathBuf]) -> Vec<String> {
+    let program = program.to_string_lossy();
+    let mut result: Vec<String> = dirs.iter()
+        .filter_map(|dir| std::fs::read_dir(dir).ok())
+        .flatten()
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| is_executable(&entry.path()))
+        .filter_map(|entry| program_name(&entry.path()))
+        .filter(|name| strsim::jaro_winkler(&program, name) >= SIMILARITY_THRESHOLD)
+        .collect();
+    result.sort();
+    result.dedup();
+    result
+}
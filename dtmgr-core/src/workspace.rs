@@ -0,0 +1,46 @@
+use std::path::{Path, PathBuf};
+use schemars::JsonSchema;
+use serde::Deserialize;
+use crate::DtMgrError;
+
+/// The `[workspace]` section of `dtmgr.toml`: directories below the project that use its
+/// environment, such as the papers of a monorepo
+#[derive(Debug, Default, Deserialize, JsonSchema, Hash)]
+#[serde(deny_unknown_fields)]
+pub struct WorkspaceConfig {
+    /// Relative to the project
+    #[serde(default)]
+    members: Vec<PathBuf>,
+}
+
+/// A member as named on the command line, and its directory
+pub struct Member {
+    pub name: String,
+    pub dir: PathBuf,
+}
+
+impl WorkspaceConfig {
+    /// Every member, or only the one named `selected`
+    pub fn members(&self, project_dir: &Path, selected: Option<&str>) -> Result<Vec<Member>, DtMgrError> {
+        if self.members.is_empty() {
+            return Err(DtMgrError::NoWorkspaceMembers);
+        }
+        let all = self.members.iter()
+            .map(|path| Member { name: path.display().to_string(), dir: project_dir.join(path) });
+        let members: Vec<Member> = match selected {
+            Some(name) => all.filter(|member| member.name.trim_end_matches('/') == name.trim_end_matches('/')).collect(),
+            None => all.collect(),
+        };
+        if let Some(name) = selected
+            && members.is_empty() {
+            return Err(DtMgrError::UnknownMember {
+                name: name.to_owned(),
+                available: self.members.iter().map(|path| path.display().to_string()).collect::<Vec<_>>().join(", "),
+            });
+        }
+        if let Some(missing) = members.iter().find(|member| !member.dir.is_dir()) {
+            return Err(DtMgrError::WorkingDirectoryNotFound { dir: missing.dir.clone() });
+        }
+        Ok(members)
+    }
+}
@@ -0,0 +1,26 @@
+use tracing::level_filters::LevelFilter;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::Layer;
+
+/// Sends log messages to stderr; `-v` shows debug messages, `-vv` traces and `-q` only errors.
+/// Warnings also go to the report of an install, even with `-q`.
+pub fn init(verbose: u8, quiet: bool) {
+    let level = match (quiet, verbose) {
+        (true, _) => LevelFilter::ERROR,
+        (false, 0) => LevelFilter::INFO,
+        (false, 1) => LevelFilter::DEBUG,
+        (false, _) => LevelFilter::TRACE,
+    };
+
+    let stderr = tracing_subscriber::fmt::layer()
+        .with_writer(std::io::stderr)
+        .with_ansi(dtmgr_core::color::stderr_enabled())
+        .with_target(false)
+        .without_time()
+        .with_filter(level);
+    tracing_subscriber::registry()
+        .with(stderr)
+        .with(dtmgr_core::report::Warnings.with_filter(LevelFilter::WARN))
+        .init();
+}
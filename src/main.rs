@@ -1,620 +1,1637 @@
-use std::collections::BTreeMap as Map;
 use std::collections::BTreeSet as Set;
-use std::ffi::{OsStr, OsString};
-use std::io::Write;
-use std::path::{Path, PathBuf};
-use std::process::{Command, ExitCode, ExitStatus, Stdio};
-use clap::{Parser, Subcommand};
-use serde::{Deserialize, Serialize};
-use sha3::{Digest, Sha3_256};
-use thiserror::Error;
-
-#[cfg(windows)]
-const KPSE_SEPARATOR: char = ';';
-#[cfg(unix)]
-const KPSE_SEPARATOR: char = ':';
-
-const CONFIG_FILE_NAME: &str = "dtmgr.toml";
+use std::path::PathBuf;
+use std::process::{Command, ExitCode};
+use clap::{ArgAction, CommandFactory, Parser, Subcommand};
+use tracing::{error, info, warn};
+use dtmgr_core::{
+    archive, audit, build, bundle, cache, check, color, command_line, config_edit, depends_txt, docs, editor, env,
+    error_report, exclude, export, generate, graph, import, info, install_log, launchers, licenses, manifest, mirror, network, output,
+    packages, plan, progress, projects, prompt, provides, prune, repair, report, run_env, sbom, scan, self_update, signals,
+    search, size, state, status, template, tlpdb, toolchain, trim, update, verify, watch,
+};
+use dtmgr_core::{
+    base_packages, build_dependency_tree, build_documents, cmd_crossplatform_in_path, config_schema, dtmgr_environment,
+    find_dtmgr_directory, get_texlive_root, hash_config, install, installed_packages, make_dot_dir,
+    native_platform, parse_config, platform_runs_here, rename_dir, run_tool_in_dtmgr, set_no_dev,
+    set_platform_override, set_texlive_root_override, sync_or_warn, texlive_platform, update_texlive, DtMgrError, TlPObjInfo,
+    CONFIG_FILE_NAME,
+};
+
+mod logging;
+mod man;
+mod ui;
 
 #[derive(Parser)]
 #[command(version, about, long_about = None, arg_required_else_help = true)]
 struct Cli {
     #[command(subcommand)]
     command: Commands,
+
+    /// How errors are printed on stderr
+    #[arg(long, global = true, value_enum, default_value_t)]
+    error_format: error_report::ErrorFormat,
+
+    /// Log more; `-vv` also shows the environment of spawned commands
+    #[arg(short, long, global = true, action = ArgAction::Count)]
+    verbose: u8,
+
+    /// Only log errors, and hide the output of TeX Live's tools unless they fail
+    #[arg(short, long, global = true, conflicts_with = "verbose")]
+    quiet: bool,
+
+    /// When to color output
+    #[arg(long, global = true, value_enum, default_value_t)]
+    color: color::ColorChoice,
+
+    /// Don't draw progress bars, e.g. for CI logs
+    #[arg(long, global = true)]
+    no_progress: bool,
+
+    /// Use this TeX Live platform's binaries (as named by `tlmgr print-platform`) instead of
+    /// the one TeX Live runs on here
+    #[arg(long, global = true, value_name = "PLATFORM")]
+    platform: Option<String>,
+
+    /// Never use the network: packages have to be in TeX Live and `git` or `ctan` sources in
+    /// the cache already
+    #[arg(long, global = true)]
+    offline: bool,
+
+    /// Use the TeX Live installed in this directory, e.g. `/opt/texlive/2023`, instead of the
+    /// one on PATH or the one `texlive-root` names
+    #[arg(long, global = true, value_name = "DIR")]
+    texlive_root: Option<PathBuf>,
 }
 
 #[derive(Subcommand)]
 enum Commands {
-    Install {},
+    Install {
+        /// Show what would be installed and linked without changing anything
+        #[arg(long)]
+        dry_run: bool,
+
+        #[arg(long, value_enum, default_value_t, requires = "dry_run")]
+        format: output::OutputFormat,
+
+        /// Show how long each phase of the install took, as a table or as JSON
+        #[arg(long, value_enum, value_name = "FORMAT", num_args = 0..=1, default_missing_value = "human", conflicts_with = "dry_run")]
+        timings: Option<output::OutputFormat>,
+
+        /// Leave out `dev-dependencies`, e.g. in CI where only the documents are built
+        #[arg(long)]
+        no_dev: bool,
+    },
+
+    /// Check dtmgr.toml without installing anything: every dependency exists, none is listed
+    /// twice and the license policy allows every package. `install` checks this first too.
+    Check {
+        /// Remove dependencies another dependency brings in anyway from dtmgr.toml
+        #[arg(long)]
+        fix: bool,
+    },
 
     #[command(disable_help_flag = true, disable_version_flag = true)]
     Run {
-        #[arg(allow_hyphen_values = true)]
-        program: String,
+        /// Install the environment first if it is missing or out of date
+        #[arg(long)]
+        ensure: bool,
+        /// Leave PATH alone, so the program and the tools it starts come from the host and only
+        /// TEXMFCNF points into the environment
+        #[arg(long, visible_alias = "no-path")]
+        keep_path: bool,
+        /// Run the program in this directory instead of the current one
+        #[arg(long, value_name = "DIR")]
+        cwd: Option<PathBuf>,
+        /// Set an environment variable for the program, overriding dtmgr's
+        #[arg(short = 'e', long = "env", value_name = "KEY=VALUE", value_parser = run_env::parse_assignment)]
+        env: Vec<(String, String)>,
+        /// Turn off shell escape and keep TeX from reading or writing files outside the project,
+        /// for building sources that are not trusted
+        #[arg(long)]
+        restricted: bool,
+        // one list, so that arguments like `-v` go to the program rather than dtmgr
+        #[arg(allow_hyphen_values = true, trailing_var_arg = true, required = true, value_name = "PROGRAM")]
+        program_and_args: Vec<String>,
+    },
 
-        #[arg(allow_hyphen_values = true, trailing_var_arg = true)]
+    /// Print a software bill of materials for the resolved packages
+    Sbom {
+        #[arg(long, value_enum, default_value_t = sbom::SbomFormat::Cyclonedx)]
+        format: sbom::SbomFormat,
+    },
+
+    /// Print the packages of a distribution that provide the resolved ones, e.g. for a Dockerfile
+    Export {
+        distribution: export::Distribution,
+    },
+
+    /// Summarize the licenses of the resolved packages
+    Licenses {},
+
+    /// Report resolved packages that are obsolete, were renamed or are no longer in the
+    /// repository, with what to use instead
+    Audit {
+        #[arg(long, value_enum, default_value_t)]
+        format: output::OutputFormat,
+    },
+
+    /// Report the disk footprint of the resolved packages
+    Size {
+        /// Attribute each package to the direct dependency that pulled it in
+        #[arg(long)]
+        tree: bool,
+    },
+
+    /// Add packages to dtmgr.toml
+    Add {
+        /// The packages, or with `--interactive` what to search for
+        #[arg(required = true)]
+        packages: Vec<String>,
+
+        /// Search package names and descriptions and pick from the matches
+        #[arg(short, long)]
+        interactive: bool,
+    },
+
+    /// Find the package that ships a file, e.g. `standalone.cls`
+    Provides {
+        file: String,
+
+        /// Add the package to dtmgr.toml without asking
+        #[arg(long)]
+        add: bool,
+    },
+
+    /// Add the package providing a missing file to dtmgr.toml and reinstall
+    Resolve {
+        /// The missing file, e.g. `xstring.sty`
+        file: String,
+    },
+
+    /// Find packages for the files a LaTeX log reports as missing
+    Scan {
+        log: PathBuf,
+
+        /// Add the packages to dtmgr.toml and reinstall
+        #[arg(long)]
+        fix: bool,
+    },
+
+    /// Add the packages loaded by the project's sources to dtmgr.toml
+    Import {
+        /// Start at this file and follow \input/\include instead of scanning every source file
+        main: Option<PathBuf>,
+
+        /// Copy the packages listed in an l3build DEPENDS.txt instead of scanning sources
+        #[arg(long, num_args = 0..=1, default_missing_value = "DEPENDS.txt", conflicts_with = "main")]
+        depends_txt: Option<PathBuf>,
+    },
+
+    /// Report declared dependencies a build never read any files of
+    #[command(visible_alias = "lint")]
+    Trim {
+        /// Use an existing recorder file instead of running a build
+        #[arg(long)]
+        fls: Option<PathBuf>,
+
+        /// Use a build's log instead of running a build; a recorder file is more complete
+        #[arg(long, conflicts_with = "fls")]
+        log: Option<PathBuf>,
+
+        /// Remove the unused dependencies from dtmgr.toml
+        #[arg(long)]
+        remove: bool,
+
+        /// Build to run with `-recorder`, e.g. `lualatex main.tex`
+        #[arg(allow_hyphen_values = true, trailing_var_arg = true, required_unless_present_any = ["fls", "log"], conflicts_with_all = ["fls", "log"])]
+        command: Vec<String>,
+    },
+
+    /// Create a dtmgr.toml in the current directory
+    Init {
+        /// List the packages currently installed in the system TeX Live
+        #[arg(long)]
+        from_system: bool,
+
+        /// With --from-system, only list packages no other installed package depends on
+        #[arg(long, requires = "from_system")]
+        leaves: bool,
+
+        /// Overwrite an existing dtmgr.toml
+        #[arg(long)]
+        force: bool,
+    },
+
+    /// Start a project in a new directory from a template, with a dtmgr.toml to match
+    New {
+        /// The directory, whose name is also the project's
+        name: PathBuf,
+
+        /// `article`, `ieee`, `beamer`, or the URL of a git repository to copy
+        #[arg(long, default_value = template::DEFAULT_TEMPLATE)]
+        template: String,
+
+        /// The commit, tag or branch of a git template, instead of its default branch
+        #[arg(long)]
+        rev: Option<String>,
+    },
+
+    /// Build the document with latexmk as configured under [build]
+    Build {
+        /// The entry of [documents] to build
+        target: Option<String>,
+
+        /// Build every entry of [documents]
+        #[arg(long, conflicts_with = "target")]
+        all: bool,
+
+        /// Rebuild whenever a source file changes, reinstalling first if dtmgr.toml changed
+        #[arg(long)]
+        watch: bool,
+
+        /// Extra arguments passed to latexmk
+        #[arg(last = true)]
         args: Vec<String>,
-    }
-}
+    },
 
-#[derive(Error, Debug)]
-pub enum DtMgrError {
-    #[error("unable to parse configuration file `dtmgr.toml`")]
-    ParseConfig {
-        #[source] source: toml::de::Error
-    },
-    #[error("unable to read file ({path})")]
-    ReadFile {
-        path: PathBuf,
-        #[source] source: std::io::Error
-    },
-    #[error("unable to hash config")]
-    HashConfig {
-        #[source] source: postcard::Error
-    },
-    #[error("system failure executing a command")]
-    CommandExecution {
-        #[source] source: std::io::Error
-    },
-    #[error("command `{command}` exited with non-zero exit code ({code:?})")]
-    CommandStatus {
-        command: String,
-        code: Option<i32>,
-    },
-    #[error("failed to parse json")]
-    JsonParse {
-        #[source] source: serde_json::Error
-    },
-    #[error("failed to retrieve current directory")]
-    CurrentDirectory {
-        #[source] source: std::io::Error
-    },
-    #[error("unable to find dtmgr.toml in current directory ({cwd}) or any of its parents")]
-    FindConfig {
-        cwd: PathBuf
-    },
-    #[error("unable to create directory ({dir})")]
-    CreateDirectory {
-        dir: PathBuf,
-        #[source] source: std::io::Error,
-    },
-    #[error("unable to write to file ({file})")]
-    WriteFile {
-        file: PathBuf,
-        #[source] source: std::io::Error,
-    },
-    #[error("unable to create symlink (src: {src}, dst: {dst})")]
-    CreateSymlink {
-        src: PathBuf,
-        dst: PathBuf,
-        #[source] source: std::io::Error,
-    },
-    #[error("unable to remove directory ({dir})")]
-    RemoveDirectory {
-        dir: PathBuf,
-        #[source] source: std::io::Error,
-    }
-}
+    /// Run the package's tests (`l3build check` unless [test] says otherwise)
+    Test {
+        /// Extra arguments passed to the test command
+        #[arg(allow_hyphen_values = true, trailing_var_arg = true)]
+        args: Vec<String>,
+    },
 
-#[derive(Debug, Deserialize, Serialize, Hash)]
-pub struct DtMgrConfig {
-    dependencies: Set<String>
-}
+    /// Show what TeX Live knows about a package, and with `--files` what linking it puts into
+    /// the environment
+    Info {
+        package: String,
 
-// https://svn.tug.org:8369/texlive/trunk/Master/tlpkg/doc/json-formats.txt?view=markup
-#[derive(Debug, Deserialize, Clone)]
-pub struct TlPObjInfo {
-    name: String,
-    shortdesc: Option<String>,
-    longdesc: Option<String>,
-    category: Option<String>,
-    catalogue: Option<String>,
-    containerchecksum: Option<String>,
-    lrev: Option<u64>,
-    rrev: Option<u64>,
-    runsize: Option<u64>,
-    docsize: Option<u64>,
-    srcsize: Option<u64>,
-    containersize: Option<u64>,
-    srccontainersize: Option<u64>,
-    doccontainersize: Option<u64>,
-    available: bool,
-    installed: Option<bool>,
-    relocated: Option<bool>,
-    runfiles: Option<Vec<String>>,
-    srcfiles: Option<Vec<String>>,
-    executes: Option<Vec<String>>,
-    depends: Option<Vec<String>>,
-    postactions: Option<Vec<String>>,
-    docfiles: Option<Vec<TlPObjDocFile>>,
-    binfiles: Option<Map<String, Vec<String>>>,
-    binsize: Option<Map<String, u64>>,
-    cataloguedata: Option<TlPObjInfoCatalogueData>,
-    rcataloguedata: Option<TlPObjInfoCatalogueData>,
-}
+        /// List the files linked into the environment, after `exclude`
+        #[arg(long)]
+        files: bool,
 
-#[derive(Debug, Deserialize, Clone)]
-pub struct TlPObjDocFile {
-    file: String,
-    lang: Option<String>,
-    detail: Option<String>,
-}
+        #[arg(long, value_enum, default_value_t)]
+        format: output::OutputFormat,
+    },
 
-#[derive(Debug, Deserialize, Clone)]
-pub struct TlPObjInfoCatalogueData {
-    topics: Option<String>,
-    version: Option<String>,
-    license: Option<String>,
-    ctan: Option<String>,
-    date: Option<String>,
-    related: Option<String>,
-}
+    /// Open a package's documentation with texdoc in the environment
+    Docs {
+        package: String,
 
-#[cfg(windows)]
-fn cmd_crossplatform_static_args<I, S>(exe_and_args: I) -> Command
-where
-    I: IntoIterator<Item = S>,
-    S: AsRef<OsStr> {
-    let mut cmd = Command::new("powershell");
-    cmd.arg("-c");
-
-    let mut command_string = String::new();
-    command_string.push_str("& ");
-
-    for (idx, elem) in exe_and_args.into_iter().enumerate() {
-        let key = format!("DTMGR_ARG{}", idx);
-        command_string.push_str("$Env:");
-        command_string.push_str(key.as_str());
-        command_string.push(' ');
-        cmd.env(key, elem);
-    }
-    cmd.arg(command_string);
-    cmd
-}
+        /// List the package's PDF manuals instead of opening one
+        #[arg(long)]
+        list: bool,
+    },
 
-#[cfg(unix)]
-fn cmd_crossplatform_static_args<I, S>(exe_and_args: I) -> Command
-where
-    I: IntoIterator<Item = S>,
-    S: AsRef<OsStr> {
-    let mut as_iter = exe_and_args.into_iter();
-    let mut cmd = Command::new(as_iter.next().expect("exe_and_args should be nonempty"));
-    cmd.args(as_iter);
-    cmd
-}
+    /// Point an editor's LaTeX tooling at the environment
+    Editor {
+        editor: editor::Editor,
 
-#[cfg(all(not(windows), not(unix)))]
-fn cmd_crossplatform_static_args<I, S>(exe_and_args: I) -> Command
-where
-    I: IntoIterator<Item = S>,
-    S: AsRef<OsStr> {
-    compile_error!("not sure how to spawn a command on this platform")
-}
+        /// Configure the TexLab extension instead of LaTeX Workshop
+        #[arg(long)]
+        texlab: bool,
+    },
 
-// TODO all of these .expect s should be replaced with proper tracing
+    /// Print the variables that enter the environment
+    Env {
+        #[arg(long, value_enum, default_value_t)]
+        format: env::EnvFormat,
+    },
 
-fn get_texlive_root() -> Result<PathBuf, DtMgrError> {
-    let kpse_out = cmd_crossplatform_static_args(["kpsewhich", "-var-value=TEXMFROOT"])
-        .output().map_err(|e| DtMgrError::CommandExecution { source: e })?;
+    /// Write a tool's configuration file for the environment into the project
+    Generate {
+        what: generate::Generated,
 
-    if kpse_out.status.success() {
-        Ok(PathBuf::from(String::from_utf8(kpse_out.stdout).expect("kpsewhich output is utf-8").trim()))
-    } else {
-        Err(DtMgrError::CommandStatus { command: "kpsewhich -var-value=TEXMFROOT".to_owned(), code: kpse_out.status.code() })
-    }
-}
+        /// Overwrite the file if it already exists
+        #[arg(long)]
+        force: bool,
+    },
 
-fn get_texlive_platform() -> Result<String, DtMgrError> {
-    let tlmgr_out = cmd_crossplatform_static_args(["tlmgr", "print-platform"])
-        .output().map_err(|e| DtMgrError::CommandExecution { source: e })?;
+    /// Show whether the environment matches dtmgr.toml
+    Status {
+        #[arg(long, value_enum, default_value_t)]
+        format: output::OutputFormat,
 
-    if tlmgr_out.status.success() {
-        Ok(String::from_utf8(tlmgr_out.stdout).expect("tlmgr output is utf-8").trim().to_owned())
-    } else {
-        Err(DtMgrError::CommandStatus { command: "tlmgr print-platform".to_owned(), code: tlmgr_out.status.code() })
-    }
-}
+        /// Only warn if the environment is out of date, or install it if `auto-sync` is set, as
+        /// the hooks from `dtmgr generate git-hooks` do
+        #[arg(long)]
+        hook: bool,
 
-fn install_packages_globally<'a, I, S>(packages: I) -> Result<(), DtMgrError>
-where
-    I: IntoIterator<Item = &'a S>,
-    S: AsRef<str> + 'a {
-    let mut packages_vec: Vec<&'a str> = Vec::new();
-    for package in packages.into_iter() {
-        packages_vec.push(package.as_ref());
-    }
+        /// Print only `ok`, `stale`, `missing` or `broken-links:<count>`, for shell prompts
+        #[arg(long, conflicts_with_all = ["format", "hook"])]
+        porcelain: bool,
+    },
 
-    let mut cmd = cmd_crossplatform_static_args(["tlmgr", "install"].into_iter().chain(packages_vec.iter().copied()));
-    let out = cmd.status()
-        .map_err(|e| DtMgrError::CommandExecution { source: e })?;
+    /// List every package in the environment
+    List {
+        #[arg(long, value_enum, default_value_t)]
+        format: output::OutputFormat,
+    },
 
-    if out.success() {
-        Ok(())
-    } else {
-        Err(DtMgrError::CommandStatus { command: "tlmgr install ".to_owned() + packages_vec.join(" ").as_str(), code: out.code() })
-    }
-}
+    /// Show the dependencies pulled in by each direct dependency
+    Tree {
+        #[arg(long, value_enum, default_value_t)]
+        format: packages::TreeFormat,
+    },
 
-fn info_about_packages<'a, I, S>(packages: I) -> Result<Vec<TlPObjInfo>, DtMgrError>
-where
-    I: IntoIterator<Item = &'a S>,
-    S: AsRef<str> + 'a {
-    let mut packages_vec: Vec<&'a str> = Vec::new();
-    for package in packages.into_iter() {
-        packages_vec.push(package.as_ref());
-    }
+    /// List packages with newer revisions in the TeX Live repository
+    Outdated {
+        #[arg(long, value_enum, default_value_t)]
+        format: output::OutputFormat,
+    },
 
-    let mut cmd = cmd_crossplatform_static_args(["tlmgr", "info", "--json"].into_iter().chain(packages_vec.iter().copied()));
-    let out = cmd.output()
-        .map_err(|e| DtMgrError::CommandExecution { source: e })?;
-    if out.status.success() {
-        let json = serde_json::from_slice::<Vec<TlPObjInfo>>(out.stdout.as_slice())
-            .map_err(|e| DtMgrError::JsonParse { source: e })?;
-        Ok(json)
-    } else {
-        Err(DtMgrError::CommandStatus { command: "tlmgr info --json ".to_owned() + packages_vec.join(" ").as_str(), code: out.status.code() })
-    }
-}
+    /// Update the environment's packages in TeX Live to the repository's revisions, then
+    /// reinstall the environment
+    Update {
+        /// Show each package's revision now and after the update, with the change in size and
+        /// files, without changing anything
+        #[arg(long)]
+        dry_run: bool,
 
-fn find_dtmgr_directory() -> Result<PathBuf, DtMgrError> {
-    let initial: &Path = &*std::env::current_dir()
-        .map_err(|e| DtMgrError::CurrentDirectory { source: e })?;
-    let mut cwd: Option<&Path> = Some(initial);
+        #[arg(long, value_enum, default_value_t, requires = "dry_run")]
+        format: output::OutputFormat,
+    },
 
-    while let Some(here) = cwd {
-        if here.join(CONFIG_FILE_NAME).exists() {
-            return Ok(PathBuf::from(here));
-        }
+    /// Compare the environment's packages to what dtmgr.toml resolves to
+    Diff {
+        #[arg(long, value_enum, default_value_t)]
+        format: output::OutputFormat,
+    },
 
-        cwd = here.parent();
-    }
+    /// Check that every package file is linked into the environment
+    Verify {
+        #[arg(long, value_enum, default_value_t)]
+        format: output::OutputFormat,
 
-    Err(DtMgrError::FindConfig { cwd: initial.to_owned() })
-}
+        /// Also read every linked file and compare it with the checksums recorded at install,
+        /// to find files changed in TeX Live underneath the environment
+        #[arg(long)]
+        deep: bool,
+    },
 
-fn parse_config(path_to_dtmgr_toml: impl AsRef<Path>) -> Result<DtMgrConfig, DtMgrError> {
-    let content = std::fs::read_to_string(&path_to_dtmgr_toml)
-        .map_err(|e| DtMgrError::ReadFile { path: path_to_dtmgr_toml.as_ref().to_owned(), source: e })?;
+    /// Link the packages TeX Live updated or broke underneath the environment again, rerunning
+    /// only the steps they affect, rather than installing it anew
+    Repair {},
+
+    /// Run a command from [scripts] in the environment, or list them
+    #[command(alias = "r")]
+    RunScript {
+        /// The script's name, then extra arguments passed to it
+        // one list, so that arguments like `-v` go to the script rather than dtmgr
+        #[arg(allow_hyphen_values = true, trailing_var_arg = true, value_name = "NAME")]
+        name_and_args: Vec<String>,
+    },
 
-    toml::from_str(content.as_str())
-        .map_err(|e|DtMgrError::ParseConfig { source: e })
-}
+    /// Archive the environment with the files its links lead to, for machines that cannot
+    /// install the packages themselves
+    Pack {
+        /// Where to write the archive, by default `dtmgr-environment-<platform>.tar.gz`
+        #[arg(short, long, value_name = "FILE")]
+        output: Option<PathBuf>,
+    },
 
-fn hash_config(config: &DtMgrConfig) -> Result<String, DtMgrError> {
-    let mut hasher = Sha3_256::new();
-    let config_bytes = postcard::to_stdvec(&config)
-        .map_err(|e| DtMgrError::HashConfig { source: e })?;
-    hasher.update(config_bytes);
-    let hash: [u8; 32] = hasher.finalize().into();
-    Ok(hex::encode(hash))
-}
+    /// Write the files TeX reads from the environment as a bundle for Tectonic
+    Bundle {
+        #[arg(long, value_enum, default_value_t)]
+        format: bundle::BundleFormat,
 
-fn make_dot_dir(dot_dir: impl AsRef<Path>) -> Result<(), DtMgrError> {
-    std::fs::create_dir(&dot_dir)
-        .map_err(|e| DtMgrError::CreateDirectory { dir: dot_dir.as_ref().to_owned(), source: e })
-}
+        /// Where to write the bundle, by default `dtmgr-bundle.<format>`
+        #[arg(short, long, value_name = "FILE")]
+        output: Option<PathBuf>,
+    },
 
-fn make_config_and_var(dot_dir: impl AsRef<Path>) -> Result<(), DtMgrError> {
-    std::fs::create_dir(dot_dir.as_ref().join("texmf-config"))
-        .map_err(|e| DtMgrError::CreateDirectory { dir: dot_dir.as_ref().to_owned(), source: e })?;
-    std::fs::create_dir(dot_dir.as_ref().join("texmf-var"))
-        .map_err(|e| DtMgrError::CreateDirectory { dir: dot_dir.as_ref().to_owned(), source: e })
-}
+    /// Replace the environment with one archived by `dtmgr pack`
+    Restore {
+        archive: PathBuf,
+    },
 
-fn make_dot_dir_version_file(dot_dir: impl AsRef<Path>, config: &DtMgrConfig) -> Result<(), DtMgrError> {
-    let version_file = dot_dir.as_ref().join("version");
-    let config_hash = hash_config(&config)?;
-    std::fs::write(&version_file, config_hash)
-        .map_err(|e| DtMgrError::WriteFile { file: version_file, source: e })
-}
+    /// Inspect or clean up dtmgr's cache of git and CTAN downloads, shared by all projects
+    Cache {
+        #[command(subcommand)]
+        command: cache::CacheCommand,
+    },
 
-fn build_dependency_tree(config: &DtMgrConfig, tlmgr_platform: impl AsRef<str>) -> Result<Map<String, TlPObjInfo>, DtMgrError> {
-    let mut queue: Set<String> = Set::new();
-    queue.insert(String::from("texlive.infra"));
-    queue.insert(String::from("kpathsea"));
+    /// Pick the fastest CTAN mirror for this machine, which tlmgr and CTAN downloads then use
+    Mirror {
+        #[command(subcommand)]
+        command: mirror::MirrorCommand,
+    },
 
-    // TODO check this for other platforms
-    if cfg!(windows) {
-        queue.insert(String::from("tlperl.windows"));
-    }
+    /// Install, list or remove the TeX Live releases dtmgr manages for projects with `toolchain`
+    Toolchain {
+        #[command(subcommand)]
+        command: toolchain::ToolchainCommand,
+    },
 
-    for dep in config.dependencies.iter() {
-        queue.insert(dep.clone());
-    }
+    /// Run a command in the environment in each [workspace] member's directory
+    Exec {
+        /// Run it in every member
+        #[arg(long, required_unless_present = "member")]
+        all: bool,
+        /// Run it only in this member
+        #[arg(long, conflicts_with = "all")]
+        member: Option<String>,
+        // one list, so that arguments like `-v` go to the program rather than dtmgr
+        #[arg(allow_hyphen_values = true, trailing_var_arg = true, required = true, value_name = "PROGRAM")]
+        program_and_args: Vec<String>,
+    },
 
-    let mut result: Map<String, TlPObjInfo> = Map::new();
-    while !queue.is_empty() {
-        let info = info_about_packages(&queue)?;
-        queue.clear();
-
-        for tlpobjinfo in info.into_iter() {
-            if let Some(depends) = &tlpobjinfo.depends {
-                for dep in depends.iter() {
-                    let true_dep = if dep.ends_with(".ARCH") {
-                        &(String::from(&dep[0..dep.len() - ".ARCH".len()]) + "." + tlmgr_platform.as_ref())
-                    } else {
-                        dep
-                    };
-                    if !result.contains_key(true_dep) {
-                        queue.insert(true_dep.clone());
-                    }
-                }
-            }
-            result.insert(tlpobjinfo.name.clone(), tlpobjinfo);
-        }
-    }
+    /// Update TeX Live with `tlmgr update --self --all`, then reinstall the environment of every
+    /// project `dtmgr install` ran in that the update outdated
+    UpgradeTexlive {
+        /// Don't update TeX Live, only reinstall what an earlier update outdated
+        #[arg(long)]
+        no_update: bool,
+    },
 
-    Ok(result)
-}
+    /// List or forget the projects `dtmgr install` ran in, which `upgrade-texlive` and
+    /// `prune-global` go through
+    Projects {
+        #[command(subcommand)]
+        command: projects::ProjectsCommand,
+    },
 
-#[cfg(windows)]
-fn create_symlink(target: impl AsRef<Path>, name: impl AsRef<Path>) -> std::io::Result<()> {
-    if target.as_ref().is_dir() {
-        std::os::windows::fs::symlink_dir(target, name)
-    } else {
-        std::os::windows::fs::symlink_file(target, name)
-    }
-}
-#[cfg(unix)]
-fn create_symlink(target: impl AsRef<Path>, name: impl AsRef<Path>) -> std::io::Result<()> {
-    std::os::unix::fs::symlink(target, name)
-}
+    /// Remove packages dtmgr had tlmgr install that no project `dtmgr install` ran in needs
+    /// anymore, asking first
+    PruneGlobal {
+        /// Only list the packages that would be removed
+        #[arg(long)]
+        dry_run: bool,
+        /// Remove them without asking
+        #[arg(long, short = 'y')]
+        yes: bool,
+    },
 
-fn create_texlive_copy(old_root: impl AsRef<Path>, new_root: impl AsRef<Path>, relative: impl AsRef<Path>) -> Result<(), DtMgrError> {
-    let full_old = old_root.as_ref().join(&relative);
-    let full_new = new_root.as_ref().join(relative);
-    let parent_dir = full_new.parent().expect("a path created by a join should have a parent");
-    std::fs::create_dir_all(parent_dir)
-        .map_err(|e| DtMgrError::CreateDirectory { dir: parent_dir.to_owned(), source: e })?;
+    /// Browse the environment's packages, broken links and last install log in the terminal,
+    /// adding, removing and installing packages as you go
+    Ui {},
 
-    std::fs::copy(full_old, &full_new)
-        .map_err(|e| DtMgrError::WriteFile { file: full_new, source: e })?;
-    Ok(())
-}
+    /// Replace this dtmgr with the latest release from GitHub
+    SelfUpdate {
+        /// Only tell whether there is a newer release
+        #[arg(long)]
+        check: bool,
+    },
 
-fn create_texlive_hardlink(old_root: impl AsRef<Path>, new_root: impl AsRef<Path>, relative: impl AsRef<Path>) -> Result<(), DtMgrError> {
-    let full_old = old_root.as_ref().join(&relative);
-    let full_new = new_root.as_ref().join(relative);
-    let parent_dir = full_new.parent().expect("a path created by a join should have a parent");
-    std::fs::create_dir_all(parent_dir)
-        .map_err(|e| DtMgrError::CreateDirectory { dir: parent_dir.to_owned(), source: e })?;
+    /// Print a JSON Schema of dtmgr.toml, for editors that complete and check TOML with one
+    Schema {},
 
-    match std::fs::hard_link(&full_old, &full_new) {
-        Ok(()) => { Ok(()) }
-        Err(_) => {
-            std::fs::copy(full_old, &full_new)
-                .map_err(|e| DtMgrError::WriteFile { file: full_new, source: e })?;
-            Ok(())
-        }
-    }
+    /// Write man pages for dtmgr and each of its subcommands, for packaging
+    #[command(hide = true)]
+    Man {
+        /// The directory to write the pages into
+        #[arg(long, default_value = ".")]
+        out_dir: PathBuf,
+    },
 }
 
-fn create_texlive_symlink(old_root: impl AsRef<Path>, new_root: impl AsRef<Path>, relative: impl AsRef<Path>) -> Result<(), DtMgrError> {
-    let full_old = old_root.as_ref().join(&relative);
-    let full_new = new_root.as_ref().join(relative);
-    let parent_dir = full_new.parent().expect("a path created by a join should have a parent");
-    std::fs::create_dir_all(parent_dir)
-        .map_err(|e| DtMgrError::CreateDirectory { dir: parent_dir.to_owned(), source: e })?;
-
-    create_symlink(&full_old, &full_new)
-        .map_err(|e| DtMgrError::CreateSymlink { src: full_old, dst: full_new, source: e })
+/// Hands the terminal over to `cmd`: dtmgr is replaced by it, so signals and the exit status reach
+/// whoever started dtmgr unchanged. Only returns if `cmd` could not be started.
+#[cfg(unix)]
+fn exec_tool(mut cmd: Command) -> Result<ExitCode, DtMgrError> {
+    use std::os::unix::process::CommandExt;
+    Err(DtMgrError::CommandExecution { source: cmd.exec() })
 }
 
-fn do_symlinks(old_root: impl AsRef<Path>, new_root: impl AsRef<Path>, platform: impl AsRef<str>, pkg: &TlPObjInfo) -> Result<(), DtMgrError> {
-    if let Some(binfiles) = &pkg.binfiles {
-        if let Some(arch_binfiles) = binfiles.get(platform.as_ref()) {
-            for file in arch_binfiles.iter() {
-                let parse = PathBuf::from(file);
+/// Runs `cmd` and exits with its full exit code, see [`signals::exit_like`]
+#[cfg(not(unix))]
+fn exec_tool(mut cmd: Command) -> Result<ExitCode, DtMgrError> {
+    let status = signals::status(&mut cmd)
+        .map_err(|e| DtMgrError::CommandExecution { source: e })?;
+    signals::exit_like(status)
+}
 
-                // We need to hardlink or copy because abs_path resolves symbolic links
-                if (cfg!(windows) && parse.ends_with("kpsewhich.exe")) || parse.ends_with("kpsewhich") {
-                    create_texlive_hardlink(&old_root, &new_root, parse)?;
-                } else {
-                    create_texlive_symlink(&old_root, &new_root, parse)?;
+fn run(cli: Cli) -> Result<ExitCode, DtMgrError> {
+    match cli.command {
+        Commands::Install { dry_run: false, timings, no_dev, .. } => {
+            if no_dev {
+                set_no_dev();
+            }
+            let dtmgr_directory = find_dtmgr_directory()?;
+            install(&dtmgr_directory)?;
+            if let Some(format) = timings {
+                // an environment that was up to date shows the install that made it
+                let timings = state::State::read(&dtmgr_directory.join(".dtmgr"))?
+                    .map(|state| state.timings)
+                    .unwrap_or_default();
+                match format {
+                    output::OutputFormat::Human => timings.print(),
+                    output::OutputFormat::Json => output::print_json(&timings)?,
                 }
             }
+            Ok(ExitCode::SUCCESS)
         }
-    }
-    if let Some(docfiles) = &pkg.docfiles {
-        for file in docfiles.iter() {
-            let parse = PathBuf::from(&file.file);
-            create_texlive_symlink(&old_root, &new_root, parse)?;
+        Commands::Install { dry_run: true, format, no_dev, .. } => {
+            if no_dev {
+                set_no_dev();
+            }
+            let dtmgr_directory = find_dtmgr_directory()?;
+            let config =
+                parse_config(dtmgr_directory.join(CONFIG_FILE_NAME))?;
+
+            let dot_dir = dtmgr_directory.join(".dtmgr");
+            let environment = status::environment_state(&dot_dir, &config)?;
+            let previous = match environment {
+                status::EnvironmentState::Incompatible => None,
+                _ => manifest::Manifest::read(&dot_dir)?,
+            };
+            let platform = texlive_platform(&config)?;
+            let dep_tree = build_dependency_tree(&config, &platform)?;
+
+            let plan = plan::install_plan(&config, environment, previous.as_ref(), &dep_tree, &platform);
+            match format {
+                output::OutputFormat::Human => plan.print(),
+                output::OutputFormat::Json => output::print_json(&plan)?,
+            }
+            Ok(ExitCode::SUCCESS)
         }
-    }
-    if let Some(runfiles) = &pkg.runfiles {
-        for file in runfiles.iter() {
-            let parse = PathBuf::from(file);
-
-            if parse.ends_with("updmap.cfg") {
-                // updmap.cfg needs to be copied to be updated with updmap-sys --syncwithtrees
-                create_texlive_copy(&old_root, &new_root, parse)?;
-            } else if cfg!(windows) && parse.extension().is_some_and(|s| s.to_str() == Some("otf")) {
-                // https://github.com/lunarmodules/luafilesystem/issues/184
-                create_texlive_hardlink(&old_root, &new_root, parse)?;
-            } else {
-                create_texlive_symlink(&old_root, &new_root, parse)?;
+        Commands::Check { fix } => {
+            let dtmgr_directory = find_dtmgr_directory()?;
+            let config =
+                parse_config(dtmgr_directory.join(CONFIG_FILE_NAME))?;
+            let platform = texlive_platform(&config)?;
+
+            let graph = check::check(&config, &platform)?;
+            let mut redundant = Vec::new();
+            for anomaly in graph.anomalies() {
+                match anomaly {
+                    // `% !dtmgr dep:` comments are not dtmgr's to edit, so those packages are only warned about
+                    graph::Anomaly::Redundant { chain } if fix && chain.last().is_some_and(|name| !config.declared_in.contains_key(name)) =>
+                        redundant.extend(chain.last().cloned()),
+                    _ => warn!("{}", anomaly),
+                }
+            }
+            println!("{} is fine: {} packages from TeX Live", CONFIG_FILE_NAME, graph.len());
+            if !redundant.is_empty() {
+                let removed = config_edit::remove_dependencies(dtmgr_directory.join(CONFIG_FILE_NAME), &redundant)?;
+                println!("removed {} from {}, as other dependencies bring them in", removed.join(", "), CONFIG_FILE_NAME);
             }
+            Ok(ExitCode::SUCCESS)
         }
-    }
-    // TODO check if this is correct
-    if let Some(srcfiles) = &pkg.srcfiles {
-        for file in srcfiles.iter() {
-            let parse = PathBuf::from(file);
-            create_texlive_symlink(&old_root, &new_root, parse)?;
+        Commands::Run { ensure, keep_path, cwd, env, restricted, program_and_args } => {
+            let dtmgr_directory = find_dtmgr_directory()?;
+            let mut config =
+                parse_config(dtmgr_directory.join(CONFIG_FILE_NAME))?;
+            sync_or_warn(&dtmgr_directory, &config, ensure || config.auto_sync)?;
+            let mut cmd = if keep_path {
+                let mut cmd = cmd_crossplatform_in_path(&program_and_args, None);
+                cmd.envs(dtmgr_environment(&dtmgr_directory)?.into_iter().filter(|(name, _)| *name != "PATH"));
+                cmd
+            } else {
+                run_tool_in_dtmgr(&program_and_args)?
+            };
+            if let Some(cwd) = cwd {
+                if !cwd.is_dir() {
+                    return Err(DtMgrError::WorkingDirectoryNotFound { dir: cwd });
+                }
+                cmd.current_dir(cwd);
+            }
+
+            // so that e.g. a manual `dtmgr run bibtex` finds what `dtmgr build` wrote
+            config.build.apply_output_environment(&mut cmd, &dtmgr_directory)?;
+            config.paths.apply_environment(&mut cmd, &dtmgr_directory)?;
+            config.run.filter_environment(&mut cmd, &config.env);
+            cmd.envs(env);
+            config.run.restricted |= restricted;
+            config.run.restrict(&mut cmd, &dtmgr_directory)?;
+
+            exec_tool(cmd)
         }
-    }
+        Commands::Build { target, all, watch, args } => {
+            let dtmgr_directory = find_dtmgr_directory()?;
+            let mut config =
+                parse_config(dtmgr_directory.join(CONFIG_FILE_NAME))?;
 
-    // TODO currently these are all handled by the tools executed later, but I'm not sure if that's right
-    // if let Some(executes) = &pkg.executes {
-    //     executes.iter().for_each(|e| {
-    //         post_installs.push("executes [".to_owned() + &pkg.name + "]: " + e)
-    //     })
-    // }
-    // if let Some(postactions) = &pkg.postactions {
-    //     postactions.iter().for_each(|p| {
-    //         post_installs.push("postactions [".to_owned() + &pkg.name + "]: " + p)
-    //     })
-    // }
-
-    Ok(())
-}
+            sync_or_warn(&dtmgr_directory, &config, config.auto_sync)?;
+            let mut targets = build::select_targets(&config.build, &config.documents, target.as_deref(), all)?;
+            let status = build_documents(&dtmgr_directory, &config, &targets, &args)?;
+            if !watch {
+                signals::exit_like(status);
+            }
 
-fn replace_path_env(old_path_env: impl AsRef<OsStr>, target: impl AsRef<Path>, replacement: impl AsRef<Path>) -> OsString {
-    let mut result = Vec::new();
-
-    // TODO check for non-existence of target
-    for part in std::env::split_paths(old_path_env.as_ref()) {
-        let entry = PathBuf::from(part);
-        let new_path = if entry.starts_with(&target) {
-            let relative = entry.strip_prefix(&target)
-                .expect("strip_prefix failed even though starts_with already checked");
-            replacement.as_ref().join(relative)
-        } else {
-            entry
-        };
-        result.push(new_path.into_os_string());
-    }
+            let ignored: Vec<PathBuf> = targets.iter()
+                .filter_map(|t| t.config.output_dir().cloned())
+                .collect();
+            println!("watching {} for changes", dtmgr_directory.display());
+            watch::watch_project(&dtmgr_directory, &ignored, |change| {
+                // failures are reported and the watch goes on, as with latexmk -pvc
+                if change == watch::Change::Config {
+                    println!("{} changed, reinstalling", CONFIG_FILE_NAME);
+                    let reinstalled = parse_config(dtmgr_directory.join(CONFIG_FILE_NAME))
+                        .and_then(|new_config| {
+                            targets = build::select_targets(&new_config.build, &new_config.documents, target.as_deref(), all)?;
+                            install(&dtmgr_directory)?;
+                            Ok(new_config)
+                        });
+                    // the build runs with what the environment was just installed from, e.g. new
+                    // `[paths]`, `[env]` or `[run]` settings
+                    match reinstalled {
+                        Ok(new_config) => config = new_config,
+                        Err(e) => {
+                            error!("{}", e);
+                            return Ok(());
+                        }
+                    }
+                }
 
-    std::env::join_paths(result).expect("replacing path shouldn't create invalid path")
-}
+                match build_documents(&dtmgr_directory, &config, &targets, &args) {
+                    Ok(status) if !status.success() => warn!("latexmk failed ({})", status),
+                    Ok(_) => {}
+                    Err(e) => error!("{}", e),
+                }
+                Ok(())
+            })?;
 
-fn run_tool_in_dtmgr<I, S>(exe_and_args: I) -> Result<Command, DtMgrError>
-where
-    I: IntoIterator<Item = S>,
-    S: AsRef<OsStr> {
-    // TODO move this to function parameter
-    let dtmgr_directory = find_dtmgr_directory()?;
-    let dot_dir = dtmgr_directory.join(".dtmgr");
-    let dot_dir_str = dot_dir.to_str()
-        .expect(".dtmgr path should be a str");
-
-    let dot_dir_web2c = dot_dir.join("texmf-dist").join("web2c");
-    let dot_dir_web2c_str = dot_dir_web2c.to_str()
-        .expect(".dtmgr/texmf-dist/web2c should be a str");
-
-    // TODO move this to function parameter
-    let old_root = get_texlive_root()?;
-
-    // TODO maybe this needs a cfg()
-    let old_path = std::env::var("PATH")
-        .expect("there should be a PATH variable");
-
-    // TODO move this to function parameter
-    let new_path = replace_path_env(&old_path, &old_root, &dot_dir);
-    let mut cmd = cmd_crossplatform_static_args(exe_and_args);
-    cmd.env("PATH", &new_path);
-
-    // TODO move this to function parameter
-    let mut texmfcnf = String::new();
-    texmfcnf.push_str(dot_dir_str);
-    texmfcnf.push(KPSE_SEPARATOR);
-    texmfcnf.push_str(dot_dir_web2c_str);
-    cmd.env("TEXMFCNF", texmfcnf);
-
-    Ok(cmd)
-}
+            Ok(ExitCode::SUCCESS)
+        }
+        Commands::Test { args } => {
+            let dtmgr_directory = find_dtmgr_directory()?;
+            let config =
+                parse_config(dtmgr_directory.join(CONFIG_FILE_NAME))?;
 
-fn run() -> Result<ExitCode, DtMgrError> {
-    let cli = Cli::parse();
+            sync_or_warn(&dtmgr_directory, &config, config.auto_sync)?;
+            let mut command = match &config.test.command {
+                Some(command) => command_line::split_command_line(command)?,
+                None => vec![String::from("l3build"), String::from("check")],
+            };
+            command.extend(args);
+
+            let mut cmd = run_tool_in_dtmgr(&command)?;
+            cmd.current_dir(&dtmgr_directory);
+            config.paths.apply_environment(&mut cmd, &dtmgr_directory)?;
+            config.run.filter_environment(&mut cmd, &config.env);
+            config.run.restrict(&mut cmd, &dtmgr_directory)?;
+            let status = signals::status(&mut cmd)
+                .map_err(|e| DtMgrError::CommandExecution { source: e })?;
 
-    match cli.command {
-        Commands::Install {} => {
+            signals::exit_like(status)
+        }
+        Commands::Info { package, files, format } => {
             let dtmgr_directory = find_dtmgr_directory()?;
+            let config =
+                parse_config(dtmgr_directory.join(CONFIG_FILE_NAME))?;
 
+            info::show(&config, &package, files, format)?;
+            Ok(ExitCode::SUCCESS)
+        }
+        Commands::Docs { package, list } => {
+            let dtmgr_directory = find_dtmgr_directory()?;
             let config =
                 parse_config(dtmgr_directory.join(CONFIG_FILE_NAME))?;
 
-            let dot_dir = dtmgr_directory.join(".dtmgr");
-            if dot_dir.is_dir() {
-                let version_file = dot_dir.join("version");
-                if version_file.is_file() {
-                    let version_contents = std::fs::read_to_string(&version_file)
-                        .map_err(|e| DtMgrError::ReadFile { path: version_file.to_owned(), source: e })?;
-                    let config_hash = hash_config(&config)?;
-                    if version_contents == config_hash {
-                        // TODO do actual logging
-                        println!("Up-to-date");
-                        return Ok(ExitCode::SUCCESS);
-                    }
-                }
+            let platform = texlive_platform(&config)?;
+            let dep_tree = build_dependency_tree(&config, &platform)?;
+            let Some(pkg) = dep_tree.get(&package) else {
+                return Err(DtMgrError::NotInEnvironment { package });
+            };
 
-                match std::fs::remove_dir_all(&dot_dir) {
-                    Ok(()) => {}
-                    Err(e) => return Err(DtMgrError::RemoveDirectory { dir: dot_dir, source: e })
-                }
+            if list {
+                docs::print_pdf_docfiles(pkg, &dtmgr_directory.join(".dtmgr"));
+                return Ok(ExitCode::SUCCESS);
             }
 
-            let root = get_texlive_root()?;
-            let platform = get_texlive_platform()?;
-
-            // TODO log progress here
-            make_dot_dir(&dot_dir)?;
+            // texdoc searches the environment's tree, so only linked docfiles are found
+            let status = signals::status(&mut run_tool_in_dtmgr(["texdoc", package.as_str()])?)
+                .map_err(|e| DtMgrError::CommandExecution { source: e })?;
+            signals::exit_like(status)
+        }
+        Commands::Editor { editor, texlab } => {
+            let dtmgr_directory = find_dtmgr_directory()?;
+            let config =
+                parse_config(dtmgr_directory.join(CONFIG_FILE_NAME))?;
 
-            match install_packages_globally(&config.dependencies) {
-                Err(DtMgrError::CommandStatus { code: Some(2), .. }) => println!("not performing install assuming tlmgr needs sudo. continuing..."),
-                other => other?,
+            let environment = editor::EditorEnvironment {
+                dtmgr: std::env::current_exe()
+                    .map_err(|e| DtMgrError::CurrentExecutable { source: e })?,
+                bin_dir: dtmgr_directory.join(".dtmgr").join("bin").join(texlive_platform(&config)?),
+                build: &config.build,
             };
+            let settings_file = match editor {
+                editor::Editor::Vscode => editor::write_vscode_settings(&dtmgr_directory, &environment, texlab)?,
+            };
+            println!("wrote {}", settings_file.display());
 
-            let dep_tree = build_dependency_tree(&config, &platform)?;
-            for tlpobj in dep_tree.values() {
-                do_symlinks(&root, &dot_dir, &platform, tlpobj)?;
-            }
+            Ok(ExitCode::SUCCESS)
+        }
+        Commands::Env { format } => {
+            let dtmgr_directory = find_dtmgr_directory()?;
+            let config =
+                parse_config(dtmgr_directory.join(CONFIG_FILE_NAME))?;
+            let mut variables = dtmgr_environment(&dtmgr_directory)?;
+            variables.extend(config.paths.environment(&dtmgr_directory)?);
+            print!("{}", env::render_env(format, &variables)?);
 
-            make_config_and_var(&dot_dir)?;
+            Ok(ExitCode::SUCCESS)
+        }
+        Commands::Generate { what, force } => {
+            let dtmgr_directory = find_dtmgr_directory()?;
+            let config =
+                parse_config(dtmgr_directory.join(CONFIG_FILE_NAME))?;
 
-            // TODO turn these expects into errors
-            let mktexlsr_status = run_tool_in_dtmgr(["mktexlsr"])?
-                .status().map_err(|e| DtMgrError::CommandExecution { source: e })?;
-            if !mktexlsr_status.success() {
-                return Err(DtMgrError::CommandStatus { command: "mktexlsr".to_owned(), code: mktexlsr_status.code() })
+            let files = match what {
+                generate::Generated::Latexmkrc => vec![(dtmgr_directory.join(generate::LATEXMKRC_FILE_NAME), generate::latexmkrc(&config.build))],
+                generate::Generated::GitHooks => generate::git_hooks(&dtmgr_directory)?,
+            };
+            if let Some((file, _)) = files.iter().find(|(file, _)| file.exists()) && !force {
+                return Err(DtMgrError::ConfigExists { path: file.clone() });
             }
 
-            let fmtutil_missing_status = run_tool_in_dtmgr(["fmtutil-sys", "--missing", "--no-strict"])?
-                .status().map_err(|e| DtMgrError::CommandExecution { source: e })?;
-            if !fmtutil_missing_status.success() {
-                return Err(DtMgrError::CommandStatus { command: "fmtutil-sys --missing --no-strict".to_owned(), code: fmtutil_missing_status.code() })
+            for (file, content) in files {
+                std::fs::write(&file, content)
+                    .map_err(|e| DtMgrError::WriteFile { file: file.clone(), source: e })?;
+                if what.executable() {
+                    launchers::make_executable(&file)?;
+                }
+                println!("wrote {}", file.display());
             }
 
-            let mut updmap_sync = run_tool_in_dtmgr(["updmap-sys", "--syncwithtrees"])?
-                .stdin(Stdio::piped())
-                .spawn()
-                .map_err(|e| DtMgrError::CommandExecution { source: e })?;
-            let updmap_sync_stdin = updmap_sync.stdin.as_mut()
-                .expect("should be able to open stdin of subprocess");
-            updmap_sync_stdin.write_all(b"y\n")
-                .expect("should be able to write to stdin of subprocess");
-            let updmap_sync_status = updmap_sync.wait()
-                .map_err(|e| DtMgrError::CommandExecution { source: e })?;
-            if !updmap_sync_status.success() {
-                return Err(DtMgrError::CommandStatus { command: "updmap-sys --syncwithtrees".to_owned(), code: updmap_sync_status.code() })
+            Ok(ExitCode::SUCCESS)
+        }
+        Commands::UpgradeTexlive { no_update } => {
+            if !no_update {
+                update_texlive()?;
             }
 
-            let updmap_status = run_tool_in_dtmgr(["updmap-sys"])?
-                .status().map_err(|e| DtMgrError::CommandExecution { source: e })?;
-            if !updmap_status.success() {
-                return Err(DtMgrError::CommandStatus { command: "updmap-sys".to_owned(), code: updmap_status.code() })
+            let mut registry = projects::Registry::read()?;
+            let mut gone = Vec::new();
+            // every project is tried, so one broken project does not keep the others outdated
+            let mut failed = Vec::new();
+            for dir in registry.projects.keys() {
+                let config_file = dir.join(CONFIG_FILE_NAME);
+                if !config_file.is_file() {
+                    gone.push(dir.clone());
+                    continue;
+                }
+                let state = parse_config(config_file)
+                    .and_then(|config| status::environment_state(&dir.join(".dtmgr"), &config));
+                let result = match state {
+                    Ok(status::EnvironmentState::Outdated | status::EnvironmentState::Incompatible) => {
+                        eprintln!("{}", color::emphasis(&format!("==> {}", dir.display()), color::Stream::Stderr));
+                        install(dir)
+                    }
+                    Ok(_) => Ok(()),
+                    Err(e) => Err(e),
+                };
+                if let Err(e) = result {
+                    error_report::report_error(&e, cli.error_format);
+                    failed.push(dir.display().to_string());
+                }
             }
 
-            make_dot_dir_version_file(&dot_dir, &config)?;
-
+            if !gone.is_empty() {
+                for dir in gone.iter() {
+                    info!("forgetting {}, which has no {} anymore", dir.display(), CONFIG_FILE_NAME);
+                    registry.projects.remove(dir);
+                }
+                registry.write()?;
+            }
+            if failed.is_empty() {
+                Ok(ExitCode::SUCCESS)
+            } else {
+                error!("failed to reinstall {}", failed.join(", "));
+                Ok(ExitCode::FAILURE)
+            }
+        }
+        Commands::Projects { command } => {
+            projects::run_projects_command(command)?;
             Ok(ExitCode::SUCCESS)
         }
-        Commands::Run { program, args } => {
-            let mut cmd = run_tool_in_dtmgr([program].iter().chain(args.iter()))?;
-            let status = cmd.status()
-                .map_err(|e| DtMgrError::CommandExecution { source: e })?;
+        Commands::PruneGlobal { dry_run, yes } => {
+            let root = get_texlive_root()?;
+            let pruning = prune::unneeded_packages(&root)?;
+            if !pruning.unknown.is_empty() {
+                for dir in pruning.unknown.iter() {
+                    warn!("{} has no installed environment, so the packages it needs are unknown", dir.display());
+                }
+                eprintln!("run `dtmgr install` in these projects, or remove them, before pruning");
+                return Ok(ExitCode::FAILURE);
+            }
+            if pruning.packages.is_empty() {
+                println!("every package dtmgr installed is still needed");
+                return Ok(ExitCode::SUCCESS);
+            }
 
-            match status.code() {
-                Some(code) => Ok(ExitCode::from(code as u8)),
-                None => Ok(ExitCode::FAILURE),
+            let total: u64 = pruning.packages.iter().map(|pkg| pkg.size).sum();
+            for pkg in pruning.packages.iter() {
+                println!("{:>10}  {}", size::format_size(pkg.size), pkg.name);
+            }
+            println!("{} packages, {} in total", pruning.packages.len(), size::format_size(total));
+            if dry_run {
+                return Ok(ExitCode::SUCCESS);
+            }
+            if !yes {
+                if !prompt::interactive() {
+                    eprintln!("pass --yes to remove them without asking");
+                    return Ok(ExitCode::FAILURE);
+                }
+                if !prompt::confirm(&format!("remove them from {}?", root.display())) {
+                    return Ok(ExitCode::SUCCESS);
+                }
             }
+            let names: Vec<String> = pruning.packages.into_iter().map(|pkg| pkg.name).collect();
+            prune::remove(&root, &names)?;
+            Ok(ExitCode::SUCCESS)
         }
-    }
-}
-
-fn main() -> ExitCode {
-    run().unwrap_or_else(|err| {
-        eprintln!("{}", err);
+        Commands::Ui {} => {
+            let dtmgr_directory = find_dtmgr_directory()?;
+            if !prompt::interactive() {
+                eprintln!("`dtmgr ui` needs a terminal; `dtmgr list`, `dtmgr verify` and `dtmgr status` print the same");
+                return Ok(ExitCode::FAILURE);
+            }
+            ui::run(dtmgr_directory)?;
+            Ok(ExitCode::SUCCESS)
+        }
+        Commands::SelfUpdate { check } => {
+            match self_update::check(env!("CARGO_PKG_VERSION"))? {
+                None => println!("dtmgr {} is the latest release", env!("CARGO_PKG_VERSION")),
+                Some(update) if check => println!("dtmgr {} is available (this is {})", update.latest, update.current),
+                Some(update) => {
+                    update.install()?;
+                    println!("updated dtmgr from {} to {}", update.current, update.latest);
+                }
+            }
+
+            Ok(ExitCode::SUCCESS)
+        }
+        Commands::Schema {} => {
+            output::print_json(&config_schema())?;
+            Ok(ExitCode::SUCCESS)
+        }
+        Commands::Man { out_dir } => {
+            for file in man::write_man_pages(Cli::command(), &out_dir)? {
+                println!("wrote {}", file.display());
+            }
+
+            Ok(ExitCode::SUCCESS)
+        }
+        Commands::Status { format, hook, porcelain } => {
+            let dtmgr_directory = find_dtmgr_directory()?;
+            let config =
+                parse_config(dtmgr_directory.join(CONFIG_FILE_NAME))?;
+            if hook {
+                sync_or_warn(&dtmgr_directory, &config, config.auto_sync)?;
+                return Ok(ExitCode::SUCCESS);
+            }
+            if porcelain {
+                println!("{}", status::porcelain(&dtmgr_directory.join(".dtmgr"), &config)?);
+                return Ok(ExitCode::SUCCESS);
+            }
+
+            let dot_dir = dtmgr_directory.join(".dtmgr");
+            let status = status::Status {
+                environment: status::environment_state(&dot_dir, &config)?,
+                last_install: report::InstallReport::read(&dot_dir).map(|report| report.summary()),
+                project_dir: dtmgr_directory,
+                dependencies: config.dependencies.iter().map(String::as_str).collect(),
+            };
+            match format {
+                output::OutputFormat::Human => status.print(),
+                output::OutputFormat::Json => output::print_json(&status)?,
+            }
+            Ok(ExitCode::SUCCESS)
+        }
+        Commands::List { format } => {
+            let dtmgr_directory = find_dtmgr_directory()?;
+            let config =
+                parse_config(dtmgr_directory.join(CONFIG_FILE_NAME))?;
+
+            let platform = texlive_platform(&config)?;
+            let dep_tree = build_dependency_tree(&config, &platform)?;
+
+            let list = packages::package_list(&config, &dep_tree);
+            match format {
+                output::OutputFormat::Human => list.print(),
+                output::OutputFormat::Json => output::print_json(&list)?,
+            }
+            Ok(ExitCode::SUCCESS)
+        }
+        Commands::Tree { format } => {
+            let dtmgr_directory = find_dtmgr_directory()?;
+            let config =
+                parse_config(dtmgr_directory.join(CONFIG_FILE_NAME))?;
+
+            let platform = texlive_platform(&config)?;
+            let dep_tree = build_dependency_tree(&config, &platform)?;
+
+            let tree = packages::dependency_tree(&config, &dep_tree, &platform);
+            match format {
+                packages::TreeFormat::Human => tree.print(),
+                packages::TreeFormat::Json => output::print_json(&tree)?,
+                packages::TreeFormat::Dot => print!("{}", packages::render_dot(&config, &dep_tree, &platform)),
+                packages::TreeFormat::Mermaid => print!("{}", packages::render_mermaid(&config, &dep_tree, &platform)),
+            }
+            Ok(ExitCode::SUCCESS)
+        }
+        Commands::Update { dry_run, format } => {
+            let dtmgr_directory = find_dtmgr_directory()?;
+            let config =
+                parse_config(dtmgr_directory.join(CONFIG_FILE_NAME))?;
+
+            let platform = texlive_platform(&config)?;
+            let dep_tree = build_dependency_tree(&config, &platform)?;
+            let preview = update::preview(&config, &dtmgr_directory.join(".dtmgr"), &dep_tree, &platform)?;
+            if dry_run {
+                match format {
+                    output::OutputFormat::Human => preview.print(),
+                    output::OutputFormat::Json => output::print_json(&preview)?,
+                }
+                return Ok(ExitCode::SUCCESS);
+            }
+            if preview.packages.is_empty() {
+                println!("every package is at the repository's revision");
+                return Ok(ExitCode::SUCCESS);
+            }
+
+            let outdated = preview.outdated_in_texlive(&dep_tree);
+            if !outdated.is_empty() {
+                update::update_packages(&get_texlive_root()?, &outdated)?;
+            }
+            install(&dtmgr_directory)?;
+            Ok(ExitCode::SUCCESS)
+        }
+        Commands::Outdated { format } => {
+            let dtmgr_directory = find_dtmgr_directory()?;
+            let config =
+                parse_config(dtmgr_directory.join(CONFIG_FILE_NAME))?;
+
+            let platform = texlive_platform(&config)?;
+            let dep_tree = build_dependency_tree(&config, &platform)?;
+
+            let report = packages::outdated_packages(&config, &dep_tree);
+            match format {
+                output::OutputFormat::Human => report.print(),
+                output::OutputFormat::Json => output::print_json(&report)?,
+            }
+            Ok(ExitCode::SUCCESS)
+        }
+        Commands::Diff { format } => {
+            let dtmgr_directory = find_dtmgr_directory()?;
+            let config =
+                parse_config(dtmgr_directory.join(CONFIG_FILE_NAME))?;
+
+            let dot_dir = dtmgr_directory.join(".dtmgr");
+            let environment = status::environment_state(&dot_dir, &config)?;
+            let previous = match environment {
+                status::EnvironmentState::Missing => Some(manifest::Manifest::default()),
+                // its manifest may mean something else
+                status::EnvironmentState::Incompatible => None,
+                _ => manifest::Manifest::read(&dot_dir)?,
+            };
+            let platform = texlive_platform(&config)?;
+            let dep_tree = build_dependency_tree(&config, &platform)?;
+            let resolved = manifest::Manifest::from_tree(&dep_tree, &platform);
+
+            let report = manifest::EnvironmentDiff {
+                environment,
+                previous_known: previous.is_some(),
+                diff: previous.unwrap_or_default().diff(&resolved),
+            };
+            match format {
+                output::OutputFormat::Human => report.print(&resolved),
+                output::OutputFormat::Json => output::print_json(&report)?,
+            }
+            Ok(ExitCode::SUCCESS)
+        }
+        Commands::Repair {} => {
+            let dtmgr_directory = find_dtmgr_directory()?;
+            let repaired = repair::repair(&dtmgr_directory)?;
+            if repaired.is_empty() {
+                println!("nothing to repair");
+            }
+            for (name, damage) in repaired.iter() {
+                println!("{}: {}", name, damage.describe());
+            }
+            Ok(ExitCode::SUCCESS)
+        }
+        Commands::Verify { format, deep } => {
+            let dtmgr_directory = find_dtmgr_directory()?;
+            let config =
+                parse_config(dtmgr_directory.join(CONFIG_FILE_NAME))?;
+
+            let dot_dir = dtmgr_directory.join(".dtmgr");
+            let environment = status::environment_state(&dot_dir, &config)?;
+            let platform = texlive_platform(&config)?;
+            let dep_tree = build_dependency_tree(&config, &platform)?;
+
+            let exclusions = exclude::Exclusions::new(&config)?;
+            let mut report = verify::verify_environment(&dot_dir, environment, &dep_tree, &platform, &exclusions);
+            if deep {
+                let manifest = manifest::Manifest::read(&dot_dir)?.unwrap_or_default();
+                let modified = verify::modified_packages(&manifest, &dep_tree, &platform, &exclusions)?;
+                report.ok &= modified.is_empty();
+                report.modified = Some(modified);
+            }
+            match format {
+                output::OutputFormat::Human => report.print(),
+                output::OutputFormat::Json => output::print_json(&report)?,
+            }
+            Ok(if report.ok { ExitCode::SUCCESS } else { ExitCode::FAILURE })
+        }
+        Commands::RunScript { name_and_args } => {
+            let dtmgr_directory = find_dtmgr_directory()?;
+            let config =
+                parse_config(dtmgr_directory.join(CONFIG_FILE_NAME))?;
+
+            let mut name_and_args = name_and_args.into_iter();
+            let Some(name) = name_and_args.next() else {
+                if config.scripts.is_empty() {
+                    println!("no scripts defined in {}", CONFIG_FILE_NAME);
+                }
+                for (name, script) in config.scripts.iter() {
+                    println!("{}: {}", name, script);
+                }
+                return Ok(ExitCode::SUCCESS);
+            };
+
+            let script = config.scripts.get(&name)
+                .ok_or_else(|| DtMgrError::UnknownScript {
+                    name: name.clone(),
+                    available: config.scripts.keys().cloned().collect::<Vec<_>>().join(", "),
+                })?;
+            let mut command = command_line::split_command_line(script)?;
+            command.extend(name_and_args);
+            sync_or_warn(&dtmgr_directory, &config, config.auto_sync)?;
+
+            let mut cmd = run_tool_in_dtmgr(&command)?;
+            cmd.current_dir(&dtmgr_directory);
+            config.build.apply_output_environment(&mut cmd, &dtmgr_directory)?;
+            config.paths.apply_environment(&mut cmd, &dtmgr_directory)?;
+            config.run.filter_environment(&mut cmd, &config.env);
+            config.run.restrict(&mut cmd, &dtmgr_directory)?;
+            exec_tool(cmd)
+        }
+        Commands::Pack { output } => {
+            let dtmgr_directory = find_dtmgr_directory()?;
+            let config =
+                parse_config(dtmgr_directory.join(CONFIG_FILE_NAME))?;
+            sync_or_warn(&dtmgr_directory, &config, config.auto_sync)?;
+
+            let dot_dir = dtmgr_directory.join(".dtmgr");
+            let platform = match manifest::Manifest::read(&dot_dir)?.and_then(|m| m.platform) {
+                Some(platform) => platform,
+                None => texlive_platform(&config)?,
+            };
+            let output = output.unwrap_or_else(|| PathBuf::from(format!("dtmgr-environment-{}.tar.gz", platform)));
+
+            info!("packing {} into {}", dot_dir.display(), output.display());
+            let file = std::fs::File::create(&output)
+                .map_err(|e| DtMgrError::WriteFile { file: output.clone(), source: e })?;
+            let packed = archive::pack(&dtmgr_directory, &dot_dir, true, std::io::BufWriter::new(file))
+                .and_then(|mut writer| std::io::Write::flush(&mut writer)
+                    .map_err(|e| DtMgrError::WriteFile { file: output.clone(), source: e }));
+            if let Err(e) = packed {
+                let _ = std::fs::remove_file(&output);
+                return Err(e);
+            }
+            println!("wrote {}", output.display());
+
+            Ok(ExitCode::SUCCESS)
+        }
+        Commands::Bundle { format, output } => {
+            let dtmgr_directory = find_dtmgr_directory()?;
+            let mut config =
+                parse_config(dtmgr_directory.join(CONFIG_FILE_NAME))?;
+            sync_or_warn(&dtmgr_directory, &config, config.auto_sync)?;
+
+            let dot_dir = dtmgr_directory.join(".dtmgr");
+            let platform = texlive_platform(&config)?;
+            let dev_files = bundle::dev_only_files(&mut config, &platform)?;
+            let output = output.unwrap_or_else(|| PathBuf::from(format!("dtmgr-bundle.{}", format.extension())));
+            info!("bundling {} into {}", dot_dir.display(), output.display());
+            match bundle::write(&config, &dtmgr_directory, &dot_dir, &dev_files, format, &output) {
+                Ok(files) => println!("wrote {} with {} files", output.display(), files),
+                Err(e) => {
+                    let _ = std::fs::remove_file(&output);
+                    return Err(e);
+                }
+            }
+
+            Ok(ExitCode::SUCCESS)
+        }
+        Commands::Restore { archive: archive_file } => {
+            let dtmgr_directory = find_dtmgr_directory()?;
+            let config =
+                parse_config(dtmgr_directory.join(CONFIG_FILE_NAME))?;
+
+            let file = std::fs::File::open(&archive_file)
+                .map_err(|e| DtMgrError::ReadFile { path: archive_file.clone(), source: e })?;
+            // unpacked next to the environment, which is only replaced once all is well
+            let staging = dtmgr_directory.join(".dtmgr.restore");
+            if staging.exists() {
+                std::fs::remove_dir_all(&staging)
+                    .map_err(|e| DtMgrError::RemoveDirectory { dir: staging.clone(), source: e })?;
+            }
+            make_dot_dir(&staging)?;
+            info!("unpacking {}", archive_file.display());
+            let unpacked = archive::unpack(std::io::BufReader::new(file), &dtmgr_directory, &staging)
+                .and_then(|()| manifest::Manifest::read(&staging))
+                .and_then(|manifest| match manifest.and_then(|m| m.platform) {
+                    Some(platform) if platform_runs_here(&platform) == Some(false) => {
+                        let native = native_platform().unwrap_or_default().to_owned();
+                        Err(DtMgrError::ArchivePlatform { platform, native })
+                    }
+                    _ => Ok(()),
+                });
+            if let Err(e) = unpacked {
+                let _ = std::fs::remove_dir_all(&staging);
+                return Err(e);
+            }
+
+            let dot_dir = dtmgr_directory.join(".dtmgr");
+            let backup = dtmgr_directory.join(".dtmgr.previous");
+            if dot_dir.is_dir() {
+                rename_dir(&dot_dir, &backup)?;
+            }
+            rename_dir(&staging, &dot_dir)?;
+            let backup_logs = backup.join(install_log::LOGS_DIR_NAME);
+            if backup_logs.is_dir() {
+                rename_dir(&backup_logs, &dot_dir.join(install_log::LOGS_DIR_NAME))?;
+            }
+            if backup.is_dir() {
+                std::fs::remove_dir_all(&backup)
+                    .map_err(|e| DtMgrError::RemoveDirectory { dir: backup, source: e })?;
+            }
+
+            let state = status::environment_state(&dot_dir, &config)?;
+            if state != status::EnvironmentState::UpToDate {
+                warn!("the archive was made from another {}, so the environment is {}", CONFIG_FILE_NAME, state.describe());
+            }
+            println!("restored the environment from {}", archive_file.display());
+
+            Ok(ExitCode::SUCCESS)
+        }
+        Commands::Cache { command } => {
+            cache::run_cache_command(command)?;
+            Ok(ExitCode::SUCCESS)
+        }
+        Commands::Mirror { command } => {
+            mirror::run_mirror_command(command)?;
+            Ok(ExitCode::SUCCESS)
+        }
+        Commands::Toolchain { command } => {
+            toolchain::run_toolchain_command(command)?;
+            Ok(ExitCode::SUCCESS)
+        }
+        Commands::Exec { all: _, member, program_and_args } => {
+            let dtmgr_directory = find_dtmgr_directory()?;
+            let config =
+                parse_config(dtmgr_directory.join(CONFIG_FILE_NAME))?;
+
+            let members = config.workspace.members(&dtmgr_directory, member.as_deref())?;
+            sync_or_warn(&dtmgr_directory, &config, config.auto_sync)?;
+
+            // every member runs, so one failing paper does not hide the state of the others
+            let mut failed = Vec::new();
+            for member in members.iter() {
+                eprintln!("{}", color::emphasis(&format!("==> {}", member.name), color::Stream::Stderr));
+                let mut cmd = run_tool_in_dtmgr(&program_and_args)?;
+                cmd.current_dir(&member.dir);
+                config.paths.apply_environment(&mut cmd, &dtmgr_directory)?;
+                config.run.filter_environment(&mut cmd, &config.env);
+                config.run.restrict(&mut cmd, &dtmgr_directory)?;
+                let status = signals::status(&mut cmd)
+                    .map_err(|e| DtMgrError::CommandExecution { source: e })?;
+                if !status.success() {
+                    failed.push(member.name.as_str());
+                }
+            }
+
+            if failed.is_empty() {
+                Ok(ExitCode::SUCCESS)
+            } else {
+                error!("failed in {}", failed.join(", "));
+                Ok(ExitCode::FAILURE)
+            }
+        }
+        Commands::Sbom { format } => {
+            let dtmgr_directory = find_dtmgr_directory()?;
+
+            let mut config =
+                parse_config(dtmgr_directory.join(CONFIG_FILE_NAME))?;
+            // the hash in `.dtmgr/state.json` while the environment is up to date
+            let strategy = state::State::read(&dtmgr_directory.join(".dtmgr"))?
+                .map(|state| state.link_strategy)
+                .unwrap_or_default();
+            let config_hash = hash_config(&config, strategy)?;
+            // what building the documents takes, not the tools for working on them
+            config.dev_dependencies.clear();
+
+            let platform = texlive_platform(&config)?;
+            let dep_tree = build_dependency_tree(&config, &platform)?;
+
+            let project_name = dtmgr_directory.file_name()
+                .map(|n| n.to_string_lossy().into_owned())
+                .unwrap_or_else(|| String::from("dtmgr-project"));
+            let sbom = sbom::render_sbom(format, &project_name, &config_hash, &dep_tree)
+                .map_err(|e| DtMgrError::JsonSerialize { source: e })?;
+            println!("{}", sbom);
+
+            Ok(ExitCode::SUCCESS)
+        }
+        Commands::Export { distribution } => {
+            let dtmgr_directory = find_dtmgr_directory()?;
+            let mut config =
+                parse_config(dtmgr_directory.join(CONFIG_FILE_NAME))?;
+            config.dev_dependencies.clear();
+
+            let platform = texlive_platform(&config)?;
+            let dep_tree = build_dependency_tree(&config, &platform)?;
+            let tlpdb = tlpdb::load_tlpdbs(get_texlive_root()?)?;
+
+            let exported = export::export(distribution, &dep_tree, &tlpdb);
+            for package in exported.packages.iter() {
+                println!("{}", package);
+            }
+            // on stderr, so that the list can still go straight into `apt-get install`
+            if !exported.unmapped.is_empty() {
+                warn!("no {} package is known to provide {}", distribution.name(), exported.unmapped.join(", "));
+            }
+            Ok(ExitCode::SUCCESS)
+        }
+        Commands::Audit { format } => {
+            let dtmgr_directory = find_dtmgr_directory()?;
+            let config =
+                parse_config(dtmgr_directory.join(CONFIG_FILE_NAME))?;
+            let platform = texlive_platform(&config)?;
+
+            let clean = audit::run_audit(&config, &platform, format)?;
+            Ok(if clean { ExitCode::SUCCESS } else { ExitCode::FAILURE })
+        }
+        Commands::Licenses {} => {
+            let dtmgr_directory = find_dtmgr_directory()?;
+
+            let config =
+                parse_config(dtmgr_directory.join(CONFIG_FILE_NAME))?;
+
+            let platform = texlive_platform(&config)?;
+            let dep_tree = build_dependency_tree(&config, &platform)?;
+
+            licenses::print_license_report(&config.licenses, &dep_tree);
+
+            match licenses::check_license_policy(&config.licenses, &dep_tree) {
+                Ok(()) => Ok(ExitCode::SUCCESS),
+                Err(_) => Ok(ExitCode::FAILURE),
+            }
+        }
+        Commands::Size { tree } => {
+            let dtmgr_directory = find_dtmgr_directory()?;
+
+            let config =
+                parse_config(dtmgr_directory.join(CONFIG_FILE_NAME))?;
+
+            let platform = texlive_platform(&config)?;
+            let dep_tree = build_dependency_tree(&config, &platform)?;
+
+            if tree {
+                size::print_size_tree(&config, &dep_tree, &platform);
+            } else {
+                size::print_size_report(&dep_tree, &platform);
+            }
+
+            Ok(ExitCode::SUCCESS)
+        }
+        Commands::Add { packages, interactive } => {
+            let dtmgr_directory = find_dtmgr_directory()?;
+
+            let to_add: Vec<String> = if interactive {
+                let query = packages.join(" ");
+                let tlpdb = tlpdb::load_tlpdbs(get_texlive_root()?)?;
+                let found = search::search(&tlpdb, &query);
+                if found.is_empty() {
+                    eprintln!("no package matches `{}`", query);
+                    return Ok(ExitCode::FAILURE);
+                }
+
+                // a query like `font` matches hundreds
+                let shown = &found[..found.len().min(30)];
+                let options: Vec<String> = shown.iter()
+                    .map(|pkg| match &pkg.shortdesc {
+                        Some(description) => format!("{} - {}", pkg.name, description),
+                        None => pkg.name.clone(),
+                    })
+                    .collect();
+                if !prompt::interactive() {
+                    for option in options.iter() {
+                        println!("{}", option);
+                    }
+                    return Ok(ExitCode::FAILURE);
+                }
+                if found.len() > shown.len() {
+                    eprintln!("{} packages match, showing the first {}", found.len(), shown.len());
+                }
+                prompt::choose_many("add which packages to dtmgr.toml?", &options).into_iter()
+                    .map(|idx| shown[idx].name.clone())
+                    .collect()
+            } else {
+                packages
+            };
+            if to_add.is_empty() {
+                return Ok(ExitCode::SUCCESS);
+            }
+
+            let added = config_edit::add_dependencies(dtmgr_directory.join(CONFIG_FILE_NAME), &to_add)?;
+            if added.is_empty() {
+                println!("{} already lists {}", CONFIG_FILE_NAME, to_add.join(", "));
+            } else {
+                println!("added {} to {}; run `dtmgr install` to relink", added.join(", "), CONFIG_FILE_NAME);
+            }
+            Ok(ExitCode::SUCCESS)
+        }
+        Commands::Provides { file, add } => {
+            let dtmgr_directory = find_dtmgr_directory()?;
+
+            let root = get_texlive_root()?;
+            let tlpdb = tlpdb::load_tlpdbs(&root)?;
+            let providers = provides::find_providers(&tlpdb, &file)?;
+
+            if providers.is_empty() {
+                eprintln!("no package provides `{}`", file);
+                return Ok(ExitCode::FAILURE);
+            }
+            for provider in providers.iter() {
+                println!("{}: {}", provider.package, provider.file);
+            }
+
+            let packages = provides::provider_packages(&providers);
+            let chosen = if add && packages.len() == 1 {
+                packages.first()
+            } else if prompt::interactive() {
+                prompt::choose("add which package to dtmgr.toml?", &packages)
+            } else {
+                None
+            };
+
+            if let Some(package) = chosen {
+                let added = config_edit::add_dependencies(dtmgr_directory.join(CONFIG_FILE_NAME), &[package])?;
+                if added.is_empty() {
+                    println!("`{}` is already a dependency", package);
+                } else {
+                    println!("added `{}` to {}; run `dtmgr install` to relink", package, CONFIG_FILE_NAME);
+                }
+            }
+
+            Ok(ExitCode::SUCCESS)
+        }
+        Commands::Resolve { file } => {
+            let dtmgr_directory = find_dtmgr_directory()?;
+            let file = provides::clean_file_name(&file);
+
+            let root = get_texlive_root()?;
+            let tlpdb = tlpdb::load_tlpdbs(&root)?;
+            let providers = provides::find_providers(&tlpdb, file)?;
+            let package = provides::pick_package(file, &providers)?;
+
+            let added = config_edit::add_dependencies(dtmgr_directory.join(CONFIG_FILE_NAME), &[&package])?;
+            if added.is_empty() {
+                println!("`{}` is provided by `{}`, which is already a dependency", file, package);
+            } else {
+                println!("`{}` is provided by `{}`; added it to {}", file, package, CONFIG_FILE_NAME);
+            }
+
+            install(&dtmgr_directory)?;
+            Ok(ExitCode::SUCCESS)
+        }
+        Commands::Scan { log, fix } => {
+            let dtmgr_directory = find_dtmgr_directory()?;
+            let config =
+                parse_config(dtmgr_directory.join(CONFIG_FILE_NAME))?;
+
+            // logs are written in whatever encoding the input used
+            let content = std::fs::read(&log)
+                .map_err(|e| DtMgrError::ReadFile { path: log.clone(), source: e })?;
+            let missing = scan::scan_log(&String::from_utf8_lossy(&content));
+            if missing.is_empty() {
+                println!("no missing files reported in {}", log.display());
+                return Ok(ExitCode::SUCCESS);
+            }
+
+            let root = get_texlive_root()?;
+            let tlpdb = tlpdb::load_tlpdbs(&root)?;
+
+            let mut to_add: Vec<String> = Vec::new();
+            let mut unresolved = false;
+            for resource in missing.iter() {
+                let providers = provides::find_requirement_providers(&tlpdb, resource)?;
+                let packages = provides::provider_packages(&providers);
+                if packages.is_empty() {
+                    println!("{}: no package found", resource.description);
+                    unresolved = true;
+                    continue;
+                }
+                if let Some(existing) = packages.iter().find(|p| config.is_direct(p)) {
+                    println!("{}: {} (already a dependency, the environment may be stale)", resource.description, existing);
+                    continue;
+                }
+
+                println!("{}: {}", resource.description, packages.join(" or "));
+                if fix {
+                    match provides::pick_package(&resource.description, &providers) {
+                        Ok(package) => to_add.push(package),
+                        Err(e) => {
+                            warn!("{}", e);
+                            unresolved = true;
+                        }
+                    }
+                } else if packages.len() == 1 {
+                    to_add.push(packages[0].clone());
+                } else {
+                    unresolved = true;
+                }
+            }
+
+            if !to_add.is_empty() {
+                if fix {
+                    let added = config_edit::add_dependencies(dtmgr_directory.join(CONFIG_FILE_NAME), &to_add)?;
+                    println!("added {} to {}", added.join(", "), CONFIG_FILE_NAME);
+                    install(&dtmgr_directory)?;
+                } else {
+                    println!("proposed additions to {}: {}", CONFIG_FILE_NAME, to_add.join(", "));
+                    println!("run `dtmgr scan --fix {}` to apply them", log.display());
+                }
+            }
+
+            if unresolved { Ok(ExitCode::FAILURE) } else { Ok(ExitCode::SUCCESS) }
+        }
+        Commands::Import { main, depends_txt } => {
+            let dtmgr_directory = match find_dtmgr_directory() {
+                Ok(dir) => dir,
+                Err(DtMgrError::FindConfig { cwd }) => {
+                    let config_file = cwd.join(CONFIG_FILE_NAME);
+                    std::fs::write(&config_file, "dependencies = []\n")
+                        .map_err(|e| DtMgrError::WriteFile { file: config_file, source: e })?;
+                    println!("created {}", CONFIG_FILE_NAME);
+                    cwd
+                }
+                Err(e) => return Err(e),
+            };
+            let config =
+                parse_config(dtmgr_directory.join(CONFIG_FILE_NAME))?;
+
+            if let Some(depends_txt) = depends_txt {
+                let listed = depends_txt::read_depends_txt(&depends_txt)?;
+                let added = config_edit::add_dependencies(dtmgr_directory.join(CONFIG_FILE_NAME), &listed)?;
+                println!("added {} of the {} packages in {} to {}", added.len(), listed.len(), depends_txt.display(), CONFIG_FILE_NAME);
+                return Ok(ExitCode::SUCCESS);
+            }
+
+            let sources = import::scan_sources(&dtmgr_directory, main.as_deref())?;
+
+            let root = get_texlive_root()?;
+            let tlpdb = tlpdb::load_tlpdbs(&root)?;
+
+            let mut to_add: Vec<String> = Vec::new();
+            let mut unresolved = false;
+            for requirement in sources.requirements.iter() {
+                let providers = provides::find_requirement_providers(&tlpdb, requirement)?;
+                match provides::pick_package(&requirement.description, &providers) {
+                    Ok(package) => {
+                        if !config.is_direct(&package) && !to_add.contains(&package) {
+                            to_add.push(package);
+                        }
+                    }
+                    Err(e) => {
+                        warn!("{}", e);
+                        unresolved = true;
+                    }
+                }
+            }
+
+            let added = config_edit::add_dependencies(dtmgr_directory.join(CONFIG_FILE_NAME), &to_add)?;
+            println!("scanned {} source files", sources.visited.len());
+            if added.is_empty() {
+                println!("{} already lists every package the sources load", CONFIG_FILE_NAME);
+            } else {
+                println!("added {} to {}", added.join(", "), CONFIG_FILE_NAME);
+            }
+
+            if unresolved { Ok(ExitCode::FAILURE) } else { Ok(ExitCode::SUCCESS) }
+        }
+        Commands::Trim { fls, log, remove, command } => {
+            let dtmgr_directory = find_dtmgr_directory()?;
+            let config =
+                parse_config(dtmgr_directory.join(CONFIG_FILE_NAME))?;
+
+            let inputs = match (fls, log) {
+                (Some(fls), _) => trim::read_fls(&fls)?,
+                (None, Some(log)) => trim::read_log(&log)?,
+                (None, None) => {
+                    // leave room for file systems with coarse modification times
+                    let started = std::time::SystemTime::now() - std::time::Duration::from_secs(2);
+                    let mut exe_and_args = vec![command[0].clone(), String::from("-recorder")];
+                    exe_and_args.extend(command[1..].iter().cloned());
+
+                    let status = signals::status(&mut run_tool_in_dtmgr(&exe_and_args)?)
+                        .map_err(|e| DtMgrError::CommandExecution { source: e })?;
+                    if !status.success() {
+                        return Err(DtMgrError::CommandStatus { command: exe_and_args.join(" "), code: status.code(), stderr: String::new() });
+                    }
+
+                    let cwd = std::env::current_dir()
+                        .map_err(|e| DtMgrError::CurrentDirectory { source: e })?;
+                    let fls = trim::find_fls(&cwd, started)
+                        .ok_or(DtMgrError::MissingRecorderFile { dir: cwd })?;
+                    trim::read_fls(&fls)?
+                }
+            };
+
+            let root = get_texlive_root()?;
+            let platform = texlive_platform(&config)?;
+            let dep_tree = build_dependency_tree(&config, &platform)?;
+
+            let dot_dir = dtmgr_directory.join(".dtmgr");
+            let accessed = trim::accessed_packages(&inputs, &[&dot_dir, &root], &dep_tree);
+            let unused = trim::unused_dependencies(&config, &dep_tree, &platform, &accessed);
+
+            if unused.is_empty() {
+                println!("every dependency was used by the build");
+                return Ok(ExitCode::SUCCESS);
+            }
+
+            println!("dependencies not used by the build:");
+            for dep in unused.iter() {
+                println!("    {}", dep);
+            }
+            if remove {
+                let removed = config_edit::remove_dependencies(dtmgr_directory.join(CONFIG_FILE_NAME), &unused)?;
+                println!("removed {} from {}; run `dtmgr install` to relink", removed.join(", "), CONFIG_FILE_NAME);
+            }
+
+            Ok(ExitCode::SUCCESS)
+        }
+        Commands::Init { from_system, leaves, force } => {
+            let cwd = std::env::current_dir()
+                .map_err(|e| DtMgrError::CurrentDirectory { source: e })?;
+            let config_file = cwd.join(CONFIG_FILE_NAME);
+            if config_file.exists() && !force {
+                return Err(DtMgrError::ConfigExists { path: config_file });
+            }
+
+            let mut packages: Vec<String> = Vec::new();
+            if from_system {
+                let installed = installed_packages()?;
+                let is_collection = |pkg: &TlPObjInfo| matches!(pkg.category.as_deref(), Some("Collection") | Some("Scheme"));
+
+                let mut required: Set<&str> = Set::new();
+                if leaves {
+                    for pkg in installed.iter().filter(|p| !is_collection(p)) {
+                        required.extend(pkg.depends.iter().flatten().map(String::as_str));
+                    }
+                }
+
+                let base = base_packages();
+                for pkg in installed.iter() {
+                    // per-platform binaries come in through `.ARCH` dependencies
+                    let internal = pkg.name.contains('.') || pkg.name.starts_with("00texlive") || base.contains(&pkg.name);
+                    if internal || (leaves && (is_collection(pkg) || required.contains(pkg.name.as_str()))) {
+                        continue;
+                    }
+                    packages.push(pkg.name.clone());
+                }
+                packages.sort();
+            }
+
+            config_edit::write_new_config(&config_file, &packages)?;
+            // ignored before the first install, in case the project is committed in between
+            make_dot_dir(cwd.join(".dtmgr"))?;
+            println!("created {} with {} dependencies", CONFIG_FILE_NAME, packages.len());
+
+            Ok(ExitCode::SUCCESS)
+        }
+        Commands::New { name, template, rev } => {
+            let dependencies = template::new_project(&name, &template, rev.as_deref())?;
+            make_dot_dir(name.join(".dtmgr"))?;
+            println!("created {} from {} with {} dependencies", name.display(), template, dependencies.len());
+
+            Ok(ExitCode::SUCCESS)
+        }
+    }
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    color::init(cli.color);
+    logging::init(cli.verbose, cli.quiet);
+    signals::init();
+    if cli.quiet {
+        install_log::set_quiet();
+    }
+    if let Some(platform) = cli.platform.clone() {
+        set_platform_override(platform);
+    }
+    if let Some(root) = cli.texlive_root.clone() {
+        set_texlive_root_override(root);
+    }
+    if cli.offline {
+        network::set_offline();
+    }
+    // with -v the bars would be torn up by log lines
+    if cli.no_progress || cli.quiet || cli.verbose > 0 {
+        progress::disable();
+    }
+    let error_format = cli.error_format;
+
+    run(cli).unwrap_or_else(|err| {
+        error_report::report_error(&err, error_format);
         ExitCode::FAILURE
     })
 }
@@ -0,0 +1,36 @@
+use std::path::{Path, PathBuf};
+use clap::Command;
+use dtmgr_core::DtMgrError;
+
+/// Writes a roff man page for `cmd` into `out_dir`, and one for each of its subcommands named
+/// after the command they belong to, like `dtmgr-cache-clean.1`
+pub fn write_man_pages(cmd: Command, out_dir: &Path) -> Result<Vec<PathBuf>, DtMgrError> {
+    std::fs::create_dir_all(out_dir)
+        .map_err(|e| DtMgrError::CreateDirectory { dir: out_dir.to_owned(), source: e })?;
+
+    let mut cmd = cmd;
+    // names the subcommands `dtmgr-cache-clean` and so on, and propagates global arguments into
+    // them so that their pages list those too
+    cmd.build();
+
+    let mut written = Vec::new();
+    write_page(&cmd, out_dir, &mut written)?;
+    Ok(written)
+}
+
+fn write_page(cmd: &Command, out_dir: &Path, written: &mut Vec<PathBuf>) -> Result<(), DtMgrError> {
+    let name = cmd.get_display_name().unwrap_or_else(|| cmd.get_name());
+    let file = out_dir.join(format!("{name}.1"));
+
+    let mut content = Vec::new();
+    clap_mangen::Man::new(cmd.clone()).render(&mut content)
+        .map_err(|e| DtMgrError::WriteFile { file: file.clone(), source: e })?;
+    std::fs::write(&file, content)
+        .map_err(|e| DtMgrError::WriteFile { file: file.clone(), source: e })?;
+    written.push(file);
+
+    for sub in cmd.get_subcommands().filter(|sub| !sub.is_hide_set() && sub.get_name() != "help") {
+        write_page(sub, out_dir, written)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,361 @@
+use std::collections::BTreeMap as Map;
+use std::path::{Path, PathBuf};
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Borders, Paragraph, Row, Table, TableState, Tabs};
+use ratatui::{DefaultTerminal, Frame};
+use dtmgr_core::{
+    build_dependency_tree, config_edit, exclude, install, install_log, packages, parse_config, size,
+    status, texlive_platform, verify, DtMgrError, CONFIG_FILE_NAME,
+};
+
+// the end of a long log is what matters after a failed install
+const LOG_LINES: usize = 1000;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Tab {
+    Packages,
+    Problems,
+    Log,
+}
+
+const TABS: [Tab; 3] = [Tab::Packages, Tab::Problems, Tab::Log];
+
+impl Tab {
+    fn title(self) -> &'static str {
+        match self {
+            Tab::Packages => "packages",
+            Tab::Problems => "broken links",
+            Tab::Log => "last install",
+        }
+    }
+}
+
+struct PackageRow {
+    name: String,
+    revision: Option<u64>,
+    size: u64,
+    direct: bool,
+    /// The repository's revision, if newer than the installed one
+    update: Option<u64>,
+}
+
+/// What the dashboard shows, read again after every action
+struct Snapshot {
+    environment: status::EnvironmentState,
+    platform: String,
+    packages: Vec<PackageRow>,
+    problems: Vec<String>,
+    log: Option<PathBuf>,
+    log_lines: Vec<String>,
+}
+
+fn latest_log(dot_dir: &Path) -> Option<PathBuf> {
+    let entries = std::fs::read_dir(dot_dir.join(install_log::LOGS_DIR_NAME)).ok()?;
+    // timestamps in the names sort chronologically
+    entries.filter_map(|e| e.ok()).map(|e| e.path()).max()
+}
+
+fn load(dtmgr_directory: &Path) -> Result<Snapshot, DtMgrError> {
+    let config = parse_config(dtmgr_directory.join(CONFIG_FILE_NAME))?;
+    let dot_dir = dtmgr_directory.join(".dtmgr");
+    let environment = status::environment_state(&dot_dir, &config)?;
+    let platform = texlive_platform(&config)?;
+    let dep_tree = build_dependency_tree(&config, &platform)?;
+
+    let updates: Map<&str, u64> = packages::outdated_packages(&config, &dep_tree).packages.iter()
+        .map(|pkg| (pkg.name, pkg.available_revision))
+        .collect();
+    let packages = dep_tree.values()
+        .map(|pkg| PackageRow {
+            name: pkg.name.clone(),
+            revision: pkg.lrev.or(pkg.rrev),
+            size: size::package_size(pkg, &platform),
+            direct: config.is_direct(&pkg.name),
+            update: updates.get(pkg.name.as_str()).copied(),
+        })
+        .collect();
+
+    let problems = if environment == status::EnvironmentState::Missing {
+        Vec::new()
+    } else {
+        let exclusions = exclude::Exclusions::new(&config)?;
+        verify::verify_environment(&dot_dir, environment, &dep_tree, &platform, &exclusions).problems.iter()
+            .map(|problem| {
+                let kind = match problem.kind {
+                    verify::ProblemKind::Missing => "missing",
+                    verify::ProblemKind::Dangling => "dangling link",
+                };
+                format!("{} ({}): {}", problem.file, problem.package, kind)
+            })
+            .collect()
+    };
+
+    let log = latest_log(&dot_dir);
+    let log_lines = log.as_ref()
+        .and_then(|path| std::fs::read(path).ok())
+        .map(|content| {
+            let content = String::from_utf8_lossy(&content);
+            let lines: Vec<&str> = content.lines().collect();
+            lines[lines.len().saturating_sub(LOG_LINES)..].iter().map(|line| line.to_string()).collect()
+        })
+        .unwrap_or_default();
+
+    Ok(Snapshot { environment, platform, packages, problems, log, log_lines })
+}
+
+struct Dashboard {
+    dtmgr_directory: PathBuf,
+    snapshot: Snapshot,
+    tab: Tab,
+    selected: TableState,
+    /// The package name being typed after `a`
+    input: Option<String>,
+    message: String,
+}
+
+impl Dashboard {
+    fn rows(&self) -> usize {
+        match self.tab {
+            Tab::Packages => self.snapshot.packages.len(),
+            Tab::Problems => self.snapshot.problems.len(),
+            Tab::Log => self.snapshot.log_lines.len(),
+        }
+    }
+
+    fn switch(&mut self, tab: Tab) {
+        self.tab = tab;
+        let last = self.rows().checked_sub(1);
+        // the log is read from its end
+        self.selected.select(if tab == Tab::Log { last } else { last.map(|_| 0) });
+    }
+
+    fn reload(&mut self) {
+        match load(&self.dtmgr_directory) {
+            Ok(snapshot) => {
+                self.snapshot = snapshot;
+                let rows = self.rows();
+                if self.selected.selected().is_some_and(|idx| idx >= rows) {
+                    self.selected.select(rows.checked_sub(1));
+                }
+            }
+            Err(e) => self.message = e.to_string(),
+        }
+    }
+
+    fn selected_package(&self) -> Option<&PackageRow> {
+        (self.tab == Tab::Packages)
+            .then(|| self.selected.selected().and_then(|idx| self.snapshot.packages.get(idx)))
+            .flatten()
+    }
+
+    fn add(&mut self, package: &str) {
+        let config_file = self.dtmgr_directory.join(CONFIG_FILE_NAME);
+        self.message = match config_edit::add_dependencies(config_file, &[package]) {
+            Ok(added) if added.is_empty() => format!("`{}` is already a dependency", package),
+            Ok(_) => format!("added `{}`; press i to install", package),
+            Err(e) => e.to_string(),
+        };
+        self.reload();
+    }
+
+    fn remove_selected(&mut self) {
+        let Some(package) = self.selected_package() else {
+            return;
+        };
+        if !package.direct {
+            self.message = format!("`{}` is only a dependency of other packages", package.name);
+            return;
+        }
+        let name = package.name.clone();
+        let config_file = self.dtmgr_directory.join(CONFIG_FILE_NAME);
+        self.message = match config_edit::remove_dependencies(config_file, &[&name]) {
+            Ok(_) => format!("removed `{}`; press i to install", name),
+            Err(e) => e.to_string(),
+        };
+        self.reload();
+    }
+
+    /// Installs on the plain terminal, where its progress bars and tools' output can be seen
+    fn install(&mut self, terminal: &mut DefaultTerminal) -> std::io::Result<()> {
+        ratatui::restore();
+        let result = install(&self.dtmgr_directory);
+        self.message = match &result {
+            Ok(()) => String::from("installed"),
+            Err(e) => format!("install failed: {}", e),
+        };
+        if let Err(e) = result {
+            eprintln!("error: {}", e);
+        }
+        eprintln!("press enter to return to the dashboard");
+        let mut line = String::new();
+        std::io::stdin().read_line(&mut line)?;
+
+        *terminal = ratatui::init();
+        self.reload();
+        Ok(())
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [header, tabs, body, footer] = Layout::vertical([
+            Constraint::Length(1),
+            Constraint::Length(1),
+            Constraint::Min(0),
+            Constraint::Length(1),
+        ]).areas(frame.area());
+
+        let total: u64 = self.snapshot.packages.iter().map(|pkg| pkg.size).sum();
+        frame.render_widget(Paragraph::new(format!(
+            "{} ({}): {}, {} packages, {}",
+            self.dtmgr_directory.display(),
+            self.snapshot.platform,
+            self.snapshot.environment.describe(),
+            self.snapshot.packages.len(),
+            size::format_size(total),
+        )).style(Style::new().add_modifier(Modifier::BOLD)), header);
+
+        let titles = TABS.iter().map(|tab| match tab {
+            Tab::Problems => format!("{} ({})", tab.title(), self.snapshot.problems.len()),
+            _ => tab.title().to_owned(),
+        });
+        let active = TABS.iter().position(|tab| *tab == self.tab).unwrap_or_default();
+        frame.render_widget(Tabs::new(titles).select(active).highlight_style(Style::new().fg(Color::Yellow)), tabs);
+
+        self.draw_body(frame, body);
+
+        let help = match &self.input {
+            Some(input) => format!("add package: {}_  (enter to add, esc to cancel)", input),
+            None if !self.message.is_empty() => self.message.clone(),
+            None => String::from("tab: switch  a: add  d: remove  i: install  r: reload  q: quit"),
+        };
+        frame.render_widget(Paragraph::new(help), footer);
+    }
+
+    fn draw_body(&mut self, frame: &mut Frame, area: Rect) {
+        let block = Block::new().borders(Borders::TOP);
+        let highlight = Style::new().add_modifier(Modifier::REVERSED);
+        match self.tab {
+            Tab::Packages => {
+                let rows = self.snapshot.packages.iter().map(|pkg| {
+                    let revision = pkg.revision.map(|r| format!("r{}", r)).unwrap_or_default();
+                    let update = pkg.update.map(|r| format!("-> r{}", r)).unwrap_or_default();
+                    let row = Row::new([
+                        pkg.name.clone(),
+                        revision,
+                        size::format_size(pkg.size),
+                        String::from(if pkg.direct { "direct" } else { "" }),
+                        update,
+                    ]);
+                    if pkg.update.is_some() { row.style(Style::new().fg(Color::Yellow)) } else { row }
+                });
+                let widths = [
+                    Constraint::Fill(1),
+                    Constraint::Length(9),
+                    Constraint::Length(10),
+                    Constraint::Length(6),
+                    Constraint::Length(11),
+                ];
+                let table = Table::new(rows, widths)
+                    .header(Row::new(["package", "revision", "size", "", "update"]).style(Style::new().add_modifier(Modifier::BOLD)))
+                    .block(block)
+                    .row_highlight_style(highlight);
+                frame.render_stateful_widget(table, area, &mut self.selected);
+            }
+            Tab::Problems => {
+                let rows: Vec<Row> = if self.snapshot.problems.is_empty() {
+                    vec![Row::new([String::from("every file is in place")])]
+                } else {
+                    self.snapshot.problems.iter().map(|problem| Row::new([problem.clone()]).style(Style::new().fg(Color::Red))).collect()
+                };
+                let table = Table::new(rows, [Constraint::Fill(1)]).block(block).row_highlight_style(highlight);
+                frame.render_stateful_widget(table, area, &mut self.selected);
+            }
+            Tab::Log => {
+                let title = match &self.snapshot.log {
+                    Some(path) => path.display().to_string(),
+                    None => String::from("nothing installed yet"),
+                };
+                let rows = self.snapshot.log_lines.iter().map(|line| Row::new([line.clone()]));
+                let table = Table::new(rows, [Constraint::Fill(1)])
+                    .block(block.title(Line::from(title)))
+                    .row_highlight_style(highlight);
+                frame.render_stateful_widget(table, area, &mut self.selected);
+            }
+        }
+    }
+
+    fn run(&mut self, terminal: &mut DefaultTerminal) -> std::io::Result<()> {
+        loop {
+            terminal.draw(|frame| self.draw(frame))?;
+            let Event::Key(key) = event::read()? else {
+                continue;
+            };
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+
+            if let Some(input) = self.input.as_mut() {
+                match key.code {
+                    KeyCode::Char(c) => input.push(c),
+                    KeyCode::Backspace => {
+                        input.pop();
+                    }
+                    KeyCode::Enter => {
+                        let package = input.trim().to_owned();
+                        self.input = None;
+                        if !package.is_empty() {
+                            self.add(&package);
+                        }
+                    }
+                    KeyCode::Esc => self.input = None,
+                    _ => {}
+                }
+                continue;
+            }
+
+            self.message.clear();
+            let rows = self.rows();
+            let page = terminal.size()?.height.saturating_sub(5) as usize;
+            let current = self.selected.selected().unwrap_or_default();
+            let active = TABS.iter().position(|tab| *tab == self.tab).unwrap_or_default();
+            match key.code {
+                KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+                KeyCode::Tab | KeyCode::Right => self.switch(TABS[(active + 1) % TABS.len()]),
+                KeyCode::BackTab | KeyCode::Left => self.switch(TABS[(active + TABS.len() - 1) % TABS.len()]),
+                KeyCode::Down | KeyCode::Char('j') if rows > 0 => self.selected.select(Some((current + 1).min(rows - 1))),
+                KeyCode::Up | KeyCode::Char('k') => self.selected.select(Some(current.saturating_sub(1))),
+                KeyCode::PageDown if rows > 0 => self.selected.select(Some((current + page).min(rows - 1))),
+                KeyCode::PageUp => self.selected.select(Some(current.saturating_sub(page))),
+                KeyCode::Home => self.selected.select(Some(0)),
+                KeyCode::End => self.selected.select(rows.checked_sub(1)),
+                KeyCode::Char('a') => self.input = Some(String::new()),
+                KeyCode::Char('d') | KeyCode::Delete => self.remove_selected(),
+                KeyCode::Char('i') => self.install(terminal)?,
+                KeyCode::Char('r') => self.reload(),
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Shows the project in `dtmgr_directory` until the user quits
+pub fn run(dtmgr_directory: PathBuf) -> Result<(), DtMgrError> {
+    eprintln!("resolving packages...");
+    let snapshot = load(&dtmgr_directory)?;
+    let mut dashboard = Dashboard {
+        dtmgr_directory,
+        snapshot,
+        tab: Tab::Packages,
+        selected: TableState::new(),
+        input: None,
+        message: String::new(),
+    };
+    dashboard.switch(Tab::Packages);
+
+    let mut terminal = ratatui::init();
+    let result = dashboard.run(&mut terminal);
+    ratatui::restore();
+    result.map_err(|e| DtMgrError::Terminal { source: e })
+}